@@ -0,0 +1,182 @@
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant, UNIX_EPOCH},
+};
+
+use crate::config::{AgentOptions, join_url_path};
+use crate::error::Error;
+use crate::history::unix_millis;
+use crate::worker::CheckRecord;
+
+/// Path of the central instance's endpoint receiving agent results.
+pub(crate) const RESULTS_PATH: &str = "api/v1/agent/results";
+
+/// Batch of check results pushed by an agent to the central instance.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct AgentReport {
+    pub(crate) agent: String,
+    pub(crate) results: Vec<AgentResult>,
+}
+
+/// A single check performed by an agent.
+///
+/// Failure streaks are not sent, the central instance tracks them itself.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct AgentResult {
+    pub(crate) url: String,
+    pub(crate) checked_at_ms: u64,
+    pub(crate) up: bool,
+    pub(crate) response_time_ms: u64,
+}
+
+impl AgentResult {
+    /// Turns the result into a check record attributed to `agent`, with the
+    /// failure streak left for the central instance to fill in.
+    pub(crate) fn into_record(self, agent: &str) -> CheckRecord {
+        CheckRecord {
+            url: self.url,
+            agent: Some(agent.to_string()),
+            checked_at: UNIX_EPOCH + Duration::from_millis(self.checked_at_ms),
+            is_up: self.up,
+            response_time: Duration::from_millis(self.response_time_ms),
+            consecutive_failures: 0,
+        }
+    }
+}
+
+impl From<&CheckRecord> for AgentResult {
+    fn from(record: &CheckRecord) -> Self {
+        Self {
+            url: record.url.clone(),
+            checked_at_ms: unix_millis(record.checked_at),
+            up: record.is_up,
+            response_time_ms: u64::try_from(record.response_time.as_millis()).unwrap_or(u64::MAX),
+        }
+    }
+}
+
+/// Pushes a batch of check results to the central instance.
+pub(crate) async fn push_results(
+    client: &Client,
+    options: &AgentOptions,
+    records: &[CheckRecord],
+) -> Result<(), Error> {
+    if records.is_empty() {
+        return Ok(());
+    }
+
+    let report = AgentReport {
+        agent: options.name.clone(),
+        results: records.iter().map(AgentResult::from).collect(),
+    };
+
+    client
+        .post(join_url_path(&options.central_url, RESULTS_PATH)?)
+        .bearer_auth(&options.token)
+        .json(&report)
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+/// When each agent last reported, on the central instance, telling which
+/// agents stopped reporting.
+#[derive(Debug, Default)]
+pub(crate) struct AgentLiveness {
+    last_report: HashMap<String, Instant>,
+    stale: HashSet<String>,
+}
+
+impl AgentLiveness {
+    /// Records a report from `agent`, returning whether it was stale until now.
+    pub(crate) fn reported(&mut self, agent: &str, now: Instant) -> bool {
+        self.last_report.insert(agent.to_string(), now);
+        self.stale.remove(agent)
+    }
+
+    /// Agents silent for `timeout` that were not stale yet, with how long
+    /// they have been silent. Each agent is returned once until it reports again.
+    pub(crate) fn newly_stale(
+        &mut self,
+        timeout: Duration,
+        now: Instant,
+    ) -> Vec<(String, Duration)> {
+        let mut agents = Vec::new();
+        for (agent, last_report) in &self.last_report {
+            let silent_for = now.saturating_duration_since(*last_report);
+            if silent_for >= timeout && self.stale.insert(agent.clone()) {
+                agents.push((agent.clone(), silent_for));
+            }
+        }
+        agents.sort();
+        agents
+    }
+
+    /// Time until the next agent goes stale, `None` when every agent already is.
+    pub(crate) fn next_timeout(&self, timeout: Duration, now: Instant) -> Option<Duration> {
+        self.last_report
+            .iter()
+            .filter(|(agent, _)| !self.stale.contains(*agent))
+            .map(|(_, last_report)| (*last_report + timeout).saturating_duration_since(now))
+            .min()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_result_round_trip_keeps_check_and_names_agent() {
+        let record = CheckRecord {
+            url: "http://internal.example".to_string(),
+            agent: None,
+            checked_at: UNIX_EPOCH + Duration::from_millis(1_700_000_000_123),
+            is_up: false,
+            response_time: Duration::from_millis(842),
+            consecutive_failures: 3,
+        };
+
+        let received = AgentResult::from(&record).into_record("vpc-a");
+
+        assert_eq!(received.url, record.url);
+        assert_eq!(received.agent.as_deref(), Some("vpc-a"));
+        assert_eq!(received.checked_at, record.checked_at);
+        assert!(!received.is_up);
+        assert_eq!(received.response_time, record.response_time);
+        assert_eq!(
+            received.consecutive_failures, 0,
+            "Failure streaks are tracked by the central instance"
+        );
+    }
+
+    #[test]
+    fn test_agents_go_stale_once_until_they_report_again() {
+        let timeout = Duration::from_mins(15);
+        let start = Instant::now();
+        let mut liveness = AgentLiveness::default();
+        assert!(!liveness.reported("vpc-a", start));
+        assert!(!liveness.reported("vpc-b", start + Duration::from_mins(10)));
+
+        let now = start + Duration::from_secs(1000);
+        assert_eq!(
+            liveness.newly_stale(timeout, now),
+            [("vpc-a".to_string(), Duration::from_secs(1000))]
+        );
+        assert!(liveness.newly_stale(timeout, now).is_empty());
+        assert_eq!(
+            liveness.next_timeout(timeout, now),
+            Some(Duration::from_secs(500))
+        );
+
+        assert!(liveness.reported("vpc-a", now));
+        assert!(!liveness.reported("vpc-a", now));
+        assert_eq!(
+            liveness.newly_stale(timeout, start + Duration::from_mins(25)),
+            [("vpc-b".to_string(), Duration::from_mins(15))]
+        );
+    }
+}
@@ -0,0 +1,990 @@
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode, header::AUTHORIZATION},
+    routing::{get, post},
+};
+use log::{error, info};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::{
+    io::{self, Write},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    path::PathBuf,
+    time::{Duration, Instant, SystemTime},
+};
+use tokio::sync::Mutex;
+use url::Url;
+
+use crate::agent::AgentReport;
+use crate::config::{self, Config};
+use crate::confirm::{ProbeRequest, ProbeResponse};
+use crate::dashboard::format_duration;
+use crate::error::Error;
+use crate::history::{format_timestamp, unix_millis};
+use crate::incident::{Incident, NotificationEvent};
+use crate::server::AppState;
+use crate::slo::SloStatus;
+use crate::status::{SiteHealth, SiteStatus, site_id, site_label};
+use crate::worker::{PacedClient, is_url_up};
+
+/// How long the `status` command waits for the running instance.
+const STATUS_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// JSON API, mounted under `/api/v1`.
+///
+/// Reads are public, adding and removing sites requires the API token,
+/// pushing agent results requires the agent token and asking for a check
+/// requires the probe token.
+pub(crate) fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/sites", get(list_sites).post(add_site))
+        .route("/sites/{id}", get(get_site).delete(remove_site))
+        .route("/incidents", get(list_incidents))
+        .route("/incidents/history", get(list_incident_history))
+        .route("/slo", get(list_slos))
+        .route("/agent/results", post(receive_agent_report))
+        .route("/probe", post(probe_site))
+}
+
+/// Settings of the site management endpoints, present when an API token is configured.
+#[derive(Debug)]
+pub(crate) struct SiteManagement {
+    token: String,
+    /// Config file the site list is written back to, changes only last until
+    /// restart when `None`.
+    config_path: Option<PathBuf>,
+    /// Serializes site list changes so concurrent requests cannot lose writes.
+    lock: Mutex<()>,
+}
+
+impl SiteManagement {
+    pub(crate) fn new(token: String, config_path: Option<PathBuf>) -> Self {
+        Self {
+            token,
+            config_path,
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Writes the new site list to the config file, if there is one.
+    async fn persist(&self, urls: Vec<String>) -> Result<(), ApiError> {
+        let Some(path) = self.config_path.clone() else {
+            return Ok(());
+        };
+
+        tokio::task::spawn_blocking(move || config::save_site_urls(&path, &urls))
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|result| result.map_err(|e| e.to_string()))
+            .map_err(|e| {
+                error!("Failed to save the site list to the config file: {e}");
+                api_error(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Failed to save the site list to the config file",
+                )
+            })
+    }
+}
+
+/// Checks the request carries `Authorization: Bearer <expected>`.
+fn authorize(headers: &HeaderMap, expected: &str) -> Result<(), ApiError> {
+    let provided = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    match provided {
+        Some(token) if constant_time_eq(token.as_bytes(), expected.as_bytes()) => Ok(()),
+        _ => Err(api_error(
+            StatusCode::UNAUTHORIZED,
+            "Missing or invalid bearer token",
+        )),
+    }
+}
+
+/// Compares two byte strings in time independent of where they differ.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[derive(Debug, Deserialize)]
+struct AddSiteRequest {
+    url: String,
+}
+
+/// Current status of a site, as returned by the API.
+#[derive(Debug, Serialize, Deserialize)]
+struct SiteResponse {
+    id: String,
+    url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    agent: Option<String>,
+    status: SiteHealth,
+    consecutive_failures: u64,
+    last_checked_at: Option<String>,
+    last_response_time_ms: Option<u64>,
+    failing_since: Option<String>,
+}
+
+impl From<&SiteStatus> for SiteResponse {
+    fn from(site: &SiteStatus) -> Self {
+        Self {
+            id: site.id(),
+            url: site.url.clone(),
+            agent: site.agent.clone(),
+            status: site.health,
+            consecutive_failures: site.consecutive_failures,
+            last_checked_at: site.last_checked_at.map(rfc3339),
+            last_response_time_ms: site.last_response_time_ms,
+            failing_since: site.failing_since.map(rfc3339),
+        }
+    }
+}
+
+/// A site that is currently failing its checks.
+#[derive(Debug, Serialize, Deserialize)]
+struct IncidentResponse {
+    site_id: String,
+    url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    agent: Option<String>,
+    status: SiteHealth,
+    started_at: String,
+    duration_secs: u64,
+    consecutive_failures: u64,
+    /// Id of the incident, `null` until the site is alerted on as DOWN.
+    incident_id: Option<u64>,
+    /// Discord user who acknowledged the incident.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    acknowledged_by: Option<String>,
+}
+
+/// An incident alerted on, open or resolved.
+#[derive(Debug, Serialize)]
+struct IncidentHistoryResponse {
+    id: u64,
+    /// Site URL, followed by the reporting agent for agent checks.
+    site: String,
+    started_at: String,
+    /// `null` while the site is still down.
+    resolved_at: Option<String>,
+    duration_secs: u64,
+    acknowledged_by: Option<String>,
+    /// Network diagnostics taken when the site went down, when configured.
+    diagnostics: Option<String>,
+    notifications: Vec<NotificationResponse>,
+}
+
+#[derive(Debug, Serialize)]
+struct NotificationResponse {
+    at: String,
+    event: NotificationEvent,
+}
+
+impl IncidentHistoryResponse {
+    fn new(incident: Incident, now_ms: u64) -> Self {
+        Self {
+            id: incident.id,
+            started_at: format_timestamp(incident.started_at_ms),
+            resolved_at: incident.resolved_at_ms.map(format_timestamp),
+            duration_secs: incident.duration(now_ms).as_secs(),
+            acknowledged_by: incident.acknowledged_by,
+            diagnostics: incident.diagnostics,
+            notifications: incident
+                .notifications
+                .into_iter()
+                .map(|log| NotificationResponse {
+                    at: format_timestamp(log.at_ms),
+                    event: log.event,
+                })
+                .collect(),
+            site: incident.site,
+        }
+    }
+}
+
+/// Standing of a site against its SLO, values are `null` until history has data.
+#[derive(Debug, Serialize)]
+struct SloResponse {
+    site_id: String,
+    url: String,
+    target_percent: f64,
+    window_days: u64,
+    compliance_percent: Option<f64>,
+    error_budget_remaining_percent: Option<f64>,
+    burn_rate: Option<f64>,
+}
+
+impl From<SloStatus> for SloResponse {
+    fn from(status: SloStatus) -> Self {
+        Self {
+            site_id: site_id(&status.url, None),
+            url: status.url,
+            target_percent: status.target,
+            window_days: status.window_days,
+            compliance_percent: status.compliance,
+            error_budget_remaining_percent: status.error_budget_remaining,
+            burn_rate: status.burn_rate,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct ErrorResponse {
+    error: String,
+}
+
+pub(crate) type ApiError = (StatusCode, Json<ErrorResponse>);
+
+pub(crate) fn api_error(status: StatusCode, message: impl Into<String>) -> ApiError {
+    (
+        status,
+        Json(ErrorResponse {
+            error: message.into(),
+        }),
+    )
+}
+
+async fn list_sites(State(state): State<AppState>) -> Json<Vec<SiteResponse>> {
+    Json(
+        state
+            .board
+            .snapshot()
+            .iter()
+            .map(SiteResponse::from)
+            .collect(),
+    )
+}
+
+async fn get_site(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<SiteResponse>, ApiError> {
+    state
+        .board
+        .snapshot()
+        .iter()
+        .find(|site| site.id() == id)
+        .map(|site| Json(SiteResponse::from(site)))
+        .ok_or_else(|| api_error(StatusCode::NOT_FOUND, format!("No site with id '{id}'")))
+}
+
+async fn add_site(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<AddSiteRequest>,
+) -> Result<(StatusCode, Json<SiteResponse>), ApiError> {
+    let management = site_management(&state, &headers)?;
+    let _guard = management.lock.lock().await;
+
+    Config::validate_urls(std::slice::from_ref(&request.url))
+        .map_err(|e| api_error(StatusCode::UNPROCESSABLE_ENTITY, e.to_string()))?;
+    if state.board.urls().contains(&request.url) {
+        return Err(api_error(
+            StatusCode::CONFLICT,
+            format!("{} is already monitored", request.url),
+        ));
+    }
+
+    // Discovered sites are not written to the config file
+    let mut urls = state.board.configured_urls();
+    urls.push(request.url.clone());
+    management.persist(urls).await?;
+    state.board.add_site(&request.url);
+    info!("{}: added through the API", request.url);
+
+    let site = state
+        .board
+        .snapshot()
+        .iter()
+        .find(|site| site.url == request.url)
+        .map(SiteResponse::from)
+        .ok_or_else(|| api_error(StatusCode::CONFLICT, "Site was removed concurrently"))?;
+    Ok((StatusCode::CREATED, Json(site)))
+}
+
+async fn remove_site(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    let management = site_management(&state, &headers)?;
+    let _guard = management.lock.lock().await;
+
+    let site = state
+        .board
+        .snapshot()
+        .into_iter()
+        .find(|site| site.agent.is_none() && site.id() == id)
+        .ok_or_else(|| api_error(StatusCode::NOT_FOUND, format!("No site with id '{id}'")))?;
+    if let Some(source) = site.discovered_by {
+        return Err(api_error(
+            StatusCode::CONFLICT,
+            format!(
+                "{} is discovered from the {source}, remove it there",
+                site.url
+            ),
+        ));
+    }
+
+    let url = site.url;
+    let urls = state
+        .board
+        .configured_urls()
+        .into_iter()
+        .filter(|configured| *configured != url)
+        .collect();
+    management.persist(urls).await?;
+    state.board.remove_site(&url);
+    info!("{url}: removed through the API");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Checks that site management is enabled and the request is authorized.
+fn site_management<'a>(
+    state: &'a AppState,
+    headers: &HeaderMap,
+) -> Result<&'a SiteManagement, ApiError> {
+    let management = state.site_management.as_deref().ok_or_else(|| {
+        api_error(
+            StatusCode::FORBIDDEN,
+            "Site management is disabled, set api_token in the [server] section",
+        )
+    })?;
+    authorize(headers, &management.token)?;
+    Ok(management)
+}
+
+async fn list_incidents(State(state): State<AppState>) -> Json<Vec<IncidentResponse>> {
+    let now = SystemTime::now();
+
+    Json(
+        state
+            .board
+            .snapshot()
+            .into_iter()
+            .filter_map(|site| {
+                let since = site.failing_since?;
+                let incident = state.notifier.incident(&site.label());
+                Some(IncidentResponse {
+                    site_id: site.id(),
+                    started_at: rfc3339(since),
+                    duration_secs: now.duration_since(since).unwrap_or_default().as_secs(),
+                    status: site.health,
+                    consecutive_failures: site.consecutive_failures,
+                    incident_id: incident.as_ref().map(|incident| incident.id),
+                    acknowledged_by: incident.and_then(|incident| incident.acknowledged_by),
+                    url: site.url,
+                    agent: site.agent,
+                })
+            })
+            .collect(),
+    )
+}
+
+/// Lists the incidents alerted on, most recent first. Resolved ones are
+/// kept as long as the check history.
+async fn list_incident_history(
+    State(state): State<AppState>,
+) -> Json<Vec<IncidentHistoryResponse>> {
+    let now_ms = unix_millis(SystemTime::now());
+    Json(
+        state
+            .notifier
+            .incidents()
+            .into_iter()
+            .map(|incident| IncidentHistoryResponse::new(incident, now_ms))
+            .collect(),
+    )
+}
+
+/// Lists the SLO status of every monitored site declaring one.
+async fn list_slos(State(state): State<AppState>) -> Result<Json<Vec<SloResponse>>, ApiError> {
+    let (Some(targets), Some(store)) = (state.slo, state.history) else {
+        return Ok(Json(Vec::new()));
+    };
+
+    let statuses = tokio::task::spawn_blocking(move || targets.evaluate(&store, SystemTime::now()))
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|result| result.map_err(|e| e.to_string()))
+        .map_err(|e| {
+            error!("Failed to compute SLOs from check history: {e}");
+            api_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to compute SLOs from check history",
+            )
+        })?;
+
+    // Sites removed at runtime keep their target in the config until restart
+    let urls = state.board.urls();
+    Ok(Json(
+        statuses
+            .into_iter()
+            .filter(|status| urls.contains(&status.url))
+            .map(SloResponse::from)
+            .collect(),
+    ))
+}
+
+/// Queues the results pushed by an agent for the worker.
+async fn receive_agent_report(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(report): Json<AgentReport>,
+) -> Result<StatusCode, ApiError> {
+    let reports = state.agent_reports.as_ref().ok_or_else(|| {
+        api_error(
+            StatusCode::FORBIDDEN,
+            "Agent reports are not accepted, set agent_token in the [server] section",
+        )
+    })?;
+    authorize(&headers, &reports.token)?;
+
+    if report.agent.trim().is_empty() {
+        return Err(api_error(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "Agent name must not be empty",
+        ));
+    }
+
+    reports.sender.send(report).await.map_err(|_| {
+        api_error(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "The monitor is shutting down",
+        )
+    })?;
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// Checks a site on behalf of another instance confirming an outage.
+async fn probe_site(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<ProbeRequest>,
+) -> Result<Json<ProbeResponse>, ApiError> {
+    let probe = state.probe.as_ref().ok_or_else(|| {
+        api_error(
+            StatusCode::FORBIDDEN,
+            "Probing is disabled, set probe_token in the [server] section",
+        )
+    })?;
+    authorize(&headers, &probe.token)?;
+
+    Config::validate_urls(std::slice::from_ref(&request.url))
+        .map_err(|e| api_error(StatusCode::UNPROCESSABLE_ENTITY, e.to_string()))?;
+
+    let started_at = Instant::now();
+    let up = is_url_up(
+        PacedClient {
+            client: &probe.client,
+            rate_limiter: probe.rate_limiter.as_deref(),
+        },
+        &request.url,
+        &request.success_statuses,
+        probe.timeout_secs,
+        request.low_bandwidth,
+    )
+    .await
+    .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(ProbeResponse {
+        url: request.url,
+        up,
+        response_time_ms: u64::try_from(started_at.elapsed().as_millis()).unwrap_or(u64::MAX),
+    }))
+}
+
+/// Prints the sites of a running instance, their state, last check and open
+/// incident as a table, used by the `status` command.
+///
+/// The instance is reached at `base_url`, or through the `[server]` section
+/// of the config when `None`.
+///
+/// # Errors
+///
+/// Returns an error when no URL is given and the HTTP server is not enabled,
+/// when the instance cannot be reached or answers with an error, or when
+/// writing to `out` fails.
+pub async fn print_status(
+    config: &Config,
+    base_url: Option<&Url>,
+    mut out: impl Write,
+) -> Result<(), Error> {
+    let api_url = match base_url {
+        Some(url) => config::join_url_path(url, "api/v1")?,
+        None => local_api_url(config)?,
+    };
+    let client = Client::builder().timeout(STATUS_TIMEOUT).build()?;
+    let (sites, incidents) = fetch_status(&client, api_url.as_str()).await?;
+    write_status_table(&sites, &incidents, &mut out)?;
+    Ok(())
+}
+
+/// API of the instance running with this config, reached over loopback
+/// when it listens on every interface.
+fn local_api_url(config: &Config) -> Result<Url, Error> {
+    let options = config.server.as_ref().ok_or_else(|| {
+        Error::Config(
+            "The HTTP server is not enabled, add a [server] section to the config or pass --url"
+                .into(),
+        )
+    })?;
+    let mut bind = options.bind;
+    if bind.ip().is_unspecified() {
+        bind.set_ip(match bind.ip() {
+            IpAddr::V4(_) => Ipv4Addr::LOCALHOST.into(),
+            IpAddr::V6(_) => Ipv6Addr::LOCALHOST.into(),
+        });
+    }
+    Ok(Url::parse(&format!("http://{bind}/api/v1"))?)
+}
+
+async fn fetch_status(
+    client: &Client,
+    api_url: &str,
+) -> Result<(Vec<SiteResponse>, Vec<IncidentResponse>), Error> {
+    let api_url = api_url.trim_end_matches('/');
+    let sites = client
+        .get(format!("{api_url}/sites"))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    let incidents = client
+        .get(format!("{api_url}/incidents"))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    Ok((sites, incidents))
+}
+
+fn write_status_table(
+    sites: &[SiteResponse],
+    incidents: &[IncidentResponse],
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let mut rows = vec![[
+        "SITE".to_string(),
+        "STATE".to_string(),
+        "LAST CHECK".to_string(),
+        "INCIDENT".to_string(),
+    ]];
+    rows.extend(sites.iter().map(|site| {
+        let incident = incidents
+            .iter()
+            .find(|incident| incident.site_id == site.id)
+            .map_or_else(|| "-".to_string(), describe_incident);
+        [
+            site_label(&site.url, site.agent.as_deref()),
+            site.status.label().to_string(),
+            site.last_checked_at
+                .clone()
+                .unwrap_or_else(|| "-".to_string()),
+            incident,
+        ]
+    }));
+
+    let widths: Vec<usize> = (0..4)
+        .map(|column| {
+            rows.iter()
+                .map(|row| row[column].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    for row in &rows {
+        let line = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell:width$}"))
+            .collect::<Vec<_>>()
+            .join("  ");
+        writeln!(out, "{}", line.trim_end())?;
+    }
+    Ok(())
+}
+
+/// Describes a failing site's incident, e.g. `#12 for 5m 3s, acknowledged by alice`.
+fn describe_incident(incident: &IncidentResponse) -> String {
+    let duration = format_duration(Duration::from_secs(incident.duration_secs));
+    let description = match incident.incident_id {
+        Some(id) => format!("#{id} for {duration}"),
+        None => format!("failing for {duration}, not alerted yet"),
+    };
+    match &incident.acknowledged_by {
+        Some(user) => format!("{description}, acknowledged by {user}"),
+        None => description,
+    }
+}
+
+fn rfc3339(time: SystemTime) -> String {
+    format_timestamp(unix_millis(time))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notify::{DownAlert, Notifier};
+    use crate::server::{AgentReports, Probe};
+    use crate::status::StatusBoard;
+    use crate::test_support::temp_dir;
+    use crate::worker::CheckRecord;
+    use serde_json::Value;
+    use std::{
+        sync::Arc,
+        time::{Duration, UNIX_EPOCH},
+    };
+    use tokio::net::TcpListener;
+
+    async fn spawn_api(board: StatusBoard, site_management: Option<Arc<SiteManagement>>) -> String {
+        spawn_app(AppState {
+            board,
+            history: None,
+            history_graphs: None,
+            site_management,
+            agent_reports: None,
+            probe: None,
+            slo: None,
+            notifier: Arc::default(),
+            discord_public_key: None,
+        })
+        .await
+    }
+
+    async fn spawn_app(state: AppState) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = routes().with_state(state);
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        format!("http://{addr}")
+    }
+
+    async fn get_json(url: String) -> (reqwest::StatusCode, Value) {
+        let response = reqwest::get(url).await.unwrap();
+        (response.status(), response.json().await.unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_sites_and_incidents() {
+        let board = StatusBoard::new(&[
+            "https://a.example".to_string(),
+            "https://b.example".to_string(),
+        ]);
+        board.record(
+            &CheckRecord {
+                url: "https://b.example".to_string(),
+                agent: None,
+                checked_at: UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+                is_up: false,
+                response_time: Duration::from_millis(42),
+                consecutive_failures: 1,
+            },
+            SiteHealth::Unreachable,
+        );
+        let base = spawn_api(board, None).await;
+
+        let (status, sites) = get_json(format!("{base}/sites")).await;
+        assert!(status.is_success());
+        assert_eq!(sites.as_array().unwrap().len(), 2);
+        assert_eq!(sites[0]["status"], "pending");
+        assert_eq!(sites[1]["failing_since"], "2023-11-14T22:13:20Z");
+
+        let id = site_id("https://b.example", None);
+        let (status, site) = get_json(format!("{base}/sites/{id}")).await;
+        assert!(status.is_success());
+        assert_eq!(site["url"], "https://b.example");
+        assert_eq!(site["last_response_time_ms"], 42);
+
+        let (status, incidents) = get_json(format!("{base}/incidents")).await;
+        assert!(status.is_success());
+        assert_eq!(incidents.as_array().unwrap().len(), 1);
+        assert_eq!(incidents[0]["site_id"], id.as_str());
+        assert_eq!(incidents[0]["status"], "unreachable");
+        assert_eq!(incidents[0]["incident_id"], Value::Null);
+    }
+
+    #[tokio::test]
+    async fn test_incident_history() {
+        let notifier = Arc::new(Notifier::default());
+        for site in ["https://a.example", "https://b.example"] {
+            notifier
+                .alert_down(site, &DownAlert::default())
+                .await
+                .unwrap();
+        }
+        notifier.resolve("https://a.example").await.unwrap();
+        let base = spawn_app(AppState {
+            board: StatusBoard::new(&[]),
+            history: None,
+            history_graphs: None,
+            site_management: None,
+            agent_reports: None,
+            probe: None,
+            slo: None,
+            notifier,
+            discord_public_key: None,
+        })
+        .await;
+
+        let (status, incidents) = get_json(format!("{base}/incidents/history")).await;
+        assert!(status.is_success());
+        assert_eq!(incidents[0]["id"], 2);
+        assert_eq!(incidents[0]["site"], "https://b.example");
+        assert_eq!(incidents[0]["resolved_at"], Value::Null);
+        assert_eq!(incidents[1]["id"], 1);
+        assert!(incidents[1]["resolved_at"].is_string());
+        assert_eq!(incidents[1]["notifications"][0]["event"], "resolved");
+    }
+
+    #[tokio::test]
+    async fn test_unknown_site_is_not_found() {
+        let base = spawn_api(StatusBoard::new(&[]), None).await;
+
+        let (status, body) = get_json(format!("{base}/sites/0000000000000000")).await;
+        assert_eq!(status, reqwest::StatusCode::NOT_FOUND);
+        assert!(body["error"].as_str().unwrap().contains("0000000000000000"));
+    }
+
+    #[tokio::test]
+    async fn test_agent_reports_are_queued() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (sender, mut receiver) = tokio::sync::mpsc::channel(1);
+        let app = routes().with_state(AppState {
+            board: StatusBoard::new(&[]),
+            history: None,
+            history_graphs: None,
+            site_management: None,
+            agent_reports: Some(AgentReports {
+                token: "agent-secret".into(),
+                sender,
+            }),
+            probe: None,
+            slo: None,
+            notifier: Arc::default(),
+            discord_public_key: None,
+        });
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let report = serde_json::json!({
+            "agent": "vpc-a",
+            "results": [{
+                "url": "http://internal.example",
+                "checked_at_ms": 1_700_000_000_000_u64,
+                "up": false,
+                "response_time_ms": 12,
+            }],
+        });
+        let client = reqwest::Client::new();
+        let push = |token: &'static str| {
+            client
+                .post(format!("http://{addr}/agent/results"))
+                .bearer_auth(token)
+                .json(&report)
+                .send()
+        };
+
+        assert_eq!(
+            push("wrong").await.unwrap().status(),
+            reqwest::StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            push("agent-secret").await.unwrap().status(),
+            reqwest::StatusCode::ACCEPTED
+        );
+        let queued = receiver.recv().await.unwrap();
+        assert_eq!(queued.agent, "vpc-a");
+        assert_eq!(queued.results[0].url, "http://internal.example");
+    }
+
+    #[tokio::test]
+    async fn test_probe_checks_site_for_other_instances() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = routes().with_state(AppState {
+            board: StatusBoard::new(&[]),
+            history: None,
+            history_graphs: None,
+            site_management: None,
+            agent_reports: None,
+            probe: Some(Probe {
+                token: "probe-secret".into(),
+                client: reqwest::Client::new(),
+                timeout_secs: 5,
+                rate_limiter: None,
+            }),
+            slo: None,
+            notifier: Arc::default(),
+            discord_public_key: None,
+        });
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let client = reqwest::Client::new();
+        let probe = |token: &'static str, url: String| {
+            client
+                .post(format!("http://{addr}/probe"))
+                .bearer_auth(token)
+                .json(&serde_json::json!({ "url": url }))
+                .send()
+        };
+
+        assert_eq!(
+            probe("wrong", format!("http://{addr}/sites"))
+                .await
+                .unwrap()
+                .status(),
+            reqwest::StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            probe("probe-secret", "not a url".to_string())
+                .await
+                .unwrap()
+                .status(),
+            reqwest::StatusCode::UNPROCESSABLE_ENTITY
+        );
+
+        // The probe's own API is a site it can reach
+        let response = probe("probe-secret", format!("http://{addr}/sites"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let body: Value = response.json().await.unwrap();
+        assert_eq!(body["up"], true);
+    }
+
+    #[tokio::test]
+    async fn test_site_management_requires_token() {
+        let client = reqwest::Client::new();
+        let disabled = spawn_api(StatusBoard::new(&[]), None).await;
+        let response = client
+            .post(format!("{disabled}/sites"))
+            .bearer_auth("secret")
+            .json(&serde_json::json!({ "url": "https://a.example" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::FORBIDDEN);
+
+        let management = Arc::new(SiteManagement::new("secret".to_string(), None));
+        let base = spawn_api(StatusBoard::new(&[]), Some(management)).await;
+        let response = client
+            .post(format!("{base}/sites"))
+            .bearer_auth("wrong")
+            .json(&serde_json::json!({ "url": "https://a.example" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_add_and_remove_site() {
+        let path = temp_dir("api").join("config.toml");
+        std::fs::write(&path, "[sites]\nurls = [\"https://a.example\"]\n").unwrap();
+
+        let board = StatusBoard::new(&["https://a.example".to_string()]);
+        board.add_discovered_site("https://d.example", "remote site list");
+        let management = Arc::new(SiteManagement::new(
+            "secret".to_string(),
+            Some(path.clone()),
+        ));
+        let base = spawn_api(board.clone(), Some(management)).await;
+        let client = reqwest::Client::new();
+
+        let add = |url: &'static str| {
+            client
+                .post(format!("{base}/sites"))
+                .bearer_auth("secret")
+                .json(&serde_json::json!({ "url": url }))
+                .send()
+        };
+        let response = add("https://b.example").await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::CREATED);
+        let site: Value = response.json().await.unwrap();
+        assert_eq!(site["status"], "pending");
+        assert_eq!(
+            add("https://b.example").await.unwrap().status(),
+            reqwest::StatusCode::CONFLICT
+        );
+        assert_eq!(
+            add("not a url").await.unwrap().status(),
+            reqwest::StatusCode::UNPROCESSABLE_ENTITY
+        );
+        assert_eq!(
+            board.urls(),
+            [
+                "https://a.example",
+                "https://d.example",
+                "https://b.example"
+            ]
+        );
+
+        let remove = |url: &'static str| {
+            client
+                .delete(format!("{base}/sites/{}", site_id(url, None)))
+                .bearer_auth("secret")
+                .send()
+        };
+        assert_eq!(
+            remove("https://a.example").await.unwrap().status(),
+            reqwest::StatusCode::NO_CONTENT
+        );
+        assert_eq!(
+            remove("https://d.example").await.unwrap().status(),
+            reqwest::StatusCode::CONFLICT,
+            "Discovered sites are removed at their source"
+        );
+        assert_eq!(board.urls(), ["https://d.example", "https://b.example"]);
+
+        let saved = std::fs::read_to_string(&path).unwrap();
+        assert!(saved.contains("\"https://b.example\""));
+        assert!(!saved.contains("\"https://a.example\""));
+        assert!(!saved.contains("\"https://d.example\""));
+    }
+
+    #[tokio::test]
+    async fn test_status_table() {
+        let board = StatusBoard::new(&[
+            "https://a.example".to_string(),
+            "https://b.example".to_string(),
+        ]);
+        board.record(
+            &CheckRecord {
+                url: "https://b.example".to_string(),
+                agent: None,
+                checked_at: UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+                is_up: false,
+                response_time: Duration::from_millis(42),
+                consecutive_failures: 1,
+            },
+            SiteHealth::Unreachable,
+        );
+        let base = spawn_api(board, None).await;
+
+        let (sites, incidents) = fetch_status(&Client::new(), &format!("{base}/"))
+            .await
+            .unwrap();
+        let mut out = Vec::new();
+        write_status_table(&sites, &incidents, &mut out).unwrap();
+
+        let table = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("SITE               STATE        LAST CHECK"));
+        assert_eq!(
+            lines[1],
+            "https://a.example  PENDING      -                     -"
+        );
+        assert!(
+            lines[2]
+                .starts_with("https://b.example  UNREACHABLE  2023-11-14T22:13:20Z  failing for ")
+        );
+        assert!(lines[2].ends_with("not alerted yet"));
+    }
+}
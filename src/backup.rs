@@ -0,0 +1,267 @@
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use log::{info, warn};
+use std::{
+    fs::{self, File},
+    path::Path,
+};
+
+use crate::config::{Config, find_config};
+use crate::error::Error;
+use crate::incident::INCIDENTS_FILE_NAME;
+use crate::silence::silences_path;
+
+// Names of the entries in a backup archive
+const CONFIG_ENTRY: &str = "config.toml";
+const HISTORY_ENTRY: &str = "history.jsonl";
+const INCIDENTS_ENTRY: &str = "incidents.json";
+const SILENCES_ENTRY: &str = "silences.json";
+const CAPTURES_ENTRY: &str = "captures";
+
+/// Bundles the config file, the incidents, the silences, the captured
+/// responses and the check history into a gzipped tarball at `archive`.
+///
+/// The data files are only included when their sections are enabled and
+/// they exist. Run it while the monitor is stopped to get a consistent copy.
+///
+/// # Errors
+///
+/// Returns an error if the config directory cannot be found or a file
+/// cannot be read or written.
+pub fn backup(config: &Config, archive: &Path) -> Result<(), Error> {
+    write_archive(&find_config()?, config, archive)
+}
+
+/// Restores a backup made by [`backup`], replacing the config file and
+/// placing the data files at the paths the restored config sets.
+///
+/// The replaced config is kept next to it as `config.toml.bak`. Stop the
+/// monitor first, it would otherwise overwrite the restored state.
+///
+/// # Errors
+///
+/// Returns an error if the archive cannot be read, holds no config, or the
+/// restored config is invalid.
+pub fn restore(archive: &Path) -> Result<(), Error> {
+    unpack_archive(archive, &find_config()?)
+}
+
+fn write_archive(config_path: &Path, config: &Config, archive: &Path) -> Result<(), Error> {
+    let mut builder = tar::Builder::new(GzEncoder::new(
+        File::create(archive)?,
+        Compression::default(),
+    ));
+    builder.append_path_with_name(config_path, CONFIG_ENTRY)?;
+
+    if let Some(history) = &config.history {
+        let incidents = history.path.with_file_name(INCIDENTS_FILE_NAME);
+        for (path, entry) in [
+            (&history.path, HISTORY_ENTRY),
+            (&incidents, INCIDENTS_ENTRY),
+        ] {
+            if path.is_file() {
+                builder.append_path_with_name(path, entry)?;
+            }
+        }
+    }
+    let silences = silences_path(config)?;
+    if silences.is_file() {
+        builder.append_path_with_name(&silences, SILENCES_ENTRY)?;
+    }
+    if let Some(capture) = &config.capture
+        && capture.path.is_dir()
+    {
+        builder.append_dir_all(CAPTURES_ENTRY, &capture.path)?;
+    }
+
+    builder.into_inner()?.finish()?;
+    info!(
+        "Backed up {} to {}",
+        config_path.display(),
+        archive.display()
+    );
+    Ok(())
+}
+
+fn unpack_archive(archive: &Path, config_path: &Path) -> Result<(), Error> {
+    // Unpacked next to the config first, so nothing is replaced when the
+    // archive turns out to be unreadable
+    let staging = config_path.with_file_name("restore.tmp");
+    if staging.exists() {
+        fs::remove_dir_all(&staging)?;
+    }
+    let restored = tar::Archive::new(GzDecoder::new(File::open(archive)?))
+        .unpack(&staging)
+        .map_err(Error::from)
+        .and_then(|()| restore_staged(&staging, config_path));
+    if let Err(e) = fs::remove_dir_all(&staging) {
+        warn!("Failed to delete {}: {e}", staging.display());
+    }
+    restored
+}
+
+fn restore_staged(staging: &Path, config_path: &Path) -> Result<(), Error> {
+    let staged_config = staging.join(CONFIG_ENTRY);
+    if !staged_config.is_file() {
+        return Err(Error::Config(format!(
+            "The archive has no {CONFIG_ENTRY}, it is not a downdetector backup"
+        )));
+    }
+    // Loaded before anything is replaced, so an invalid config is never restored
+    let config = Config::load_from(&staged_config)?;
+
+    if config_path.exists() {
+        let kept = config_path.with_extension("toml.bak");
+        fs::copy(config_path, &kept)?;
+        info!("The replaced config is kept as {}", kept.display());
+    }
+    replace_file(&staged_config, config_path)?;
+
+    let staged_history = staging.join(HISTORY_ENTRY);
+    let staged_incidents = staging.join(INCIDENTS_ENTRY);
+    match &config.history {
+        Some(history) => {
+            let incidents = history.path.with_file_name(INCIDENTS_FILE_NAME);
+            for (staged, path) in [
+                (staged_history, &history.path),
+                (staged_incidents, &incidents),
+            ] {
+                if staged.is_file() {
+                    replace_file(&staged, path)?;
+                }
+            }
+        }
+        None if staged_history.exists() || staged_incidents.exists() => {
+            warn!("History is not enabled in the restored config, skipping history and incidents");
+        }
+        None => {}
+    }
+
+    let staged_silences = staging.join(SILENCES_ENTRY);
+    if staged_silences.is_file() {
+        replace_file(&staged_silences, &silences_path(&config)?)?;
+    }
+
+    let staged_captures = staging.join(CAPTURES_ENTRY);
+    if staged_captures.is_dir() {
+        match &config.capture {
+            Some(capture) => {
+                for entry in fs::read_dir(&staged_captures)? {
+                    let entry = entry?;
+                    replace_file(&entry.path(), &capture.path.join(entry.file_name()))?;
+                }
+            }
+            None => warn!("Capture is not enabled in the restored config, skipping captures"),
+        }
+    }
+    Ok(())
+}
+
+/// Replaces `to` with a copy of `from` through a temporary file next to it,
+/// so it is never left half written.
+fn replace_file(from: &Path, to: &Path) -> Result<(), Error> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut tmp_path = to.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    fs::copy(from, &tmp_path)?;
+    fs::rename(tmp_path, to)?;
+    info!("Restored {}", to.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    #[test]
+    fn test_backup_round_trip() {
+        let dir = temp_dir("backup");
+        let data = dir.join("data");
+        fs::create_dir_all(data.join("captures")).unwrap();
+
+        let config_path = dir.join("config.toml");
+        let content = format!(
+            "version = 2\n\n[history]\npath = {:?}\n\n[capture]\npath = {:?}\n",
+            data.join("history.jsonl"),
+            data.join("captures")
+        );
+        fs::write(&config_path, &content).unwrap();
+        fs::write(data.join("history.jsonl"), "{}\n").unwrap();
+        fs::write(data.join(INCIDENTS_FILE_NAME), "{\"last_id\":3}").unwrap();
+        fs::write(data.join("silences.json"), "{\"silences\":[]}").unwrap();
+        fs::write(data.join("captures/incident-3.txt"), "503").unwrap();
+
+        let archive = dir.join("backup.tar.gz");
+        let config = Config::load_from(&config_path).unwrap();
+        write_archive(&config_path, &config, &archive).unwrap();
+
+        // Moving to a new host: no data and another config
+        fs::remove_dir_all(&data).unwrap();
+        fs::write(&config_path, "version = 2\n").unwrap();
+        unpack_archive(&archive, &config_path).unwrap();
+
+        assert_eq!(fs::read_to_string(&config_path).unwrap(), content);
+        assert_eq!(
+            fs::read_to_string(dir.join("config.toml.bak")).unwrap(),
+            "version = 2\n"
+        );
+        assert_eq!(
+            fs::read_to_string(data.join("history.jsonl")).unwrap(),
+            "{}\n"
+        );
+        assert_eq!(
+            fs::read_to_string(data.join(INCIDENTS_FILE_NAME)).unwrap(),
+            "{\"last_id\":3}"
+        );
+        assert_eq!(
+            fs::read_to_string(data.join("silences.json")).unwrap(),
+            "{\"silences\":[]}"
+        );
+        assert_eq!(
+            fs::read_to_string(data.join("captures/incident-3.txt")).unwrap(),
+            "503"
+        );
+        assert!(!dir.join("restore.tmp").exists());
+    }
+
+    #[test]
+    fn test_restore_rejects_archive_without_config() {
+        let dir = temp_dir("backup-invalid");
+        let archive = dir.join("backup.tar.gz");
+        let mut builder = tar::Builder::new(GzEncoder::new(
+            File::create(&archive).unwrap(),
+            Compression::default(),
+        ));
+        builder.append_dir("captures", &dir).unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+
+        let config_path = dir.join("config.toml");
+        fs::write(&config_path, "version = 2\n").unwrap();
+        assert!(unpack_archive(&archive, &config_path).is_err());
+        assert_eq!(fs::read_to_string(&config_path).unwrap(), "version = 2\n");
+    }
+
+    #[test]
+    fn test_restore_keeps_config_when_archived_one_is_invalid() {
+        let dir = temp_dir("backup-invalid-config");
+        let archived = dir.join("archived.toml");
+        fs::write(&archived, "version = 2\n\n[server]\nbind = \"localhost\"\n").unwrap();
+        let archive = dir.join("backup.tar.gz");
+        let mut builder = tar::Builder::new(GzEncoder::new(
+            File::create(&archive).unwrap(),
+            Compression::default(),
+        ));
+        builder
+            .append_path_with_name(&archived, CONFIG_ENTRY)
+            .unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+
+        let config_path = dir.join("config.toml");
+        fs::write(&config_path, "version = 2\n").unwrap();
+        assert!(unpack_archive(&archive, &config_path).is_err());
+        assert_eq!(fs::read_to_string(&config_path).unwrap(), "version = 2\n");
+        assert!(!dir.join("config.toml.bak").exists());
+    }
+}
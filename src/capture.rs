@@ -0,0 +1,244 @@
+use log::warn;
+use reqwest::{Response, StatusCode, Version, header::HeaderMap};
+use std::{
+    fmt::Write,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use crate::config::CaptureOptions;
+use crate::error::Error;
+use crate::history::{format_timestamp, unix_millis};
+
+/// Prefix of the capture files, followed by the incident id.
+const CAPTURE_FILE_PREFIX: &str = "incident-";
+
+/// A failing response, with the start of its body.
+#[derive(Debug, Clone)]
+pub(crate) struct FailedResponse {
+    version: Version,
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Vec<u8>,
+    /// Whether the body went on past what was kept.
+    truncated: bool,
+    /// Why the body could not be read to the end, if it could not.
+    read_error: Option<String>,
+}
+
+impl FailedResponse {
+    /// Reads `response`, keeping at most `max_body_bytes` of its body.
+    pub(crate) async fn read(mut response: Response, max_body_bytes: usize) -> Self {
+        let mut captured = Self {
+            version: response.version(),
+            status: response.status(),
+            headers: response.headers().clone(),
+            body: Vec::new(),
+            truncated: false,
+            read_error: None,
+        };
+
+        loop {
+            match response.chunk().await {
+                Ok(Some(chunk)) => {
+                    let room = max_body_bytes - captured.body.len();
+                    if chunk.len() > room {
+                        captured.body.extend_from_slice(&chunk[..room]);
+                        captured.truncated = true;
+                        break;
+                    }
+                    captured.body.extend_from_slice(&chunk);
+                }
+                Ok(None) => break,
+                Err(e) => {
+                    captured.read_error = Some(e.to_string());
+                    break;
+                }
+            }
+        }
+        captured
+    }
+
+    /// Renders the response as text, like it went over the wire.
+    fn render(&self, site: &str, captured_at: SystemTime) -> String {
+        let mut text = format!(
+            "Site: {site}\nCaptured at: {}\n\n{:?} {}\n",
+            format_timestamp(unix_millis(captured_at)),
+            self.version,
+            self.status
+        );
+        for (name, value) in &self.headers {
+            let _ = writeln!(
+                text,
+                "{name}: {}",
+                String::from_utf8_lossy(value.as_bytes())
+            );
+        }
+        text.push('\n');
+        text.push_str(&String::from_utf8_lossy(&self.body));
+        if self.truncated {
+            let _ = write!(text, "\n[truncated after {} bytes]", self.body.len());
+        }
+        if let Some(e) = &self.read_error {
+            let _ = write!(text, "\n[body read failed: {e}]");
+        }
+        text.push('\n');
+        text
+    }
+}
+
+/// Directory of the failing responses captured for incidents.
+#[derive(Debug)]
+pub(crate) struct CaptureStore {
+    dir: PathBuf,
+    max_body_bytes: usize,
+    retention: Duration,
+    max_files: usize,
+}
+
+impl CaptureStore {
+    pub(crate) fn new(options: &CaptureOptions) -> Self {
+        Self {
+            dir: options.path.clone(),
+            max_body_bytes: usize::try_from(options.max_body_kb * 1024).unwrap_or(usize::MAX),
+            retention: Duration::from_hours(24 * options.retention_days),
+            max_files: options.max_files,
+        }
+    }
+
+    /// Body bytes kept from failing responses.
+    pub(crate) fn max_body_bytes(&self) -> usize {
+        self.max_body_bytes
+    }
+
+    /// Saves the `response` that opened the incident `incident_id` of
+    /// `site`, then deletes the captures past the retention limits.
+    pub(crate) async fn save(
+        &self,
+        incident_id: u64,
+        site: &str,
+        response: &FailedResponse,
+    ) -> Result<PathBuf, Error> {
+        tokio::fs::create_dir_all(&self.dir).await?;
+        let path = self
+            .dir
+            .join(format!("{CAPTURE_FILE_PREFIX}{incident_id}.txt"));
+        tokio::fs::write(&path, response.render(site, SystemTime::now())).await?;
+
+        if let Err(e) = self.prune().await {
+            warn!(
+                "Failed to delete old captures in {}: {e}",
+                self.dir.display()
+            );
+        }
+        Ok(path)
+    }
+
+    /// Deletes the captures older than the retention, then the oldest ones
+    /// past the maximum number of files.
+    async fn prune(&self) -> Result<(), Error> {
+        let mut captures = list_captures(&self.dir).await?;
+        captures.sort_by_key(|(_, modified)| std::cmp::Reverse(*modified));
+
+        let cutoff = SystemTime::now()
+            .checked_sub(self.retention)
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+        for (index, (path, modified)) in captures.iter().enumerate() {
+            if index >= self.max_files || *modified < cutoff {
+                tokio::fs::remove_file(path).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Capture files in `dir` with when they were last written.
+async fn list_captures(dir: &Path) -> Result<Vec<(PathBuf, SystemTime)>, Error> {
+    let mut captures = Vec::new();
+    let mut entries = tokio::fs::read_dir(dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name();
+        if !name.to_string_lossy().starts_with(CAPTURE_FILE_PREFIX) {
+            continue;
+        }
+        captures.push((entry.path(), entry.metadata().await?.modified()?));
+    }
+    Ok(captures)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+    use axum::{Router, http::header, routing::get};
+    use tokio::net::TcpListener;
+
+    fn temp_store(name: &str, max_files: usize) -> CaptureStore {
+        CaptureStore::new(&CaptureOptions {
+            path: temp_dir(&format!("captures-{name}")),
+            max_body_kb: 1,
+            retention_days: 30,
+            max_files,
+        })
+    }
+
+    async fn failing_response(body: String) -> Response {
+        let app = Router::new().route(
+            "/",
+            get(move || async move {
+                (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    [(header::RETRY_AFTER, "120")],
+                    body,
+                )
+            }),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        reqwest::get(format!("http://{addr}/")).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_capture_keeps_status_headers_and_body_start() {
+        let store = temp_store("render", 10);
+        let response = failing_response(format!("upstream timeout{}", "!".repeat(2000))).await;
+        let captured = FailedResponse::read(response, store.max_body_bytes()).await;
+        assert_eq!(captured.body.len(), 1024);
+        assert!(captured.truncated);
+
+        let path = store
+            .save(87, "https://a.example", &captured)
+            .await
+            .unwrap();
+        assert!(path.ends_with("incident-87.txt"));
+        let text = std::fs::read_to_string(path).unwrap();
+        assert!(text.starts_with("Site: https://a.example\nCaptured at: "));
+        assert!(text.contains("\n\nHTTP/1.1 503 Service Unavailable\n"));
+        assert!(text.contains("\nretry-after: 120\n"));
+        assert!(text.contains("\n\nupstream timeout!!!"));
+        assert!(text.ends_with("\n[truncated after 1024 bytes]\n"));
+    }
+
+    #[tokio::test]
+    async fn test_oldest_captures_are_deleted_past_max_files() {
+        let store = temp_store("prune", 2);
+        let captured = FailedResponse::read(failing_response("down".into()).await, 1024).await;
+
+        for incident_id in 1..=3 {
+            store
+                .save(incident_id, "https://a.example", &captured)
+                .await
+                .unwrap();
+            // Keeps modification times apart on coarse-grained filesystems
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+
+        let mut names: Vec<String> = std::fs::read_dir(&store.dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        assert_eq!(names, ["incident-2.txt", "incident-3.txt"]);
+    }
+}
@@ -1,6 +1,23 @@
 use crate::error::Error;
-use serde::Deserialize;
-use std::{fs, path::PathBuf};
+use crate::interactions;
+use crate::migrate;
+use crate::pinning;
+use crate::templates::{
+    DEGRADED_VARIABLES, DIGEST_VARIABLES, DOWN_VARIABLES, MessageTemplates, RECOVERY_VARIABLES,
+    Template,
+};
+use crate::worker::FAILED_SITE_RETRY_SECS;
+use ed25519_dalek::VerifyingKey;
+use jiff::{civil::Time, tz::TimeZone};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs, io,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+};
+use toml_edit::{Array, DocumentMut, Item, Value};
 use url::Url;
 
 const DEFAULT_CONFIG: &str = include_str!("../config.default.toml");
@@ -9,17 +26,77 @@ const DEFAULT_CONFIG: &str = include_str!("../config.default.toml");
 const DEFAULT_TIMEOUT_SECS: u64 = 30;
 const DEFAULT_CHECK_INTERVAL_SECS: u64 = 300;
 const DEFAULT_FAILURE_THRESHOLD: u64 = 5;
+const DEFAULT_STATSD_HOST: &str = "127.0.0.1";
+const DEFAULT_STATSD_PORT: u16 = 8125;
+const DEFAULT_STATSD_PREFIX: &str = "downdetector";
+const DEFAULT_HISTORY_RETENTION_DAYS: u64 = 30;
+const DEFAULT_SERVER_BIND: &str = "127.0.0.1:8080";
+const DEFAULT_AGENT_TIMEOUT_SECS: u64 = 900;
+const DEFAULT_SLO_WINDOW_DAYS: u64 = 30;
+const DEFAULT_SLO_BURN_RATE_ALERT: f64 = 14.4;
+const DEFAULT_TRACEROUTE_MAX_HOPS: u8 = 15;
+const DEFAULT_CAPTURE_MAX_BODY_KB: u64 = 16;
+const MAX_CAPTURE_BODY_KB: u64 = 1024;
+const DEFAULT_CAPTURE_RETENTION_DAYS: u64 = 30;
+const DEFAULT_CAPTURE_MAX_FILES: usize = 100;
+const DEFAULT_SITE_SOURCE_REFRESH_SECS: u64 = 300;
+const DEFAULT_DOCKER_SOCKET: &str = "/var/run/docker.sock";
+const DEFAULT_DOCKER_LABEL: &str = "downdetector.url";
+const DEFAULT_LATENCY_WINDOW_CHECKS: usize = 20;
+/// Lowest global check rate, one check every 1000 seconds.
+const MIN_REQUESTS_PER_SEC: f64 = 0.001;
+const MAX_LATENCY_WINDOW_CHECKS: usize = 1000;
+const MIN_SITE_SOURCE_REFRESH_SECS: u64 = 10;
+/// More hops than traceroute's own default of 30 are never needed on the internet.
+const MAX_TRACEROUTE_HOPS: u8 = 30;
 
 /// Configuration structure for the downtime detector application.
 ///
 /// This struct contains both the application configuration options
 /// and the list of sites to monitor.
 #[derive(Debug)]
+#[allow(clippy::struct_field_names)]
 pub struct Config {
     /// Application configuration options
     pub config: ConfigOptions,
     /// List of sites to monitor
     pub sites: SiteList,
+    /// Optional InfluxDB export of check results
+    pub influxdb: Option<InfluxDbOptions>,
+    /// Optional StatsD emission of check results
+    pub statsd: Option<StatsdOptions>,
+    /// Optional persistent history of check results
+    pub history: Option<HistoryOptions>,
+    /// Optional built-in HTTP server serving the status dashboard
+    pub server: Option<ServerOptions>,
+    /// Optional agent mode, pushing check results to a central instance
+    pub agent: Option<AgentOptions>,
+    /// Optional second opinion before alerting on a site that went down
+    pub confirmation: Option<ConfirmationOptions>,
+    /// SLO reporting settings, present when at least one site declares an SLO
+    pub slo: Option<SloOptions>,
+    /// Optional daily window during which non-critical alerts are held
+    pub quiet_hours: Option<QuietHoursOptions>,
+    /// Where the alerts of each severity are sent
+    pub routing: RoutingOptions,
+    /// Optional Discord thread opened on the first alert of every incident
+    pub incident_threads: Option<IncidentThreadOptions>,
+    /// Phrasing of the notification messages
+    pub templates: MessageTemplates,
+    /// Optional network diagnostics attached to the first DOWN alert of an incident
+    pub diagnostics: Option<DiagnosticsOptions>,
+    /// Optional capture of the failing response of every incident
+    pub capture: Option<CaptureOptions>,
+    /// Optional discovery of the sites to monitor from Kubernetes Ingresses (`k8s` feature)
+    pub kubernetes: Option<KubernetesOptions>,
+    /// Optional discovery of the sites to monitor from Docker container labels
+    pub docker: Option<DockerOptions>,
+    /// Optional pacing of the checks, globally and per host
+    pub rate_limit: Option<RateLimitOptions>,
+    /// Optional summary of the sites down after each cycle, replacing their DOWN alerts
+    pub cycle_summary: Option<CycleSummaryOptions>,
+    /// Response time percentile alerting
+    pub latency: LatencyOptions,
 }
 
 /// Application configuration options.
@@ -29,15 +106,30 @@ pub struct Config {
 /// and Discord notification settings.
 #[derive(Debug)]
 pub struct ConfigOptions {
-    /// HTTP request timeout in seconds.
+    /// HTTP request timeout in seconds, covering the whole check including redirects.
     /// Must be greater than 0.
     pub timeout_secs: u64,
+    /// Longest time in seconds spent resolving and connecting to a site,
+    /// bounded by `timeout_secs` only when unset.
+    /// Must be greater than 0 and at most `timeout_secs`.
+    pub connect_timeout_secs: Option<u64>,
+    /// Longest time in seconds waited for each read of a response,
+    /// bounded by `timeout_secs` only when unset.
+    /// Must be greater than 0 and at most `timeout_secs`.
+    pub read_timeout_secs: Option<u64>,
     /// Interval between site checks in seconds.
     /// Must be between 1 and 86399 (inclusive).
     pub check_interval_secs: u64,
     /// Number of consecutive failed checks required before a site is considered down.
     /// Must be greater than 0.
     pub failure_threshold: u64,
+    /// Whether the monitor starts in low-bandwidth mode.
+    /// Uses HEAD requests where possible, stretches check intervals and caps body reads.
+    /// Can be toggled at runtime by sending `SIGUSR1` to the process (Unix only).
+    pub low_bandwidth: bool,
+    /// Whether the monitor runs with a constrained memory budget.
+    /// Uses a single-threaded runtime, caps buffered check results and skips response decompression.
+    pub low_memory: bool,
     /// Discord webhook URL for sending notifications.
     /// Must be a valid Discord webhook URL starting with `https://discord.com/api/webhooks/`.
     /// Can also be set via the `WEBHOOK_URL` environment variable.
@@ -45,18 +137,655 @@ pub struct ConfigOptions {
     /// Discord user ID for mentions in notifications.
     /// Can also be set via the `DISCORD_ID` environment variable.
     pub discord_id: Option<u64>,
+    /// HTTP statuses counted as a site being up, for sites without their own
+    /// `expected_status`. Only 2xx statuses by default.
+    pub success_status_classes: SuccessStatuses,
+    /// Longest delay in seconds between the checks of a DOWN site.
+    /// When set, the 10-second retry delay doubles with every failed check
+    /// past the failure threshold until it reaches this value.
+    /// Must be between 10 and 86399 (inclusive).
+    pub down_backoff_max_secs: Option<u64>,
+    /// Language of the built-in notification messages.
+    pub language: Language,
+    /// Whether the notification channels are checked at startup, and what
+    /// happens when one is broken.
+    pub notification_check: NotificationCheck,
+    /// Whether URLs sharing a host are only requested once a connection to
+    /// the host succeeds, failing them all without a request otherwise.
+    pub group_by_host: bool,
+}
+
+impl ConfigOptions {
+    /// Timeouts of the checks of sites without their own.
+    pub(crate) fn timeouts(&self) -> Timeouts {
+        Timeouts {
+            total: self.timeout_secs,
+            connect: self.connect_timeout_secs,
+            read: self.read_timeout_secs,
+        }
+    }
+}
+
+/// Timeouts of a site check, in seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Timeouts {
+    /// Whole check, including redirects.
+    pub total: u64,
+    /// Resolving and connecting, bounded by `total` only when `None`.
+    pub connect: Option<u64>,
+    /// Each read of the response, bounded by `total` only when `None`.
+    pub read: Option<u64>,
 }
 
 /// List of sites to monitor.
 ///
 /// Contains a vector of URLs that will be checked periodically
-/// for availability.
-#[derive(Debug, Deserialize, Default)]
+/// for availability. Each entry is either a plain URL or an inline table
+/// such as `{ url = "https://example.com", slo = 99.9 }` carrying per-site settings.
+#[derive(Debug, Default)]
 pub struct SiteList {
     /// URLs to monitor for downtime.
     /// Each URL must be valid and parseable.
-    #[serde(default)]
     pub urls: Vec<String>,
+    /// Settings of the sites declared as tables, keyed by URL.
+    pub options: HashMap<String, SiteOptions>,
+    /// Remote list of sites monitored along with `urls`.
+    pub source: Option<SiteSource>,
+}
+
+/// Remote endpoint serving a JSON list of sites to monitor, maintained by
+/// another system.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SiteSource {
+    /// URL of the list, either an array of URLs or an object with a `urls` array.
+    pub url: String,
+    /// Seconds between two fetches of the list.
+    pub refresh_secs: u64,
+}
+
+/// Settings of a single site.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SiteOptions {
+    /// Availability objective in percent over the SLO window, e.g. `99.9`.
+    /// Must be between 0 and 100 (exclusive).
+    pub slo: Option<f64>,
+    /// How urgent alerts for this site are, which decides how they are routed.
+    /// `critical = true` is accepted as a shorthand for `severity = "critical"`.
+    pub severity: Severity,
+    /// SHA-256 fingerprint of the leaf certificate the site must present,
+    /// HTTPS sites only.
+    pub pinned_cert_sha256: Option<[u8; 32]>,
+    /// HTTP statuses counted as the site being up, overriding the global
+    /// `success_status_classes`.
+    pub expected_status: Option<SuccessStatuses>,
+    /// Check timeout in seconds, overriding the global `timeout_secs`.
+    pub timeout_secs: Option<u64>,
+    /// Connect timeout in seconds, overriding the global `connect_timeout_secs`.
+    pub connect_timeout_secs: Option<u64>,
+    /// Read timeout in seconds, overriding the global `read_timeout_secs`.
+    pub read_timeout_secs: Option<u64>,
+    /// Labels grouping the site with others, e.g. to silence them together.
+    pub tags: Vec<String>,
+    /// Discord user mentioned in the alerts of the site, instead of the one
+    /// of its severity route.
+    pub discord_id: Option<u64>,
+    /// Discord role mentioned in the alerts of the site, instead of the one
+    /// of its severity route.
+    pub role_id: Option<u64>,
+    /// Expected size of the response body, not checked when `None`.
+    pub body_size: Option<BodySizeBounds>,
+    /// p95 response time in milliseconds above which the site is alerted on,
+    /// overriding the `[latency]` `p95_ms`.
+    pub latency_p95_ms: Option<u64>,
+}
+
+/// Size bounds of the response body of a site, catching e.g. a `200` with an
+/// empty body served by a broken CDN.
+///
+/// Bodies are not read in low-bandwidth mode, so their size is not checked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BodySizeBounds {
+    /// Fewest bytes the body may have, none when `None`.
+    pub min_bytes: Option<u64>,
+    /// Most bytes the body may have, unlimited when `None`.
+    /// Must not be less than `min_bytes`.
+    pub max_bytes: Option<u64>,
+    /// What a body outside the bounds counts as.
+    pub mismatch: BodySizeMismatch,
+}
+
+impl BodySizeBounds {
+    /// Bytes of the body worth reading: one past the maximum shows it is
+    /// exceeded, and reaching the minimum is enough without one.
+    pub(crate) fn read_limit(self) -> u64 {
+        self.max_bytes
+            .map_or(self.min_bytes.unwrap_or(0), |max| max.saturating_add(1))
+    }
+}
+
+/// What a response body outside the size bounds of its site counts as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BodySizeMismatch {
+    /// A failed check, the site goes DOWN once failing long enough.
+    #[default]
+    Down,
+    /// The site stays up, and is alerted on once until the size is back in bounds.
+    Degraded,
+}
+
+impl SiteList {
+    /// HTTP statuses counted as `url` being up, its `expected_status` or
+    /// else `default`.
+    pub(crate) fn success_statuses<'a>(
+        &'a self,
+        url: &str,
+        default: &'a SuccessStatuses,
+    ) -> &'a SuccessStatuses {
+        self.options
+            .get(url)
+            .and_then(|options| options.expected_status.as_ref())
+            .unwrap_or(default)
+    }
+
+    /// p95 response time threshold of `url` in milliseconds, its own or else `default`.
+    pub(crate) fn latency_p95_ms(&self, url: &str, default: Option<u64>) -> Option<u64> {
+        self.options
+            .get(url)
+            .and_then(|options| options.latency_p95_ms)
+            .or(default)
+    }
+
+    /// Whether any site is tagged `tag`.
+    pub(crate) fn has_tag(&self, tag: &str) -> bool {
+        self.options
+            .values()
+            .any(|options| options.tags.iter().any(|site_tag| site_tag == tag))
+    }
+
+    /// Timeouts of the checks of `url`, its own or else the ones of `options`.
+    pub(crate) fn timeouts(&self, url: &str, options: &ConfigOptions) -> Timeouts {
+        let Some(site) = self.options.get(url) else {
+            return options.timeouts();
+        };
+        Timeouts {
+            total: site.timeout_secs.unwrap_or(options.timeout_secs),
+            connect: site.connect_timeout_secs.or(options.connect_timeout_secs),
+            read: site.read_timeout_secs.or(options.read_timeout_secs),
+        }
+    }
+}
+
+/// HTTP statuses counted as a site being up, written as classes such as
+/// `"2xx"` and single codes such as `301`. Only successful (2xx) statuses by default.
+///
+/// Redirects are followed unless their status is accepted itself.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "RawStatuses", into = "Vec<String>")]
+pub struct SuccessStatuses(Vec<StatusPattern>);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StatusPattern {
+    /// Every status starting with this digit.
+    Class(u16),
+    Code(u16),
+}
+
+impl SuccessStatuses {
+    /// Whether `status` counts as the site being up.
+    pub fn matches(&self, status: u16) -> bool {
+        self.0.iter().any(|pattern| match *pattern {
+            StatusPattern::Class(class) => status / 100 == class,
+            StatusPattern::Code(code) => status == code,
+        })
+    }
+}
+
+impl Default for SuccessStatuses {
+    fn default() -> Self {
+        Self(vec![StatusPattern::Class(2)])
+    }
+}
+
+impl TryFrom<RawStatuses> for SuccessStatuses {
+    type Error = String;
+
+    fn try_from(raw: RawStatuses) -> Result<Self, String> {
+        let raw = match raw {
+            RawStatuses::One(status) => vec![status],
+            RawStatuses::Many(statuses) => statuses,
+        };
+        if raw.is_empty() {
+            return Err("at least one status must be accepted".into());
+        }
+
+        raw.into_iter()
+            .map(|status| {
+                let pattern = match &status {
+                    RawStatus::Code(code) => Some(StatusPattern::Code(*code)),
+                    RawStatus::Pattern(pattern) => {
+                        let pattern = pattern.trim().to_ascii_lowercase();
+                        match pattern.strip_suffix("xx") {
+                            Some(class) => class.parse().ok().map(StatusPattern::Class),
+                            None => pattern.parse().ok().map(StatusPattern::Code),
+                        }
+                    }
+                };
+                pattern
+                    .filter(|pattern| match *pattern {
+                        StatusPattern::Class(class) => (1..=5).contains(&class),
+                        StatusPattern::Code(code) => (100..600).contains(&code),
+                    })
+                    .ok_or_else(|| {
+                        format!("{status} is not an HTTP status code or class such as \"2xx\"")
+                    })
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+impl From<SuccessStatuses> for Vec<String> {
+    fn from(statuses: SuccessStatuses) -> Self {
+        statuses
+            .0
+            .into_iter()
+            .map(|pattern| match pattern {
+                StatusPattern::Class(class) => format!("{class}xx"),
+                StatusPattern::Code(code) => code.to_string(),
+            })
+            .collect()
+    }
+}
+
+/// Accepted statuses as written in the config, a single one or a list.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum RawStatuses {
+    One(RawStatus),
+    Many(Vec<RawStatus>),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum RawStatus {
+    Code(u16),
+    Pattern(String),
+}
+
+impl std::fmt::Display for RawStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Code(code) => write!(f, "{code}"),
+            Self::Pattern(pattern) => write!(f, "{pattern:?}"),
+        }
+    }
+}
+
+/// Urgency of the alerts of a site.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Alerts right away, even during quiet hours.
+    Critical,
+    /// Default severity of every site.
+    #[default]
+    Warning,
+    Info,
+}
+
+/// Language of the built-in notification messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    #[default]
+    En,
+    Fr,
+    De,
+    Es,
+}
+
+/// What the startup check of the notification channels does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationCheck {
+    /// Channels are not checked.
+    #[default]
+    Off,
+    /// Broken channels are logged, the monitor starts anyway.
+    Warn,
+    /// The monitor refuses to start while a channel is broken.
+    Fail,
+}
+
+impl Severity {
+    /// Name of the severity, as written in the config.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Critical => "critical",
+            Self::Warning => "warning",
+            Self::Info => "info",
+        }
+    }
+}
+
+/// Where and how the alerts of each severity are sent.
+///
+/// Every severity goes to the `[config]` webhook and mentions the `[config]`
+/// Discord user unless its `[routing.<severity>]` table says otherwise.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RoutingOptions {
+    /// Route of the alerts of critical sites.
+    pub critical: RouteOptions,
+    /// Route of the alerts of warning sites, also used for quiet hours summaries.
+    pub warning: RouteOptions,
+    /// Route of the alerts of info sites.
+    pub info: RouteOptions,
+}
+
+impl RoutingOptions {
+    /// Route of alerts of the given severity.
+    pub fn route(&self, severity: Severity) -> &RouteOptions {
+        match severity {
+            Severity::Critical => &self.critical,
+            Severity::Warning => &self.warning,
+            Severity::Info => &self.info,
+        }
+    }
+}
+
+/// Delivery of the alerts of one severity.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RouteOptions {
+    /// Discord webhook the alerts are posted to, no alert is sent when `None`.
+    pub webhook_url: Option<String>,
+    /// Discord user mentioned in the alerts.
+    pub discord_id: Option<u64>,
+    /// Discord role mentioned in the alerts, e.g. the on-call rotation.
+    pub role_id: Option<u64>,
+    /// Whether alerts are posted without triggering push and desktop notifications.
+    pub silent: bool,
+}
+
+/// SLO reporting options.
+///
+/// Compliance and error budgets are computed from the check history, so
+/// declaring an SLO on a site requires the `[history]` section.
+#[derive(Debug, Clone)]
+pub struct SloOptions {
+    /// Rolling window SLOs are measured over, in days.
+    /// Must be greater than 0 and fit within the history retention.
+    pub window_days: u64,
+    /// Burn rate over the last hour from which an alert is sent, e.g. `14.4`
+    /// means the whole window's error budget would be gone in about two days.
+    /// Must be greater than 0.
+    pub burn_rate_alert: f64,
+}
+
+/// InfluxDB export options.
+///
+/// When present, every check result is written to InfluxDB in line protocol,
+/// batched once per monitoring cycle.
+#[derive(Debug, Clone)]
+pub struct InfluxDbOptions {
+    /// Base URL of the InfluxDB server, e.g. `http://localhost:8086`.
+    pub url: Url,
+    /// Organization owning the bucket.
+    pub org: String,
+    /// Bucket the check results are written to.
+    pub bucket: String,
+    /// API token with write access to the bucket.
+    /// Can also be set via the `INFLUXDB_TOKEN` environment variable.
+    pub token: String,
+}
+
+/// StatsD emission options.
+///
+/// When present, per-check gauges and timings are sent over UDP to a StatsD
+/// compatible agent (Telegraf, Datadog agent, statsd, ...).
+#[derive(Debug, Clone)]
+pub struct StatsdOptions {
+    /// Hostname or IP address of the agent.
+    pub host: String,
+    /// UDP port of the agent.
+    pub port: u16,
+    /// Prefix prepended to every metric name.
+    pub prefix: String,
+    /// How the monitored site is attached to each metric.
+    pub tag_format: StatsdTagFormat,
+}
+
+/// Tagging dialect used for StatsD metrics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StatsdTagFormat {
+    /// Plain StatsD, the site is encoded in the metric name.
+    #[default]
+    None,
+    /// Datadog `|#site:value` tags.
+    DogStatsd,
+    /// Telegraf/InfluxDB `metric,site=value` tags.
+    Influxdb,
+    /// Graphite `metric;site=value` tags.
+    Graphite,
+}
+
+/// Check history persistence options.
+///
+/// When present, every check result is appended to a JSON lines file so it
+/// can be exported or analyzed later.
+#[derive(Debug, Clone)]
+pub struct HistoryOptions {
+    /// History file location.
+    /// Defaults to `history.jsonl` in the platform data directory
+    /// (e.g. `~/.local/share/downdetector/history.jsonl` on Linux).
+    pub path: PathBuf,
+    /// Number of days check results are kept for.
+    /// Must be greater than 0.
+    pub retention_days: u64,
+}
+
+/// Built-in HTTP server options.
+///
+/// When present, a status dashboard is served over HTTP. Uptime history is
+/// only shown when `[history]` is enabled as well.
+#[derive(Debug, Clone)]
+pub struct ServerOptions {
+    /// Address and port the server listens on, e.g. `0.0.0.0:8080`.
+    pub bind: SocketAddr,
+    /// Bearer token required to add or remove sites through the API.
+    /// Site management is disabled when unset.
+    /// Can also be set via the `API_TOKEN` environment variable.
+    pub api_token: Option<String>,
+    /// Bearer token agents must present to push their check results.
+    /// The instance only acts as a central instance when set.
+    /// Can also be set via the `AGENT_TOKEN` environment variable.
+    pub agent_token: Option<String>,
+    /// Seconds without a report after which the sites of an agent are marked
+    /// unknown and an alert is sent. Must be greater than 0.
+    pub agent_timeout_secs: u64,
+    /// Bearer token other instances must present to use this one as a
+    /// confirmation probe. The probe endpoint is disabled when unset.
+    /// Can also be set via the `PROBE_TOKEN` environment variable.
+    pub probe_token: Option<String>,
+    /// Public key of the Discord application whose Acknowledge buttons are
+    /// clicked, verifying the interactions Discord sends. DOWN alerts carry
+    /// no button when unset.
+    /// Can also be set via the `DISCORD_PUBLIC_KEY` environment variable.
+    pub discord_public_key: Option<VerifyingKey>,
+}
+
+/// Agent mode options.
+///
+/// When present, check results are pushed to a central downdetector instance
+/// which tracks the site states and sends every notification. The agent
+/// itself never notifies.
+#[derive(Debug, Clone)]
+pub struct AgentOptions {
+    /// Base URL of the central instance's server, e.g. `https://downdetector.example.com`.
+    pub central_url: Url,
+    /// Name identifying this agent on the central instance, e.g. `vpc-eu-west`.
+    pub name: String,
+    /// Bearer token matching the central instance's `agent_token`.
+    /// Can also be set via the `AGENT_TOKEN` environment variable.
+    pub token: String,
+}
+
+/// Quiet hours options.
+///
+/// Between `start` and `end` every day, alerts for sites not marked
+/// `critical` are held and delivered as a single summary once quiet hours end.
+#[derive(Debug, Clone)]
+pub struct QuietHoursOptions {
+    /// Local time quiet hours start at, e.g. `23:00`.
+    pub start: Time,
+    /// Local time quiet hours end at, e.g. `07:00`. May be earlier than
+    /// `start` for quiet hours spanning midnight.
+    pub end: Time,
+    /// Time zone `start` and `end` are expressed in, e.g. `Europe/Paris`.
+    /// Defaults to the system time zone.
+    pub timezone: TimeZone,
+}
+
+/// Incident thread options.
+///
+/// When present, a thread is started from the first DOWN alert of every
+/// incident. Its updates and recovery are posted in the thread, so the
+/// channel keeps a single message per incident.
+#[derive(Debug, Clone)]
+pub struct IncidentThreadOptions {
+    /// Token of a Discord bot allowed to create public threads in the alert
+    /// channel, webhooks cannot start threads from their own messages.
+    /// Can also be set via the `DISCORD_BOT_TOKEN` environment variable.
+    pub bot_token: String,
+}
+
+/// Network diagnostics options.
+///
+/// When present, the DNS resolution and TCP connection to a site are timed
+/// when it goes down, and the summary is attached to its DOWN alert.
+#[derive(Debug, Clone)]
+pub struct DiagnosticsOptions {
+    /// Whether `traceroute` is run too, when installed on the host.
+    pub traceroute: bool,
+    /// Maximum number of hops probed by traceroute.
+    /// Must be between 1 and 30 (inclusive).
+    pub traceroute_max_hops: u8,
+}
+
+/// Check rate limiting options.
+///
+/// When present, checks wait their turn so monitoring many URLs of one host
+/// does not trip its rate limits or look like an attack.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimitOptions {
+    /// Most requests started per second across every site, unlimited when `None`.
+    /// Must be at least 0.001.
+    pub requests_per_sec: Option<f64>,
+    /// Least time in milliseconds between the start of two checks of the
+    /// same host, none when `None`.
+    /// Must be greater than 0.
+    pub per_host_delay_ms: Option<u64>,
+}
+
+/// Response time percentile alerting options.
+///
+/// A site is alerted on once the 95th percentile of its response times over
+/// its last successful checks exceeds its threshold, catching sustained
+/// slowness that single checks miss.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LatencyOptions {
+    /// p95 response time in milliseconds above which every site is alerted
+    /// on, unless it sets its own `latency_p95_ms`. Only sites setting
+    /// their own are alerted on when `None`.
+    /// Must be greater than 0.
+    pub p95_ms: Option<u64>,
+    /// Number of last successful checks the percentile is computed over.
+    /// Must be between 1 and 1000.
+    pub window_checks: usize,
+}
+
+impl Default for LatencyOptions {
+    fn default() -> Self {
+        Self {
+            p95_ms: None,
+            window_checks: DEFAULT_LATENCY_WINDOW_CHECKS,
+        }
+    }
+}
+
+/// End-of-cycle summary options.
+///
+/// When present, the covered sites are not alerted on one by one: after each
+/// check cycle, a single message lists the ones that are down, so a
+/// widespread outage does not flood the channel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CycleSummaryOptions {
+    /// Tags summarized in a message of their own, covering the sites with
+    /// one of them. Every site is covered by a single summary when empty.
+    pub tags: Vec<String>,
+}
+
+/// Failing response capture options.
+///
+/// When present, the status, headers and start of the body of the response
+/// that made a site go DOWN are saved to one file per incident.
+#[derive(Debug, Clone)]
+pub struct CaptureOptions {
+    /// Directory the captures are written to.
+    /// Defaults to `captures` in the platform data directory
+    /// (e.g. `~/.local/share/downdetector/captures` on Linux).
+    pub path: PathBuf,
+    /// Kilobytes of the response body kept, the rest is dropped.
+    /// Must be between 1 and 1024 (inclusive).
+    pub max_body_kb: u64,
+    /// Number of days captures are kept for.
+    /// Must be greater than 0.
+    pub retention_days: u64,
+    /// Number of captures kept, the oldest are deleted first.
+    /// Must be greater than 0.
+    pub max_files: usize,
+}
+
+/// Kubernetes Ingress discovery options, available with the `k8s` feature.
+///
+/// When present, the hosts of the Ingresses of the cluster the monitor runs
+/// in are monitored, and kept in sync as Ingresses come and go.
+#[derive(Debug, Clone)]
+pub struct KubernetesOptions {
+    /// Namespace the Ingresses are watched in, every namespace when `None`.
+    pub namespace: Option<String>,
+    /// Annotation an Ingress must set to `"true"` to be monitored, e.g.
+    /// `downdetector/enable`. Every Ingress is monitored when `None`.
+    pub annotation: Option<String>,
+}
+
+/// Docker container discovery options, available on Unix.
+///
+/// When present, the containers of the local Docker daemon setting `label`
+/// are monitored at the URL it holds, and kept in sync as they start and stop.
+#[derive(Debug, Clone)]
+pub struct DockerOptions {
+    /// Path of the Docker daemon socket.
+    pub socket: PathBuf,
+    /// Label holding the URL to monitor, e.g. `downdetector.url`.
+    pub label: String,
+}
+
+/// Down confirmation options.
+///
+/// When present, a site about to trigger a DOWN alert is checked again from
+/// another vantage point first. The alert is only sent if no vantage point
+/// sees the site up, so a network hiccup at the monitor host does not page anyone.
+#[derive(Debug, Clone)]
+pub struct ConfirmationOptions {
+    /// HTTP(S) proxy the site is checked through again, e.g. `http://proxy.internal:3128`.
+    pub proxy: Option<Url>,
+    /// Base URL of another downdetector instance checking the site on our behalf.
+    pub probe_url: Option<Url>,
+    /// Bearer token matching the probe instance's `probe_token`.
+    /// Can also be set via the `PROBE_TOKEN` environment variable.
+    pub probe_token: Option<String>,
 }
 
 impl Config {
@@ -67,6 +796,8 @@ impl Config {
     /// - Windows: `%APPDATA%\downdetector\config.toml`
     ///
     /// If the config file doesn't exist, a default one will be created.
+    /// A file written for an older config version is upgraded in place,
+    /// keeping the original as a backup.
     ///
     /// # Errors
     ///
@@ -74,6 +805,7 @@ impl Config {
     /// - The config directory cannot be determined
     /// - The config file cannot be read or created
     /// - The TOML content is invalid
+    /// - The file was written for a newer config version
     /// - Any validation fails (invalid URLs, out-of-range values, etc.)
     ///
     /// # Examples
@@ -83,8 +815,13 @@ impl Config {
     /// println!("Monitoring {} sites", config.sites.urls.len());
     /// ```
     pub fn load() -> Result<Self, Error> {
-        let path = find_config()?;
-        let content = fs::read_to_string(path)?;
+        Self::load_from(&find_config()?)
+    }
+
+    /// Loads the config file at `path`, upgrading it first when it was
+    /// written for an older version.
+    pub(crate) fn load_from(path: &Path) -> Result<Self, Error> {
+        let content = migrate::migrate_file(path)?;
         let raw: RawConfig = toml::from_str(&content)?;
         raw.try_into()
     }
@@ -95,197 +832,2225 @@ struct RawConfig {
     #[serde(default)]
     config: RawConfigOptions,
     #[serde(default)]
-    sites: SiteList,
+    sites: RawSiteList,
+    influxdb: Option<RawInfluxDbOptions>,
+    statsd: Option<RawStatsdOptions>,
+    history: Option<RawHistoryOptions>,
+    server: Option<RawServerOptions>,
+    agent: Option<RawAgentOptions>,
+    confirmation: Option<RawConfirmationOptions>,
+    slo: Option<RawSloOptions>,
+    quiet_hours: Option<RawQuietHoursOptions>,
+    #[serde(default)]
+    routing: HashMap<Severity, RawRouteOptions>,
+    incident_threads: Option<RawIncidentThreadOptions>,
+    #[serde(default)]
+    templates: RawTemplates,
+    diagnostics: Option<RawDiagnosticsOptions>,
+    capture: Option<RawCaptureOptions>,
+    kubernetes: Option<RawKubernetesOptions>,
+    docker: Option<RawDockerOptions>,
+    rate_limit: Option<RawRateLimitOptions>,
+    cycle_summary: Option<RawCycleSummaryOptions>,
+    #[serde(default)]
+    latency: RawLatencyOptions,
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(default)]
-struct RawConfigOptions {
-    timeout_secs: u64,
-    check_interval_secs: u64,
-    failure_threshold: u64,
-    webhook_url: Option<String>,
-    discord_id: Option<u64>,
+struct RawSiteList {
+    /// Parsed one by one, so a non-strict list skips the malformed entries.
+    urls: Vec<toml::Value>,
+    strict: bool,
+    source_url: Option<String>,
+    source_refresh_secs: u64,
 }
 
-// Implement Default for RawConfigOptions
-impl Default for RawConfigOptions {
+impl Default for RawSiteList {
     fn default() -> Self {
         Self {
-            timeout_secs: DEFAULT_TIMEOUT_SECS,
-            check_interval_secs: DEFAULT_CHECK_INTERVAL_SECS,
-            failure_threshold: DEFAULT_FAILURE_THRESHOLD,
-            webhook_url: None,
-            discord_id: None,
+            urls: Vec::new(),
+            strict: true,
+            source_url: None,
+            source_refresh_secs: DEFAULT_SITE_SOURCE_REFRESH_SECS,
         }
     }
 }
 
-// Builder pattern for validation
-impl Config {
-    fn validate_timeout(timeout_secs: u64) -> Result<u64, Error> {
-        if timeout_secs == 0 {
-            return Err(Error::Config("timeout_secs must be > 0".into()));
-        }
-        Ok(timeout_secs)
-    }
+#[derive(Debug)]
+enum RawSite {
+    Url(String),
+    Table(Box<RawSiteOptions>),
+}
 
-    fn validate_check_interval(check_interval_secs: u64) -> Result<u64, Error> {
-        if !(1..86400).contains(&check_interval_secs) {
-            return Err(Error::Config(
-                "check_interval_secs must be > 0 and < 86400".into(),
-            ));
-        }
-        Ok(check_interval_secs)
-    }
+impl TryFrom<toml::Value> for RawSite {
+    type Error = Error;
 
-    fn validate_failure_threshold(failure_threshold: u64) -> Result<u64, Error> {
-        if failure_threshold == 0 {
-            return Err(Error::Config("failure_threshold must be > 0".into()));
-        }
-        Ok(failure_threshold)
+    fn try_from(value: toml::Value) -> Result<Self, Self::Error> {
+        Ok(match value {
+            toml::Value::String(url) => RawSite::Url(url),
+            table => RawSite::Table(Box::new(table.try_into()?)),
+        })
     }
+}
 
-    fn validate_webhook_url(raw_url: Option<String>) -> Result<Option<String>, Error> {
-        let webhook_url = match dotenvy::var("WEBHOOK_URL").ok().or(raw_url) {
-            Some(url) if !url.trim().is_empty() => url,
-            _ => return Ok(None),
-        };
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawSiteOptions {
+    url: String,
+    slo: Option<f64>,
+    severity: Option<Severity>,
+    #[serde(default)]
+    critical: bool,
+    pinned_cert_sha256: Option<String>,
+    expected_status: Option<RawStatuses>,
+    timeout_secs: Option<u64>,
+    connect_timeout_secs: Option<u64>,
+    read_timeout_secs: Option<u64>,
+    #[serde(default)]
+    tags: Vec<String>,
+    discord_id: Option<u64>,
+    role_id: Option<u64>,
+    min_body_bytes: Option<u64>,
+    max_body_bytes: Option<u64>,
+    body_size_mismatch: Option<BodySizeMismatch>,
+    latency_p95_ms: Option<u64>,
+}
 
-        let parsed_url = Url::parse(&webhook_url)
-            .map_err(|_| Error::Config("Invalid webhook URL format".into()))?;
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawRouteOptions {
+    webhook_url: Option<String>,
+    #[serde(default = "default_true")]
+    mention: bool,
+    discord_id: Option<u64>,
+    role_id: Option<u64>,
+    #[serde(default)]
+    silent: bool,
+}
 
-        if parsed_url.scheme() != "https"
-            || parsed_url.host_str() != Some("discord.com")
-            || !parsed_url.path().starts_with("/api/webhooks/")
-        {
-            return Err(Error::Config(
-            "Webhook URL must be a valid Discord webhook starting with https://discord.com/api/webhooks/".into()
-        ));
-        }
+fn default_true() -> bool {
+    true
+}
 
-        // Extract webhook ID from the path
-        let path_parts: Vec<&str> = parsed_url
-            .path()
-            .strip_prefix("/api/webhooks/")
-            .unwrap_or("")
-            .split('/')
-            .collect();
+#[derive(Debug, Deserialize)]
+struct RawIncidentThreadOptions {
+    bot_token: Option<String>,
+}
 
-        // Ensure we have at least webhook_id/webhook_token
-        if path_parts.len() < 2 || path_parts[0].is_empty() || path_parts[1].is_empty() {
-            return Err(Error::Config(
-                "Webhook URL must contain both webhook ID and token".into(),
-            ));
-        }
+#[derive(Debug, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct RawTemplates {
+    down: Option<String>,
+    recovery: Option<String>,
+    digest: Option<String>,
+    degraded: Option<String>,
+}
 
-        // Validate webhook ID is a valid snowflake (parsable as u64)
-        let webhook_id = path_parts[0];
-        webhook_id.parse::<u64>().map_err(|_| {
-            Error::Config(format!(
-                "Invalid webhook ID '{webhook_id}': must be a valid Discord snowflake (numeric ID)"
-            ))
-        })?;
+#[derive(Debug, Deserialize)]
+struct RawQuietHoursOptions {
+    start: String,
+    end: String,
+    timezone: Option<String>,
+}
 
-        Ok(Some(webhook_url))
-    }
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawKubernetesOptions {
+    namespace: Option<String>,
+    annotation: Option<String>,
+}
 
-    fn validate_discord_id(raw_id: Option<u64>) -> Option<u64> {
-        dotenvy::var("DISCORD_ID")
-            .ok()
-            .and_then(|v| v.parse().ok())
-            .or(raw_id)
-    }
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawLatencyOptions {
+    p95_ms: Option<u64>,
+    window_checks: usize,
+}
 
-    fn validate_urls(urls: &[String]) -> Result<(), Error> {
-        for url in urls {
-            Url::parse(url).map_err(|_| Error::Config(format!("Invalid URL: {url}")))?;
+impl Default for RawLatencyOptions {
+    fn default() -> Self {
+        Self {
+            p95_ms: None,
+            window_checks: DEFAULT_LATENCY_WINDOW_CHECKS,
         }
-        Ok(())
     }
 }
 
-impl TryFrom<RawConfig> for Config {
-    type Error = Error;
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawCycleSummaryOptions {
+    tags: Vec<String>,
+}
 
-    fn try_from(raw: RawConfig) -> Result<Self, Error> {
-        // Validate all fields
-        let timeout_secs = Config::validate_timeout(raw.config.timeout_secs)?;
-        let check_interval_secs = Config::validate_check_interval(raw.config.check_interval_secs)?;
-        let failure_threshold = Config::validate_failure_threshold(raw.config.failure_threshold)?;
-        let webhook_url = Config::validate_webhook_url(raw.config.webhook_url)?;
-        let discord_id = Config::validate_discord_id(raw.config.discord_id);
-        Config::validate_urls(&raw.sites.urls)?;
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawRateLimitOptions {
+    requests_per_sec: Option<f64>,
+    per_host_delay_ms: Option<u64>,
+}
 
-        Ok(Config {
-            config: ConfigOptions {
-                timeout_secs,
-                check_interval_secs,
-                failure_threshold,
-                webhook_url,
-                discord_id,
-            },
-            sites: raw.sites,
-        })
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawDockerOptions {
+    socket: PathBuf,
+    label: String,
+}
+
+impl Default for RawDockerOptions {
+    fn default() -> Self {
+        Self {
+            socket: PathBuf::from(DEFAULT_DOCKER_SOCKET),
+            label: DEFAULT_DOCKER_LABEL.to_string(),
+        }
     }
 }
 
-fn find_config() -> Result<PathBuf, Error> {
-    let config_path = dirs::config_dir()
-        .ok_or_else(|| Error::Config("Unable to find config directory".into()))?
-        .join("downdetector")
-        .join("config.toml");
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+struct RawDiagnosticsOptions {
+    traceroute: bool,
+    traceroute_max_hops: u8,
+}
+
+impl Default for RawDiagnosticsOptions {
+    fn default() -> Self {
+        Self {
+            traceroute: true,
+            traceroute_max_hops: DEFAULT_TRACEROUTE_MAX_HOPS,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+struct RawCaptureOptions {
+    path: Option<PathBuf>,
+    max_body_kb: u64,
+    retention_days: u64,
+    max_files: usize,
+}
+
+impl Default for RawCaptureOptions {
+    fn default() -> Self {
+        Self {
+            path: None,
+            max_body_kb: DEFAULT_CAPTURE_MAX_BODY_KB,
+            retention_days: DEFAULT_CAPTURE_RETENTION_DAYS,
+            max_files: DEFAULT_CAPTURE_MAX_FILES,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+struct RawSloOptions {
+    window_days: u64,
+    burn_rate_alert: f64,
+}
+
+impl Default for RawSloOptions {
+    fn default() -> Self {
+        Self {
+            window_days: DEFAULT_SLO_WINDOW_DAYS,
+            burn_rate_alert: DEFAULT_SLO_BURN_RATE_ALERT,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+struct RawConfigOptions {
+    timeout_secs: u64,
+    connect_timeout_secs: Option<u64>,
+    read_timeout_secs: Option<u64>,
+    check_interval_secs: u64,
+    failure_threshold: u64,
+    low_bandwidth: bool,
+    low_memory: bool,
+    webhook_url: Option<String>,
+    discord_id: Option<u64>,
+    success_status_classes: Option<RawStatuses>,
+    down_backoff_max_secs: Option<u64>,
+    language: Language,
+    notification_check: NotificationCheck,
+    group_by_host: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawInfluxDbOptions {
+    url: String,
+    org: String,
+    bucket: String,
+    token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+struct RawStatsdOptions {
+    host: String,
+    port: u16,
+    prefix: String,
+    tag_format: StatsdTagFormat,
+}
+
+impl Default for RawStatsdOptions {
+    fn default() -> Self {
+        Self {
+            host: DEFAULT_STATSD_HOST.to_string(),
+            port: DEFAULT_STATSD_PORT,
+            prefix: DEFAULT_STATSD_PREFIX.to_string(),
+            tag_format: StatsdTagFormat::default(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+struct RawHistoryOptions {
+    path: Option<PathBuf>,
+    retention_days: u64,
+}
+
+impl Default for RawHistoryOptions {
+    fn default() -> Self {
+        Self {
+            path: None,
+            retention_days: DEFAULT_HISTORY_RETENTION_DAYS,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+struct RawServerOptions {
+    bind: String,
+    api_token: Option<String>,
+    agent_token: Option<String>,
+    agent_timeout_secs: u64,
+    probe_token: Option<String>,
+    discord_public_key: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawConfirmationOptions {
+    proxy: Option<String>,
+    probe_url: Option<String>,
+    probe_token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawAgentOptions {
+    central_url: String,
+    name: String,
+    token: Option<String>,
+}
+
+impl Default for RawServerOptions {
+    fn default() -> Self {
+        Self {
+            bind: DEFAULT_SERVER_BIND.to_string(),
+            api_token: None,
+            agent_token: None,
+            agent_timeout_secs: DEFAULT_AGENT_TIMEOUT_SECS,
+            probe_token: None,
+            discord_public_key: None,
+        }
+    }
+}
+
+// Implement Default for RawConfigOptions
+impl Default for RawConfigOptions {
+    fn default() -> Self {
+        Self {
+            timeout_secs: DEFAULT_TIMEOUT_SECS,
+            connect_timeout_secs: None,
+            read_timeout_secs: None,
+            check_interval_secs: DEFAULT_CHECK_INTERVAL_SECS,
+            failure_threshold: DEFAULT_FAILURE_THRESHOLD,
+            low_bandwidth: false,
+            low_memory: false,
+            webhook_url: None,
+            discord_id: None,
+            success_status_classes: None,
+            down_backoff_max_secs: None,
+            language: Language::default(),
+            notification_check: NotificationCheck::default(),
+            group_by_host: false,
+        }
+    }
+}
+
+// Builder pattern for validation
+impl Config {
+    fn validate_timeout(timeout_secs: u64) -> Result<u64, Error> {
+        if timeout_secs == 0 {
+            return Err(Error::Config("timeout_secs must be > 0".into()));
+        }
+        Ok(timeout_secs)
+    }
+
+    /// Checks the connect and read timeouts of `timeouts` fit in its total one,
+    /// `name` tells where they are set in error messages.
+    fn validate_phase_timeouts(name: &str, timeouts: Timeouts) -> Result<(), Error> {
+        for (phase, secs) in [
+            ("connect_timeout_secs", timeouts.connect),
+            ("read_timeout_secs", timeouts.read),
+        ] {
+            if let Some(secs) = secs
+                && (secs == 0 || secs > timeouts.total)
+            {
+                return Err(Error::Config(format!(
+                    "{phase}{name} must be > 0 and <= its timeout_secs of {}, got {secs}",
+                    timeouts.total
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_check_interval(check_interval_secs: u64) -> Result<u64, Error> {
+        if !(1..86400).contains(&check_interval_secs) {
+            return Err(Error::Config(
+                "check_interval_secs must be > 0 and < 86400".into(),
+            ));
+        }
+        Ok(check_interval_secs)
+    }
+
+    fn validate_down_backoff(down_backoff_max_secs: Option<u64>) -> Result<Option<u64>, Error> {
+        if let Some(max_secs) = down_backoff_max_secs
+            && !(FAILED_SITE_RETRY_SECS..86400).contains(&max_secs)
+        {
+            return Err(Error::Config(format!(
+                "down_backoff_max_secs must be >= {FAILED_SITE_RETRY_SECS} and < 86400"
+            )));
+        }
+        Ok(down_backoff_max_secs)
+    }
+
+    fn validate_failure_threshold(failure_threshold: u64) -> Result<u64, Error> {
+        if failure_threshold == 0 {
+            return Err(Error::Config("failure_threshold must be > 0".into()));
+        }
+        Ok(failure_threshold)
+    }
+
+    fn validate_webhook_url(raw_url: Option<String>) -> Result<Option<String>, Error> {
+        match dotenvy::var("WEBHOOK_URL").ok().or(raw_url) {
+            Some(url) if !url.trim().is_empty() => Config::check_webhook_url(url).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// Checks `webhook_url` points to a Discord webhook.
+    fn check_webhook_url(webhook_url: String) -> Result<String, Error> {
+        let parsed_url = Url::parse(&webhook_url)
+            .map_err(|_| Error::Config("Invalid webhook URL format".into()))?;
+
+        if parsed_url.scheme() != "https"
+            || parsed_url.host_str() != Some("discord.com")
+            || !parsed_url.path().starts_with("/api/webhooks/")
+        {
+            return Err(Error::Config(
+            "Webhook URL must be a valid Discord webhook starting with https://discord.com/api/webhooks/".into()
+        ));
+        }
+
+        // Extract webhook ID from the path
+        let path_parts: Vec<&str> = parsed_url
+            .path()
+            .strip_prefix("/api/webhooks/")
+            .unwrap_or("")
+            .split('/')
+            .collect();
+
+        // Ensure we have at least webhook_id/webhook_token
+        if path_parts.len() < 2 || path_parts[0].is_empty() || path_parts[1].is_empty() {
+            return Err(Error::Config(
+                "Webhook URL must contain both webhook ID and token".into(),
+            ));
+        }
+
+        // Validate webhook ID is a valid snowflake (parsable as u64)
+        let webhook_id = path_parts[0];
+        webhook_id.parse::<u64>().map_err(|_| {
+            Error::Config(format!(
+                "Invalid webhook ID '{webhook_id}': must be a valid Discord snowflake (numeric ID)"
+            ))
+        })?;
+
+        Ok(webhook_url)
+    }
+
+    fn validate_discord_id(raw_id: Option<u64>) -> Option<u64> {
+        dotenvy::var("DISCORD_ID")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .or(raw_id)
+    }
+
+    fn validate_influxdb(
+        raw: Option<RawInfluxDbOptions>,
+    ) -> Result<Option<InfluxDbOptions>, Error> {
+        let Some(raw) = raw else {
+            return Ok(None);
+        };
+
+        let url = Url::parse(&raw.url)
+            .map_err(|_| Error::Config(format!("Invalid InfluxDB URL: {}", raw.url)))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(Error::Config("InfluxDB URL must use http or https".into()));
+        }
+
+        if raw.org.trim().is_empty() || raw.bucket.trim().is_empty() {
+            return Err(Error::Config(
+                "InfluxDB org and bucket must not be empty".into(),
+            ));
+        }
+
+        let token = env_or("INFLUXDB_TOKEN", raw.token).ok_or_else(|| {
+            Error::Config("InfluxDB token must be set in the config or via INFLUXDB_TOKEN".into())
+        })?;
+
+        Ok(Some(InfluxDbOptions {
+            url,
+            org: raw.org,
+            bucket: raw.bucket,
+            token,
+        }))
+    }
+
+    fn validate_statsd(raw: Option<RawStatsdOptions>) -> Result<Option<StatsdOptions>, Error> {
+        let Some(raw) = raw else {
+            return Ok(None);
+        };
+
+        if raw.host.trim().is_empty() {
+            return Err(Error::Config("StatsD host must not be empty".into()));
+        }
+        if raw.port == 0 {
+            return Err(Error::Config("StatsD port must be > 0".into()));
+        }
+        if raw.prefix.is_empty()
+            || raw.prefix.contains(|c: char| {
+                matches!(c, ':' | '|' | ',' | ';' | '#' | '@') || c.is_whitespace()
+            })
+        {
+            return Err(Error::Config(format!(
+                "Invalid StatsD prefix '{}': must be non-empty without ':|,;#@' or whitespace",
+                raw.prefix
+            )));
+        }
+
+        Ok(Some(StatsdOptions {
+            host: raw.host,
+            port: raw.port,
+            prefix: raw.prefix,
+            tag_format: raw.tag_format,
+        }))
+    }
+
+    fn validate_history(raw: Option<RawHistoryOptions>) -> Result<Option<HistoryOptions>, Error> {
+        let Some(raw) = raw else {
+            return Ok(None);
+        };
+
+        if raw.retention_days == 0 {
+            return Err(Error::Config("history retention_days must be > 0".into()));
+        }
+
+        let path = match raw.path {
+            Some(path) => path,
+            None => data_dir()?.join("history.jsonl"),
+        };
+
+        Ok(Some(HistoryOptions {
+            path,
+            retention_days: raw.retention_days,
+        }))
+    }
+
+    fn validate_server(raw: Option<RawServerOptions>) -> Result<Option<ServerOptions>, Error> {
+        let Some(raw) = raw else {
+            return Ok(None);
+        };
+
+        let bind = raw
+            .bind
+            .parse()
+            .map_err(|_| Error::Config(format!("Invalid server bind address: {}", raw.bind)))?;
+
+        let api_token = env_or("API_TOKEN", raw.api_token);
+
+        let agent_token = env_or("AGENT_TOKEN", raw.agent_token);
+        if raw.agent_timeout_secs == 0 {
+            return Err(Error::Config(
+                "Server agent_timeout_secs must be greater than 0".into(),
+            ));
+        }
+
+        let probe_token = env_or("PROBE_TOKEN", raw.probe_token);
+
+        let discord_public_key = env_or("DISCORD_PUBLIC_KEY", raw.discord_public_key)
+            .map(|key| {
+                interactions::parse_public_key(key.trim()).ok_or_else(|| {
+                    Error::Config(format!(
+                        "Invalid discord_public_key, expected 64 hexadecimal characters: {key}"
+                    ))
+                })
+            })
+            .transpose()?;
+
+        Ok(Some(ServerOptions {
+            bind,
+            api_token,
+            agent_token,
+            agent_timeout_secs: raw.agent_timeout_secs,
+            probe_token,
+            discord_public_key,
+        }))
+    }
+
+    fn validate_confirmation(
+        raw: Option<RawConfirmationOptions>,
+    ) -> Result<Option<ConfirmationOptions>, Error> {
+        let Some(raw) = raw else {
+            return Ok(None);
+        };
+
+        let parse_http_url = |name: &str, value: Option<String>| {
+            value
+                .map(|value| {
+                    Url::parse(&value)
+                        .ok()
+                        .filter(|url| matches!(url.scheme(), "http" | "https"))
+                        .ok_or_else(|| {
+                            Error::Config(format!(
+                                "Invalid confirmation {name}, expected an http(s) URL: {value}"
+                            ))
+                        })
+                })
+                .transpose()
+        };
+        let proxy = parse_http_url("proxy", raw.proxy)?;
+        let probe_url = parse_http_url("probe_url", raw.probe_url)?;
+
+        if proxy.is_none() && probe_url.is_none() {
+            return Err(Error::Config(
+                "Confirmation needs a proxy or a probe_url".into(),
+            ));
+        }
+
+        let probe_token = env_or("PROBE_TOKEN", raw.probe_token);
+        if probe_url.is_some() && probe_token.is_none() {
+            return Err(Error::Config(
+                "Confirmation probe token must be set in the config or via PROBE_TOKEN".into(),
+            ));
+        }
+
+        Ok(Some(ConfirmationOptions {
+            proxy,
+            probe_url,
+            probe_token,
+        }))
+    }
+
+    fn validate_agent(raw: Option<RawAgentOptions>) -> Result<Option<AgentOptions>, Error> {
+        let Some(raw) = raw else {
+            return Ok(None);
+        };
+
+        let central_url = Url::parse(&raw.central_url).map_err(|_| {
+            Error::Config(format!("Invalid agent central_url: {}", raw.central_url))
+        })?;
+        if !matches!(central_url.scheme(), "http" | "https") {
+            return Err(Error::Config(
+                "Agent central_url must use http or https".into(),
+            ));
+        }
+
+        if raw.name.trim().is_empty() {
+            return Err(Error::Config("Agent name must not be empty".into()));
+        }
+
+        let token = env_or("AGENT_TOKEN", raw.token).ok_or_else(|| {
+            Error::Config("Agent token must be set in the config or via AGENT_TOKEN".into())
+        })?;
+
+        Ok(Some(AgentOptions {
+            central_url,
+            name: raw.name,
+            token,
+        }))
+    }
+
+    fn validate_sites(raw: RawSiteList) -> Result<SiteList, Error> {
+        let mut sites = SiteList::default();
+
+        for site in raw.urls {
+            let (url, options) = match RawSite::try_from(site).and_then(Config::validate_site) {
+                Ok(site) => site,
+                Err(e) if !raw.strict => {
+                    warn!("Skipping site: {e}");
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            if let Some(options) = options {
+                sites.options.insert(url.clone(), options);
+            }
+            sites.urls.push(url);
+        }
+
+        sites.source = raw
+            .source_url
+            .map(|url| Config::validate_site_source(url, raw.source_refresh_secs))
+            .transpose()?;
+        Ok(sites)
+    }
+
+    /// Validates one entry of the site list, returning its URL and options.
+    fn validate_site(site: RawSite) -> Result<(String, Option<SiteOptions>), Error> {
+        let (url, options) = match site {
+            RawSite::Url(url) => (url, None),
+            RawSite::Table(options) => {
+                let RawSiteOptions {
+                    url,
+                    slo,
+                    severity,
+                    critical,
+                    pinned_cert_sha256,
+                    expected_status,
+                    timeout_secs,
+                    connect_timeout_secs,
+                    read_timeout_secs,
+                    tags,
+                    discord_id,
+                    role_id,
+                    min_body_bytes,
+                    max_body_bytes,
+                    body_size_mismatch,
+                    latency_p95_ms,
+                } = *options;
+                if timeout_secs == Some(0) {
+                    return Err(Error::Config(format!("timeout_secs of {url} must be > 0")));
+                }
+                if latency_p95_ms == Some(0) {
+                    return Err(Error::Config(format!(
+                        "latency_p95_ms of {url} must be > 0"
+                    )));
+                }
+                if tags.iter().any(|tag| tag.trim().is_empty()) {
+                    return Err(Error::Config(format!("Tags of {url} cannot be empty")));
+                }
+                if let Some(slo) = slo.filter(|slo| !(*slo > 0.0 && *slo < 100.0)) {
+                    return Err(Error::Config(format!(
+                        "SLO of {url} must be between 0 and 100 (exclusive), got {slo}"
+                    )));
+                }
+                let severity = match (severity, critical) {
+                    (Some(severity), false) => severity,
+                    (None | Some(Severity::Critical), true) => Severity::Critical,
+                    (None, false) => Severity::default(),
+                    (Some(_), true) => {
+                        return Err(Error::Config(format!(
+                            "Site {url} sets critical = true with another severity"
+                        )));
+                    }
+                };
+                let pinned_cert_sha256 = match pinned_cert_sha256 {
+                    Some(pin) => Some(Config::validate_pinned_cert(&url, &pin)?),
+                    None => None,
+                };
+                let body_size = Config::validate_body_size(
+                    &url,
+                    min_body_bytes,
+                    max_body_bytes,
+                    body_size_mismatch,
+                )?;
+                let expected_status = expected_status
+                    .map(|raw| Config::validate_statuses(&format!("expected_status of {url}"), raw))
+                    .transpose()?;
+                (
+                    url,
+                    Some(SiteOptions {
+                        slo,
+                        severity,
+                        pinned_cert_sha256,
+                        expected_status,
+                        timeout_secs,
+                        connect_timeout_secs,
+                        read_timeout_secs,
+                        tags,
+                        discord_id,
+                        role_id,
+                        body_size,
+                        latency_p95_ms,
+                    }),
+                )
+            }
+        };
+
+        Config::validate_urls(std::slice::from_ref(&url))?;
+        Ok((url, options))
+    }
+
+    fn validate_body_size(
+        url: &str,
+        min_bytes: Option<u64>,
+        max_bytes: Option<u64>,
+        mismatch: Option<BodySizeMismatch>,
+    ) -> Result<Option<BodySizeBounds>, Error> {
+        if min_bytes.is_none() && max_bytes.is_none() {
+            return match mismatch {
+                Some(_) => Err(Error::Config(format!(
+                    "body_size_mismatch of {url} needs min_body_bytes or max_body_bytes"
+                ))),
+                None => Ok(None),
+            };
+        }
+        if let (Some(min), Some(max)) = (min_bytes, max_bytes)
+            && min > max
+        {
+            return Err(Error::Config(format!(
+                "min_body_bytes of {url} ({min}) exceeds its max_body_bytes ({max})"
+            )));
+        }
+        Ok(Some(BodySizeBounds {
+            min_bytes,
+            max_bytes,
+            mismatch: mismatch.unwrap_or_default(),
+        }))
+    }
+
+    fn validate_site_source(url: String, refresh_secs: u64) -> Result<SiteSource, Error> {
+        if !Url::parse(&url).is_ok_and(|parsed| matches!(parsed.scheme(), "http" | "https")) {
+            return Err(Error::Config(format!(
+                "Invalid source_url: {url}, expected an HTTP(S) URL"
+            )));
+        }
+        if refresh_secs < MIN_SITE_SOURCE_REFRESH_SECS {
+            return Err(Error::Config(format!(
+                "source_refresh_secs must be at least {MIN_SITE_SOURCE_REFRESH_SECS}"
+            )));
+        }
+        Ok(SiteSource { url, refresh_secs })
+    }
+
+    fn validate_statuses(name: &str, raw: RawStatuses) -> Result<SuccessStatuses, Error> {
+        SuccessStatuses::try_from(raw).map_err(|e| Error::Config(format!("Invalid {name}: {e}")))
+    }
+
+    fn validate_pinned_cert(url: &str, pin: &str) -> Result<[u8; 32], Error> {
+        if !url.starts_with("https://") {
+            return Err(Error::Config(format!(
+                "Site {url} pins a certificate but is not an HTTPS site"
+            )));
+        }
+        pinning::parse_fingerprint(pin).ok_or_else(|| {
+            Error::Config(format!(
+                "Invalid pinned_cert_sha256 for {url}, expected 64 hexadecimal characters"
+            ))
+        })
+    }
+
+    fn validate_routing(
+        raw: HashMap<Severity, RawRouteOptions>,
+        webhook_url: Option<&String>,
+        discord_id: Option<u64>,
+    ) -> Result<RoutingOptions, Error> {
+        let default_route = RouteOptions {
+            webhook_url: webhook_url.cloned(),
+            discord_id,
+            role_id: None,
+            silent: false,
+        };
+        let mut routing = RoutingOptions {
+            critical: default_route.clone(),
+            warning: default_route.clone(),
+            info: default_route,
+        };
+
+        for (severity, raw) in raw {
+            let route = match severity {
+                Severity::Critical => &mut routing.critical,
+                Severity::Warning => &mut routing.warning,
+                Severity::Info => &mut routing.info,
+            };
+            if let Some(url) = raw.webhook_url {
+                route.webhook_url = Some(Config::check_webhook_url(url)?);
+            }
+            if raw.mention {
+                route.discord_id = raw.discord_id.or(route.discord_id);
+                route.role_id = raw.role_id;
+            } else {
+                route.discord_id = None;
+                route.role_id = None;
+            }
+            route.silent = raw.silent;
+        }
+
+        Ok(routing)
+    }
+
+    fn validate_quiet_hours(
+        raw: Option<RawQuietHoursOptions>,
+    ) -> Result<Option<QuietHoursOptions>, Error> {
+        let Some(raw) = raw else {
+            return Ok(None);
+        };
+
+        let parse_time = |name: &str, value: &str| {
+            Time::strptime("%H:%M", value).map_err(|_| {
+                Error::Config(format!(
+                    "Invalid quiet_hours {name}, expected HH:MM: {value}"
+                ))
+            })
+        };
+        let start = parse_time("start", &raw.start)?;
+        let end = parse_time("end", &raw.end)?;
+        if start == end {
+            return Err(Error::Config(
+                "quiet_hours start and end must differ".into(),
+            ));
+        }
+
+        let timezone = match raw.timezone {
+            Some(name) => TimeZone::get(&name)
+                .map_err(|_| Error::Config(format!("Unknown quiet_hours timezone: {name}")))?,
+            None => TimeZone::system(),
+        };
+
+        Ok(Some(QuietHoursOptions {
+            start,
+            end,
+            timezone,
+        }))
+    }
+
+    fn validate_incident_threads(
+        raw: Option<RawIncidentThreadOptions>,
+    ) -> Result<Option<IncidentThreadOptions>, Error> {
+        let Some(raw) = raw else {
+            return Ok(None);
+        };
+
+        let bot_token = env_or("DISCORD_BOT_TOKEN", raw.bot_token).ok_or_else(|| {
+            Error::Config(
+                "Incident threads need a bot_token in the config or via DISCORD_BOT_TOKEN".into(),
+            )
+        })?;
+
+        Ok(Some(IncidentThreadOptions { bot_token }))
+    }
+
+    fn validate_diagnostics(
+        raw: Option<RawDiagnosticsOptions>,
+    ) -> Result<Option<DiagnosticsOptions>, Error> {
+        let Some(raw) = raw else {
+            return Ok(None);
+        };
+
+        if raw.traceroute_max_hops == 0 || raw.traceroute_max_hops > MAX_TRACEROUTE_HOPS {
+            return Err(Error::Config(format!(
+                "Diagnostics traceroute_max_hops must be between 1 and {MAX_TRACEROUTE_HOPS}"
+            )));
+        }
+
+        Ok(Some(DiagnosticsOptions {
+            traceroute: raw.traceroute,
+            traceroute_max_hops: raw.traceroute_max_hops,
+        }))
+    }
+
+    fn validate_rate_limit(
+        raw: Option<RawRateLimitOptions>,
+    ) -> Result<Option<RateLimitOptions>, Error> {
+        let Some(raw) = raw else {
+            return Ok(None);
+        };
+
+        if let Some(rate) = raw
+            .requests_per_sec
+            .filter(|rate| !(rate.is_finite() && *rate >= MIN_REQUESTS_PER_SEC))
+        {
+            return Err(Error::Config(format!(
+                "Rate limit requests_per_sec must be at least {MIN_REQUESTS_PER_SEC}, got {rate}"
+            )));
+        }
+        if raw.per_host_delay_ms == Some(0) {
+            return Err(Error::Config(
+                "Rate limit per_host_delay_ms must be > 0, remove it instead".into(),
+            ));
+        }
+        if raw.requests_per_sec.is_none() && raw.per_host_delay_ms.is_none() {
+            return Err(Error::Config(
+                "Rate limit needs requests_per_sec or per_host_delay_ms".into(),
+            ));
+        }
+
+        Ok(Some(RateLimitOptions {
+            requests_per_sec: raw.requests_per_sec,
+            per_host_delay_ms: raw.per_host_delay_ms,
+        }))
+    }
+
+    fn validate_latency(raw: RawLatencyOptions) -> Result<LatencyOptions, Error> {
+        if raw.p95_ms == Some(0) {
+            return Err(Error::Config("Latency p95_ms must be > 0".into()));
+        }
+        if !(1..=MAX_LATENCY_WINDOW_CHECKS).contains(&raw.window_checks) {
+            return Err(Error::Config(format!(
+                "Latency window_checks must be between 1 and {MAX_LATENCY_WINDOW_CHECKS}"
+            )));
+        }
+
+        Ok(LatencyOptions {
+            p95_ms: raw.p95_ms,
+            window_checks: raw.window_checks,
+        })
+    }
+
+    fn validate_cycle_summary(
+        raw: Option<RawCycleSummaryOptions>,
+        sites: &SiteList,
+    ) -> Result<Option<CycleSummaryOptions>, Error> {
+        let Some(raw) = raw else {
+            return Ok(None);
+        };
+
+        if let Some(tag) = raw.tags.iter().find(|tag| !sites.has_tag(tag)) {
+            return Err(Error::Config(format!(
+                "Cycle summary tag {tag} is not a tag of any configured site"
+            )));
+        }
+
+        Ok(Some(CycleSummaryOptions { tags: raw.tags }))
+    }
+
+    fn validate_kubernetes(
+        raw: Option<RawKubernetesOptions>,
+    ) -> Result<Option<KubernetesOptions>, Error> {
+        let Some(raw) = raw else {
+            return Ok(None);
+        };
+
+        if cfg!(not(feature = "k8s")) {
+            return Err(Error::Config(
+                "Kubernetes discovery requires building with the k8s feature".into(),
+            ));
+        }
+        for (name, value) in [
+            ("namespace", &raw.namespace),
+            ("annotation", &raw.annotation),
+        ] {
+            if value
+                .as_deref()
+                .is_some_and(|value| value.trim().is_empty())
+            {
+                return Err(Error::Config(format!(
+                    "Kubernetes {name} cannot be empty, remove it instead"
+                )));
+            }
+        }
+
+        Ok(Some(KubernetesOptions {
+            namespace: raw.namespace,
+            annotation: raw.annotation,
+        }))
+    }
+
+    fn validate_docker(raw: Option<RawDockerOptions>) -> Result<Option<DockerOptions>, Error> {
+        let Some(raw) = raw else {
+            return Ok(None);
+        };
+
+        if cfg!(not(unix)) {
+            return Err(Error::Config(
+                "Docker discovery is only available on Unix".into(),
+            ));
+        }
+        if raw.socket.as_os_str().is_empty() {
+            return Err(Error::Config("Docker socket cannot be empty".into()));
+        }
+        if raw.label.trim().is_empty() {
+            return Err(Error::Config("Docker label cannot be empty".into()));
+        }
+
+        Ok(Some(DockerOptions {
+            socket: raw.socket,
+            label: raw.label,
+        }))
+    }
+
+    fn validate_capture(raw: Option<RawCaptureOptions>) -> Result<Option<CaptureOptions>, Error> {
+        let Some(raw) = raw else {
+            return Ok(None);
+        };
+
+        if raw.max_body_kb == 0 || raw.max_body_kb > MAX_CAPTURE_BODY_KB {
+            return Err(Error::Config(format!(
+                "Capture max_body_kb must be between 1 and {MAX_CAPTURE_BODY_KB}"
+            )));
+        }
+        if raw.retention_days == 0 {
+            return Err(Error::Config("Capture retention_days must be > 0".into()));
+        }
+        if raw.max_files == 0 {
+            return Err(Error::Config("Capture max_files must be > 0".into()));
+        }
+
+        let path = match raw.path {
+            Some(path) => path,
+            None => data_dir()?.join("captures"),
+        };
+
+        Ok(Some(CaptureOptions {
+            path,
+            max_body_kb: raw.max_body_kb,
+            retention_days: raw.retention_days,
+            max_files: raw.max_files,
+        }))
+    }
+
+    fn validate_templates(
+        raw: RawTemplates,
+        language: Language,
+    ) -> Result<MessageTemplates, Error> {
+        let defaults = MessageTemplates::builtin(language);
+        let parse = |name: &str, source: Option<String>, variables, default| match source {
+            Some(source) => Template::parse(&source, variables)
+                .map_err(|e| Error::Config(format!("Invalid templates {name}: {e}"))),
+            None => Ok(default),
+        };
+
+        Ok(MessageTemplates {
+            down: parse("down", raw.down, DOWN_VARIABLES, defaults.down)?,
+            recovery: parse(
+                "recovery",
+                raw.recovery,
+                RECOVERY_VARIABLES,
+                defaults.recovery,
+            )?,
+            digest: parse("digest", raw.digest, DIGEST_VARIABLES, defaults.digest)?,
+            degraded: parse(
+                "degraded",
+                raw.degraded,
+                DEGRADED_VARIABLES,
+                defaults.degraded,
+            )?,
+        })
+    }
+
+    fn validate_slo(
+        raw: Option<RawSloOptions>,
+        sites: &SiteList,
+        history: Option<&HistoryOptions>,
+    ) -> Result<Option<SloOptions>, Error> {
+        if !sites.options.values().any(|options| options.slo.is_some()) {
+            return Ok(None);
+        }
+        let raw = raw.unwrap_or_default();
+
+        let history = history.ok_or_else(|| {
+            Error::Config(
+                "Site SLOs are computed from check history, add a [history] section".into(),
+            )
+        })?;
+        if raw.window_days == 0 || raw.window_days > history.retention_days {
+            return Err(Error::Config(format!(
+                "SLO window_days must be between 1 and the history retention_days ({})",
+                history.retention_days
+            )));
+        }
+        if raw.burn_rate_alert.is_nan() || raw.burn_rate_alert <= 0.0 {
+            return Err(Error::Config("SLO burn_rate_alert must be > 0".into()));
+        }
+
+        Ok(Some(SloOptions {
+            window_days: raw.window_days,
+            burn_rate_alert: raw.burn_rate_alert,
+        }))
+    }
+
+    pub(crate) fn validate_urls(urls: &[String]) -> Result<(), Error> {
+        for url in urls {
+            Url::parse(url).map_err(|_| Error::Config(format!("Invalid URL: {url}")))?;
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<RawConfig> for Config {
+    type Error = Error;
+
+    fn try_from(raw: RawConfig) -> Result<Self, Error> {
+        // Validate all fields
+        let timeout_secs = Config::validate_timeout(raw.config.timeout_secs)?;
+        let check_interval_secs = Config::validate_check_interval(raw.config.check_interval_secs)?;
+        let failure_threshold = Config::validate_failure_threshold(raw.config.failure_threshold)?;
+        let down_backoff_max_secs =
+            Config::validate_down_backoff(raw.config.down_backoff_max_secs)?;
+        let success_status_classes = match raw.config.success_status_classes {
+            Some(raw) => Config::validate_statuses("success_status_classes", raw)?,
+            None => SuccessStatuses::default(),
+        };
+        let webhook_url = Config::validate_webhook_url(raw.config.webhook_url)?;
+        let discord_id = Config::validate_discord_id(raw.config.discord_id);
+        let sites = Config::validate_sites(raw.sites)?;
+        let options = ConfigOptions {
+            timeout_secs,
+            connect_timeout_secs: raw.config.connect_timeout_secs,
+            read_timeout_secs: raw.config.read_timeout_secs,
+            check_interval_secs,
+            failure_threshold,
+            low_bandwidth: raw.config.low_bandwidth,
+            low_memory: raw.config.low_memory,
+            webhook_url,
+            discord_id,
+            success_status_classes,
+            down_backoff_max_secs,
+            language: raw.config.language,
+            notification_check: raw.config.notification_check,
+            group_by_host: raw.config.group_by_host,
+        };
+        Config::validate_phase_timeouts("", options.timeouts())?;
+        for url in sites.options.keys() {
+            Config::validate_phase_timeouts(&format!(" of {url}"), sites.timeouts(url, &options))?;
+        }
+        let influxdb = Config::validate_influxdb(raw.influxdb)?;
+        let statsd = Config::validate_statsd(raw.statsd)?;
+        let history = Config::validate_history(raw.history)?;
+        let server = Config::validate_server(raw.server)?;
+        let agent = Config::validate_agent(raw.agent)?;
+        let confirmation = Config::validate_confirmation(raw.confirmation)?;
+        let slo = Config::validate_slo(raw.slo, &sites, history.as_ref())?;
+        let quiet_hours = Config::validate_quiet_hours(raw.quiet_hours)?;
+        let routing = Config::validate_routing(
+            raw.routing,
+            options.webhook_url.as_ref(),
+            options.discord_id,
+        )?;
+        let incident_threads = Config::validate_incident_threads(raw.incident_threads)?;
+        let templates = Config::validate_templates(raw.templates, raw.config.language)?;
+        let diagnostics = Config::validate_diagnostics(raw.diagnostics)?;
+        let capture = Config::validate_capture(raw.capture)?;
+        let kubernetes = Config::validate_kubernetes(raw.kubernetes)?;
+        let docker = Config::validate_docker(raw.docker)?;
+        let rate_limit = Config::validate_rate_limit(raw.rate_limit)?;
+        let cycle_summary = Config::validate_cycle_summary(raw.cycle_summary, &sites)?;
+        let latency = Config::validate_latency(raw.latency)?;
+
+        Ok(Config {
+            config: options,
+            sites,
+            influxdb,
+            statsd,
+            history,
+            server,
+            agent,
+            confirmation,
+            slo,
+            quiet_hours,
+            routing,
+            incident_threads,
+            templates,
+            diagnostics,
+            capture,
+            kubernetes,
+            docker,
+            rate_limit,
+            cycle_summary,
+            latency,
+        })
+    }
+}
+
+/// Value of the environment variable `name`, or `configured` when it is unset
+/// or empty: Compose passes unset variables on as empty strings. `None` when
+/// both are empty.
+fn env_or(name: &str, configured: Option<String>) -> Option<String> {
+    non_empty_or(dotenvy::var(name).ok(), configured)
+}
+
+fn non_empty_or(value: Option<String>, fallback: Option<String>) -> Option<String> {
+    let non_empty = |value: &String| !value.trim().is_empty();
+    value.filter(non_empty).or(fallback).filter(non_empty)
+}
+
+/// Directory holding the files downdetector writes at runtime, such as check history.
+pub(crate) fn data_dir() -> Result<PathBuf, Error> {
+    Ok(dirs::data_dir()
+        .ok_or_else(|| Error::Config("Unable to find data directory".into()))?
+        .join("downdetector"))
+}
+
+/// Resolves `path` under `base`, keeping the path of `base` even when it
+/// does not end with a slash, e.g. `http://host/prefix` + `api` gives
+/// `http://host/prefix/api`.
+pub(crate) fn join_url_path(base: &Url, path: &str) -> Result<Url, url::ParseError> {
+    let mut base = base.clone();
+    if !base.path().ends_with('/') {
+        base.set_path(&format!("{}/", base.path()));
+    }
+    base.join(path)
+}
+
+/// Rewrites the `[sites] urls` list of the config file at `path`, keeping
+/// every other value, comment and formatting untouched.
+///
+/// Sites still listed keep their existing entry, settings included, new
+/// sites are added as plain URLs. Entries skipped by a non-strict list are
+/// kept as they are, after the listed sites.
+pub(crate) fn save_site_urls(path: &Path, urls: &[String]) -> Result<(), Error> {
+    let mut document: DocumentMut = fs::read_to_string(path)?.parse()?;
+
+    let sites = document
+        .entry("sites")
+        .or_insert_with(toml_edit::table)
+        .as_table_like_mut()
+        .ok_or_else(|| Error::Config("[sites] must be a table".into()))?;
+
+    let entry_url = |value: &Value| match value {
+        Value::String(entry) => Some(entry.value().clone()),
+        Value::InlineTable(table) => table.get("url")?.as_str().map(str::to_string),
+        _ => None,
+    };
+    // Entries are matched by URL, the first one listed wins
+    let mut existing: HashMap<String, Value> = HashMap::new();
+    let mut skipped = Vec::new();
+    for value in sites
+        .get("urls")
+        .and_then(Item::as_array)
+        .into_iter()
+        .flatten()
+    {
+        if !is_loaded_site(value) {
+            skipped.push(value.clone());
+        } else if let Some(url) = entry_url(value) {
+            existing.entry(url).or_insert_with(|| value.clone());
+        }
+    }
+    let mut array = Array::new();
+    let listed = urls
+        .iter()
+        .map(|url| {
+            existing
+                .remove(url)
+                .unwrap_or_else(|| Value::from(url.as_str()))
+        })
+        .chain(skipped);
+    for mut value in listed {
+        value.decor_mut().set_prefix("\n    ");
+        value.decor_mut().set_suffix("");
+        array.push_formatted(value);
+    }
+    array.set_trailing("\n");
+    array.set_trailing_comma(!array.is_empty());
+
+    sites.insert("urls", Item::Value(Value::Array(array)));
+
+    write_atomically(path, document.to_string().as_bytes())?;
+    Ok(())
+}
+
+/// Whether `value`, an entry of `[sites] urls`, is loaded by the monitor
+/// rather than skipped as invalid.
+fn is_loaded_site(value: &Value) -> bool {
+    let mut value = value.clone();
+    value.decor_mut().clear();
+    value
+        .to_string()
+        .parse::<toml::Value>()
+        .map_err(Error::from)
+        .and_then(RawSite::try_from)
+        .and_then(Config::validate_site)
+        .is_ok()
+}
+
+/// Replaces the file at `path` with `content` through a temporary file, so
+/// it is never left half written, nor read half written.
+pub(crate) fn write_atomically(path: &Path, content: &[u8]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    fs::write(&tmp_path, content)?;
+    fs::rename(tmp_path, path)
+}
+
+/// Location of the config file, which may not exist yet.
+pub(crate) fn config_path() -> Result<PathBuf, Error> {
+    Ok(dirs::config_dir()
+        .ok_or_else(|| Error::Config("Unable to find config directory".into()))?
+        .join("downdetector")
+        .join("config.toml"))
+}
+
+/// Location of the config file, created with the default config when missing.
+pub(crate) fn find_config() -> Result<PathBuf, Error> {
+    let config_path = config_path()?;
+
+    if config_path.exists() {
+        return Ok(config_path);
+    }
+
+    if let Some(parent) = config_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    fs::write(&config_path, DEFAULT_CONFIG)?;
+    Ok(config_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::templates::Variables;
+    use crate::test_support::temp_dir;
+
+    const EXAMPLE_CONFIG: &str = include_str!("../config.example.toml");
+
+    #[test]
+    fn test_shipped_configs_are_current() {
+        for content in [DEFAULT_CONFIG, EXAMPLE_CONFIG] {
+            let mut document: DocumentMut = content.parse().unwrap();
+            assert_eq!(migrate::migrate(&mut document).unwrap(), None);
+        }
+    }
+
+    #[test]
+    fn default_config_is_valid() {
+        let _config: Config = toml::from_str::<RawConfig>(DEFAULT_CONFIG)
+            .expect("Failed to parse config")
+            .try_into()
+            .expect("Failed to convert to Config");
+    }
+
+    #[test]
+    fn example_config_is_valid() {
+        let _config: Config = toml::from_str::<RawConfig>(EXAMPLE_CONFIG)
+            .expect("Failed to parse config")
+            .try_into()
+            .expect("Failed to convert to Config");
+    }
+
+    #[test]
+    fn test_load_config_from_toml() {
+        let toml_content = r#"
+            [config]
+            timeout_secs = 5
+            check_interval_secs = 60
+            failure_threshold = 5
+            webhook_url = "https://discord.com/api/webhooks/1234567890/abcdefg"
+            discord_id = 1234567890
+            
+            [sites]
+            urls = [
+                "https://www.google.com",
+                "https://www.rust-lang.org",
+                "https://invalid.url"
+            ]
+        "#;
+
+        let config: Config = toml::from_str::<RawConfig>(toml_content)
+            .expect("Failed to parse config")
+            .try_into()
+            .expect("Failed to convert to Config");
+
+        assert_eq!(config.config.timeout_secs, 5);
+        assert_eq!(config.config.check_interval_secs, 60);
+        assert_eq!(config.config.failure_threshold, 5);
+        assert_eq!(config.sites.urls.len(), 3);
+        assert_eq!(config.sites.urls[0], "https://www.google.com");
+        assert_eq!(config.sites.urls[1], "https://www.rust-lang.org");
+        assert_eq!(config.sites.urls[2], "https://invalid.url");
+        assert_eq!(config.config.discord_id, Some(1_234_567_890));
+        assert_eq!(
+            config.config.webhook_url,
+            Some("https://discord.com/api/webhooks/1234567890/abcdefg".to_string())
+        );
+    }
+
+    #[test]
+    fn test_empty_environment_values_do_not_mask_the_config() {
+        let token = || Some("from-config".to_string());
+        assert_eq!(non_empty_or(Some(String::new()), token()), token());
+        assert_eq!(non_empty_or(Some("  ".to_string()), token()), token());
+        assert_eq!(
+            non_empty_or(Some("from-env".to_string()), token()),
+            Some("from-env".to_string())
+        );
+        assert_eq!(non_empty_or(None, Some(String::new())), None);
+    }
+
+    #[test]
+    fn test_join_url_path_keeps_the_base_path() {
+        let join = |base: &str| {
+            join_url_path(&Url::parse(base).unwrap(), "api/v2/write")
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(
+            join("http://influx:8086"),
+            "http://influx:8086/api/v2/write"
+        );
+        assert_eq!(
+            join("http://host/influx"),
+            "http://host/influx/api/v2/write"
+        );
+        assert_eq!(
+            join("http://host/influx/"),
+            "http://host/influx/api/v2/write"
+        );
+    }
+
+    #[test]
+    fn test_low_bandwidth_option() {
+        let toml_content = r#"
+            [config]
+            low_bandwidth = true
+
+            [sites]
+            urls = ["https://www.google.com"]
+        "#;
+
+        let config: Config = toml::from_str::<RawConfig>(toml_content)
+            .expect("Failed to parse config")
+            .try_into()
+            .expect("Failed to convert to Config");
+
+        assert!(config.config.low_bandwidth);
+    }
+
+    #[test]
+    fn test_partial_config_uses_defaults() {
+        // Minimal config with defaults
+        let toml_content = r#"
+            [config]
+            webhook_url = "https://discord.com/api/webhooks/1234567890/abcdefg"
+            discord_id = 1234567890
+            
+            [sites]
+            urls = ["https://www.google.com"]
+        "#;
+
+        let config: Config = toml::from_str::<RawConfig>(toml_content)
+            .expect("Failed to parse config")
+            .try_into()
+            .expect("Failed to convert to Config");
+
+        // Should use default values
+        assert_eq!(config.config.timeout_secs, DEFAULT_TIMEOUT_SECS);
+        assert_eq!(
+            config.config.check_interval_secs,
+            DEFAULT_CHECK_INTERVAL_SECS
+        );
+        assert_eq!(config.config.failure_threshold, DEFAULT_FAILURE_THRESHOLD);
+        assert!(!config.config.low_bandwidth);
+        assert!(!config.config.low_memory);
+    }
+
+    #[test]
+    fn test_empty_config_section_uses_defaults() {
+        // Config with empty sections
+        let toml_content = r#"
+            [config]
+            webhook_url = "https://discord.com/api/webhooks/1234567890/abcdefg"
+            discord_id = 1234567890
+            
+            [sites]
+        "#;
+
+        let config: Config = toml::from_str::<RawConfig>(toml_content)
+            .expect("Failed to parse config")
+            .try_into()
+            .expect("Failed to convert to Config");
+
+        // Sites should have empty URLs vector
+        assert_eq!(config.sites.urls.len(), 0);
+    }
+
+    #[test]
+    fn test_invalid_timeout() {
+        let toml_content = r#"
+            [config]
+            timeout_secs = 0
+            check_interval_secs = 60
+            webhook_url = "https://discord.com/api/webhooks/1234567890/abcdefg"
+            discord_id = 1234567890
+            
+            [sites]
+            urls = ["https://www.google.com"]
+        "#;
+
+        let result: Result<Config, Error> = toml::from_str::<RawConfig>(toml_content)
+            .expect("Failed to parse config")
+            .try_into();
+
+        assert!(result.is_err(), "Expected error for invalid timeout");
+    }
+
+    #[test]
+    fn test_invalid_check_interval() {
+        let toml_content = r#"
+            [config]
+            timeout_secs = 5
+            check_interval_secs = 86400
+            failure_threshold = 5
+            webhook_url = "https://discord.com/api/webhooks/1234567890/abcdefg"
+            discord_id = 1234567890
+            
+            [sites]
+            urls = ["https://www.google.com"]
+        "#;
+
+        let result: Result<Config, Error> = toml::from_str::<RawConfig>(toml_content)
+            .expect("Failed to parse config")
+            .try_into();
+
+        assert!(result.is_err(), "Expected error for invalid check interval");
+    }
+
+    #[test]
+    fn test_down_backoff() {
+        let parse = |toml_content: &str| -> Result<Config, Error> {
+            toml::from_str::<RawConfig>(toml_content)
+                .expect("Failed to parse config")
+                .try_into()
+        };
+
+        assert_eq!(parse("").unwrap().config.down_backoff_max_secs, None);
+        assert_eq!(
+            parse("[config]\ndown_backoff_max_secs = 600")
+                .unwrap()
+                .config
+                .down_backoff_max_secs,
+            Some(600)
+        );
+        for max_secs in [0, 9, 86400] {
+            assert!(
+                parse(&format!("[config]\ndown_backoff_max_secs = {max_secs}")).is_err(),
+                "Expected error for down_backoff_max_secs = {max_secs}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_failure_threshold() {
+        let toml_content = r#"
+            [config]
+            timeout_secs = 5
+            check_interval_secs = 60
+            failure_threshold = 0
+            webhook_url = "https://discord.com/api/webhooks/1234567890/abcdefg"
+            discord_id = 1234567890
+
+            [sites]
+            urls = ["https://www.google.com"]
+        "#;
+
+        let result: Result<Config, Error> = toml::from_str::<RawConfig>(toml_content)
+            .expect("Failed to parse config")
+            .try_into();
+
+        assert!(
+            result.is_err(),
+            "Expected error for invalid failure threshold"
+        );
+    }
+
+    #[test]
+    fn test_invalid_webhook_url() {
+        let toml_content = r#"
+            [config]
+            timeout_secs = 5
+            check_interval_secs = 60
+            failure_threshold = 5
+            webhook_url = "invalid-url"
+            discord_id = 1234567890
+            
+            [sites]
+            urls = ["https://www.google.com"]
+        "#;
+
+        let result: Result<Config, Error> = toml::from_str::<RawConfig>(toml_content)
+            .expect("Failed to parse config")
+            .try_into();
+
+        assert!(result.is_err(), "Expected error for invalid webhook URL");
+    }
+
+    #[test]
+    fn test_influxdb_section() {
+        let toml_content = r#"
+            [sites]
+            urls = ["https://www.google.com"]
+
+            [influxdb]
+            url = "http://localhost:8086"
+            org = "homelab"
+            bucket = "downdetector"
+            token = "secret"
+        "#;
+
+        let config: Config = toml::from_str::<RawConfig>(toml_content)
+            .expect("Failed to parse config")
+            .try_into()
+            .expect("Failed to convert to Config");
+
+        let influxdb = config.influxdb.expect("Expected InfluxDB options");
+        assert_eq!(influxdb.url.as_str(), "http://localhost:8086/");
+        assert_eq!(influxdb.org, "homelab");
+        assert_eq!(influxdb.bucket, "downdetector");
+    }
+
+    #[test]
+    fn test_invalid_influxdb_url() {
+        let toml_content = r#"
+            [influxdb]
+            url = "localhost:8086"
+            org = "homelab"
+            bucket = "downdetector"
+            token = "secret"
+        "#;
+
+        let result: Result<Config, Error> = toml::from_str::<RawConfig>(toml_content)
+            .expect("Failed to parse config")
+            .try_into();
+
+        assert!(result.is_err(), "Expected error for invalid InfluxDB URL");
+    }
+
+    #[test]
+    fn test_statsd_section_uses_defaults() {
+        let toml_content = r#"
+            [statsd]
+            tag_format = "dogstatsd"
+        "#;
+
+        let config: Config = toml::from_str::<RawConfig>(toml_content)
+            .expect("Failed to parse config")
+            .try_into()
+            .expect("Failed to convert to Config");
+
+        let statsd = config.statsd.expect("Expected StatsD options");
+        assert_eq!(statsd.host, DEFAULT_STATSD_HOST);
+        assert_eq!(statsd.port, DEFAULT_STATSD_PORT);
+        assert_eq!(statsd.prefix, DEFAULT_STATSD_PREFIX);
+        assert_eq!(statsd.tag_format, StatsdTagFormat::DogStatsd);
+    }
+
+    #[test]
+    fn test_invalid_statsd_prefix() {
+        let toml_content = r#"
+            [statsd]
+            prefix = "down:detector"
+        "#;
+
+        let result: Result<Config, Error> = toml::from_str::<RawConfig>(toml_content)
+            .expect("Failed to parse config")
+            .try_into();
+
+        assert!(result.is_err(), "Expected error for invalid StatsD prefix");
+    }
+
+    #[test]
+    fn test_history_section() {
+        let toml_content = r#"
+            [history]
+            path = "/var/lib/downdetector/history.jsonl"
+        "#;
+
+        let config: Config = toml::from_str::<RawConfig>(toml_content)
+            .expect("Failed to parse config")
+            .try_into()
+            .expect("Failed to convert to Config");
+
+        let history = config.history.expect("Expected history options");
+        assert_eq!(
+            history.path,
+            PathBuf::from("/var/lib/downdetector/history.jsonl")
+        );
+        assert_eq!(history.retention_days, DEFAULT_HISTORY_RETENTION_DAYS);
+    }
+
+    #[test]
+    fn test_invalid_history_retention() {
+        let toml_content = r"
+            [history]
+            retention_days = 0
+        ";
+
+        let result: Result<Config, Error> = toml::from_str::<RawConfig>(toml_content)
+            .expect("Failed to parse config")
+            .try_into();
+
+        assert!(
+            result.is_err(),
+            "Expected error for invalid history retention"
+        );
+    }
+
+    #[test]
+    fn test_server_section_uses_defaults() {
+        let config: Config = toml::from_str::<RawConfig>("[server]")
+            .expect("Failed to parse config")
+            .try_into()
+            .expect("Failed to convert to Config");
+
+        let server = config.server.expect("Expected server options");
+        assert_eq!(server.bind.to_string(), DEFAULT_SERVER_BIND);
+        assert_eq!(server.agent_timeout_secs, DEFAULT_AGENT_TIMEOUT_SECS);
+    }
+
+    #[test]
+    fn test_invalid_agent_timeout() {
+        let toml_content = r"
+            [server]
+            agent_timeout_secs = 0
+        ";
+
+        let result: Result<Config, Error> = toml::from_str::<RawConfig>(toml_content)
+            .expect("Failed to parse config")
+            .try_into();
+
+        assert!(result.is_err(), "Expected error for a zero agent timeout");
+    }
+
+    #[test]
+    fn test_invalid_server_bind() {
+        let toml_content = r#"
+            [server]
+            bind = "localhost"
+        "#;
+
+        let result: Result<Config, Error> = toml::from_str::<RawConfig>(toml_content)
+            .expect("Failed to parse config")
+            .try_into();
+
+        assert!(result.is_err(), "Expected error for invalid bind address");
+    }
+
+    #[test]
+    fn test_invalid_discord_public_key() {
+        let toml_content = r#"
+            [server]
+            discord_public_key = "not-a-key"
+        "#;
+
+        let result: Result<Config, Error> = toml::from_str::<RawConfig>(toml_content)
+            .expect("Failed to parse config")
+            .try_into();
+
+        assert!(
+            result.is_err(),
+            "Expected error for invalid Discord public key"
+        );
+    }
+
+    #[test]
+    fn test_agent_section() {
+        let toml_content = r#"
+            [agent]
+            central_url = "https://central.example.com"
+            name = "vpc-eu-west"
+            token = "secret"
+        "#;
+
+        let config: Config = toml::from_str::<RawConfig>(toml_content)
+            .expect("Failed to parse config")
+            .try_into()
+            .expect("Failed to convert to Config");
+
+        let agent = config.agent.expect("Expected agent options");
+        assert_eq!(agent.central_url.as_str(), "https://central.example.com/");
+        assert_eq!(agent.name, "vpc-eu-west");
+    }
+
+    #[test]
+    fn test_invalid_agent_name() {
+        let toml_content = r#"
+            [agent]
+            central_url = "https://central.example.com"
+            name = " "
+            token = "secret"
+        "#;
+
+        let result: Result<Config, Error> = toml::from_str::<RawConfig>(toml_content)
+            .expect("Failed to parse config")
+            .try_into();
+
+        assert!(result.is_err(), "Expected error for empty agent name");
+    }
+
+    #[test]
+    fn test_confirmation_section() {
+        let toml_content = r#"
+            [confirmation]
+            proxy = "http://proxy.internal:3128"
+        "#;
+
+        let config: Config = toml::from_str::<RawConfig>(toml_content)
+            .expect("Failed to parse config")
+            .try_into()
+            .expect("Failed to convert to Config");
+
+        let confirmation = config.confirmation.expect("Expected confirmation options");
+        assert_eq!(
+            confirmation.proxy.map(String::from).as_deref(),
+            Some("http://proxy.internal:3128/")
+        );
+        assert!(confirmation.probe_url.is_none());
+    }
+
+    #[test]
+    fn test_invalid_confirmation() {
+        for toml_content in [
+            "[confirmation]",
+            "[confirmation]\nproxy = \"socks5://proxy.internal:1080\"",
+        ] {
+            let result: Result<Config, Error> = toml::from_str::<RawConfig>(toml_content)
+                .expect("Failed to parse config")
+                .try_into();
+
+            assert!(
+                result.is_err(),
+                "Expected error for invalid confirmation: {toml_content}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_diagnostics_section() {
+        let parse = |toml_content: &str| -> Result<Config, Error> {
+            toml::from_str::<RawConfig>(toml_content)
+                .expect("Failed to parse config")
+                .try_into()
+        };
+
+        assert!(parse("").unwrap().diagnostics.is_none());
+
+        let diagnostics = parse("[diagnostics]")
+            .unwrap()
+            .diagnostics
+            .expect("Expected diagnostics options");
+        assert!(diagnostics.traceroute);
+        assert_eq!(diagnostics.traceroute_max_hops, DEFAULT_TRACEROUTE_MAX_HOPS);
+
+        let diagnostics = parse("[diagnostics]\ntraceroute = false")
+            .unwrap()
+            .diagnostics
+            .unwrap();
+        assert!(!diagnostics.traceroute);
+
+        for max_hops in [0, 31] {
+            assert!(
+                parse(&format!("[diagnostics]\ntraceroute_max_hops = {max_hops}")).is_err(),
+                "Expected error for traceroute_max_hops = {max_hops}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_capture_section() {
+        let toml_content = r#"
+            [capture]
+            path = "/var/lib/downdetector/captures"
+            max_body_kb = 64
+        "#;
+
+        let config: Config = toml::from_str::<RawConfig>(toml_content)
+            .expect("Failed to parse config")
+            .try_into()
+            .expect("Failed to convert to Config");
+
+        let capture = config.capture.expect("Expected capture options");
+        assert_eq!(
+            capture.path,
+            PathBuf::from("/var/lib/downdetector/captures")
+        );
+        assert_eq!(capture.max_body_kb, 64);
+        assert_eq!(capture.retention_days, DEFAULT_CAPTURE_RETENTION_DAYS);
+        assert_eq!(capture.max_files, DEFAULT_CAPTURE_MAX_FILES);
+    }
+
+    #[test]
+    fn test_invalid_capture() {
+        for toml_content in [
+            "[capture]\nmax_body_kb = 0",
+            "[capture]\nmax_body_kb = 2048",
+            "[capture]\nretention_days = 0",
+            "[capture]\nmax_files = 0",
+        ] {
+            let result: Result<Config, Error> = toml::from_str::<RawConfig>(toml_content)
+                .expect("Failed to parse config")
+                .try_into();
+
+            assert!(
+                result.is_err(),
+                "Expected error for invalid capture: {toml_content}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_kubernetes_section() {
+        let result: Result<Config, Error> =
+            toml::from_str::<RawConfig>("[kubernetes]\nannotation = \"downdetector/enable\"")
+                .expect("Failed to parse config")
+                .try_into();
+        assert_eq!(
+            result.is_ok(),
+            cfg!(feature = "k8s"),
+            "Kubernetes discovery is only available with the k8s feature"
+        );
+
+        let result: Result<Config, Error> =
+            toml::from_str::<RawConfig>("[kubernetes]\nnamespace = \" \"")
+                .expect("Failed to parse config")
+                .try_into();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_rate_limit_section() {
+        let config: Config = toml::from_str::<RawConfig>(
+            "[rate_limit]\nrequests_per_sec = 0.5\nper_host_delay_ms = 2000",
+        )
+        .expect("Failed to parse config")
+        .try_into()
+        .expect("Failed to convert to Config");
+        assert_eq!(
+            config.rate_limit,
+            Some(RateLimitOptions {
+                requests_per_sec: Some(0.5),
+                per_host_delay_ms: Some(2000),
+            })
+        );
+
+        for toml_content in [
+            "[rate_limit]",
+            "[rate_limit]\nrequests_per_sec = 0",
+            "[rate_limit]\nrequests_per_sec = -1.0",
+            "[rate_limit]\nrequests_per_sec = 1e-300",
+            "[rate_limit]\nper_host_delay_ms = 0",
+        ] {
+            let result: Result<Config, Error> = toml::from_str::<RawConfig>(toml_content)
+                .expect("Failed to parse config")
+                .try_into();
+            assert!(result.is_err(), "Expected an error for: {toml_content}");
+        }
+    }
+
+    #[test]
+    fn test_latency_section() {
+        let config: Config = toml::from_str::<RawConfig>(
+            r#"
+            [latency]
+            p95_ms = 800
+
+            [sites]
+            urls = [
+                "https://a.example",
+                { url = "https://b.example", latency_p95_ms = 2000 },
+            ]
+            "#,
+        )
+        .expect("Failed to parse config")
+        .try_into()
+        .expect("Failed to convert to Config");
+        assert_eq!(
+            config.latency,
+            LatencyOptions {
+                p95_ms: Some(800),
+                window_checks: DEFAULT_LATENCY_WINDOW_CHECKS,
+            }
+        );
+        let threshold = |url| config.sites.latency_p95_ms(url, config.latency.p95_ms);
+        assert_eq!(threshold("https://a.example"), Some(800));
+        assert_eq!(threshold("https://b.example"), Some(2000));
 
-    if config_path.exists() {
-        return Ok(config_path);
+        for toml_content in [
+            "[latency]\np95_ms = 0",
+            "[latency]\nwindow_checks = 0",
+            "[latency]\nwindow_checks = 1001",
+            "[sites]\nurls = [{ url = \"https://a.example\", latency_p95_ms = 0 }]",
+        ] {
+            let result: Result<Config, Error> = toml::from_str::<RawConfig>(toml_content)
+                .expect("Failed to parse config")
+                .try_into();
+            assert!(result.is_err(), "Expected an error for: {toml_content}");
+        }
     }
 
-    if let Some(parent) = config_path.parent() {
-        fs::create_dir_all(parent)?;
-    }
+    #[test]
+    fn test_cycle_summary_section() {
+        let sites = r#"
+            [sites]
+            urls = [{ url = "https://a.example", tags = ["web"] }]
+        "#;
+        let load = |section: &str| -> Result<Config, Error> {
+            toml::from_str::<RawConfig>(&format!("{sites}\n{section}"))
+                .expect("Failed to parse config")
+                .try_into()
+        };
 
-    fs::write(&config_path, DEFAULT_CONFIG)?;
-    Ok(config_path)
-}
+        assert_eq!(
+            load("[cycle_summary]").unwrap().cycle_summary,
+            Some(CycleSummaryOptions { tags: Vec::new() })
+        );
+        assert_eq!(
+            load("[cycle_summary]\ntags = [\"web\"]")
+                .unwrap()
+                .cycle_summary,
+            Some(CycleSummaryOptions {
+                tags: vec!["web".to_string()]
+            })
+        );
+        assert!(load("[cycle_summary]\ntags = [\"api\"]").is_err());
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_docker_section() {
+        let config: Result<Config, Error> = toml::from_str::<RawConfig>("[docker]")
+            .expect("Failed to parse config")
+            .try_into();
+        if cfg!(unix) {
+            let docker = config.unwrap().docker.unwrap();
+            assert_eq!(docker.socket, PathBuf::from(DEFAULT_DOCKER_SOCKET));
+            assert_eq!(docker.label, DEFAULT_DOCKER_LABEL);
+        } else {
+            assert!(config.is_err());
+        }
 
-    const EXAMPLE_CONFIG: &str = include_str!("../config.example.toml");
+        let result: Result<Config, Error> = toml::from_str::<RawConfig>("[docker]\nlabel = \"\"")
+            .expect("Failed to parse config")
+            .try_into();
+        assert!(result.is_err());
+    }
 
     #[test]
-    fn default_config_is_valid() {
-        let _config: Config = toml::from_str::<RawConfig>(DEFAULT_CONFIG)
+    fn test_site_source() {
+        let toml_content = r#"
+            [sites]
+            urls = ["https://example.com"]
+            source_url = "https://inventory.example/sites.json"
+        "#;
+
+        let config: Config = toml::from_str::<RawConfig>(toml_content)
             .expect("Failed to parse config")
             .try_into()
             .expect("Failed to convert to Config");
+
+        assert_eq!(
+            config.sites.source,
+            Some(SiteSource {
+                url: "https://inventory.example/sites.json".to_string(),
+                refresh_secs: DEFAULT_SITE_SOURCE_REFRESH_SECS,
+            })
+        );
+
+        for toml_content in [
+            "[sites]\nsource_url = \"ftp://inventory.example/sites.json\"",
+            "[sites]\nsource_url = \"https://inventory.example\"\nsource_refresh_secs = 5",
+        ] {
+            let result: Result<Config, Error> = toml::from_str::<RawConfig>(toml_content)
+                .expect("Failed to parse config")
+                .try_into();
+            assert!(
+                result.is_err(),
+                "Expected error for invalid site source: {toml_content}"
+            );
+        }
     }
 
     #[test]
-    fn example_config_is_valid() {
-        let _config: Config = toml::from_str::<RawConfig>(EXAMPLE_CONFIG)
+    fn test_save_site_urls_keeps_the_rest_of_the_file() {
+        let path = temp_dir("sites").join("config.toml");
+        fs::write(&path, EXAMPLE_CONFIG).unwrap();
+
+        save_site_urls(
+            &path,
+            &[
+                "https://a.example".to_string(),
+                "https://b.example".to_string(),
+            ],
+        )
+        .unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(
+            content.contains("urls = [\n    \"https://a.example\",\n    \"https://b.example\",\n]")
+        );
+        let config: Config = toml::from_str::<RawConfig>(&content)
             .expect("Failed to parse config")
             .try_into()
             .expect("Failed to convert to Config");
+        assert_eq!(
+            config.sites.urls,
+            ["https://a.example", "https://b.example"]
+        );
+        assert_eq!(config.config.check_interval_secs, 60);
     }
 
     #[test]
-    fn test_load_config_from_toml() {
+    fn test_save_site_urls_keeps_site_settings() {
+        let path = temp_dir("site-settings").join("config.toml");
+        fs::write(
+            &path,
+            "[sites]\nurls = [\"https://a.example\", { url = \"https://b.example\", slo = 99.9 }]\n",
+        )
+        .unwrap();
+
+        save_site_urls(
+            &path,
+            &[
+                "https://b.example".to_string(),
+                "https://c.example".to_string(),
+            ],
+        )
+        .unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.contains(
+            "urls = [\n    { url = \"https://b.example\", slo = 99.9 },\n    \"https://c.example\",\n]"
+        ));
+    }
+
+    #[test]
+    fn test_save_site_urls_keeps_skipped_entries() {
+        let path = temp_dir("site-skipped").join("config.toml");
+        fs::write(
+            &path,
+            "[sites]\nstrict = false\nurls = [\"invalid-url\", \"https://a.example\", { url = \"https://b.example\", unknown = true }]\n",
+        )
+        .unwrap();
+
+        save_site_urls(&path, &["https://c.example".to_string()]).unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.contains(
+            "urls = [\n    \"https://c.example\",\n    \"invalid-url\",\n    { url = \"https://b.example\", unknown = true },\n]"
+        ));
+    }
+
+    #[test]
+    fn test_site_tables_carry_settings() {
         let toml_content = r#"
-            [config]
-            timeout_secs = 5
-            check_interval_secs = 60
-            failure_threshold = 5
-            webhook_url = "https://discord.com/api/webhooks/1234567890/abcdefg"
-            discord_id = 1234567890
-            
             [sites]
             urls = [
-                "https://www.google.com",
-                "https://www.rust-lang.org",
-                "https://invalid.url"
+                "https://a.example",
+                { url = "https://b.example", slo = 99.9 },
             ]
+
+            [history]
+            path = "/tmp/history.jsonl"
+
+            [slo]
+            window_days = 7
         "#;
 
         let config: Config = toml::from_str::<RawConfig>(toml_content)
@@ -293,30 +3058,27 @@ mod tests {
             .try_into()
             .expect("Failed to convert to Config");
 
-        assert_eq!(config.config.timeout_secs, 5);
-        assert_eq!(config.config.check_interval_secs, 60);
-        assert_eq!(config.config.failure_threshold, 5);
-        assert_eq!(config.sites.urls.len(), 3);
-        assert_eq!(config.sites.urls[0], "https://www.google.com");
-        assert_eq!(config.sites.urls[1], "https://www.rust-lang.org");
-        assert_eq!(config.sites.urls[2], "https://invalid.url");
-        assert_eq!(config.config.discord_id, Some(1_234_567_890));
         assert_eq!(
-            config.config.webhook_url,
-            Some("https://discord.com/api/webhooks/1234567890/abcdefg".to_string())
+            config.sites.urls,
+            ["https://a.example", "https://b.example"]
         );
+        assert!(!config.sites.options.contains_key("https://a.example"));
+        assert_eq!(config.sites.options["https://b.example"].slo, Some(99.9));
+        let slo = config.slo.expect("Expected SLO options");
+        assert_eq!(slo.window_days, 7);
+        assert!((slo.burn_rate_alert - DEFAULT_SLO_BURN_RATE_ALERT).abs() < f64::EPSILON);
     }
 
     #[test]
-    fn test_partial_config_uses_defaults() {
-        // Minimal config with defaults
+    fn test_quiet_hours_section() {
         let toml_content = r#"
-            [config]
-            webhook_url = "https://discord.com/api/webhooks/1234567890/abcdefg"
-            discord_id = 1234567890
-            
             [sites]
-            urls = ["https://www.google.com"]
+            urls = [{ url = "https://a.example", critical = true }]
+
+            [quiet_hours]
+            start = "23:00"
+            end = "07:30"
+            timezone = "Europe/Paris"
         "#;
 
         let config: Config = toml::from_str::<RawConfig>(toml_content)
@@ -324,24 +3086,35 @@ mod tests {
             .try_into()
             .expect("Failed to convert to Config");
 
-        // Should use default values
-        assert_eq!(config.config.timeout_secs, DEFAULT_TIMEOUT_SECS);
         assert_eq!(
-            config.config.check_interval_secs,
-            DEFAULT_CHECK_INTERVAL_SECS
+            config.sites.options["https://a.example"].severity,
+            Severity::Critical
         );
-        assert_eq!(config.config.failure_threshold, DEFAULT_FAILURE_THRESHOLD);
+        let quiet_hours = config.quiet_hours.expect("Expected quiet hours");
+        assert_eq!(quiet_hours.start, Time::constant(23, 0, 0, 0));
+        assert_eq!(quiet_hours.end, Time::constant(7, 30, 0, 0));
+        assert_eq!(quiet_hours.timezone.iana_name(), Some("Europe/Paris"));
     }
 
     #[test]
-    fn test_empty_config_section_uses_defaults() {
-        // Config with empty sections
+    fn test_routing_defaults_to_config_webhook() {
         let toml_content = r#"
             [config]
             webhook_url = "https://discord.com/api/webhooks/1234567890/abcdefg"
             discord_id = 1234567890
-            
+
             [sites]
+            urls = [{ url = "https://a.example", severity = "info" }]
+
+            [routing.critical]
+            role_id = 42
+
+            [routing.warning]
+            mention = false
+            silent = true
+
+            [routing.info]
+            webhook_url = "https://discord.com/api/webhooks/987/low-priority"
         "#;
 
         let config: Config = toml::from_str::<RawConfig>(toml_content)
@@ -349,114 +3122,398 @@ mod tests {
             .try_into()
             .expect("Failed to convert to Config");
 
-        // Sites should have empty URLs vector
-        assert_eq!(config.sites.urls.len(), 0);
+        assert_eq!(
+            config.sites.options["https://a.example"].severity,
+            Severity::Info
+        );
+        let default_webhook =
+            Some("https://discord.com/api/webhooks/1234567890/abcdefg".to_string());
+        assert_eq!(
+            config.routing.critical,
+            RouteOptions {
+                webhook_url: default_webhook.clone(),
+                discord_id: Some(1_234_567_890),
+                role_id: Some(42),
+                silent: false,
+            }
+        );
+        assert_eq!(
+            config.routing.warning,
+            RouteOptions {
+                webhook_url: default_webhook,
+                discord_id: None,
+                role_id: None,
+                silent: true,
+            }
+        );
+        assert_eq!(
+            config.routing.info.webhook_url.as_deref(),
+            Some("https://discord.com/api/webhooks/987/low-priority")
+        );
+        assert_eq!(config.routing.info.discord_id, Some(1_234_567_890));
     }
 
     #[test]
-    fn test_invalid_timeout() {
-        let toml_content = r#"
+    fn test_invalid_routing() {
+        for toml_content in [
+            "[routing.urgent]\nsilent = true",
+            "[routing.info]\nwebhook_url = \"https://example.com/hook\"",
+            "[sites]\nurls = [{ url = \"https://a.example\", severity = \"info\", critical = true }]",
+        ] {
+            let result = toml::from_str::<RawConfig>(toml_content)
+                .map_err(Error::from)
+                .and_then(Config::try_from);
+
+            assert!(
+                result.is_err(),
+                "Expected error for invalid routing: {toml_content}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_quiet_hours() {
+        for toml_content in [
+            "[quiet_hours]\nstart = \"11pm\"\nend = \"07:00\"",
+            "[quiet_hours]\nstart = \"07:00\"\nend = \"07:00\"",
+            "[quiet_hours]\nstart = \"23:00\"\nend = \"07:00\"\ntimezone = \"Mars/Olympus\"",
+        ] {
+            let result: Result<Config, Error> = toml::from_str::<RawConfig>(toml_content)
+                .expect("Failed to parse config")
+                .try_into();
+
+            assert!(
+                result.is_err(),
+                "Expected error for invalid quiet hours: {toml_content}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_incident_threads_section() {
+        let config: Config =
+            toml::from_str::<RawConfig>("[incident_threads]\nbot_token = \"bot-secret\"")
+                .expect("Failed to parse config")
+                .try_into()
+                .expect("Failed to convert to Config");
+
+        let threads = config.incident_threads.expect("Expected incident threads");
+        assert!(!threads.bot_token.is_empty());
+        assert!(
+            toml::from_str::<RawConfig>("")
+                .unwrap()
+                .incident_threads
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_templates_section() {
+        let config: Config = toml::from_str::<RawConfig>(
+            "[templates]\ndown = \"{{site.name}} is down ({{latency_ms}} ms)\"",
+        )
+        .expect("Failed to parse config")
+        .try_into()
+        .expect("Failed to convert to Config");
+        assert_ne!(config.templates.down, MessageTemplates::default().down);
+        assert_eq!(
+            config.templates.recovery,
+            MessageTemplates::default().recovery
+        );
+
+        let result: Result<Config, Error> =
+            toml::from_str::<RawConfig>("[templates]\ndown = \"{{duration}}\"")
+                .expect("Failed to parse config")
+                .try_into();
+        assert!(
+            matches!(result, Err(Error::Config(message)) if message.contains("templates down")),
+            "Variables of other templates are rejected"
+        );
+
+        let config: Config = toml::from_str::<RawConfig>(
+            "[templates]\ndegraded = \"{{site.name}} ({{severity}}): {{reason}}\"",
+        )
+        .expect("Failed to parse config")
+        .try_into()
+        .expect("Failed to convert to Config");
+        assert_eq!(
+            config.templates.degraded.render(
+                &Variables::default()
+                    .set("site.name", "a.example")
+                    .set("severity", "warning")
+                    .set("reason", "Response body of 12 bytes, expected at least 512")
+            ),
+            "a.example (warning): Response body of 12 bytes, expected at least 512"
+        );
+    }
+
+    #[test]
+    fn test_pinned_cert_sha256() {
+        let pin = "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a";
+        let config: Config = toml::from_str::<RawConfig>(&format!(
+            "[sites]\nurls = [{{ url = \"https://a.example\", pinned_cert_sha256 = \"{pin}\" }}]"
+        ))
+        .expect("Failed to parse config")
+        .try_into()
+        .expect("Failed to convert to Config");
+        assert_eq!(
+            config.sites.options["https://a.example"].pinned_cert_sha256,
+            pinning::parse_fingerprint(pin)
+        );
+
+        for toml_content in [
+            format!(
+                "[sites]\nurls = [{{ url = \"http://a.example\", pinned_cert_sha256 = \"{pin}\" }}]"
+            ),
+            "[sites]\nurls = [{ url = \"https://a.example\", pinned_cert_sha256 = \"abcd\" }]"
+                .to_string(),
+        ] {
+            let result: Result<Config, Error> = toml::from_str::<RawConfig>(&toml_content)
+                .expect("Failed to parse config")
+                .try_into();
+            assert!(result.is_err(), "Expected an error for: {toml_content}");
+        }
+    }
+
+    #[test]
+    fn test_success_statuses() {
+        let config: Config = toml::from_str::<RawConfig>(
+            r#"
             [config]
-            timeout_secs = 0
-            check_interval_secs = 60
-            webhook_url = "https://discord.com/api/webhooks/1234567890/abcdefg"
-            discord_id = 1234567890
-            
+            success_status_classes = ["2xx", "3xx"]
+
             [sites]
-            urls = ["https://www.google.com"]
-        "#;
+            urls = [
+                "https://a.example",
+                { url = "https://b.example", expected_status = 401 },
+                { url = "https://c.example", expected_status = [200, "204"] },
+            ]
+            "#,
+        )
+        .expect("Failed to parse config")
+        .try_into()
+        .expect("Failed to convert to Config");
 
-        let result: Result<Config, Error> = toml::from_str::<RawConfig>(toml_content)
-            .expect("Failed to parse config")
-            .try_into();
+        let statuses = |url| {
+            config
+                .sites
+                .success_statuses(url, &config.config.success_status_classes)
+        };
+        assert!(statuses("https://a.example").matches(301));
+        assert!(statuses("https://a.example").matches(200));
+        assert!(!statuses("https://a.example").matches(404));
+        assert!(statuses("https://b.example").matches(401));
+        assert!(!statuses("https://b.example").matches(200));
+        assert!(statuses("https://c.example").matches(204));
+        assert!(!statuses("https://c.example").matches(201));
 
-        assert!(result.is_err(), "Expected error for invalid timeout");
+        let defaults = SuccessStatuses::default();
+        assert!(defaults.matches(204));
+        assert!(!defaults.matches(301));
     }
 
     #[test]
-    fn test_invalid_check_interval() {
-        let toml_content = r#"
-            [config]
-            timeout_secs = 5
-            check_interval_secs = 86400
-            failure_threshold = 5
-            webhook_url = "https://discord.com/api/webhooks/1234567890/abcdefg"
-            discord_id = 1234567890
-            
+    fn test_site_body_size_bounds() {
+        let config: Config = toml::from_str::<RawConfig>(
+            r#"
             [sites]
-            urls = ["https://www.google.com"]
-        "#;
+            urls = [
+                { url = "https://a.example", min_body_bytes = 512 },
+                { url = "https://b.example", max_body_bytes = 1024, body_size_mismatch = "degraded" },
+            ]
+            "#,
+        )
+        .expect("Failed to parse config")
+        .try_into()
+        .expect("Failed to convert to Config");
 
-        let result: Result<Config, Error> = toml::from_str::<RawConfig>(toml_content)
-            .expect("Failed to parse config")
-            .try_into();
+        let bounds = |url: &str| config.sites.options[url].body_size.unwrap();
+        assert_eq!(
+            bounds("https://a.example"),
+            BodySizeBounds {
+                min_bytes: Some(512),
+                max_bytes: None,
+                mismatch: BodySizeMismatch::Down,
+            }
+        );
+        assert_eq!(bounds("https://a.example").read_limit(), 512);
+        assert_eq!(
+            bounds("https://b.example").mismatch,
+            BodySizeMismatch::Degraded
+        );
+        assert_eq!(bounds("https://b.example").read_limit(), 1025);
 
-        assert!(result.is_err(), "Expected error for invalid check interval");
+        for toml_content in [
+            "[sites]\nurls = [{ url = \"https://a.example\", min_body_bytes = 10, max_body_bytes = 5 }]",
+            "[sites]\nurls = [{ url = \"https://a.example\", body_size_mismatch = \"degraded\" }]",
+        ] {
+            let result: Result<Config, Error> = toml::from_str::<RawConfig>(toml_content)
+                .expect("Failed to parse config")
+                .try_into();
+            assert!(result.is_err(), "Expected an error for: {toml_content}");
+        }
     }
 
     #[test]
-    fn test_invalid_failure_threshold() {
-        let toml_content = r#"
+    fn test_site_timeouts() {
+        let config: Config = toml::from_str::<RawConfig>(
+            r#"
             [config]
-            timeout_secs = 5
-            check_interval_secs = 60
-            failure_threshold = 0
-            webhook_url = "https://discord.com/api/webhooks/1234567890/abcdefg"
-            discord_id = 1234567890
+            timeout_secs = 20
+            connect_timeout_secs = 5
 
             [sites]
-            urls = ["https://www.google.com"]
-        "#;
+            urls = [
+                "https://a.example",
+                { url = "https://b.example", timeout_secs = 60, read_timeout_secs = 30 },
+            ]
+            "#,
+        )
+        .expect("Failed to parse config")
+        .try_into()
+        .expect("Failed to convert to Config");
 
-        let result: Result<Config, Error> = toml::from_str::<RawConfig>(toml_content)
+        assert_eq!(
+            config.sites.timeouts("https://a.example", &config.config),
+            Timeouts {
+                total: 20,
+                connect: Some(5),
+                read: None,
+            }
+        );
+        assert_eq!(
+            config.sites.timeouts("https://b.example", &config.config),
+            Timeouts {
+                total: 60,
+                connect: Some(5),
+                read: Some(30),
+            }
+        );
+
+        for toml_content in [
+            "[config]\nconnect_timeout_secs = 0",
+            "[config]\ntimeout_secs = 10\nread_timeout_secs = 11",
+            "[sites]\nurls = [{ url = \"https://a.example\", timeout_secs = 0 }]",
+            "[config]\nconnect_timeout_secs = 20\n[sites]\nurls = [{ url = \"https://a.example\", timeout_secs = 10 }]",
+        ] {
+            let result: Result<Config, Error> = toml::from_str::<RawConfig>(toml_content)
+                .expect("Failed to parse config")
+                .try_into();
+            assert!(result.is_err(), "Expected an error for: {toml_content}");
+        }
+    }
+
+    #[test]
+    fn test_invalid_success_statuses() {
+        for toml_content in [
+            "[config]\nsuccess_status_classes = []",
+            "[config]\nsuccess_status_classes = [\"6xx\"]",
+            "[config]\nsuccess_status_classes = [99]",
+            "[config]\nsuccess_status_classes = \"ok\"",
+            "[sites]\nurls = [{ url = \"https://a.example\", expected_status = [\"2xy\"] }]",
+        ] {
+            let result: Result<Config, Error> = toml::from_str::<RawConfig>(toml_content)
+                .expect("Failed to parse config")
+                .try_into();
+            assert!(result.is_err(), "Expected an error for: {toml_content}");
+        }
+    }
+
+    #[test]
+    fn test_language() {
+        let config: Config = toml::from_str::<RawConfig>("[config]\nlanguage = \"fr\"")
             .expect("Failed to parse config")
-            .try_into();
+            .try_into()
+            .expect("Failed to convert to Config");
+        assert_eq!(config.config.language, Language::Fr);
+        assert_eq!(config.templates, MessageTemplates::builtin(Language::Fr));
 
         assert!(
-            result.is_err(),
-            "Expected error for invalid failure threshold"
+            toml::from_str::<RawConfig>("[config]\nlanguage = \"it\"").is_err(),
+            "Unsupported languages are rejected"
         );
     }
 
     #[test]
-    fn test_invalid_webhook_url() {
+    fn test_invalid_slo() {
+        for toml_content in [
+            // Out of range target
+            "[sites]\nurls = [{ url = \"https://a.example\", slo = 100.0 }]\n[history]",
+            // No history to compute it from
+            "[sites]\nurls = [{ url = \"https://a.example\", slo = 99.9 }]",
+            // Window longer than the history retention
+            "[sites]\nurls = [{ url = \"https://a.example\", slo = 99.9 }]\n[history]\nretention_days = 7\n[slo]\nwindow_days = 30",
+        ] {
+            let result: Result<Config, Error> = toml::from_str::<RawConfig>(toml_content)
+                .expect("Failed to parse config")
+                .try_into();
+
+            assert!(
+                result.is_err(),
+                "Expected error for invalid SLO: {toml_content}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_monitored_url() {
         let toml_content = r#"
             [config]
             timeout_secs = 5
             check_interval_secs = 60
             failure_threshold = 5
-            webhook_url = "invalid-url"
+            webhook_url = "https://discord.com/api/webhooks/1234567890/abcdefg"
             discord_id = 1234567890
             
             [sites]
-            urls = ["https://www.google.com"]
+            urls = ["invalid-url"]
         "#;
 
         let result: Result<Config, Error> = toml::from_str::<RawConfig>(toml_content)
             .expect("Failed to parse config")
             .try_into();
 
-        assert!(result.is_err(), "Expected error for invalid webhook URL");
+        assert!(result.is_err(), "Expected error for invalid URL");
     }
 
     #[test]
-    fn test_invalid_monitored_url() {
+    fn test_non_strict_sites_skip_invalid_entries() {
         let toml_content = r#"
-            [config]
-            timeout_secs = 5
-            check_interval_secs = 60
-            failure_threshold = 5
-            webhook_url = "https://discord.com/api/webhooks/1234567890/abcdefg"
-            discord_id = 1234567890
-            
             [sites]
-            urls = ["invalid-url"]
+            strict = false
+            urls = [
+                "invalid-url",
+                "https://a.example",
+                { url = "https://b.example", slo = 120.0 },
+                { url = "https://c.example", tags = ["web"] },
+                { url = "https://d.example", unknown = true },
+                { url = "https://e.example", slo = "high" },
+                42,
+            ]
         "#;
 
-        let result: Result<Config, Error> = toml::from_str::<RawConfig>(toml_content)
+        let config: Config = toml::from_str::<RawConfig>(toml_content)
             .expect("Failed to parse config")
-            .try_into();
+            .try_into()
+            .unwrap();
 
-        assert!(result.is_err(), "Expected error for invalid URL");
+        assert_eq!(
+            config.sites.urls,
+            ["https://a.example", "https://c.example"]
+        );
+        assert!(config.sites.has_tag("web"));
+
+        let config: Config = toml::from_str::<RawConfig>("[sites]\nstrict = false")
+            .unwrap()
+            .try_into()
+            .unwrap();
+        assert!(config.sites.urls.is_empty());
+
+        let strict: Result<Config, _> = toml::from_str::<RawConfig>(
+            "[sites]\nurls = [{ url = \"https://d.example\", unknown = true }]",
+        )
+        .unwrap()
+        .try_into();
+        assert!(strict.is_err());
     }
 }
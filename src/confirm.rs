@@ -0,0 +1,231 @@
+use log::warn;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use url::Url;
+
+use crate::config::{ConfigOptions, ConfirmationOptions, SuccessStatuses, join_url_path};
+use crate::error::Error;
+use crate::rate_limit::RateLimiter;
+use crate::worker::{PacedClient, build_check_client, is_url_up};
+
+/// Path of the endpoint other instances ask to check a site on their behalf.
+pub(crate) const PROBE_PATH: &str = "api/v1/probe";
+
+/// Extra time given to a probe on top of its own check timeout.
+const PROBE_TIMEOUT_MARGIN_SECS: u64 = 5;
+
+/// Site a probe instance is asked to check.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct ProbeRequest {
+    pub(crate) url: String,
+    /// Whether the probe should check the way low-bandwidth mode does.
+    #[serde(default)]
+    pub(crate) low_bandwidth: bool,
+    /// Statuses counting as the site being up, only 2xx when omitted.
+    #[serde(default)]
+    pub(crate) success_statuses: SuccessStatuses,
+}
+
+/// Outcome of a check made by a probe instance.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct ProbeResponse {
+    pub(crate) url: String,
+    pub(crate) up: bool,
+    pub(crate) response_time_ms: u64,
+}
+
+/// Second opinion on sites about to be reported DOWN.
+#[derive(Debug)]
+pub(crate) struct Confirmer {
+    /// Check client sending its requests through the configured proxy.
+    proxy_client: Option<Client>,
+    /// Probe instance base URL and the token it expects.
+    probe: Option<(Url, String)>,
+}
+
+impl Confirmer {
+    pub(crate) fn new(
+        options: &ConfirmationOptions,
+        check_options: &ConfigOptions,
+    ) -> Result<Self, Error> {
+        let proxy_client = options
+            .proxy
+            .as_ref()
+            .map(|proxy| build_check_client(check_options, Some(proxy)))
+            .transpose()?;
+        let probe = options.probe_url.clone().zip(options.probe_token.clone());
+
+        Ok(Self {
+            proxy_client,
+            probe,
+        })
+    }
+
+    /// Checks `url` again from every configured vantage point and returns the
+    /// first one that still reaches it, `None` when the outage is confirmed.
+    ///
+    /// A probe that cannot be asked at all does not count as seeing the site
+    /// up, so a broken probe never silences a real outage. Every request
+    /// waits for `rate_limiter`.
+    pub(crate) async fn up_vantage(
+        &self,
+        url: &str,
+        success: &SuccessStatuses,
+        timeout_secs: u64,
+        low_bandwidth: bool,
+        rate_limiter: Option<&RateLimiter>,
+    ) -> Option<&'static str> {
+        if let Some(client) = &self.proxy_client
+            && is_url_up(
+                PacedClient {
+                    client,
+                    rate_limiter,
+                },
+                url,
+                success,
+                timeout_secs,
+                low_bandwidth,
+            )
+            .await
+            .unwrap_or(false)
+        {
+            return Some("the confirmation proxy");
+        }
+
+        if let Some((probe_url, token)) = &self.probe {
+            let request = ProbeRequest {
+                url: url.to_string(),
+                low_bandwidth,
+                success_statuses: success.clone(),
+            };
+            RateLimiter::wait_for(rate_limiter, probe_url.as_str()).await;
+            match ask_probe(probe_url, token, &request, timeout_secs).await {
+                Ok(true) => return Some("the confirmation probe"),
+                Ok(false) => {}
+                Err(e) => warn!("Confirmation probe could not check {url}: {e}"),
+            }
+        }
+
+        None
+    }
+}
+
+/// Asks another instance whether it can reach `url`.
+async fn ask_probe(
+    probe_url: &Url,
+    token: &str,
+    request: &ProbeRequest,
+    timeout_secs: u64,
+) -> Result<bool, Error> {
+    let response: ProbeResponse = Client::new()
+        .post(join_url_path(probe_url, PROBE_PATH)?)
+        .bearer_auth(token)
+        .timeout(Duration::from_secs(
+            timeout_secs.saturating_add(PROBE_TIMEOUT_MARGIN_SECS),
+        ))
+        .json(request)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    Ok(response.up)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{check_options, spawn_test_http_server};
+
+    #[tokio::test]
+    async fn test_proxy_that_reaches_the_site_holds_back_the_alert() {
+        // Proxied plain HTTP requests are sent to the proxy as-is, so a
+        // server answering 200 to everything acts as a proxy seeing the site up
+        let proxy = spawn_test_http_server("HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n").await;
+        let confirmer = Confirmer::new(
+            &ConfirmationOptions {
+                proxy: Some(Url::parse(&proxy).unwrap()),
+                probe_url: None,
+                probe_token: None,
+            },
+            &check_options(60),
+        )
+        .unwrap();
+
+        assert_eq!(
+            confirmer
+                .up_vantage(
+                    "http://unreachable.invalid",
+                    &SuccessStatuses::default(),
+                    5,
+                    false,
+                    None
+                )
+                .await,
+            Some("the confirmation proxy")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_probe_verdict_is_used() {
+        let body = r#"{"url":"http://unreachable.invalid","up":true,"response_time_ms":42}"#;
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        );
+        let probe = spawn_test_http_server(response.leak()).await;
+        let confirmer = Confirmer::new(
+            &ConfirmationOptions {
+                proxy: None,
+                probe_url: Some(Url::parse(&probe).unwrap()),
+                probe_token: Some("probe-secret".to_string()),
+            },
+            &check_options(60),
+        )
+        .unwrap();
+
+        assert_eq!(
+            confirmer
+                .up_vantage(
+                    "http://unreachable.invalid",
+                    &SuccessStatuses::default(),
+                    5,
+                    false,
+                    None
+                )
+                .await,
+            Some("the confirmation probe")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failing_probe_confirms_the_outage() {
+        let probe = spawn_test_http_server(
+            "HTTP/1.1 500 Internal Server Error\r\nContent-Length: 0\r\n\r\n",
+        )
+        .await;
+        let confirmer = Confirmer::new(
+            &ConfirmationOptions {
+                proxy: None,
+                probe_url: Some(Url::parse(&probe).unwrap()),
+                probe_token: Some("probe-secret".to_string()),
+            },
+            &check_options(60),
+        )
+        .unwrap();
+
+        assert_eq!(
+            confirmer
+                .up_vantage(
+                    "http://unreachable.invalid",
+                    &SuccessStatuses::default(),
+                    5,
+                    false,
+                    None
+                )
+                .await,
+            None
+        );
+    }
+}
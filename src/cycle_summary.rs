@@ -0,0 +1,201 @@
+use log::info;
+use std::{collections::HashMap, sync::Mutex};
+
+use crate::config::{Config, CycleSummaryOptions};
+use crate::status::{SiteHealth, SiteStatus, site_url};
+
+/// Summary of the sites down after each check cycle, sent in place of their
+/// own DOWN alerts.
+///
+/// A summary is only sent when the sites it lists changed since the last one,
+/// so a lasting outage is not reported again on every cycle.
+#[derive(Debug, Default)]
+pub(crate) struct CycleSummary {
+    /// Tags summarized apart, every site is in a single summary when empty.
+    tags: Vec<String>,
+    /// Tags of the configured sites having some.
+    site_tags: HashMap<String, Vec<String>>,
+    /// Sites listed by the last summary of every tag, `None` for the one of
+    /// every site.
+    last_down: Mutex<HashMap<Option<String>, Vec<String>>>,
+}
+
+/// Sites down among the ones of a tag, or among every site.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct GroupSummary {
+    pub(crate) tag: Option<String>,
+    /// Labels of the down sites, in configuration order.
+    pub(crate) down: Vec<String>,
+    /// Number of sites in the group, down or not.
+    pub(crate) total: usize,
+}
+
+impl CycleSummary {
+    pub(crate) fn from_config(config: &Config) -> Option<Self> {
+        let options = config.cycle_summary.as_ref()?;
+        Some(Self {
+            tags: options.tags.clone(),
+            site_tags: config
+                .sites
+                .options
+                .iter()
+                .filter(|(_, options)| !options.tags.is_empty())
+                .map(|(url, options)| (url.clone(), options.tags.clone()))
+                .collect(),
+            last_down: Mutex::default(),
+        })
+    }
+
+    /// Whether the DOWN alerts of `site` are left to the summary.
+    ///
+    /// Sites checked by agents are covered along with the site they check.
+    pub(crate) fn covers(&self, site: &str) -> bool {
+        self.tags.is_empty()
+            || self
+                .tags_of(site_url(site))
+                .iter()
+                .any(|tag| self.tags.contains(tag))
+    }
+
+    fn tags_of(&self, url: &str) -> &[String] {
+        self.site_tags.get(url).map_or(&[][..], Vec::as_slice)
+    }
+
+    /// Summaries to send given the status of every site: the ones listing
+    /// down sites that differ from their last summary.
+    ///
+    /// Sites for which `ignored` returns true, such as silenced ones, are
+    /// counted but never listed as down.
+    pub(crate) fn due(
+        &self,
+        sites: &[SiteStatus],
+        ignored: impl Fn(&str) -> bool,
+    ) -> Vec<GroupSummary> {
+        let groups: Vec<Option<&String>> = if self.tags.is_empty() {
+            vec![None]
+        } else {
+            self.tags.iter().map(Some).collect()
+        };
+        let mut last_down = self.last_down.lock().expect("Cycle summary lock poisoned");
+
+        groups
+            .into_iter()
+            .filter_map(|tag| {
+                let members: Vec<&SiteStatus> = sites
+                    .iter()
+                    .filter(|site| tag.is_none_or(|tag| self.tags_of(&site.url).contains(tag)))
+                    .collect();
+                let down: Vec<String> = members
+                    .iter()
+                    .filter(|site| site.health == SiteHealth::Down)
+                    .map(|site| site.label())
+                    .filter(|label| !ignored(label))
+                    .collect();
+
+                let tag = tag.cloned();
+                let previous = last_down
+                    .insert(tag.clone(), down.clone())
+                    .unwrap_or_default();
+                (!down.is_empty() && down != previous).then_some(GroupSummary {
+                    tag,
+                    down,
+                    total: members.len(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Logs which sites the summary replaces the alerts of.
+pub(crate) fn log_enabled(options: &CycleSummaryOptions) {
+    if options.tags.is_empty() {
+        info!("Cycle summary is enabled, replacing the DOWN alerts of every site");
+    } else {
+        info!(
+            "Cycle summary is enabled, replacing the DOWN alerts of the sites tagged {}",
+            options.tags.join(", ")
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn site(url: &str, agent: Option<&str>, health: SiteHealth) -> SiteStatus {
+        SiteStatus {
+            url: url.to_string(),
+            agent: agent.map(str::to_string),
+            health,
+            consecutive_failures: 0,
+            last_checked_at: None,
+            last_response_time_ms: None,
+            failing_since: None,
+            discovered_by: None,
+        }
+    }
+
+    fn summary(tags: &[&str]) -> CycleSummary {
+        CycleSummary {
+            tags: tags.iter().map(ToString::to_string).collect(),
+            site_tags: HashMap::from([
+                ("https://a.example".to_string(), vec!["web".to_string()]),
+                ("https://b.example".to_string(), vec!["api".to_string()]),
+            ]),
+            last_down: Mutex::default(),
+        }
+    }
+
+    #[test]
+    fn test_summaries_are_sent_when_down_sites_change() {
+        let summary = summary(&[]);
+        let mut sites = vec![
+            site("https://a.example", None, SiteHealth::Down),
+            site("https://b.example", None, SiteHealth::Up),
+            site("https://a.example", Some("eu"), SiteHealth::Unreachable),
+        ];
+        assert!(summary.covers("https://c.example"));
+        assert_eq!(
+            summary.due(&sites, |_| false),
+            [GroupSummary {
+                tag: None,
+                down: vec!["https://a.example".to_string()],
+                total: 3,
+            }]
+        );
+        assert_eq!(summary.due(&sites, |_| false), [], "Unchanged outage");
+
+        sites[2].health = SiteHealth::Down;
+        let due = summary.due(&sites, |_| false);
+        assert_eq!(
+            due[0].down,
+            ["https://a.example", "https://a.example (via eu)"]
+        );
+
+        // Silenced sites are left out, and nothing is sent once all are back up
+        sites[0].health = SiteHealth::Up;
+        assert_eq!(summary.due(&sites, |label| label.contains("(via eu)")), []);
+        sites[2].health = SiteHealth::Up;
+        assert_eq!(summary.due(&sites, |_| false), []);
+    }
+
+    #[test]
+    fn test_tags_are_summarized_apart() {
+        let summary = summary(&["web"]);
+        assert!(summary.covers("https://a.example (via eu)"));
+        assert!(!summary.covers("https://b.example"));
+
+        let sites = [
+            site("https://a.example", None, SiteHealth::Down),
+            site("https://b.example", None, SiteHealth::Down),
+        ];
+        assert_eq!(
+            summary.due(&sites, |_| false),
+            [GroupSummary {
+                tag: Some("web".to_string()),
+                down: vec!["https://a.example".to_string()],
+                total: 1,
+            }]
+        );
+    }
+}
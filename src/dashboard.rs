@@ -0,0 +1,577 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write,
+    sync::{Mutex, PoisonError},
+    time::{Duration, SystemTime},
+};
+
+use crate::error::Error;
+use crate::history::{HistoryRecord, HistoryStore, unix_millis};
+use crate::status::{SiteHealth, SiteStatus};
+
+const HOUR_MS: u64 = 60 * 60 * 1000;
+const DAY_MS: u64 = 24 * HOUR_MS;
+const DAILY_BARS: usize = 30;
+const HOURLY_POINTS: usize = 24;
+const REFRESH_SECS: u64 = 60;
+
+/// Uptime windows shown for every site, in days.
+const UPTIME_WINDOW_DAYS: [u64; 3] = [1, 7, 30];
+
+/// Number of successful checks out of all checks in a time slice.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Tally {
+    up: u64,
+    total: u64,
+}
+
+impl Tally {
+    fn add(&mut self, up: bool) {
+        self.up += u64::from(up);
+        self.total += 1;
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.up += other.up;
+        self.total += other.total;
+    }
+
+    /// Percentage of successful checks, `None` without any check.
+    #[allow(clippy::cast_precision_loss)]
+    fn uptime_percent(self) -> Option<f64> {
+        (self.total > 0).then(|| self.up as f64 * 100.0 / self.total as f64)
+    }
+}
+
+/// Uptime history of a single site, aggregated from the history store.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct SiteHistory {
+    /// One tally per entry of [`UPTIME_WINDOW_DAYS`].
+    windows: [Tally; UPTIME_WINDOW_DAYS.len()],
+    /// One tally per day over the last 30 days, oldest first.
+    daily: [Tally; DAILY_BARS],
+    /// Sum and count of response times of successful checks per hour over
+    /// the last 24 hours, oldest first.
+    hourly_latency: [(u64, u64); HOURLY_POINTS],
+}
+
+/// Checks of a site aggregated over a time slice.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct SliceTally {
+    checks: Tally,
+    /// Sum and count of response times of successful checks.
+    latency: (u64, u64),
+}
+
+impl SliceTally {
+    fn add(&mut self, record: &HistoryRecord) {
+        self.checks.add(record.up);
+        if record.up {
+            self.latency.0 += record.response_time_ms;
+            self.latency.1 += 1;
+        }
+    }
+
+    fn merge(&mut self, other: &Self) {
+        self.checks.merge(other.checks);
+        self.latency.0 += other.latency.0;
+        self.latency.1 += other.latency.1;
+    }
+}
+
+impl From<&HistoryRecord> for SliceTally {
+    fn from(record: &HistoryRecord) -> Self {
+        let mut tally = Self::default();
+        tally.add(record);
+        tally
+    }
+}
+
+impl SiteHistory {
+    /// Adds the checks of `tally`, made at `timestamp_ms`.
+    fn add(&mut self, timestamp_ms: u64, tally: &SliceTally, now_ms: u64) {
+        let age_ms = now_ms.saturating_sub(timestamp_ms);
+
+        for (window, days) in self.windows.iter_mut().zip(UPTIME_WINDOW_DAYS) {
+            if age_ms < days * DAY_MS {
+                window.merge(tally.checks);
+            }
+        }
+
+        if let Some(slot) = slot_index(age_ms, DAY_MS, DAILY_BARS) {
+            self.daily[slot].merge(tally.checks);
+        }
+
+        if let Some(slot) = slot_index(age_ms, HOUR_MS, HOURLY_POINTS) {
+            self.hourly_latency[slot].0 += tally.latency.0;
+            self.hourly_latency[slot].1 += tally.latency.1;
+        }
+    }
+
+    /// Average response time per hour, `None` for hours without a successful check.
+    fn hourly_average_latency(&self) -> impl Iterator<Item = Option<u64>> + '_ {
+        self.hourly_latency
+            .iter()
+            .map(|&(sum, count)| sum.checked_div(count))
+    }
+}
+
+/// Index of the slice of `slot_ms` a record `age_ms` old falls in, counting
+/// `slots` slices back from now with the oldest first.
+fn slot_index(age_ms: u64, slot_ms: u64, slots: usize) -> Option<usize> {
+    let slots_ago = usize::try_from(age_ms / slot_ms).ok()?;
+    (slots_ago < slots).then(|| slots - 1 - slots_ago)
+}
+
+/// History aggregated per site and hour, kept between dashboard loads so a
+/// refresh only reads the records appended since the previous one.
+#[derive(Debug, Default)]
+pub(crate) struct HistoryCache {
+    state: Mutex<CachedHistory>,
+}
+
+#[derive(Debug, Default)]
+struct CachedHistory {
+    /// First line of the history file as last read, which pruning changes.
+    first_line: String,
+    /// Offset of the history file read up to.
+    offset: u64,
+    /// Checks of every `(url, agent)` per hour, keyed by the start of the hour.
+    hours: HashMap<(String, Option<String>), BTreeMap<u64, SliceTally>>,
+}
+
+impl HistoryCache {
+    /// Aggregates the last 30 days of history for each `(url, agent)` of
+    /// `sites`, in order, by the hour.
+    pub(crate) fn load(
+        &self,
+        store: &HistoryStore,
+        sites: &[(String, Option<String>)],
+        now: SystemTime,
+    ) -> Result<Vec<SiteHistory>, Error> {
+        let now_ms = unix_millis(now);
+        let from_ms = now_ms.saturating_sub(DAILY_BARS as u64 * DAY_MS);
+        let mut cache = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let mut appended = store.read_appended(cache.offset)?;
+        if cache.offset > 0 && appended.first_line != cache.first_line {
+            // Pruning rewrote the file, so it is read again from the start
+            *cache = CachedHistory::default();
+            appended = store.read_appended(0)?;
+        }
+        cache.first_line = appended.first_line;
+        cache.offset = appended.offset;
+
+        for record in appended
+            .records
+            .iter()
+            .filter(|record| record.timestamp_ms >= from_ms)
+        {
+            cache
+                .hours
+                .entry((record.url.clone(), record.agent.clone()))
+                .or_default()
+                .entry(record.timestamp_ms / HOUR_MS * HOUR_MS)
+                .or_default()
+                .merge(&SliceTally::from(record));
+        }
+        for hours in cache.hours.values_mut() {
+            *hours = hours.split_off(&(from_ms / HOUR_MS * HOUR_MS));
+        }
+        cache.hours.retain(|_, hours| !hours.is_empty());
+
+        Ok(sites
+            .iter()
+            .map(|site| {
+                let mut history = SiteHistory::default();
+                for (&hour_ms, tally) in cache.hours.get(site).into_iter().flatten() {
+                    history.add(hour_ms, tally, now_ms);
+                }
+                history
+            })
+            .collect())
+    }
+}
+
+/// Renders the dashboard page.
+///
+/// `histories` holds one entry per site, in the same order as `sites`, and is
+/// `None` when check history is not recorded.
+pub(crate) fn render(
+    sites: &[SiteStatus],
+    histories: Option<&[SiteHistory]>,
+    now: SystemTime,
+) -> String {
+    let mut html = String::with_capacity(4096 + sites.len() * 2048);
+
+    let _ = write!(
+        html,
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<meta http-equiv="refresh" content="{REFRESH_SECS}">
+<title>downdetector</title>
+<style>{STYLE}</style>
+</head>
+<body>
+<h1>downdetector</h1>
+"#
+    );
+
+    render_incidents(&mut html, sites, now);
+
+    html.push_str("<h2>Sites</h2>\n");
+    if sites.is_empty() {
+        html.push_str("<p class=\"empty\">No sites are monitored.</p>\n");
+    }
+    if histories.is_none() {
+        html.push_str(
+            "<p class=\"empty\">Uptime history is not recorded, add a [history] section to the config to see it.</p>\n",
+        );
+    }
+    for (index, site) in sites.iter().enumerate() {
+        render_site(&mut html, site, histories.and_then(|h| h.get(index)));
+    }
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+fn render_incidents(html: &mut String, sites: &[SiteStatus], now: SystemTime) {
+    html.push_str("<h2>Current incidents</h2>\n");
+
+    let failing: Vec<_> = sites
+        .iter()
+        .filter_map(|site| site.failing_since.map(|since| (site, since)))
+        .collect();
+    if failing.is_empty() {
+        html.push_str("<p class=\"empty\">All sites are operational.</p>\n");
+        return;
+    }
+
+    html.push_str("<ul class=\"incidents\">\n");
+    for (site, since) in failing {
+        let _ = writeln!(
+            html,
+            "<li><span class=\"badge {}\">{}</span> {} &mdash; failing for {} ({} consecutive failed checks)</li>",
+            health_class(site.health),
+            site.health.label(),
+            escape_html(&site.label()),
+            format_duration(now.duration_since(since).unwrap_or_default()),
+            site.consecutive_failures
+        );
+    }
+    html.push_str("</ul>\n");
+}
+
+fn render_site(html: &mut String, site: &SiteStatus, history: Option<&SiteHistory>) {
+    let _ = write!(
+        html,
+        "<section class=\"site\">\n<div class=\"head\"><span class=\"badge {}\">{}</span> <a href=\"{}\">{}</a>",
+        health_class(site.health),
+        site.health.label(),
+        escape_html(&site.url),
+        escape_html(&site.label())
+    );
+    if let Some(ms) = site.last_response_time_ms {
+        let _ = write!(html, " <span class=\"muted\">{ms} ms</span>");
+    }
+    html.push_str("</div>\n");
+
+    let Some(history) = history else {
+        html.push_str("</section>\n");
+        return;
+    };
+
+    html.push_str("<div class=\"stats\">");
+    for (tally, label) in history.windows.iter().zip(["24h", "7d", "30d"]) {
+        let _ = write!(
+            html,
+            "<span>{label}: <b>{}</b></span>",
+            format_uptime(*tally)
+        );
+    }
+    html.push_str("<span>latency 24h: ");
+    render_sparkline(html, history);
+    html.push_str("</span></div>\n<div class=\"bars\">");
+    for (days_ago, tally) in history.daily.iter().rev().enumerate().rev() {
+        let day = match days_ago {
+            0 => "today".to_string(),
+            1 => "yesterday".to_string(),
+            days => format!("{days} days ago"),
+        };
+        let _ = write!(
+            html,
+            "<span class=\"bar {}\" title=\"{day}: {}\"></span>",
+            uptime_class(*tally),
+            format_uptime(*tally)
+        );
+    }
+    html.push_str("</div>\n</section>\n");
+}
+
+/// Draws the hourly average latency as an inline SVG polyline.
+fn render_sparkline(html: &mut String, history: &SiteHistory) {
+    const WIDTH: u64 = 120;
+    const HEIGHT: u64 = 24;
+
+    let latencies: Vec<_> = history.hourly_average_latency().collect();
+    let Some(max) = latencies.iter().flatten().copied().max() else {
+        html.push_str("<span class=\"muted\">no data</span>");
+        return;
+    };
+    let max = max.max(1);
+    let step = WIDTH / (HOURLY_POINTS as u64 - 1);
+    let points: Vec<String> = (0..)
+        .zip(&latencies)
+        .filter_map(|(hour, latency)| {
+            latency.map(|ms| format!("{},{}", hour * step, HEIGHT - ms * HEIGHT / max))
+        })
+        .collect();
+
+    let _ = write!(
+        html,
+        "<svg class=\"spark\" width=\"{WIDTH}\" height=\"{HEIGHT}\" viewBox=\"0 0 {WIDTH} {HEIGHT}\"><title>max {max} ms</title><polyline points=\"{}\"/></svg>",
+        points.join(" ")
+    );
+}
+
+fn health_class(health: SiteHealth) -> &'static str {
+    match health {
+        SiteHealth::Pending => "pending",
+        SiteHealth::Up => "up",
+        SiteHealth::Unreachable => "unreachable",
+        SiteHealth::Down => "down",
+        SiteHealth::Unknown => "unknown",
+    }
+}
+
+fn uptime_class(tally: Tally) -> &'static str {
+    match tally.uptime_percent() {
+        None => "none",
+        Some(percent) if percent >= 99.9 => "up",
+        Some(percent) if percent >= 95.0 => "unreachable",
+        Some(_) => "down",
+    }
+}
+
+fn format_uptime(tally: Tally) -> String {
+    tally
+        .uptime_percent()
+        .map_or_else(|| "no data".to_string(), |percent| format!("{percent:.2}%"))
+}
+
+/// Formats a duration as e.g. `2h 5m` or `14m 32s`.
+pub(crate) fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (days, hours, minutes, seconds) =
+        (secs / 86_400, secs / 3600 % 24, secs / 60 % 60, secs % 60);
+
+    if days > 0 {
+        format!("{days}d {hours}h")
+    } else if hours > 0 {
+        format!("{hours}h {minutes}m")
+    } else if minutes > 0 {
+        format!("{minutes}m {seconds}s")
+    } else {
+        format!("{seconds}s")
+    }
+}
+
+pub(crate) fn escape_html(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+const STYLE: &str = "
+body{font-family:system-ui,sans-serif;max-width:56rem;margin:2rem auto;padding:0 1rem;color:#1f2328;background:#f6f8fa}
+h1{font-size:1.5rem}h2{font-size:1.1rem;margin-top:2rem}
+a{color:inherit}
+.site{background:#fff;border:1px solid #d0d7de;border-radius:6px;padding:.75rem 1rem;margin:.5rem 0}
+.head{font-weight:600;word-break:break-all}
+.muted,.empty{color:#656d76;font-weight:400}
+.stats{display:flex;flex-wrap:wrap;gap:1.5rem;align-items:center;margin:.5rem 0;font-size:.9rem}
+.bars{display:flex;gap:2px}
+.bar{flex:1;height:1.5rem;border-radius:2px}
+.badge{display:inline-block;padding:0 .4rem;border-radius:4px;color:#fff;font-size:.75rem}
+.up{background:#1a7f37}.unreachable{background:#bf8700}.down{background:#cf222e}.pending,.unknown,.none{background:#8c959f}
+.incidents li{margin:.25rem 0}
+.spark{vertical-align:middle}.spark polyline{fill:none;stroke:#0969da;stroke-width:1.5}
+";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{check_record, temp_history};
+    use crate::worker::CheckRecord;
+    use std::{fs, io::Write as _, time::UNIX_EPOCH};
+
+    const NOW_MS: u64 = 40 * DAY_MS;
+
+    fn record(url: &str, age_ms: u64, up: bool, response_time_ms: u64) -> HistoryRecord {
+        HistoryRecord::from(&CheckRecord {
+            response_time: Duration::from_millis(response_time_ms),
+            ..check_record(url, NOW_MS - age_ms, up)
+        })
+    }
+
+    fn status(url: &str, health: SiteHealth, failing_since: Option<SystemTime>) -> SiteStatus {
+        SiteStatus {
+            url: url.to_string(),
+            agent: None,
+            health,
+            consecutive_failures: u64::from(failing_since.is_some()),
+            last_checked_at: None,
+            last_response_time_ms: None,
+            failing_since,
+            discovered_by: None,
+        }
+    }
+
+    #[test]
+    fn test_site_history_windows_and_slots() {
+        let mut history = SiteHistory::default();
+        for record in [
+            record("https://a.example", HOUR_MS / 2, true, 100),
+            record("https://a.example", HOUR_MS / 2, true, 300),
+            record("https://a.example", 2 * DAY_MS, false, 0),
+            record("https://a.example", 10 * DAY_MS, true, 100),
+            record("https://a.example", 31 * DAY_MS, false, 0),
+        ] {
+            history.add(record.timestamp_ms, &SliceTally::from(&record), NOW_MS);
+        }
+
+        assert_eq!(history.windows[0], Tally { up: 2, total: 2 });
+        assert_eq!(history.windows[1], Tally { up: 2, total: 3 });
+        assert_eq!(history.windows[2], Tally { up: 3, total: 4 });
+        assert_eq!(history.daily[DAILY_BARS - 1], Tally { up: 2, total: 2 });
+        assert_eq!(history.daily[DAILY_BARS - 3], Tally { up: 0, total: 1 });
+        assert_eq!(
+            history.hourly_average_latency().last(),
+            Some(Some(200)),
+            "Latest hour averages successful checks"
+        );
+        assert_eq!(history.hourly_average_latency().next(), Some(None));
+    }
+
+    #[test]
+    fn test_history_cache_reads_only_appended_records() {
+        let store = temp_history("dashboard-cache", 30);
+        let path = store.path().clone();
+        let line = |record: HistoryRecord| format!("{}\n", serde_json::to_string(&record).unwrap());
+        let now = UNIX_EPOCH + Duration::from_millis(NOW_MS);
+        let sites = [
+            ("https://a.example".to_string(), None),
+            ("https://b.example".to_string(), None),
+        ];
+        let cache = HistoryCache::default();
+
+        fs::write(
+            &path,
+            line(record("https://a.example", 2 * DAY_MS, true, 100))
+                + &line(record("https://a.example", 31 * DAY_MS, true, 100)),
+        )
+        .unwrap();
+        let histories = cache.load(&store, &sites, now).unwrap();
+        assert_eq!(histories[0].windows[2], Tally { up: 1, total: 1 });
+        assert_eq!(histories[1], SiteHistory::default());
+
+        // Appended records add up, a line still being written waits for the next load
+        let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+        let partial = line(record("https://b.example", HOUR_MS, true, 100));
+        write!(
+            file,
+            "{}{}",
+            line(record("https://a.example", HOUR_MS, false, 0)),
+            &partial[..10]
+        )
+        .unwrap();
+        let histories = cache.load(&store, &sites, now).unwrap();
+        assert_eq!(histories[0].windows[2], Tally { up: 1, total: 2 });
+        assert_eq!(histories[1], SiteHistory::default());
+        write!(file, "{}", &partial[10..]).unwrap();
+        let histories = cache.load(&store, &sites, now).unwrap();
+        assert_eq!(histories[1].windows[0], Tally { up: 1, total: 1 });
+
+        // A rewritten file is read again from the start
+        fs::write(&path, line(record("https://b.example", HOUR_MS, false, 0))).unwrap();
+        let histories = cache.load(&store, &sites, now).unwrap();
+        assert_eq!(histories[0], SiteHistory::default());
+        assert_eq!(histories[1].windows[0], Tally { up: 0, total: 1 });
+    }
+
+    #[test]
+    fn test_uptime_formatting() {
+        assert_eq!(format_uptime(Tally { up: 0, total: 0 }), "no data");
+        assert_eq!(
+            format_uptime(Tally {
+                up: 999,
+                total: 1000
+            }),
+            "99.90%"
+        );
+        assert_eq!(
+            uptime_class(Tally {
+                up: 999,
+                total: 1000
+            }),
+            "up"
+        );
+        assert_eq!(uptime_class(Tally { up: 96, total: 100 }), "unreachable");
+        assert_eq!(uptime_class(Tally { up: 1, total: 2 }), "down");
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::from_secs(42)), "42s");
+        assert_eq!(format_duration(Duration::from_secs(872)), "14m 32s");
+        assert_eq!(format_duration(Duration::from_mins(125)), "2h 5m");
+        assert_eq!(format_duration(Duration::from_hours(25)), "1d 1h");
+    }
+
+    #[test]
+    fn test_render_escapes_urls_and_lists_incidents() {
+        let now = UNIX_EPOCH + Duration::from_millis(NOW_MS);
+        let sites = [
+            status("https://a.example/?q=<script>", SiteHealth::Up, None),
+            status(
+                "https://b.example",
+                SiteHealth::Down,
+                Some(now - Duration::from_secs(872)),
+            ),
+        ];
+        let histories = vec![SiteHistory::default(); sites.len()];
+
+        let html = render(&sites, Some(&histories), now);
+
+        assert!(html.contains("https://a.example/?q=&lt;script&gt;"));
+        assert!(!html.contains("<script>"));
+        assert!(html.contains("https://b.example &mdash; failing for 14m 32s"));
+        assert_eq!(html.matches("class=\"bar none\"").count(), 2 * DAILY_BARS);
+    }
+
+    #[test]
+    fn test_render_without_history() {
+        let html = render(
+            &[status("https://a.example", SiteHealth::Pending, None)],
+            None,
+            SystemTime::now(),
+        );
+
+        assert!(html.contains("All sites are operational."));
+        assert!(html.contains("Uptime history is not recorded"));
+        assert!(!html.contains("class=\"bars\""));
+    }
+}
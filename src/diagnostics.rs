@@ -0,0 +1,198 @@
+use std::{
+    net::SocketAddr,
+    process::Stdio,
+    time::{Duration, Instant},
+};
+use tokio::{net::TcpStream, process::Command, time::timeout};
+use url::Url;
+
+use crate::config::DiagnosticsOptions;
+
+/// Traceroute hops kept in the summary, the ones closest to the site.
+const TRACEROUTE_KEPT_HOPS: usize = 5;
+
+/// Seconds traceroute waits for each probe.
+const TRACEROUTE_WAIT_SECS: u64 = 1;
+
+/// Probes the network path to `url` and summarizes what was found, one
+/// line per step, to attach to its DOWN alert.
+///
+/// Every step is bounded by `step_timeout`, traceroute by one second per hop.
+pub(crate) async fn diagnose(
+    url: &str,
+    options: &DiagnosticsOptions,
+    step_timeout: Duration,
+) -> String {
+    let Some((host, port)) = Url::parse(url).ok().and_then(|url| {
+        Some((
+            url.host_str()?.trim_matches(['[', ']']).to_string(),
+            url.port_or_known_default()?,
+        ))
+    }) else {
+        return format!("Diagnostics: no host to probe in {url}");
+    };
+
+    let (resolution, address) = resolve(&host, port, step_timeout).await;
+    let mut lines = vec![resolution];
+    if let Some(address) = address {
+        lines.push(connect(address, step_timeout).await);
+    }
+    if options.traceroute {
+        lines.push(traceroute(&host, options.traceroute_max_hops).await);
+    }
+    lines.join("\n")
+}
+
+/// Resolves `host`, returning the first address found.
+async fn resolve(host: &str, port: u16, step_timeout: Duration) -> (String, Option<SocketAddr>) {
+    let started_at = Instant::now();
+    match timeout(step_timeout, tokio::net::lookup_host((host, port))).await {
+        Ok(Ok(mut addresses)) => match addresses.next() {
+            Some(address) => (
+                format!(
+                    "DNS: {host} resolved to {} in {} ms",
+                    address.ip(),
+                    started_at.elapsed().as_millis()
+                ),
+                Some(address),
+            ),
+            None => (format!("DNS: {host} has no address"), None),
+        },
+        Ok(Err(e)) => (
+            format!(
+                "DNS: {host} failed after {} ms: {e}",
+                started_at.elapsed().as_millis()
+            ),
+            None,
+        ),
+        Err(_) => (
+            format!("DNS: {host} timed out after {} s", step_timeout.as_secs()),
+            None,
+        ),
+    }
+}
+
+async fn connect(address: SocketAddr, step_timeout: Duration) -> String {
+    let started_at = Instant::now();
+    match timeout(step_timeout, TcpStream::connect(address)).await {
+        Ok(Ok(_)) => format!(
+            "TCP: connected to {address} in {} ms",
+            started_at.elapsed().as_millis()
+        ),
+        Ok(Err(e)) => format!(
+            "TCP: connecting to {address} failed after {} ms: {e}",
+            started_at.elapsed().as_millis()
+        ),
+        Err(_) => format!(
+            "TCP: connecting to {address} timed out after {} s",
+            step_timeout.as_secs()
+        ),
+    }
+}
+
+/// Runs the system `traceroute`, keeping the last hops of its output.
+///
+/// Hosts starting with `-` are refused so they are never read as options,
+/// even by `traceroute` implementations not honoring `--`.
+async fn traceroute(host: &str, max_hops: u8) -> String {
+    if host.starts_with('-') {
+        return format!("Traceroute: refusing to trace {host}");
+    }
+
+    let run = Command::new("traceroute")
+        .args(["-n", "-q", "1", "-w", &TRACEROUTE_WAIT_SECS.to_string()])
+        .args(["-m", &max_hops.to_string(), "--", host])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .output();
+    let limit = Duration::from_secs(TRACEROUTE_WAIT_SECS * u64::from(max_hops) + 5);
+
+    match timeout(limit, run).await {
+        Ok(Ok(output)) => {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            // The first line is a header naming the target
+            let hops: Vec<&str> = stdout.lines().skip(1).collect();
+            if hops.is_empty() {
+                return "Traceroute: no hops reported".to_string();
+            }
+            let kept = &hops[hops.len().saturating_sub(TRACEROUTE_KEPT_HOPS)..];
+            format!(
+                "Traceroute ({} hops, last {}):\n{}",
+                hops.len(),
+                kept.len(),
+                kept.join("\n")
+            )
+        }
+        Ok(Err(_)) => "Traceroute: not available on this host".to_string(),
+        Err(_) => format!("Traceroute: timed out after {} s", limit.as_secs()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    const OPTIONS: DiagnosticsOptions = DiagnosticsOptions {
+        traceroute: false,
+        traceroute_max_hops: 15,
+    };
+
+    #[tokio::test]
+    async fn test_diagnose_reachable_port() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let summary = diagnose(
+            &format!("http://127.0.0.1:{port}/health"),
+            &OPTIONS,
+            Duration::from_secs(5),
+        )
+        .await;
+        let lines: Vec<&str> = summary.lines().collect();
+        assert_eq!(lines.len(), 2, "{summary}");
+        assert!(lines[0].starts_with("DNS: 127.0.0.1 resolved to 127.0.0.1 in "));
+        assert!(lines[1].starts_with(&format!("TCP: connected to 127.0.0.1:{port} in ")));
+    }
+
+    #[tokio::test]
+    async fn test_diagnose_closed_port() {
+        // Bind then drop a listener to get a port nothing listens on
+        let port = TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+
+        let summary = diagnose(
+            &format!("http://127.0.0.1:{port}"),
+            &OPTIONS,
+            Duration::from_secs(5),
+        )
+        .await;
+        assert!(
+            summary.contains(&format!(
+                "TCP: connecting to 127.0.0.1:{port} failed after "
+            )),
+            "{summary}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_traceroute_refuses_option_like_hosts() {
+        assert_eq!(
+            traceroute("-fhelp", 1).await,
+            "Traceroute: refusing to trace -fhelp"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_diagnose_invalid_url() {
+        assert_eq!(
+            diagnose("not a url", &OPTIONS, Duration::from_secs(5)).await,
+            "Diagnostics: no host to probe in not a url"
+        );
+    }
+}
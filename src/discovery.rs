@@ -0,0 +1,190 @@
+use log::{info, warn};
+use reqwest::Client;
+use serde::Deserialize;
+use std::time::Duration;
+use tokio::{select, time::sleep};
+use tokio_util::sync::CancellationToken;
+use url::Url;
+
+use crate::config::SiteSource;
+use crate::error::Error;
+use crate::status::StatusBoard;
+
+/// Name of the remote site list as a discovery source, shown in logs.
+const REMOTE_SOURCE: &str = "remote site list";
+
+/// Brings the sites found by the discovery `source` on the board in line
+/// with `urls`, adding the new ones and removing the ones no longer listed.
+///
+/// Configured sites are left alone, even when a source lists them too.
+pub(crate) fn sync(board: &StatusBoard, source: &'static str, urls: &[String]) {
+    for url in board.discovered_urls(source) {
+        if !urls.contains(&url) && board.remove_discovered_site(&url, source) {
+            info!("{url}: removed, no longer listed by the {source}");
+        }
+    }
+    for url in urls {
+        if board.add_discovered_site(url, source) {
+            info!("{url}: added from the {source}");
+        }
+    }
+}
+
+/// Body of a remote site list.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum RemoteSiteList {
+    Urls(Vec<RemoteSite>),
+    Object { urls: Vec<RemoteSite> },
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum RemoteSite {
+    Url(String),
+    Table { url: String },
+}
+
+/// Parses a remote site list, skipping the entries that are not valid URLs.
+fn parse_site_list(body: &[u8]) -> Result<Vec<String>, Error> {
+    let (RemoteSiteList::Urls(sites) | RemoteSiteList::Object { urls: sites }) =
+        serde_json::from_slice(body)?;
+
+    Ok(sites
+        .into_iter()
+        .map(|site| match site {
+            RemoteSite::Url(url) | RemoteSite::Table { url } => url,
+        })
+        .filter(|url| {
+            let valid = Url::parse(url).is_ok();
+            if !valid {
+                warn!("Skipping invalid URL from the {REMOTE_SOURCE}: {url}");
+            }
+            valid
+        })
+        .collect())
+}
+
+async fn fetch_site_list(client: &Client, url: &str) -> Result<Vec<String>, Error> {
+    let body = client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+    parse_site_list(&body)
+}
+
+/// Fetches the site list from `source` now and every `refresh_secs` after,
+/// keeping the sites of the board in sync with it.
+///
+/// The sites of the last successful fetch stay monitored while the list
+/// cannot be fetched.
+pub(crate) fn spawn_remote_source(
+    source: SiteSource,
+    timeout: Duration,
+    board: StatusBoard,
+    token: CancellationToken,
+) {
+    tokio::spawn(async move {
+        let client = match Client::builder().timeout(timeout).build() {
+            Ok(client) => client,
+            Err(e) => {
+                warn!("Failed to build the {REMOTE_SOURCE} HTTP client: {e}");
+                return;
+            }
+        };
+
+        loop {
+            match fetch_site_list(&client, &source.url).await {
+                Ok(urls) => sync(&board, REMOTE_SOURCE, &urls),
+                Err(e) => warn!(
+                    "Failed to fetch the {REMOTE_SOURCE} from {}, keeping the last one: {e}",
+                    source.url
+                ),
+            }
+
+            select! {
+                () = sleep(Duration::from_secs(source.refresh_secs)) => {},
+                () = token.cancelled() => break,
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Router, routing::get};
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_parse_site_list_forms() {
+        assert_eq!(
+            parse_site_list(br#"["https://a.example", "not a url"]"#).unwrap(),
+            ["https://a.example"]
+        );
+        assert_eq!(
+            parse_site_list(
+                br#"{ "urls": ["https://a.example", { "url": "https://b.example" }] }"#
+            )
+            .unwrap(),
+            ["https://a.example", "https://b.example"]
+        );
+        assert!(parse_site_list(br#"{ "sites": [] }"#).is_err());
+    }
+
+    #[test]
+    fn test_sync_keeps_configured_sites() {
+        let board = StatusBoard::new(&["https://configured.example".to_string()]);
+        let listed = |urls: &[&str]| urls.iter().map(ToString::to_string).collect::<Vec<_>>();
+
+        sync(
+            &board,
+            REMOTE_SOURCE,
+            &listed(&["https://configured.example", "https://a.example"]),
+        );
+        assert_eq!(
+            board.urls(),
+            ["https://configured.example", "https://a.example"]
+        );
+        assert_eq!(board.configured_urls(), ["https://configured.example"]);
+
+        sync(&board, REMOTE_SOURCE, &listed(&["https://b.example"]));
+        assert_eq!(
+            board.urls(),
+            ["https://configured.example", "https://b.example"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_remote_sites_are_added() {
+        let app = Router::new().route(
+            "/sites.json",
+            get(|| async { r#"{ "urls": ["https://a.example"] }"# }),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let board = StatusBoard::new(&[]);
+        let token = CancellationToken::new();
+        spawn_remote_source(
+            SiteSource {
+                url: format!("http://{addr}/sites.json"),
+                refresh_secs: 300,
+            },
+            Duration::from_secs(5),
+            board.clone(),
+            token.clone(),
+        );
+
+        tokio::time::timeout(Duration::from_secs(5), board.sites_changed())
+            .await
+            .unwrap();
+        assert_eq!(board.urls(), ["https://a.example"]);
+        assert_eq!(board.configured_urls(), Vec::<String>::new());
+        token.cancel();
+    }
+}
@@ -0,0 +1,256 @@
+use log::{info, warn};
+use serde::Deserialize;
+use std::{collections::HashMap, path::Path, time::Duration};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::UnixStream,
+    select,
+    time::sleep,
+};
+use tokio_util::sync::CancellationToken;
+use url::{Url, form_urlencoded};
+
+use crate::config::DockerOptions;
+use crate::discovery;
+use crate::error::Error;
+use crate::status::StatusBoard;
+
+/// Name of the containers as a discovery source, shown in logs.
+const SOURCE: &str = "Docker containers";
+
+/// Delay before retrying after the Docker daemon could not be reached.
+const RETRY_DELAY: Duration = Duration::from_secs(10);
+
+/// Running container, as listed by the Docker API.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Container {
+    #[serde(default)]
+    names: Vec<String>,
+    #[serde(default)]
+    labels: HashMap<String, String>,
+}
+
+/// Sends a GET request for `path` over the Docker socket, returning the
+/// response body once the status is known to be successful.
+///
+/// HTTP/1.0 makes the daemon close the connection at the end of the body
+/// instead of chunking it, so streamed bodies can be read line by line.
+async fn get(socket: &Path, path: &str) -> Result<BufReader<UnixStream>, Error> {
+    let mut stream = UnixStream::connect(socket).await?;
+    stream
+        .write_all(format!("GET {path} HTTP/1.0\r\nHost: docker\r\n\r\n").as_bytes())
+        .await?;
+
+    let mut reader = BufReader::new(stream);
+    let mut status_line = String::new();
+    reader.read_line(&mut status_line).await?;
+    let status = status_line.split_whitespace().nth(1).unwrap_or_default();
+
+    // Skip the headers
+    let mut header = String::new();
+    while reader.read_line(&mut header).await? > 0 && header.trim_end() != "" {
+        header.clear();
+    }
+
+    if !status.starts_with('2') {
+        let mut body = String::new();
+        reader.read_to_string(&mut body).await?;
+        return Err(Error::Discovery(format!(
+            "Docker API answered {} to {path}: {}",
+            status_line.trim_end(),
+            body.trim()
+        )));
+    }
+    Ok(reader)
+}
+
+/// Query string filtering on containers carrying `label`.
+fn label_filter(label: &str, extra: &str) -> String {
+    let filters = format!(r#"{{"label":["{label}"]{extra}}}"#);
+    form_urlencoded::byte_serialize(filters.as_bytes()).collect()
+}
+
+/// URLs set by the `label` of the running containers.
+async fn list_urls(options: &DockerOptions) -> Result<Vec<String>, Error> {
+    let mut body = Vec::new();
+    get(
+        &options.socket,
+        &format!(
+            "/containers/json?filters={}",
+            label_filter(&options.label, "")
+        ),
+    )
+    .await?
+    .read_to_end(&mut body)
+    .await?;
+    let containers: Vec<Container> = serde_json::from_slice(&body)?;
+
+    let mut urls: Vec<String> = containers
+        .into_iter()
+        .filter_map(|container| {
+            let url = container.labels.get(&options.label)?.trim().to_string();
+            if Url::parse(&url).is_err() {
+                warn!(
+                    "Skipping invalid {} label of container {}: {url}",
+                    options.label,
+                    container.names.join(", ")
+                );
+                return None;
+            }
+            Some(url)
+        })
+        .collect();
+    urls.sort();
+    urls.dedup();
+    Ok(urls)
+}
+
+/// Lists the labeled containers, then lists them again every time one of
+/// them starts or stops, until the daemon closes the event stream.
+async fn watch(options: &DockerOptions, board: &StatusBoard) -> Result<(), Error> {
+    discovery::sync(board, SOURCE, &list_urls(options).await?);
+
+    let events_path = format!(
+        "/events?filters={}",
+        label_filter(
+            &options.label,
+            r#","type":["container"],"event":["start","die"]"#
+        )
+    );
+    let mut events = get(&options.socket, &events_path).await?.lines();
+    // Every event is a JSON object on its own line, only its arrival matters
+    while events.next_line().await?.is_some() {
+        discovery::sync(board, SOURCE, &list_urls(options).await?);
+    }
+    Ok(())
+}
+
+/// Watches the containers of the Docker daemon and keeps the URLs of their
+/// label monitored, until `token` is cancelled.
+pub(crate) fn spawn_discovery(
+    options: DockerOptions,
+    board: StatusBoard,
+    token: CancellationToken,
+) {
+    tokio::spawn(async move {
+        loop {
+            let result = select! {
+                result = watch(&options, &board) => result,
+                () = token.cancelled() => break,
+            };
+            match result {
+                Ok(()) => warn!(
+                    "Docker closed the event stream, reconnecting in {} s",
+                    RETRY_DELAY.as_secs()
+                ),
+                Err(e) => warn!(
+                    "Watching Docker containers failed, retrying in {} s: {e}",
+                    RETRY_DELAY.as_secs()
+                ),
+            }
+            select! {
+                () = sleep(RETRY_DELAY) => {},
+                () = token.cancelled() => break,
+            }
+        }
+    });
+}
+
+/// Logs where containers are discovered from.
+pub(crate) fn log_enabled(options: &DockerOptions) {
+    info!(
+        "Docker discovery is enabled, monitoring the {} label of the containers of {}",
+        options.label,
+        options.socket.display()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+    use std::path::PathBuf;
+    use tokio::net::UnixListener;
+
+    /// Fake Docker daemon answering the container lists in `lists` in turn,
+    /// and the event stream with a single event.
+    fn spawn_daemon(name: &str, lists: Vec<&'static str>) -> PathBuf {
+        let socket = temp_dir(&format!("docker-{name}")).join("docker.sock");
+        let listener = UnixListener::bind(&socket).unwrap();
+
+        tokio::spawn(async move {
+            let mut lists = lists.into_iter();
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut request = String::new();
+                reader.read_line(&mut request).await.unwrap();
+
+                let body = if request.starts_with("GET /containers/json?filters=") {
+                    lists.next().unwrap_or("[]").to_string()
+                } else if request.starts_with("GET /events?filters=") {
+                    "{\"status\":\"start\"}\n".to_string()
+                } else {
+                    panic!("Unexpected request: {request}");
+                };
+                let response =
+                    format!("HTTP/1.0 200 OK\r\nContent-Type: application/json\r\n\r\n{body}");
+                reader
+                    .get_mut()
+                    .write_all(response.as_bytes())
+                    .await
+                    .unwrap();
+            }
+        });
+        socket
+    }
+
+    #[tokio::test]
+    async fn test_containers_are_followed_through_events() {
+        let socket = spawn_daemon(
+            "watch",
+            vec![
+                r#"[
+                    { "Names": ["/a"], "Labels": { "downdetector.url": "https://a.example" } },
+                    { "Names": ["/b"], "Labels": { "downdetector.url": "not a url" } }
+                ]"#,
+                r#"[{ "Names": ["/c"], "Labels": { "downdetector.url": "https://c.example" } }]"#,
+            ],
+        );
+        let options = DockerOptions {
+            socket,
+            label: "downdetector.url".to_string(),
+        };
+        let board = StatusBoard::new(&["https://configured.example".to_string()]);
+
+        watch(&options, &board).await.unwrap();
+        assert_eq!(
+            board.urls(),
+            ["https://configured.example", "https://c.example"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_daemon_errors_are_reported() {
+        let socket = temp_dir("docker-error").join("docker.sock");
+        let listener = UnixListener::bind(&socket).unwrap();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut reader = BufReader::new(stream);
+            reader.read_line(&mut String::new()).await.unwrap();
+            reader
+                .get_mut()
+                .write_all(b"HTTP/1.0 500 Internal Server Error\r\n\r\n{\"message\":\"boom\"}")
+                .await
+                .unwrap();
+        });
+
+        let error = get(&socket, "/containers/json").await.unwrap_err();
+        assert!(
+            error.to_string().contains("500 Internal Server Error"),
+            "{error}"
+        );
+        assert!(error.to_string().contains("boom"), "{error}");
+    }
+}
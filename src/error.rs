@@ -21,6 +21,13 @@ pub enum Error {
     #[error("TOML parse error: {0}")]
     TomlParse(#[from] toml::de::Error),
 
+    /// Failed to edit the TOML configuration file.
+    ///
+    /// Occurs when the site list changed at runtime cannot be written
+    /// back because the configuration file no longer parses.
+    #[error("TOML edit error: {0}")]
+    TomlEdit(#[from] toml_edit::TomlError),
+
     /// Failed to read an environment variable.
     ///
     /// Occurs when attempting to load environment variables for
@@ -56,4 +63,24 @@ pub enum Error {
     /// variables.
     #[error("Integer parsing error: {0}")]
     ParseInt(#[from] std::num::ParseIntError),
+
+    /// Failed to serialize or deserialize JSON.
+    ///
+    /// Occurs when reading or writing check history records.
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    /// The check history store could not be maintained.
+    ///
+    /// Occurs when a background history task, such as pruning
+    /// expired records, fails to complete.
+    #[error("History error: {0}")]
+    History(String),
+
+    /// A discovery source reported an error.
+    ///
+    /// Occurs when the Kubernetes API ends a watch of Ingresses with
+    /// an error event, or the Docker API answers with an error status.
+    #[error("Discovery error: {0}")]
+    Discovery(String),
 }
@@ -0,0 +1,135 @@
+#[cfg(feature = "otel")]
+use log::warn;
+use log::{error, info};
+use reqwest::Client;
+use std::time::{Duration, SystemTime};
+
+use crate::config::{AgentOptions, Config, InfluxDbOptions, StatsdOptions};
+use crate::error::Error;
+use crate::history::HistoryStore;
+#[cfg(feature = "otel")]
+use crate::otel::OtlpExporter;
+use crate::worker::CheckRecord;
+use crate::{agent, influxdb, statsd};
+
+/// Fans check results out to every configured exporter.
+///
+/// Export failures are logged and never interrupt monitoring.
+#[derive(Debug, Default)]
+pub(crate) struct Exporters {
+    /// Client of the HTTP exporters, bounded by the check timeout so a hung
+    /// endpoint never stalls the checks.
+    client: Client,
+    history: Option<HistoryStore>,
+    influxdb: Option<InfluxDbOptions>,
+    statsd: Option<StatsdOptions>,
+    #[cfg(feature = "otel")]
+    otlp: Option<OtlpExporter>,
+    agent: Option<AgentOptions>,
+}
+
+impl Exporters {
+    /// Sets up the exporters enabled by the configuration and environment.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the HTTP client cannot be built.
+    pub(crate) fn from_config(config: &Config) -> Result<Self, Error> {
+        Ok(Self {
+            client: Client::builder()
+                .timeout(Duration::from_secs(config.config.timeout_secs))
+                .build()?,
+            history: config.history.as_ref().map(HistoryStore::new),
+            influxdb: config.influxdb.clone(),
+            statsd: config.statsd.clone(),
+            #[cfg(feature = "otel")]
+            otlp: otlp_from_env(config),
+            agent: config.agent.clone(),
+        })
+    }
+
+    /// Logs which exporters are active.
+    pub(crate) fn log_enabled(&self) {
+        if let Some(history) = &self.history {
+            info!("Check history is recorded to {}", history.path().display());
+        }
+
+        if let Some(influxdb) = &self.influxdb {
+            info!(
+                "InfluxDB export enabled, writing to bucket '{}' at {}",
+                influxdb.bucket, influxdb.url
+            );
+        }
+
+        if let Some(statsd) = &self.statsd {
+            info!(
+                "StatsD emission enabled, sending to {}:{} with prefix '{}'",
+                statsd.host, statsd.port, statsd.prefix
+            );
+        }
+
+        #[cfg(feature = "otel")]
+        if let Some(otlp) = &self.otlp {
+            info!(
+                "OpenTelemetry export enabled, sending to {}",
+                otlp.traces_endpoint()
+            );
+        }
+    }
+
+    /// Sends a batch of check results gathered since `cycle_started_at`.
+    pub(crate) async fn export(&self, cycle_started_at: SystemTime, records: &[CheckRecord]) {
+        if let Some(history) = &self.history
+            && let Err(e) = history.append(records).await
+        {
+            error!("Error recording check history: {e}");
+        }
+
+        if let Some(influxdb) = &self.influxdb
+            && let Err(e) = influxdb::write_checks(&self.client, influxdb, records).await
+        {
+            error!("Error exporting check results to InfluxDB: {e}");
+        }
+
+        if let Some(statsd) = &self.statsd
+            && let Err(e) = statsd::send_checks(statsd, records).await
+        {
+            error!("Error sending check metrics to StatsD: {e}");
+        }
+
+        #[cfg(feature = "otel")]
+        if let Some(otlp) = &self.otlp
+            && let Err(e) = otlp.export_cycle(cycle_started_at, records).await
+        {
+            error!("Error exporting check results to OpenTelemetry: {e}");
+        }
+
+        #[cfg(not(feature = "otel"))]
+        let _ = cycle_started_at;
+
+        if let Some(agent) = &self.agent
+            && let Err(e) = agent::push_results(&self.client, agent, records).await
+        {
+            error!(
+                "Error pushing check results to the central instance at {}: {e}",
+                agent.central_url
+            );
+        }
+    }
+}
+
+#[cfg(feature = "otel")]
+fn otlp_from_env(config: &Config) -> Option<OtlpExporter> {
+    match OtlpExporter::from_env() {
+        // Traces need the whole cycle in memory, which low-memory mode avoids
+        Ok(Some(_)) if config.config.low_memory => {
+            warn!("OpenTelemetry export is disabled in low-memory mode");
+            None
+        }
+        Ok(otlp) => otlp,
+        Err(e) => {
+            error!("OpenTelemetry export disabled: {e}");
+            None
+        }
+    }
+}
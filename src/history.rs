@@ -0,0 +1,504 @@
+use clap::ValueEnum;
+use jiff::Timestamp;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    io::{BufRead, BufReader, BufWriter, ErrorKind, Seek, SeekFrom, Write},
+    path::PathBuf,
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tokio::{fs::OpenOptions, io::AsyncWriteExt};
+
+use crate::config::{Config, HistoryOptions};
+use crate::error::Error;
+use crate::worker::CheckRecord;
+
+const PRUNE_INTERVAL: Duration = Duration::from_hours(1);
+
+/// Persistent, append-only store of check results.
+///
+/// Each check is stored as one JSON object per line, which keeps appends cheap
+/// and lets readers stream the file without loading it in memory. Records
+/// older than the retention period are pruned at most once an hour.
+#[derive(Debug)]
+pub(crate) struct HistoryStore {
+    path: PathBuf,
+    retention: Duration,
+    last_pruned_at: Mutex<Option<Instant>>,
+    /// Held by appends until they and any pruning they trigger are done, so a
+    /// prune rewriting the file cannot drop a concurrent append.
+    write_lock: tokio::sync::Mutex<()>,
+}
+
+/// A single check result as persisted in the history file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct HistoryRecord {
+    pub(crate) timestamp_ms: u64,
+    pub(crate) url: String,
+    /// Agent that performed the check, `None` for checks made by this instance.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) agent: Option<String>,
+    pub(crate) up: bool,
+    pub(crate) response_time_ms: u64,
+    pub(crate) consecutive_failures: u64,
+}
+
+impl From<&CheckRecord> for HistoryRecord {
+    fn from(record: &CheckRecord) -> Self {
+        Self {
+            timestamp_ms: unix_millis(record.checked_at),
+            url: record.url.clone(),
+            agent: record.agent.clone(),
+            up: record.is_up,
+            response_time_ms: u64::try_from(record.response_time.as_millis()).unwrap_or(u64::MAX),
+            consecutive_failures: record.consecutive_failures,
+        }
+    }
+}
+
+impl HistoryStore {
+    pub(crate) fn new(options: &HistoryOptions) -> Self {
+        Self {
+            path: options.path.clone(),
+            retention: Duration::from_hours(24 * options.retention_days),
+            last_pruned_at: Mutex::new(None),
+            write_lock: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Location of the history file.
+    pub(crate) fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    /// Appends a batch of check results, pruning expired records when due.
+    pub(crate) async fn append(&self, records: &[CheckRecord]) -> Result<(), Error> {
+        if records.is_empty() {
+            return Ok(());
+        }
+
+        let mut lines = Vec::new();
+        for record in records {
+            serde_json::to_writer(&mut lines, &HistoryRecord::from(record))?;
+            lines.push(b'\n');
+        }
+
+        let _guard = self.write_lock.lock().await;
+        if let Some(parent) = self.path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        file.write_all(&lines).await?;
+        // Tokio finishes writes in the background, wait for them before pruning reads the file
+        file.flush().await?;
+
+        if self.prune_due() {
+            let path = self.path.clone();
+            let cutoff_ms = unix_millis(
+                SystemTime::now()
+                    .checked_sub(self.retention)
+                    .unwrap_or(UNIX_EPOCH),
+            );
+            tokio::task::spawn_blocking(move || prune(&path, cutoff_ms))
+                .await
+                .map_err(|e| Error::History(format!("History pruning task failed: {e}")))??;
+        }
+        Ok(())
+    }
+
+    /// Streams the records whose timestamp falls within `[from_ms, to_ms]`.
+    ///
+    /// A missing history file yields no records.
+    pub(crate) fn read_range(
+        &self,
+        from_ms: Option<u64>,
+        to_ms: Option<u64>,
+    ) -> Result<impl Iterator<Item = Result<HistoryRecord, Error>>, Error> {
+        let reader = match File::open(&self.path) {
+            Ok(file) => Some(BufReader::new(file)),
+            Err(e) if e.kind() == ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(reader
+            .into_iter()
+            .flat_map(BufRead::lines)
+            .filter(|line| !line.as_ref().is_ok_and(|line| line.trim().is_empty()))
+            .map(|line| Ok(serde_json::from_str::<HistoryRecord>(&line?)?))
+            .filter(move |record| {
+                record.as_ref().map_or(true, |record| {
+                    from_ms.is_none_or(|from| record.timestamp_ms >= from)
+                        && to_ms.is_none_or(|to| record.timestamp_ms <= to)
+                })
+            }))
+    }
+
+    /// Reads the records appended after byte `offset` of the file, returning
+    /// them with the offset past the last complete line.
+    ///
+    /// Also returns the first line of the file, which changes when pruning
+    /// rewrote it, invalidating offsets. A missing history file yields no
+    /// records.
+    pub(crate) fn read_appended(&self, offset: u64) -> Result<AppendedRecords, Error> {
+        let mut reader = match File::open(&self.path) {
+            Ok(file) => BufReader::new(file),
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(AppendedRecords::default()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut first_line = String::new();
+        reader.read_line(&mut first_line)?;
+        reader.seek(SeekFrom::Start(offset))?;
+
+        let mut appended = AppendedRecords {
+            first_line,
+            offset,
+            records: Vec::new(),
+        };
+        let mut line = String::new();
+        loop {
+            line.clear();
+            let read = reader.read_line(&mut line)?;
+            // A line without its newline is still being appended
+            if read == 0 || !line.ends_with('\n') {
+                break;
+            }
+            appended.offset += read as u64;
+            // Unreadable lines are skipped, like pruning drops them
+            if let Ok(record) = serde_json::from_str::<HistoryRecord>(&line) {
+                appended.records.push(record);
+            }
+        }
+        Ok(appended)
+    }
+
+    fn prune_due(&self) -> bool {
+        let mut last_pruned_at = self
+            .last_pruned_at
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+
+        if last_pruned_at.is_some_and(|at| at.elapsed() < PRUNE_INTERVAL) {
+            return false;
+        }
+        *last_pruned_at = Some(Instant::now());
+        true
+    }
+}
+
+/// Records appended to the history file since a given offset.
+#[derive(Debug, Default)]
+pub(crate) struct AppendedRecords {
+    pub(crate) first_line: String,
+    /// Offset to read the next records from.
+    pub(crate) offset: u64,
+    pub(crate) records: Vec<HistoryRecord>,
+}
+
+/// Rewrites the history file without the records older than `cutoff_ms`.
+fn prune(path: &PathBuf, cutoff_ms: u64) -> Result<(), Error> {
+    let reader = BufReader::new(File::open(path)?);
+    let pruned_path = path.with_extension("jsonl.tmp");
+    let mut writer = BufWriter::new(File::create(&pruned_path)?);
+
+    for line in reader.lines() {
+        let line = line?;
+        // Unreadable lines are dropped rather than blocking pruning forever
+        let Ok(record) = serde_json::from_str::<HistoryRecord>(&line) else {
+            continue;
+        };
+        if record.timestamp_ms >= cutoff_ms {
+            writeln!(writer, "{line}")?;
+        }
+    }
+
+    writer.flush()?;
+    fs::rename(pruned_path, path)?;
+    Ok(())
+}
+
+/// Output format of the `export` command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    /// Comma separated values with a header row
+    Csv,
+    /// A JSON array of records
+    Json,
+}
+
+/// Writes the stored check history from `from` (inclusive) until `to`
+/// (exclusive) to `out`.
+///
+/// # Errors
+///
+/// Returns an error if history is not enabled in the configuration, the
+/// history file cannot be read, or writing to `out` fails.
+pub fn export_history(
+    config: &Config,
+    from: Option<Timestamp>,
+    to: Option<Timestamp>,
+    format: ExportFormat,
+    mut out: impl Write,
+) -> Result<(), Error> {
+    let options = config.history.as_ref().ok_or_else(|| {
+        Error::Config("History is not enabled, add a [history] section to the config".into())
+    })?;
+    let store = HistoryStore::new(options);
+    let to_ms = |ts: Timestamp| u64::try_from(ts.as_millisecond()).unwrap_or(0);
+    // Ranges are read inclusively, end one millisecond before `to`
+    let records = store.read_range(from.map(to_ms), to.map(|to| to_ms(to).saturating_sub(1)))?;
+
+    match format {
+        ExportFormat::Csv => {
+            writeln!(
+                out,
+                "timestamp,url,up,response_time_ms,consecutive_failures,agent"
+            )?;
+            for record in records {
+                let record = record?;
+                writeln!(
+                    out,
+                    "{},{},{},{},{},{}",
+                    format_timestamp(record.timestamp_ms),
+                    csv_field(&record.url),
+                    record.up,
+                    record.response_time_ms,
+                    record.consecutive_failures,
+                    csv_field(record.agent.as_deref().unwrap_or_default())
+                )?;
+            }
+        }
+        ExportFormat::Json => {
+            write!(out, "[")?;
+            for (i, record) in records.enumerate() {
+                if i > 0 {
+                    write!(out, ",")?;
+                }
+                serde_json::to_writer(&mut out, &ExportedRecord::from(record?))?;
+            }
+            writeln!(out, "]")?;
+        }
+    }
+
+    out.flush()?;
+    Ok(())
+}
+
+/// History record with a human readable timestamp, as written by the JSON export.
+#[derive(Serialize)]
+struct ExportedRecord {
+    timestamp: String,
+    url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    agent: Option<String>,
+    up: bool,
+    response_time_ms: u64,
+    consecutive_failures: u64,
+}
+
+impl From<HistoryRecord> for ExportedRecord {
+    fn from(record: HistoryRecord) -> Self {
+        Self {
+            timestamp: format_timestamp(record.timestamp_ms),
+            url: record.url,
+            agent: record.agent,
+            up: record.up,
+            response_time_ms: record.response_time_ms,
+            consecutive_failures: record.consecutive_failures,
+        }
+    }
+}
+
+pub(crate) fn format_timestamp(timestamp_ms: u64) -> String {
+    i64::try_from(timestamp_ms)
+        .ok()
+        .and_then(|ms| Timestamp::from_millisecond(ms).ok())
+        .map_or_else(|| timestamp_ms.to_string(), |ts| ts.to_string())
+}
+
+/// Quotes a CSV field when it contains a delimiter, quote or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+pub(crate) fn unix_millis(time: SystemTime) -> u64 {
+    u64::try_from(
+        time.duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis(),
+    )
+    .unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::test_support::{check_record, temp_dir, temp_history};
+
+    fn temp_store(name: &str) -> HistoryStore {
+        temp_history(&format!("history-{name}"), 30)
+    }
+
+    #[tokio::test]
+    async fn test_append_and_read_range() {
+        let store = temp_store("range");
+        // Recent enough to survive the pruning triggered by the first append
+        let now_secs = unix_millis(SystemTime::now()) / 1000;
+        store
+            .append(&[
+                check_record("https://a.example", (now_secs - 30) * 1000, true),
+                check_record("https://b.example", (now_secs - 20) * 1000, false),
+                check_record("https://a.example", (now_secs - 10) * 1000, true),
+            ])
+            .await
+            .unwrap();
+
+        let all: Vec<_> = store
+            .read_range(None, None)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(all.len(), 3);
+
+        let ranged: Vec<_> = store
+            .read_range(Some((now_secs - 20) * 1000), Some((now_secs - 10) * 1000))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(ranged.len(), 2);
+        assert_eq!(ranged[0].url, "https://b.example");
+        assert!(!ranged[0].up);
+    }
+
+    #[tokio::test]
+    async fn test_expired_records_are_pruned() {
+        let store = temp_store("prune");
+        let now_secs = unix_millis(SystemTime::now()) / 1000;
+        let expired_secs = now_secs - 31 * 24 * 60 * 60;
+
+        fs::create_dir_all(store.path().parent().unwrap()).unwrap();
+        fs::write(
+            store.path(),
+            format!(
+                "{}\n",
+                serde_json::to_string(&HistoryRecord::from(&check_record(
+                    "https://old.example",
+                    expired_secs * 1000,
+                    true
+                )))
+                .unwrap()
+            ),
+        )
+        .unwrap();
+
+        store
+            .append(&[check_record("https://new.example", now_secs * 1000, true)])
+            .await
+            .unwrap();
+
+        let urls: Vec<_> = store
+            .read_range(None, None)
+            .unwrap()
+            .map(|record| record.unwrap().url)
+            .collect();
+        assert_eq!(urls, ["https://new.example"]);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_pruning_keeps_concurrent_appends() {
+        let store = std::sync::Arc::new(temp_store("concurrent"));
+        let now_secs = unix_millis(SystemTime::now()) / 1000;
+
+        let appends: Vec<_> = (0..20)
+            .map(|i| {
+                let store = std::sync::Arc::clone(&store);
+                tokio::spawn(async move {
+                    // Every append prunes, racing the others
+                    *store.last_pruned_at.lock().unwrap() = None;
+                    store
+                        .append(&[check_record(
+                            &format!("https://{i}.example"),
+                            now_secs * 1000,
+                            true,
+                        )])
+                        .await
+                        .unwrap();
+                })
+            })
+            .collect();
+        for append in appends {
+            append.await.unwrap();
+        }
+
+        assert_eq!(store.read_range(None, None).unwrap().count(), 20);
+    }
+
+    #[tokio::test]
+    async fn test_export_end_is_exclusive() {
+        let dir = temp_dir("history-export");
+        let path = dir.join("history.jsonl");
+        let config_path = dir.join("config.toml");
+        // Long enough a retention to keep these records when appending prunes
+        fs::write(
+            &config_path,
+            format!(
+                "version = 2\n[sites]\nurls = []\n[history]\npath = '{}'\nretention_days = 36500\n",
+                path.display()
+            ),
+        )
+        .unwrap();
+        let config = Config::load_from(&config_path).unwrap();
+        HistoryStore::new(config.history.as_ref().unwrap())
+            .append(&[
+                check_record("https://a.example", 1_700_000_000_000, true),
+                check_record("https://b.example", 1_700_000_010_000, true),
+            ])
+            .await
+            .unwrap();
+
+        let mut out = Vec::new();
+        export_history(
+            &config,
+            None,
+            Some(Timestamp::from_second(1_700_000_010).unwrap()),
+            ExportFormat::Csv,
+            &mut out,
+        )
+        .unwrap();
+
+        let csv = String::from_utf8(out).unwrap();
+        assert!(csv.contains("https://a.example"));
+        assert!(!csv.contains("https://b.example"));
+    }
+
+    #[test]
+    fn test_missing_history_file_reads_empty() {
+        let store = temp_store("missing");
+        assert_eq!(store.read_range(None, None).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_csv_field_quoting() {
+        assert_eq!(csv_field("https://a.example"), "https://a.example");
+        assert_eq!(
+            csv_field("https://a.example/?q=1,2"),
+            "\"https://a.example/?q=1,2\""
+        );
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn test_format_timestamp_is_rfc3339() {
+        assert_eq!(format_timestamp(1_700_000_000_000), "2023-11-14T22:13:20Z");
+    }
+}
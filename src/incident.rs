@@ -0,0 +1,405 @@
+use log::{error, warn};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, SystemTime},
+};
+
+use crate::config::{Config, data_dir, write_atomically};
+use crate::error::Error;
+use crate::history::unix_millis;
+
+/// File the incidents are saved to, next to the check history.
+pub(crate) const INCIDENTS_FILE_NAME: &str = "incidents.json";
+
+/// An outage of a site, from its first DOWN alert until it is back up.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Incident {
+    pub(crate) id: u64,
+    /// Site label, the URL followed by the reporting agent for agent checks.
+    pub(crate) site: String,
+    pub(crate) started_at_ms: u64,
+    /// `None` while the site is still down.
+    pub(crate) resolved_at_ms: Option<u64>,
+    /// Name of the Discord user who acknowledged the incident.
+    pub(crate) acknowledged_by: Option<String>,
+    /// Every notification sent or skipped for the incident, oldest first.
+    #[serde(default)]
+    pub(crate) notifications: Vec<NotificationLog>,
+    /// Network diagnostics taken when the site went down, when configured.
+    #[serde(default)]
+    pub(crate) diagnostics: Option<String>,
+    /// Discord message of the alert, edited as the incident goes on. `None`
+    /// until the alert is posted, e.g. while held for quiet hours.
+    pub(crate) message_id: Option<String>,
+    /// Discord thread started from the alert, updates are posted there.
+    pub(crate) thread_id: Option<String>,
+}
+
+/// A notification of an incident.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct NotificationLog {
+    pub(crate) at_ms: u64,
+    pub(crate) event: NotificationEvent,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum NotificationEvent {
+    /// The DOWN alert was posted.
+    Alerted,
+    /// The alert was edited with the time the site has been down.
+    Updated,
+    /// No alert was sent since the incident is acknowledged.
+    Suppressed,
+    Acknowledged,
+    Resolved,
+}
+
+/// Outcome of clicking the Acknowledge button of an incident.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Acknowledgement {
+    Recorded {
+        site: String,
+    },
+    AlreadyAcknowledged {
+        by: String,
+    },
+    /// The site recovered since the alert.
+    NotOpen,
+}
+
+impl Incident {
+    /// Time the site has been down at `now_ms`, or was down once resolved.
+    pub(crate) fn duration(&self, now_ms: u64) -> Duration {
+        let ended_at_ms = self.resolved_at_ms.unwrap_or(now_ms);
+        Duration::from_millis(ended_at_ms.saturating_sub(self.started_at_ms))
+    }
+}
+
+/// Incidents, open and resolved, optionally saved to a file so their ids,
+/// acknowledgements and alert messages survive restarts.
+///
+/// Resolved incidents are forgotten after the check history retention. Without
+/// check history only the id of the last incident is saved, so the
+/// Acknowledge button of an alert from a previous run never matches a new
+/// incident.
+#[derive(Debug, Default)]
+pub(crate) struct IncidentStore {
+    /// File the incidents are saved to, kept in memory only when `None`.
+    path: Option<PathBuf>,
+    retention: Option<Duration>,
+    /// Whether only `last_id` is saved, the incidents being kept in memory.
+    ids_only: bool,
+    state: Mutex<IncidentFile>,
+    /// Serializes saves so an older state never overwrites a newer one.
+    save_lock: tokio::sync::Mutex<()>,
+}
+
+/// Content of the incidents file.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct IncidentFile {
+    /// Id of the last incident opened, ids are never reused.
+    last_id: u64,
+    /// Oldest first.
+    incidents: Vec<Incident>,
+}
+
+impl IncidentStore {
+    /// Loads the incidents saved next to the check history. When history is
+    /// disabled, incidents are kept in memory and only the last id is loaded,
+    /// from the data directory.
+    pub(crate) fn from_config(config: &Config) -> Self {
+        let (path, retention, ids_only) = match &config.history {
+            Some(history) => (
+                history.path.with_file_name(INCIDENTS_FILE_NAME),
+                Some(Duration::from_hours(24 * history.retention_days)),
+                false,
+            ),
+            None => match data_dir() {
+                Ok(dir) => (dir.join(INCIDENTS_FILE_NAME), None, true),
+                Err(e) => {
+                    warn!("Incident ids will start over on restart: {e}");
+                    return Self::default();
+                }
+            },
+        };
+
+        let state = match load(&path) {
+            Ok(state) if ids_only => IncidentFile {
+                last_id: state.last_id,
+                incidents: Vec::new(),
+            },
+            Ok(state) => state,
+            Err(e) => {
+                error!(
+                    "Failed to load incidents from {}, starting afresh: {e}",
+                    path.display()
+                );
+                IncidentFile::default()
+            }
+        };
+        Self {
+            path: Some(path),
+            retention,
+            ids_only,
+            state: Mutex::new(state),
+            save_lock: tokio::sync::Mutex::default(),
+        }
+    }
+
+    /// Open incident of `site`, opened at `now_ms` if it has none yet.
+    pub(crate) fn open(&self, site: &str, now_ms: u64) -> Incident {
+        let mut state = self.state.lock().expect("Incidents lock poisoned");
+        if let Some(incident) = find_open(&mut state.incidents, site) {
+            return incident.clone();
+        }
+
+        state.last_id += 1;
+        let incident = Incident {
+            id: state.last_id,
+            site: site.to_string(),
+            started_at_ms: now_ms,
+            resolved_at_ms: None,
+            acknowledged_by: None,
+            notifications: Vec::new(),
+            diagnostics: None,
+            message_id: None,
+            thread_id: None,
+        };
+        state.incidents.push(incident.clone());
+        incident
+    }
+
+    /// Open incident of `site`, if any.
+    pub(crate) fn get_open(&self, site: &str) -> Option<Incident> {
+        let mut state = self.state.lock().expect("Incidents lock poisoned");
+        find_open(&mut state.incidents, site).cloned()
+    }
+
+    /// Applies `change` to the incident `id`, if it is still open.
+    pub(crate) fn update_open(&self, id: u64, change: impl FnOnce(&mut Incident)) {
+        let mut state = self.state.lock().expect("Incidents lock poisoned");
+        if let Some(incident) = state
+            .incidents
+            .iter_mut()
+            .find(|incident| incident.id == id && incident.resolved_at_ms.is_none())
+        {
+            change(incident);
+        }
+    }
+
+    /// Records `event` in the notification log of the incident `id`.
+    pub(crate) fn log(&self, id: u64, event: NotificationEvent, at_ms: u64) {
+        self.update_open(id, |incident| {
+            incident
+                .notifications
+                .push(NotificationLog { at_ms, event });
+        });
+    }
+
+    /// Marks the open incident of `site` as resolved at `now_ms`, returning it.
+    pub(crate) fn resolve(&self, site: &str, now_ms: u64) -> Option<Incident> {
+        let mut state = self.state.lock().expect("Incidents lock poisoned");
+        let incident = find_open(&mut state.incidents, site)?;
+        incident.resolved_at_ms = Some(now_ms);
+        incident.notifications.push(NotificationLog {
+            at_ms: now_ms,
+            event: NotificationEvent::Resolved,
+        });
+        Some(incident.clone())
+    }
+
+    /// Records that `user` acknowledged the incident `id`, the first
+    /// acknowledgement is kept.
+    pub(crate) fn acknowledge(&self, id: u64, user: &str, now_ms: u64) -> Acknowledgement {
+        let mut state = self.state.lock().expect("Incidents lock poisoned");
+        let Some(incident) = state
+            .incidents
+            .iter_mut()
+            .find(|incident| incident.id == id && incident.resolved_at_ms.is_none())
+        else {
+            return Acknowledgement::NotOpen;
+        };
+
+        if let Some(by) = &incident.acknowledged_by {
+            return Acknowledgement::AlreadyAcknowledged { by: by.clone() };
+        }
+        incident.acknowledged_by = Some(user.to_string());
+        incident.notifications.push(NotificationLog {
+            at_ms: now_ms,
+            event: NotificationEvent::Acknowledged,
+        });
+        Acknowledgement::Recorded {
+            site: incident.site.clone(),
+        }
+    }
+
+    /// Every known incident, most recent first.
+    pub(crate) fn list(&self) -> Vec<Incident> {
+        let state = self.state.lock().expect("Incidents lock poisoned");
+        state.incidents.iter().rev().cloned().collect()
+    }
+
+    /// Writes the incidents to their file, forgetting the resolved ones
+    /// past retention. Failures are logged, incidents stay in memory.
+    pub(crate) async fn save(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let _guard = self.save_lock.lock().await;
+
+        let json = {
+            let mut state = self.state.lock().expect("Incidents lock poisoned");
+            if let Some(retention) = self.retention {
+                let cutoff_ms = unix_millis(
+                    SystemTime::now()
+                        .checked_sub(retention)
+                        .unwrap_or(SystemTime::UNIX_EPOCH),
+                );
+                state.incidents.retain(|incident| {
+                    incident
+                        .resolved_at_ms
+                        .is_none_or(|resolved_at_ms| resolved_at_ms >= cutoff_ms)
+                });
+            }
+            if self.ids_only {
+                serde_json::to_vec_pretty(&IncidentFile {
+                    last_id: state.last_id,
+                    incidents: Vec::new(),
+                })
+            } else {
+                serde_json::to_vec_pretty(&*state)
+            }
+        };
+
+        let result = match json {
+            Ok(json) => {
+                let path = path.clone();
+                tokio::task::spawn_blocking(move || write_atomically(&path, &json))
+                    .await
+                    .map_err(|e| e.to_string())
+                    .and_then(|result| result.map_err(|e| e.to_string()))
+            }
+            Err(e) => Err(e.to_string()),
+        };
+        if let Err(e) = result {
+            warn!("Failed to save incidents to {}: {e}", path.display());
+        }
+    }
+}
+
+fn find_open<'a>(incidents: &'a mut [Incident], site: &str) -> Option<&'a mut Incident> {
+    incidents
+        .iter_mut()
+        .rev()
+        .find(|incident| incident.site == site && incident.resolved_at_ms.is_none())
+}
+
+/// Reads the incidents file, a missing file holds no incidents.
+fn load(path: &Path) -> Result<IncidentFile, Error> {
+    match fs::read(path) {
+        Ok(content) => Ok(serde_json::from_slice(&content)?),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(IncidentFile::default()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Replaces the file at `path` with `content`, never leaving it half written.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dashboard::format_duration;
+    use crate::test_support::temp_dir;
+
+    const NOW_MS: u64 = 1_700_000_000_000;
+
+    fn temp_store(name: &str) -> IncidentStore {
+        IncidentStore {
+            path: Some(temp_dir(&format!("incidents-{name}")).join(INCIDENTS_FILE_NAME)),
+            ..IncidentStore::default()
+        }
+    }
+
+    #[test]
+    fn test_incident_lifecycle() {
+        let store = IncidentStore::default();
+        let first = store.open("https://a.example", NOW_MS);
+        assert_eq!(store.open("https://a.example", NOW_MS + 60_000), first);
+        assert_eq!(store.open("https://b.example", NOW_MS).id, first.id + 1);
+
+        assert_eq!(
+            store.acknowledge(first.id, "alice", NOW_MS + 1000),
+            Acknowledgement::Recorded {
+                site: "https://a.example".to_string()
+            }
+        );
+        assert_eq!(
+            store.acknowledge(first.id, "bob", NOW_MS + 2000),
+            Acknowledgement::AlreadyAcknowledged {
+                by: "alice".to_string()
+            }
+        );
+
+        let resolved = store
+            .resolve("https://a.example", NOW_MS + 872_000)
+            .unwrap();
+        assert_eq!(format_duration(resolved.duration(u64::MAX)), "14m 32s");
+        assert_eq!(
+            store.acknowledge(first.id, "bob", NOW_MS + 873_000),
+            Acknowledgement::NotOpen
+        );
+        assert_eq!(store.get_open("https://a.example"), None);
+        assert_eq!(store.open("https://a.example", NOW_MS + 900_000).id, 3);
+
+        let events: Vec<_> = store.list()[2]
+            .notifications
+            .iter()
+            .map(|log| log.event)
+            .collect();
+        assert_eq!(
+            events,
+            [NotificationEvent::Acknowledged, NotificationEvent::Resolved]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_incidents_survive_restarts() {
+        let store = temp_store("restart");
+        let incident = store.open("https://a.example", NOW_MS);
+        store.update_open(incident.id, |incident| {
+            incident.message_id = Some("555".to_string());
+        });
+        store.save().await;
+
+        let reloaded = IncidentStore {
+            state: Mutex::new(load(store.path.as_ref().unwrap()).unwrap()),
+            ..temp_store("unused")
+        };
+        assert_eq!(
+            reloaded
+                .get_open("https://a.example")
+                .and_then(|incident| incident.message_id),
+            Some("555".to_string())
+        );
+        assert_eq!(reloaded.open("https://b.example", NOW_MS).id, 2);
+    }
+
+    #[tokio::test]
+    async fn test_ids_are_not_reused_without_history() {
+        let store = IncidentStore {
+            ids_only: true,
+            ..temp_store("ids-only")
+        };
+        store.open("https://a.example", NOW_MS);
+        store.open("https://b.example", NOW_MS);
+        store.save().await;
+
+        let saved = load(store.path.as_ref().unwrap()).unwrap();
+        assert_eq!(saved.last_id, 2);
+        assert!(saved.incidents.is_empty(), "Incidents stay in memory");
+    }
+}
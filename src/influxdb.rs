@@ -0,0 +1,119 @@
+use reqwest::{Client, header::AUTHORIZATION};
+use std::{fmt::Write, time::UNIX_EPOCH};
+
+use crate::config::{InfluxDbOptions, join_url_path};
+use crate::error::Error;
+use crate::worker::CheckRecord;
+
+const MEASUREMENT: &str = "downdetector_check";
+
+/// Writes a batch of check results to InfluxDB using the v2 write API.
+pub(crate) async fn write_checks(
+    client: &Client,
+    options: &InfluxDbOptions,
+    records: &[CheckRecord],
+) -> Result<(), Error> {
+    if records.is_empty() {
+        return Ok(());
+    }
+
+    let write_url = join_url_path(&options.url, "api/v2/write")?;
+
+    client
+        .post(write_url)
+        .query(&[
+            ("org", options.org.as_str()),
+            ("bucket", options.bucket.as_str()),
+            ("precision", "ms"),
+        ])
+        .header(AUTHORIZATION, format!("Token {}", options.token))
+        .body(to_line_protocol(records))
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+/// Serializes check results to InfluxDB line protocol, one line per record.
+fn to_line_protocol(records: &[CheckRecord]) -> String {
+    let mut body = String::new();
+
+    for record in records {
+        let timestamp_ms = record
+            .checked_at
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+
+        let agent_tag = record
+            .agent
+            .as_deref()
+            .map(|agent| format!(",agent={}", escape_tag_value(agent)))
+            .unwrap_or_default();
+
+        let _ = writeln!(
+            body,
+            "{MEASUREMENT},url={}{agent_tag} up={}i,response_time_ms={}i,consecutive_failures={}i {timestamp_ms}",
+            escape_tag_value(&record.url),
+            u8::from(record.is_up),
+            record.response_time.as_millis(),
+            record.consecutive_failures,
+        );
+    }
+
+    body
+}
+
+/// Escapes the characters line protocol reserves in tag values.
+fn escape_tag_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, ',' | '=' | ' ' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn test_line_protocol_format() {
+        let records = [
+            CheckRecord {
+                url: "https://www.google.com".to_string(),
+                agent: None,
+                checked_at: SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+                is_up: true,
+                response_time: Duration::from_millis(123),
+                consecutive_failures: 0,
+            },
+            CheckRecord {
+                url: "https://invalid.url".to_string(),
+                agent: None,
+                checked_at: SystemTime::UNIX_EPOCH + Duration::from_millis(1_700_000_000_500),
+                is_up: false,
+                response_time: Duration::from_secs(5),
+                consecutive_failures: 3,
+            },
+        ];
+
+        assert_eq!(
+            to_line_protocol(&records),
+            "downdetector_check,url=https://www.google.com up=1i,response_time_ms=123i,consecutive_failures=0i 1700000000000\n\
+             downdetector_check,url=https://invalid.url up=0i,response_time_ms=5000i,consecutive_failures=3i 1700000000500\n"
+        );
+    }
+
+    #[test]
+    fn test_tag_values_are_escaped() {
+        assert_eq!(
+            escape_tag_value("https://example.com/?a=1,b=2 c"),
+            "https://example.com/?a\\=1\\,b\\=2\\ c"
+        );
+    }
+}
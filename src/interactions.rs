@@ -0,0 +1,338 @@
+use axum::{
+    Json, Router,
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    routing::post,
+};
+use ed25519_dalek::{Signature, VerifyingKey};
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::api::{ApiError, api_error};
+use crate::incident::Acknowledgement;
+use crate::notify::{ACKNOWLEDGE_ID_PREFIX, ActionRow, Notifier};
+use crate::server::AppState;
+use crate::templates::{Variables, render_phrase};
+
+const SIGNATURE_HEADER: &str = "x-signature-ed25519";
+const TIMESTAMP_HEADER: &str = "x-signature-timestamp";
+
+const PING: u8 = 1;
+const MESSAGE_COMPONENT: u8 = 3;
+const PONG: u8 = 1;
+const CHANNEL_MESSAGE: u8 = 4;
+const UPDATE_MESSAGE: u8 = 7;
+/// Message flag showing a reply to the clicking user only.
+const EPHEMERAL: u64 = 1 << 6;
+
+/// Discord interactions endpoint, mounted under `/api/v1`.
+///
+/// Set it as the Interactions Endpoint URL of the Discord application owning
+/// the alert webhook, every request must be signed with its key.
+pub(crate) fn routes() -> Router<AppState> {
+    Router::new().route("/discord/interactions", post(receive_interaction))
+}
+
+/// Parses the hex-encoded public key of a Discord application.
+pub(crate) fn parse_public_key(hex: &str) -> Option<VerifyingKey> {
+    let bytes = decode_hex(hex)?.try_into().ok()?;
+    VerifyingKey::from_bytes(&bytes).ok()
+}
+
+pub(crate) fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+#[derive(Debug, Deserialize)]
+struct Interaction {
+    #[serde(rename = "type")]
+    kind: u8,
+    data: Option<ComponentData>,
+    /// Clicking member, set for interactions in a server.
+    member: Option<Member>,
+    /// Clicking user, set for interactions in direct messages.
+    user: Option<User>,
+    message: Option<Message>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ComponentData {
+    custom_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Member {
+    user: User,
+}
+
+#[derive(Debug, Deserialize)]
+struct User {
+    id: String,
+    username: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Message {
+    content: String,
+}
+
+#[derive(Debug, Serialize)]
+struct InteractionResponse {
+    #[serde(rename = "type")]
+    kind: u8,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<ResponseData>,
+}
+
+#[derive(Debug, Serialize)]
+struct ResponseData {
+    content: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    flags: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    components: Option<Vec<ActionRow>>,
+    allowed_mentions: AllowedMentions,
+}
+
+/// Mentions Discord pings, none since the alert already did.
+#[derive(Debug, Serialize)]
+struct AllowedMentions {
+    parse: [&'static str; 0],
+}
+
+impl InteractionResponse {
+    fn reply(content: String) -> Self {
+        Self {
+            kind: CHANNEL_MESSAGE,
+            data: Some(ResponseData {
+                content,
+                flags: Some(EPHEMERAL),
+                components: None,
+                allowed_mentions: AllowedMentions { parse: [] },
+            }),
+        }
+    }
+
+    /// Replaces the clicked message with `content`, without its buttons.
+    fn update(content: String) -> Self {
+        Self {
+            kind: UPDATE_MESSAGE,
+            data: Some(ResponseData {
+                content,
+                flags: None,
+                components: Some(Vec::new()),
+                allowed_mentions: AllowedMentions { parse: [] },
+            }),
+        }
+    }
+}
+
+/// Answers Discord's endpoint checks and records Acknowledge button clicks.
+async fn receive_interaction(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<InteractionResponse>, ApiError> {
+    let public_key = state.discord_public_key.as_ref().ok_or_else(|| {
+        api_error(
+            StatusCode::FORBIDDEN,
+            "Discord interactions are disabled, set discord_public_key in the [server] section",
+        )
+    })?;
+    // Discord checks that unsigned requests are rejected before saving the endpoint
+    if !verify_signature(public_key, &headers, &body) {
+        return Err(api_error(
+            StatusCode::UNAUTHORIZED,
+            "Invalid request signature",
+        ));
+    }
+
+    let interaction: Interaction = serde_json::from_slice(&body)
+        .map_err(|e| api_error(StatusCode::BAD_REQUEST, e.to_string()))?;
+    match interaction.kind {
+        PING => Ok(Json(InteractionResponse {
+            kind: PONG,
+            data: None,
+        })),
+        MESSAGE_COMPONENT => Ok(Json(acknowledge(&state.notifier, interaction).await)),
+        kind => Err(api_error(
+            StatusCode::BAD_REQUEST,
+            format!("Unsupported interaction type {kind}"),
+        )),
+    }
+}
+
+/// Checks the request was signed by Discord with the application key.
+fn verify_signature(public_key: &VerifyingKey, headers: &HeaderMap, body: &[u8]) -> bool {
+    let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+    let (Some(signature), Some(timestamp)) = (header(SIGNATURE_HEADER), header(TIMESTAMP_HEADER))
+    else {
+        return false;
+    };
+    let Some(signature) = decode_hex(signature).and_then(|bytes| bytes.try_into().ok()) else {
+        return false;
+    };
+
+    let mut message = timestamp.as_bytes().to_vec();
+    message.extend_from_slice(body);
+    public_key
+        .verify_strict(&message, &Signature::from_bytes(&signature))
+        .is_ok()
+}
+
+/// Records who clicked the Acknowledge button of an incident.
+async fn acknowledge(notifier: &Notifier, interaction: Interaction) -> InteractionResponse {
+    let incident_id = interaction.data.as_ref().and_then(|data| {
+        data.custom_id
+            .strip_prefix(ACKNOWLEDGE_ID_PREFIX)?
+            .parse::<u64>()
+            .ok()
+    });
+    let user = interaction
+        .member
+        .map(|member| member.user)
+        .or(interaction.user);
+    let (Some(incident_id), Some(user)) = (incident_id, user) else {
+        return InteractionResponse::reply("This button is not handled by downdetector".into());
+    };
+
+    match notifier.acknowledge(incident_id, &user.username).await {
+        Acknowledgement::Recorded { site } => {
+            info!(
+                "{site}: incident #{incident_id} acknowledged by {}",
+                user.username
+            );
+            let content = interaction
+                .message
+                .map(|message| message.content)
+                .unwrap_or_default();
+            let acknowledged = render_phrase(
+                notifier.phrases().acknowledged,
+                &Variables::default().set("user", format!("<@{}>", user.id)),
+            );
+            InteractionResponse::update(format!("{content}\n{acknowledged}"))
+        }
+        Acknowledgement::AlreadyAcknowledged { by } => InteractionResponse::reply(format!(
+            "Incident #{incident_id} was already acknowledged by {by}"
+        )),
+        Acknowledgement::NotOpen => {
+            InteractionResponse::reply(format!("Incident #{incident_id} is already over"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notify::DownAlert;
+    use crate::status::StatusBoard;
+    use ed25519_dalek::{Signer, SigningKey};
+    use serde_json::Value;
+    use std::{fmt::Write, sync::Arc};
+    use tokio::net::TcpListener;
+
+    const SIGNING_KEY: [u8; 32] = [7; 32];
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().fold(String::new(), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        })
+    }
+
+    async fn spawn_interactions(notifier: Arc<Notifier>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = routes().with_state(AppState {
+            board: StatusBoard::new(&[]),
+            history: None,
+            history_graphs: None,
+            site_management: None,
+            agent_reports: None,
+            probe: None,
+            slo: None,
+            notifier,
+            discord_public_key: parse_public_key(&hex(SigningKey::from_bytes(&SIGNING_KEY)
+                .verifying_key()
+                .as_bytes())),
+        });
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        format!("http://{addr}/discord/interactions")
+    }
+
+    async fn post_signed(url: &str, body: &str) -> reqwest::Response {
+        let timestamp = "1700000000";
+        let signature =
+            SigningKey::from_bytes(&SIGNING_KEY).sign(format!("{timestamp}{body}").as_bytes());
+        reqwest::Client::new()
+            .post(url)
+            .header(SIGNATURE_HEADER, hex(&signature.to_bytes()))
+            .header(TIMESTAMP_HEADER, timestamp)
+            .body(body.to_string())
+            .send()
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_unsigned_interactions_are_rejected() {
+        let url = spawn_interactions(Arc::new(Notifier::default())).await;
+
+        let unsigned = reqwest::Client::new()
+            .post(&url)
+            .body(r#"{"type":1}"#)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(unsigned.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+        let ping = post_signed(&url, r#"{"type":1}"#).await;
+        assert!(ping.status().is_success());
+        assert_eq!(
+            ping.json::<Value>().await.unwrap(),
+            serde_json::json!({ "type": 1 })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_acknowledge_button_records_the_user() {
+        let notifier = Arc::new(Notifier::default());
+        notifier
+            .alert_down("https://a.example", &DownAlert::default())
+            .await
+            .unwrap();
+        let incident_id = notifier.incident("https://a.example").unwrap().id;
+        let url = spawn_interactions(Arc::clone(&notifier)).await;
+
+        let body = serde_json::json!({
+            "type": 3,
+            "data": { "custom_id": format!("ack:{incident_id}") },
+            "member": { "user": { "id": "1234", "username": "alice" } },
+            "message": { "content": "Alert: https://a.example is DOWN!" },
+        })
+        .to_string();
+        let response: Value = post_signed(&url, &body).await.json().await.unwrap();
+
+        assert_eq!(response["type"], 7);
+        assert_eq!(
+            response["data"]["content"],
+            "Alert: https://a.example is DOWN!\nAcknowledged by <@1234>"
+        );
+        assert_eq!(
+            notifier
+                .incident("https://a.example")
+                .unwrap()
+                .acknowledged_by
+                .as_deref(),
+            Some("alice")
+        );
+    }
+}
@@ -0,0 +1,369 @@
+use log::{error, info, warn};
+use reqwest::{Certificate, Client, Response};
+use serde::Deserialize;
+use std::{collections::HashMap, fs, time::Duration};
+use tokio::{select, time::sleep};
+use tokio_util::sync::CancellationToken;
+
+use crate::config::KubernetesOptions;
+use crate::discovery;
+use crate::error::Error;
+use crate::status::StatusBoard;
+
+/// Name of the Ingresses as a discovery source, shown in logs.
+const SOURCE: &str = "Kubernetes Ingresses";
+
+/// Where the service account of the pod is mounted.
+const SERVICE_ACCOUNT_DIR: &str = "/var/run/secrets/kubernetes.io/serviceaccount";
+
+/// Seconds the API server keeps a watch open before the Ingresses are listed again.
+const WATCH_TIMEOUT_SECS: u64 = 300;
+
+/// Delay before retrying after the API server could not be reached.
+const RETRY_DELAY: Duration = Duration::from_secs(10);
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Client of the API server of the cluster the monitor runs in.
+struct ApiClient {
+    client: Client,
+    base_url: String,
+    /// File holding the service account token, read on every request as it
+    /// is rotated by the kubelet.
+    token_path: Option<String>,
+}
+
+impl ApiClient {
+    /// Connects with the service account of the pod, as set up by Kubernetes.
+    fn in_cluster() -> Result<Self, Error> {
+        let (Ok(host), Ok(port)) = (
+            std::env::var("KUBERNETES_SERVICE_HOST"),
+            std::env::var("KUBERNETES_SERVICE_PORT"),
+        ) else {
+            return Err(Error::Config(
+                "Not running in a Kubernetes pod, KUBERNETES_SERVICE_HOST and KUBERNETES_SERVICE_PORT are not set".into(),
+            ));
+        };
+        let host = if host.contains(':') {
+            format!("[{host}]")
+        } else {
+            host
+        };
+
+        let ca = fs::read(format!("{SERVICE_ACCOUNT_DIR}/ca.crt"))?;
+        let client = Client::builder()
+            .add_root_certificate(Certificate::from_pem(&ca)?)
+            .connect_timeout(CONNECT_TIMEOUT)
+            .build()?;
+        Ok(Self {
+            client,
+            base_url: format!("https://{host}:{port}"),
+            token_path: Some(format!("{SERVICE_ACCOUNT_DIR}/token")),
+        })
+    }
+
+    async fn get(&self, path: &str, query: &[(&str, &str)]) -> Result<Response, Error> {
+        let mut request = self
+            .client
+            .get(format!("{}{path}", self.base_url))
+            .query(query);
+        if let Some(token_path) = &self.token_path {
+            request = request.bearer_auth(fs::read_to_string(token_path)?.trim());
+        }
+        Ok(request.send().await?.error_for_status()?)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct IngressList {
+    metadata: ListMeta,
+    items: Vec<Ingress>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ListMeta {
+    resource_version: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Ingress {
+    metadata: ObjectMeta,
+    #[serde(default)]
+    spec: IngressSpec,
+}
+
+#[derive(Debug, Deserialize)]
+struct ObjectMeta {
+    name: String,
+    #[serde(default)]
+    namespace: String,
+    #[serde(default)]
+    annotations: HashMap<String, String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct IngressSpec {
+    #[serde(default)]
+    rules: Vec<IngressRule>,
+    #[serde(default)]
+    tls: Vec<IngressTls>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IngressRule {
+    host: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IngressTls {
+    #[serde(default)]
+    hosts: Vec<String>,
+}
+
+/// A change to the watched Ingresses.
+#[derive(Debug, Deserialize)]
+struct WatchEvent {
+    #[serde(rename = "type")]
+    kind: String,
+    object: serde_json::Value,
+}
+
+impl Ingress {
+    fn key(&self) -> String {
+        format!("{}/{}", self.metadata.namespace, self.metadata.name)
+    }
+
+    /// URLs of the hosts of the Ingress, HTTPS for the ones it terminates
+    /// TLS for. None when it lacks the required `annotation`.
+    fn urls(&self, annotation: Option<&str>) -> Vec<String> {
+        if let Some(annotation) = annotation
+            && self
+                .metadata
+                .annotations
+                .get(annotation)
+                .map(String::as_str)
+                != Some("true")
+        {
+            return Vec::new();
+        }
+
+        self.spec
+            .rules
+            .iter()
+            .filter_map(|rule| rule.host.as_deref())
+            // Wildcard hosts name no site in particular
+            .filter(|host| !host.starts_with('*'))
+            .map(|host| {
+                let tls = self
+                    .spec
+                    .tls
+                    .iter()
+                    .any(|tls| tls.hosts.iter().any(|tls_host| tls_host == host));
+                let scheme = if tls { "https" } else { "http" };
+                format!("{scheme}://{host}")
+            })
+            .collect()
+    }
+}
+
+/// Watches the Ingresses and keeps their hosts monitored, until `token` is cancelled.
+pub(crate) fn spawn_discovery(
+    options: KubernetesOptions,
+    board: StatusBoard,
+    token: CancellationToken,
+) {
+    tokio::spawn(async move {
+        let api = match ApiClient::in_cluster() {
+            Ok(api) => api,
+            Err(e) => {
+                error!("Kubernetes discovery is disabled: {e}");
+                return;
+            }
+        };
+
+        let mut ingresses = HashMap::new();
+        loop {
+            let result = select! {
+                result = watch(&api, &options, &board, &mut ingresses) => result,
+                () = token.cancelled() => break,
+            };
+            // A watch ending normally is resumed right away
+            if let Err(e) = result {
+                warn!(
+                    "Watching Kubernetes Ingresses failed, retrying in {} s: {e}",
+                    RETRY_DELAY.as_secs()
+                );
+                select! {
+                    () = sleep(RETRY_DELAY) => {},
+                    () = token.cancelled() => break,
+                }
+            }
+        }
+    });
+}
+
+/// Lists the Ingresses, then follows their changes until the API server
+/// ends the watch. `ingresses` holds the URLs of every Ingress by name.
+async fn watch(
+    api: &ApiClient,
+    options: &KubernetesOptions,
+    board: &StatusBoard,
+    ingresses: &mut HashMap<String, Vec<String>>,
+) -> Result<(), Error> {
+    let path = match &options.namespace {
+        Some(namespace) => format!("/apis/networking.k8s.io/v1/namespaces/{namespace}/ingresses"),
+        None => "/apis/networking.k8s.io/v1/ingresses".to_string(),
+    };
+    let annotation = options.annotation.as_deref();
+
+    let list: IngressList = api.get(&path, &[]).await?.json().await?;
+    ingresses.clear();
+    for ingress in list.items {
+        ingresses.insert(ingress.key(), ingress.urls(annotation));
+    }
+    sync(board, ingresses);
+
+    let mut response = api
+        .get(
+            &path,
+            &[
+                ("watch", "true"),
+                ("resourceVersion", &list.metadata.resource_version),
+                ("timeoutSeconds", &WATCH_TIMEOUT_SECS.to_string()),
+            ],
+        )
+        .await?;
+
+    // Events are streamed as one JSON object per line
+    let mut buffer = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        buffer.extend_from_slice(&chunk);
+        while let Some(end) = buffer.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=end).collect();
+            let event: WatchEvent = serde_json::from_slice(&line)?;
+            match event.kind.as_str() {
+                "ADDED" | "MODIFIED" => {
+                    let ingress: Ingress = serde_json::from_value(event.object)?;
+                    ingresses.insert(ingress.key(), ingress.urls(annotation));
+                }
+                "DELETED" => {
+                    let ingress: Ingress = serde_json::from_value(event.object)?;
+                    ingresses.remove(&ingress.key());
+                }
+                // Usually 410 Gone once the resource version is too old to resume from
+                "ERROR" => {
+                    let message = event.object["message"].as_str().unwrap_or("unknown error");
+                    return Err(Error::Discovery(format!("Ingress watch ended: {message}")));
+                }
+                _ => continue,
+            }
+            sync(board, ingresses);
+        }
+    }
+    Ok(())
+}
+
+fn sync(board: &StatusBoard, ingresses: &HashMap<String, Vec<String>>) {
+    let mut urls: Vec<String> = ingresses.values().flatten().cloned().collect();
+    urls.sort();
+    urls.dedup();
+    discovery::sync(board, SOURCE, &urls);
+}
+
+/// Logs where Ingresses are discovered from.
+pub(crate) fn log_enabled(options: &KubernetesOptions) {
+    let scope = options
+        .namespace
+        .as_deref()
+        .map_or("every namespace".to_string(), |namespace| {
+            format!("namespace {namespace}")
+        });
+    match &options.annotation {
+        Some(annotation) => info!(
+            "Kubernetes discovery is enabled, monitoring the Ingresses of {scope} annotated {annotation}: \"true\""
+        ),
+        None => info!("Kubernetes discovery is enabled, monitoring the Ingresses of {scope}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Router, extract::Query, routing::get};
+    use tokio::net::TcpListener;
+
+    fn ingress(name: &str, host: &str, enabled: bool) -> serde_json::Value {
+        serde_json::json!({
+            "metadata": {
+                "name": name,
+                "namespace": "web",
+                "annotations": { "downdetector/enable": enabled.to_string() },
+            },
+            "spec": {
+                "rules": [{ "host": host }, { "host": "*.wildcard.example" }, {}],
+                "tls": [{ "hosts": ["a.example"] }],
+            },
+        })
+    }
+
+    #[test]
+    fn test_ingress_urls() {
+        let enabled: Ingress = serde_json::from_value(ingress("a", "a.example", true)).unwrap();
+        assert_eq!(enabled.key(), "web/a");
+        assert_eq!(
+            enabled.urls(Some("downdetector/enable")),
+            ["https://a.example"]
+        );
+
+        let disabled: Ingress = serde_json::from_value(ingress("b", "b.example", false)).unwrap();
+        assert_eq!(disabled.urls(None), ["http://b.example"]);
+        assert!(disabled.urls(Some("downdetector/enable")).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_watch_follows_ingress_changes() {
+        let app = Router::new().route(
+            "/apis/networking.k8s.io/v1/namespaces/web/ingresses",
+            get(|Query(query): Query<HashMap<String, String>>| async move {
+                if query.get("watch").map(String::as_str) != Some("true") {
+                    return serde_json::json!({
+                        "metadata": { "resourceVersion": "41" },
+                        "items": [ingress("a", "a.example", true), ingress("b", "b.example", true)],
+                    })
+                    .to_string();
+                }
+                assert_eq!(query["resourceVersion"], "41");
+                [
+                    serde_json::json!({ "type": "ADDED", "object": ingress("c", "c.example", true) }),
+                    serde_json::json!({ "type": "BOOKMARK", "object": {} }),
+                    serde_json::json!({ "type": "DELETED", "object": ingress("a", "a.example", true) }),
+                    serde_json::json!({ "type": "MODIFIED", "object": ingress("b", "b.example", false) }),
+                ]
+                .map(|event| format!("{event}\n"))
+                .concat()
+            }),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let api = ApiClient {
+            client: Client::new(),
+            base_url: format!("http://{addr}"),
+            token_path: None,
+        };
+        let options = KubernetesOptions {
+            namespace: Some("web".to_string()),
+            annotation: Some("downdetector/enable".to_string()),
+        };
+        let board = StatusBoard::new(&["http://configured.example".to_string()]);
+        let mut ingresses = HashMap::new();
+        watch(&api, &options, &board, &mut ingresses).await.unwrap();
+
+        assert_eq!(
+            board.urls(),
+            ["http://configured.example", "http://c.example"]
+        );
+    }
+}
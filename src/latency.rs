@@ -0,0 +1,122 @@
+use std::{collections::VecDeque, time::Duration};
+
+/// Response times of the last successful checks of a site.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct LatencyWindow {
+    samples: VecDeque<Duration>,
+    /// Whether the site was alerted on for its current slowness.
+    alerted: bool,
+}
+
+/// Outcome of adding a response time to a [`LatencyWindow`].
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum LatencyCheck {
+    /// Nothing to report, the window is not full yet or nothing changed.
+    Unchanged,
+    /// The p95 response time went above the threshold.
+    Exceeded(Duration),
+    /// The p95 response time is back under the threshold after an alert.
+    Restored(Duration),
+}
+
+impl LatencyWindow {
+    /// Adds the response time of a successful check, keeping the last
+    /// `size` ones, and compares their p95 to `threshold` once `size` are known.
+    ///
+    /// A slowness is reported once rather than on every check.
+    pub(crate) fn record(
+        &mut self,
+        latency: Duration,
+        size: usize,
+        threshold: Duration,
+    ) -> LatencyCheck {
+        self.samples.push_back(latency);
+        while self.samples.len() > size {
+            self.samples.pop_front();
+        }
+        if self.samples.len() < size {
+            return LatencyCheck::Unchanged;
+        }
+
+        let p95 = p95(&self.samples);
+        match (p95 > threshold, self.alerted) {
+            (true, false) => {
+                self.alerted = true;
+                LatencyCheck::Exceeded(p95)
+            }
+            (false, true) => {
+                self.alerted = false;
+                LatencyCheck::Restored(p95)
+            }
+            _ => LatencyCheck::Unchanged,
+        }
+    }
+}
+
+/// 95th percentile of `samples` by the nearest-rank method.
+fn p95(samples: &VecDeque<Duration>) -> Duration {
+    let mut sorted: Vec<Duration> = samples.iter().copied().collect();
+    sorted.sort_unstable();
+    let rank = (sorted.len() * 95).div_ceil(100);
+    sorted[rank.saturating_sub(1)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn test_p95_uses_the_nearest_rank() {
+        let samples: VecDeque<Duration> = (1..=20).map(|i| ms(i * 100)).collect();
+        assert_eq!(p95(&samples), ms(1900));
+        assert_eq!(p95(&VecDeque::from([ms(300)])), ms(300));
+    }
+
+    #[test]
+    fn test_sustained_slowness_is_reported_once() {
+        let mut window = LatencyWindow::default();
+        let threshold = ms(500);
+        for _ in 0..19 {
+            assert_eq!(
+                window.record(ms(900), 20, threshold),
+                LatencyCheck::Unchanged,
+                "The window is not full yet"
+            );
+        }
+        assert_eq!(
+            window.record(ms(900), 20, threshold),
+            LatencyCheck::Exceeded(ms(900))
+        );
+        assert_eq!(
+            window.record(ms(900), 20, threshold),
+            LatencyCheck::Unchanged
+        );
+
+        let checks: Vec<LatencyCheck> = (0..20)
+            .map(|_| window.record(ms(100), 20, threshold))
+            .collect();
+        assert_eq!(
+            checks
+                .iter()
+                .filter(|check| **check != LatencyCheck::Unchanged)
+                .count(),
+            1
+        );
+        assert_eq!(
+            checks[18],
+            LatencyCheck::Restored(ms(100)),
+            "Down to one slow check"
+        );
+        assert_eq!(window.samples.len(), 20);
+
+        // A single slow check among fast ones is not sustained slowness
+        assert_eq!(
+            window.record(ms(5000), 20, threshold),
+            LatencyCheck::Unchanged
+        );
+    }
+}
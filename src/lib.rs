@@ -8,10 +8,40 @@
 //! - Periodic monitoring of multiple websites
 //! - Configurable check intervals and timeouts
 //! - Consecutive failure threshold to reduce false positives
+//! - Per-site response body size bounds, failing or degrading the check
+//! - p95 response time alerts over a window of the last checks of each site
+//! - Optional host grouping, skipping the URLs of a host accepting no connection
 //! - Automatic 10-second retries for unreachable sites
+//! - Low-bandwidth mode for metered links, toggleable at runtime with `SIGUSR1`
+//! - Low-memory mode for constrained devices
 //! - Discord webhook integration for downtime alerts
 //! - Optional user mentions in Discord notifications
+//! - Optional startup check of the notification channels, warning or failing fast
+//! - Optional InfluxDB export of every check result, batched per cycle
+//! - Optional StatsD/DogStatsD gauges and timings over UDP
+//! - Optional OTLP traces and metrics export behind the `otel` feature
 //! - Automatic configuration file creation with sensible defaults
+//! - Optional persistent check history, exportable as CSV or JSON
+//! - Optional web dashboard with per-site uptime bars and latency sparklines
+//! - JSON API exposing site status and current incidents
+//! - Status table of a running instance from the command line
+//! - Adding and removing sites at runtime through the authenticated API
+//! - Sites fetched from a remote list maintained by another system
+//! - Kubernetes Ingress discovery behind the `k8s` feature
+//! - Docker container label discovery on Unix
+//! - Agent mode pushing results to a central instance that handles alerting
+//! - Down confirmation through a proxy or another instance before alerting
+//! - Per-site SLOs with error budget and burn rate alerts
+//! - Quiet hours holding non-critical alerts for a morning summary
+//! - Optional end-of-cycle summary of the down sites, globally or per tag, replacing their alerts
+//! - Per-site severity with alerts routed to channels and mentions by severity
+//! - Incident acknowledgement through a Discord button, stopping repeat alerts
+//! - Optional Discord thread per incident holding its updates and summary
+//! - Optional network diagnostics attached to DOWN alerts
+//! - Optional capture of the response that opened each incident
+//! - Backup and restore of the config and state, to move the monitor to a new host
+//! - Silencing the alerts of a site or tag during expected downtime, from the command line
+//! - Windows service install and control, logging to the event log
 //!
 //! # Configuration
 //!
@@ -24,10 +54,14 @@
 //! Example configuration:
 //!
 //! ```toml
+//! version = 2
+//!
 //! [config]
 //! timeout_secs = 30
 //! check_interval_secs = 300
 //! failure_threshold = 5
+//! low_bandwidth = false
+//! low_memory = false
 //! webhook_url = "https://discord.com/api/webhooks/..."
 //! discord_id = 123456789
 //!
@@ -39,18 +73,77 @@
 //! ```
 //!
 //! Sites that fail a check are retried every 10 seconds until they recover.
-//! Repeat downtime alerts remain throttled by `check_interval_secs`.
+//! Repeat downtime alerts remain throttled by `check_interval_secs` and edit
+//! the message of the first alert instead of posting new ones.
 //!
 //! # Environment Variables
 //!
 //! The following environment variables can override config file values:
 //! - `WEBHOOK_URL`: Discord webhook URL for notifications
 //! - `DISCORD_ID`: Discord user ID for mentions
+//! - `INFLUXDB_TOKEN`: API token for the `[influxdb]` exporter
+//! - `API_TOKEN`: Bearer token for the site management API
+//! - `AGENT_TOKEN`: Token shared by agents and the central instance
+//! - `PROBE_TOKEN`: Token shared by an instance and the probe confirming its outages
+//! - `DISCORD_PUBLIC_KEY`: Public key of the Discord application receiving Acknowledge clicks
+//! - `DISCORD_BOT_TOKEN`: Token of the Discord bot starting incident threads
+//! - `OTEL_EXPORTER_OTLP_ENDPOINT` and related `OTEL_*` variables: OTLP export (`otel` feature)
 
+mod agent;
+mod api;
+mod backup;
+mod capture;
 mod config;
+mod confirm;
+mod cycle_summary;
+mod dashboard;
+mod diagnostics;
+mod discovery;
+#[cfg(unix)]
+mod docker;
 mod error;
+mod export;
+mod history;
+mod incident;
+mod influxdb;
+mod interactions;
+#[cfg(feature = "k8s")]
+mod kubernetes;
+mod latency;
+mod locale;
+mod migrate;
+mod notify;
+#[cfg(feature = "otel")]
+mod otel;
+mod pinning;
+mod quiet_hours;
+mod rate_limit;
+mod server;
+mod silence;
+mod slo;
+mod statsd;
+mod status;
+mod templates;
+#[cfg(test)]
+mod test_support;
 mod worker;
 
+/// Table of the sites of a running instance, printed by the `status` command.
+pub use api::print_status;
+/// Backup and restore of the config and state, used by the `backup` and
+/// `restore` commands.
+pub use backup::{backup, restore};
+/// The monitor configuration, loaded from the default config file location.
+pub use config::Config;
+/// The error type returned by the fallible public functions.
+pub use error::Error;
+/// Export of the stored check history, used by the `export` command.
+pub use history::{ExportFormat, export_history};
+/// Expected downtime acknowledgement, used by the `silence` command.
+pub use silence::{Silence, silence};
+/// Startup check of the notification channels, run by the monitor command.
+pub use {config::NotificationCheck, notify::check_notification_channels};
+
 /// The main monitoring function that continuously checks website availability.
 ///
 /// See the [module documentation](crate) for usage examples.
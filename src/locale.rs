@@ -0,0 +1,242 @@
+use crate::config::Language;
+
+/// Built-in phrasing of the notifications in one language.
+///
+/// Every phrase is a template, see [`crate::templates::Template`], with the
+/// variables listed next to it.
+#[derive(Debug)]
+pub(crate) struct Phrases {
+    /// Default `down` template.
+    pub(crate) down: &'static str,
+    /// Default `recovery` template.
+    pub(crate) recovery: &'static str,
+    /// Default `digest` template.
+    pub(crate) digest: &'static str,
+    /// Appended to the edited DOWN alert: `duration`.
+    pub(crate) still_down: &'static str,
+    /// Posted in the incident thread on every repeat alert: `duration`.
+    pub(crate) thread_update: &'static str,
+    /// Posted in the incident thread once resolved: `site.url`.
+    pub(crate) thread_resolved: &'static str,
+    /// `incident.id`, `duration`, `started_at`, `resolved_at`, `acknowledged_by`.
+    pub(crate) incident_summary: &'static str,
+    /// Appended to an acknowledged alert: `user`.
+    pub(crate) acknowledged: &'static str,
+    /// Digest line when no site is failing anymore.
+    pub(crate) all_up: &'static str,
+    pub(crate) still_failing: &'static str,
+    /// `site`, `status`, `since`.
+    pub(crate) failing_since: &'static str,
+    pub(crate) slo_header: &'static str,
+    /// Last line of a truncated digest: `count`.
+    pub(crate) more_lines: &'static str,
+    /// `site.url`, `burn_rate`, `summary`.
+    pub(crate) slo_alert: &'static str,
+    /// `compliance`, `window_days`, `target`, `remaining`.
+    pub(crate) slo_summary: &'static str,
+    /// `target`.
+    pub(crate) slo_no_data: &'static str,
+    /// `site.url`, `fingerprint`, `pinned`.
+    pub(crate) certificate_mismatch: &'static str,
+    /// End-of-cycle summary: `down`, `total`, `tag`, `sites`.
+    pub(crate) cycle_summary: &'static str,
+    /// Why a check failed or degraded: `size`, `min_bytes`.
+    pub(crate) body_too_small: &'static str,
+    /// Why a check failed or degraded: `max_bytes`.
+    pub(crate) body_too_large: &'static str,
+    /// Default `degraded` template.
+    pub(crate) degraded: &'static str,
+    /// Why a site was not requested: `host`.
+    pub(crate) host_unreachable: &'static str,
+    /// `site.url`, `p95_ms`, `checks`, `threshold_ms`.
+    pub(crate) latency_alert: &'static str,
+    /// `agent`, `duration`, `sites`.
+    pub(crate) agent_stale: &'static str,
+}
+
+const EN: Phrases = Phrases {
+    down: "Alert: {{site.url}} is DOWN!",
+    recovery: "Resolved: {{site.url}} is back UP, down for {{duration}} (incident #{{incident.id}}){{#if acknowledged_by}}, acknowledged by {{acknowledged_by}}{{/if}}",
+    digest: "Quiet hours summary ({{start}} to {{end}}), {{held_count}} alert(s) held:",
+    still_down: "Down for {{duration}} so far",
+    thread_update: "Still DOWN after {{duration}}",
+    thread_resolved: "Resolved: {{site.url}} is back UP",
+    incident_summary: "Incident #{{incident.id}} lasted {{duration}}, from {{started_at}} to {{resolved_at}}{{#if acknowledged_by}}, acknowledged by {{acknowledged_by}}{{/if}}",
+    acknowledged: "Acknowledged by {{user}}",
+    all_up: "Every site is back up.",
+    still_failing: "Still failing:",
+    failing_since: "- {{site}} is {{status}} since {{since}}",
+    slo_header: "SLOs:",
+    more_lines: "... and {{count}} more line(s)",
+    slo_alert: "SLO alert: {{site.url}} is burning its error budget {{burn_rate}}x too fast, {{summary}}",
+    slo_summary: "{{compliance}}% over {{window_days}} days (target {{target}}%, {{remaining}}% of error budget left)",
+    slo_no_data: "no data yet (target {{target}}%)",
+    certificate_mismatch: "Certificate alert: {{site.url}} presented an unexpected certificate (SHA-256 {{fingerprint}}, pinned {{pinned}})",
+    cycle_summary: "Summary: {{down}}/{{total}} sites DOWN{{#if tag}} tagged {{tag}}{{/if}}: {{sites}}",
+    body_too_small: "Response body of {{size}} bytes, expected at least {{min_bytes}}",
+    body_too_large: "Response body over {{max_bytes}} bytes, the most expected",
+    degraded: "Degraded: {{site.url}} is UP but answers unexpectedly\n{{reason}}",
+    host_unreachable: "Host {{host}} accepted no connection, so the site was not requested",
+    latency_alert: "Latency alert: {{site.url}} answered in {{p95_ms}} ms at p95 over its last {{checks}} checks, above {{threshold_ms}} ms",
+    agent_stale: "Agent alert: {{agent}} has not reported for {{duration}}, the status of its {{sites}} site(s) is UNKNOWN",
+};
+
+const FR: Phrases = Phrases {
+    down: "Alerte : {{site.url}} est HORS LIGNE !",
+    recovery: "Résolu : {{site.url}} est de nouveau EN LIGNE, hors ligne pendant {{duration}} (incident n°{{incident.id}}){{#if acknowledged_by}}, pris en charge par {{acknowledged_by}}{{/if}}",
+    digest: "Résumé des heures calmes ({{start}} à {{end}}), {{held_count}} alerte(s) retenue(s) :",
+    still_down: "Hors ligne depuis {{duration}}",
+    thread_update: "Toujours HORS LIGNE après {{duration}}",
+    thread_resolved: "Résolu : {{site.url}} est de nouveau EN LIGNE",
+    incident_summary: "L'incident n°{{incident.id}} a duré {{duration}}, du {{started_at}} au {{resolved_at}}{{#if acknowledged_by}}, pris en charge par {{acknowledged_by}}{{/if}}",
+    acknowledged: "Pris en charge par {{user}}",
+    all_up: "Tous les sites sont de nouveau en ligne.",
+    still_failing: "Toujours en échec :",
+    failing_since: "- {{site}} est {{status}} depuis {{since}}",
+    slo_header: "SLO :",
+    more_lines: "... et {{count}} ligne(s) de plus",
+    slo_alert: "Alerte SLO : {{site.url}} consomme son budget d'erreur {{burn_rate}}x trop vite, {{summary}}",
+    slo_summary: "{{compliance}} % sur {{window_days}} jours (objectif {{target}} %, {{remaining}} % du budget d'erreur restant)",
+    slo_no_data: "pas encore de données (objectif {{target}} %)",
+    certificate_mismatch: "Alerte certificat : {{site.url}} a présenté un certificat inattendu (SHA-256 {{fingerprint}}, épinglé {{pinned}})",
+    cycle_summary: "Résumé : {{down}}/{{total}} sites HORS LIGNE{{#if tag}} étiquetés {{tag}}{{/if}} : {{sites}}",
+    body_too_small: "Corps de réponse de {{size}} octets, au moins {{min_bytes}} attendus",
+    body_too_large: "Corps de réponse de plus de {{max_bytes}} octets, le maximum attendu",
+    degraded: "Dégradé : {{site.url}} est EN LIGNE mais répond de façon inattendue\n{{reason}}",
+    host_unreachable: "L'hôte {{host}} n'a accepté aucune connexion, le site n'a donc pas été interrogé",
+    latency_alert: "Alerte de latence : {{site.url}} a répondu en {{p95_ms}} ms au p95 sur ses {{checks}} derniers contrôles, au-delà de {{threshold_ms}} ms",
+    agent_stale: "Alerte agent : {{agent}} n'a rien envoyé depuis {{duration}}, l'état de ses {{sites}} site(s) est INCONNU",
+};
+
+const DE: Phrases = Phrases {
+    down: "Alarm: {{site.url}} ist DOWN!",
+    recovery: "Behoben: {{site.url}} ist wieder UP, war {{duration}} lang down (Vorfall #{{incident.id}}){{#if acknowledged_by}}, bestätigt von {{acknowledged_by}}{{/if}}",
+    digest: "Zusammenfassung der Ruhezeit ({{start}} bis {{end}}), {{held_count}} Alarm(e) zurückgehalten:",
+    still_down: "Seit {{duration}} down",
+    thread_update: "Nach {{duration}} immer noch DOWN",
+    thread_resolved: "Behoben: {{site.url}} ist wieder UP",
+    incident_summary: "Vorfall #{{incident.id}} dauerte {{duration}}, von {{started_at}} bis {{resolved_at}}{{#if acknowledged_by}}, bestätigt von {{acknowledged_by}}{{/if}}",
+    acknowledged: "Bestätigt von {{user}}",
+    all_up: "Alle Seiten sind wieder erreichbar.",
+    still_failing: "Weiterhin fehlerhaft:",
+    failing_since: "- {{site}} ist {{status}} seit {{since}}",
+    slo_header: "SLOs:",
+    more_lines: "... und {{count}} weitere Zeile(n)",
+    slo_alert: "SLO-Alarm: {{site.url}} verbraucht sein Fehlerbudget {{burn_rate}}x zu schnell, {{summary}}",
+    slo_summary: "{{compliance}} % über {{window_days}} Tage (Ziel {{target}} %, {{remaining}} % des Fehlerbudgets übrig)",
+    slo_no_data: "noch keine Daten (Ziel {{target}} %)",
+    certificate_mismatch: "Zertifikatsalarm: {{site.url}} hat ein unerwartetes Zertifikat vorgelegt (SHA-256 {{fingerprint}}, gepinnt {{pinned}})",
+    cycle_summary: "Zusammenfassung: {{down}}/{{total}} Seiten DOWN{{#if tag}} mit dem Tag {{tag}}{{/if}}: {{sites}}",
+    body_too_small: "Antworttext mit {{size}} Bytes, mindestens {{min_bytes}} erwartet",
+    body_too_large: "Antworttext mit mehr als {{max_bytes}} Bytes, dem erwarteten Maximum",
+    degraded: "Beeinträchtigt: {{site.url}} ist UP, antwortet aber unerwartet\n{{reason}}",
+    host_unreachable: "Der Host {{host}} hat keine Verbindung angenommen, die Seite wurde daher nicht abgefragt",
+    latency_alert: "Latenzalarm: {{site.url}} antwortete im p95 über die letzten {{checks}} Prüfungen in {{p95_ms}} ms, über {{threshold_ms}} ms",
+    agent_stale: "Agentenalarm: {{agent}} hat seit {{duration}} nichts gemeldet, der Status seiner {{sites}} Seite(n) ist UNBEKANNT",
+};
+
+const ES: Phrases = Phrases {
+    down: "Alerta: ¡{{site.url}} está CAÍDO!",
+    recovery: "Resuelto: {{site.url}} vuelve a estar ACTIVO, caído durante {{duration}} (incidente n.º {{incident.id}}){{#if acknowledged_by}}, reconocido por {{acknowledged_by}}{{/if}}",
+    digest: "Resumen de las horas de silencio ({{start}} a {{end}}), {{held_count}} alerta(s) retenida(s):",
+    still_down: "Caído desde hace {{duration}}",
+    thread_update: "Sigue CAÍDO tras {{duration}}",
+    thread_resolved: "Resuelto: {{site.url}} vuelve a estar ACTIVO",
+    incident_summary: "El incidente n.º {{incident.id}} duró {{duration}}, del {{started_at}} al {{resolved_at}}{{#if acknowledged_by}}, reconocido por {{acknowledged_by}}{{/if}}",
+    acknowledged: "Reconocido por {{user}}",
+    all_up: "Todos los sitios vuelven a estar activos.",
+    still_failing: "Siguen fallando:",
+    failing_since: "- {{site}} está {{status}} desde las {{since}}",
+    slo_header: "SLO:",
+    more_lines: "... y {{count}} línea(s) más",
+    slo_alert: "Alerta de SLO: {{site.url}} consume su presupuesto de errores {{burn_rate}}x demasiado rápido, {{summary}}",
+    slo_summary: "{{compliance}} % en {{window_days}} días (objetivo {{target}} %, queda el {{remaining}} % del presupuesto de errores)",
+    slo_no_data: "aún sin datos (objetivo {{target}} %)",
+    certificate_mismatch: "Alerta de certificado: {{site.url}} presentó un certificado inesperado (SHA-256 {{fingerprint}}, fijado {{pinned}})",
+    cycle_summary: "Resumen: {{down}}/{{total}} sitios CAÍDOS{{#if tag}} con la etiqueta {{tag}}{{/if}}: {{sites}}",
+    body_too_small: "Cuerpo de respuesta de {{size}} bytes, se esperaban al menos {{min_bytes}}",
+    body_too_large: "Cuerpo de respuesta de más de {{max_bytes}} bytes, el máximo esperado",
+    degraded: "Degradado: {{site.url}} está ACTIVO pero responde de forma inesperada\n{{reason}}",
+    host_unreachable: "El host {{host}} no aceptó ninguna conexión, así que no se consultó el sitio",
+    latency_alert: "Alerta de latencia: {{site.url}} respondió en {{p95_ms}} ms en el p95 de sus últimas {{checks}} comprobaciones, por encima de {{threshold_ms}} ms",
+    agent_stale: "Alerta de agente: {{agent}} no ha informado desde hace {{duration}}, el estado de sus {{sites}} sitio(s) es DESCONOCIDO",
+};
+
+/// Built-in phrasing of the notifications in `language`.
+pub(crate) fn phrases(language: Language) -> &'static Phrases {
+    match language {
+        Language::En => &EN,
+        Language::Fr => &FR,
+        Language::De => &DE,
+        Language::Es => &ES,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::templates::Template;
+
+    #[test]
+    fn test_every_phrase_is_a_valid_template() {
+        for language in [Language::En, Language::Fr, Language::De, Language::Es] {
+            let Phrases {
+                down,
+                recovery,
+                digest,
+                still_down,
+                thread_update,
+                thread_resolved,
+                incident_summary,
+                acknowledged,
+                all_up,
+                still_failing,
+                failing_since,
+                slo_header,
+                more_lines,
+                slo_alert,
+                slo_summary,
+                slo_no_data,
+                certificate_mismatch,
+                cycle_summary,
+                body_too_small,
+                body_too_large,
+                degraded,
+                host_unreachable,
+                latency_alert,
+                agent_stale,
+            } = phrases(language);
+            for phrase in [
+                down,
+                recovery,
+                digest,
+                still_down,
+                thread_update,
+                thread_resolved,
+                incident_summary,
+                acknowledged,
+                all_up,
+                still_failing,
+                failing_since,
+                slo_header,
+                more_lines,
+                slo_alert,
+                slo_summary,
+                slo_no_data,
+                certificate_mismatch,
+                cycle_summary,
+                body_too_small,
+                body_too_large,
+                degraded,
+                host_unreachable,
+                latency_alert,
+                agent_stale,
+            ] {
+                assert!(
+                    Template::parse_builtin(phrase).is_ok(),
+                    "Invalid {language:?} phrase: {phrase}"
+                );
+            }
+        }
+    }
+}
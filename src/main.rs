@@ -1,20 +1,354 @@
-#[tokio::main]
-async fn main() {
-    // Initialize logging
+use clap::{Parser, Subcommand};
+use downdetector::{Config, ExportFormat, NotificationCheck};
+use jiff::{SignedDuration, Timestamp, civil::Date, tz::TimeZone};
+use log::{error, info, warn};
+use std::{io, path::PathBuf, process::ExitCode, time::Duration};
+use tokio::runtime::{Builder, Runtime};
+use tokio_util::sync::CancellationToken;
+use url::Url;
+
+#[cfg(windows)]
+mod service;
+
+// Runtime limits applied in low-memory mode
+const LOW_MEMORY_MAX_BLOCKING_THREADS: usize = 2;
+const LOW_MEMORY_THREAD_STACK_SIZE: usize = 256 * 1024;
+
+/// Monitors website availability and sends Discord notifications when sites go down.
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Dump the stored check history for offline analysis
+    Export {
+        /// Only include checks at or after this time (RFC 3339, date or unix seconds)
+        #[arg(long, value_parser = parse_timestamp)]
+        from: Option<Timestamp>,
+        /// Only include checks before this time (RFC 3339, date or unix seconds), a date includes the whole day
+        #[arg(long, value_parser = parse_end_timestamp)]
+        to: Option<Timestamp>,
+        /// Output format written to stdout
+        #[arg(long, value_enum, default_value_t = ExportFormat::Csv)]
+        format: ExportFormat,
+    },
+    /// Manage the Windows service running the monitor at boot
+    #[cfg(windows)]
+    Service {
+        #[command(subcommand)]
+        action: service::ServiceAction,
+    },
+    /// Bundle the config, incidents, captures and check history into an archive
+    Backup {
+        /// Archive to write, a gzipped tarball
+        file: PathBuf,
+    },
+    /// Replace the config and state with the ones of a `backup` archive
+    Restore {
+        /// Archive to read
+        file: PathBuf,
+    },
+    /// Print the sites of the running instance, their state and open incidents
+    Status {
+        /// Base URL of the instance, defaults to the address in the [server] section
+        #[arg(long)]
+        url: Option<Url>,
+    },
+    /// Stop alerting on a site or tag during expected downtime, checks go on
+    Silence {
+        /// URL of the site, or tag of the sites, to silence
+        target: String,
+        /// How long the silence lasts, e.g. 2h, 30m or 1h30m
+        #[arg(long = "for", value_parser = parse_duration)]
+        duration: Duration,
+        /// Why alerts are silenced, shown in the logs
+        #[arg(long)]
+        reason: Option<String>,
+    },
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    // Handled first, the service logs to the event log instead
+    #[cfg(windows)]
+    if let Some(Command::Service { action }) = cli.command {
+        return service::handle(action);
+    }
+
+    init_logging();
+
+    // Restoring replaces the config, which may not load before that
+    if let Some(Command::Restore { file }) = &cli.command {
+        return match downdetector::restore(file) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                error!("Failed to restore {}: {e}", file.display());
+                ExitCode::FAILURE
+            }
+        };
+    }
+
+    // Load configuration first, it decides how the runtime is built
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            error!("Failed to load configuration: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match cli.command {
+        None => run_monitor(config, CancellationToken::new()),
+        Some(Command::Export { from, to, format }) => {
+            match downdetector::export_history(&config, from, to, format, io::stdout().lock()) {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(e) => {
+                    error!("Failed to export history: {e}");
+                    ExitCode::FAILURE
+                }
+            }
+        }
+        Some(Command::Backup { file }) => match downdetector::backup(&config, &file) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                error!("Failed to back up to {}: {e}", file.display());
+                ExitCode::FAILURE
+            }
+        },
+        Some(Command::Silence {
+            target,
+            duration,
+            reason,
+        }) => match downdetector::silence(&config, &target, duration, reason) {
+            Ok(silence) => {
+                info!(
+                    "Alerts about {target} silenced until {}",
+                    format_millis(silence.until_ms)
+                );
+                ExitCode::SUCCESS
+            }
+            Err(e) => {
+                error!("Failed to silence {target}: {e}");
+                ExitCode::FAILURE
+            }
+        },
+        Some(Command::Status { url }) => print_status(&config, url.as_ref()),
+        Some(Command::Restore { .. }) => unreachable!("restored before loading the config"),
+        #[cfg(windows)]
+        Some(Command::Service { .. }) => unreachable!("handled before loading the config"),
+    }
+}
+
+fn init_logging() {
     env_logger::init_from_env(env_logger::Env::default().default_filter_or("info"));
+}
+
+/// Runs the monitor until CTRL+C or until `token` is cancelled.
+fn run_monitor(config: Config, token: CancellationToken) -> ExitCode {
+    let runtime = match build_runtime(config.config.low_memory) {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            error!("Failed to start async runtime: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    runtime.block_on(async {
+        if !check_notification_channels(&config).await {
+            return ExitCode::FAILURE;
+        }
+
+        // Spawn the shutdown handler
+        let shutdown_token = token.clone();
+        tokio::spawn(async move {
+            shutdown_signal().await;
+            shutdown_token.cancel();
+        });
+
+        // Start monitoring (runs forever)
+        downdetector::monitor_websites(config, token).await;
+        ExitCode::SUCCESS
+    })
+}
+
+/// Prints the status of the running instance, used by the `status` command.
+fn print_status(config: &Config, url: Option<&Url>) -> ExitCode {
+    let result = Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(downdetector::Error::from)
+        .and_then(|runtime| {
+            runtime.block_on(downdetector::print_status(config, url, io::stdout().lock()))
+        });
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            error!("Failed to get the status of the running instance: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Checks the notification channels when enabled, returning whether the
+/// monitor may start.
+async fn check_notification_channels(config: &Config) -> bool {
+    let mode = config.config.notification_check;
+    if mode == NotificationCheck::Off {
+        return true;
+    }
+    // Agents report to the central instance, which is the one alerting
+    if config.agent.is_some() {
+        info!("Notification channels are not checked in agent mode, the central instance alerts");
+        return true;
+    }
+    match downdetector::check_notification_channels(config).await {
+        Ok(()) => {
+            info!("Notification channels are reachable");
+            true
+        }
+        Err(e) if mode == NotificationCheck::Fail => {
+            error!("{e}");
+            false
+        }
+        Err(e) => {
+            warn!("{e}, alerts may not be delivered");
+            true
+        }
+    }
+}
+
+/// Resolves on CTRL+C, or on the `SIGTERM` and `SIGQUIT` Docker and systemd
+/// stop processes with on Unix.
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        let mut terminate =
+            signal(SignalKind::terminate()).expect("Failed to install SIGTERM handler");
+        let mut quit = signal(SignalKind::quit()).expect("Failed to install SIGQUIT handler");
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => result.expect("Failed to install CTRL+C handler"),
+            _ = terminate.recv() => log::info!("Received SIGTERM"),
+            _ = quit.recv() => log::info!("Received SIGQUIT"),
+        }
+    }
+    #[cfg(not(unix))]
+    tokio::signal::ctrl_c()
+        .await
+        .expect("Failed to install CTRL+C handler");
+}
+
+/// Builds a multi-threaded runtime, or a single-threaded one with a small
+/// blocking pool when running in low-memory mode.
+fn build_runtime(low_memory: bool) -> io::Result<Runtime> {
+    if low_memory {
+        Builder::new_current_thread()
+            .max_blocking_threads(LOW_MEMORY_MAX_BLOCKING_THREADS)
+            .thread_stack_size(LOW_MEMORY_THREAD_STACK_SIZE)
+            .enable_all()
+            .build()
+    } else {
+        Builder::new_multi_thread().enable_all().build()
+    }
+}
+
+/// Parses a positive duration such as `2h`, `1h30m` or `90 minutes`.
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    value
+        .parse::<SignedDuration>()
+        .ok()
+        .filter(SignedDuration::is_positive)
+        .and_then(|duration| Duration::try_from(duration).ok())
+        .ok_or_else(|| format!("'{value}' is not a positive duration such as 2h or 30m"))
+}
+
+/// Formats unix milliseconds as an RFC 3339 timestamp.
+fn format_millis(millis: u64) -> String {
+    i64::try_from(millis)
+        .ok()
+        .and_then(|millis| Timestamp::from_millisecond(millis).ok())
+        .map_or_else(|| millis.to_string(), |timestamp| timestamp.to_string())
+}
+
+/// Parses the exclusive end of a range like [`parse_timestamp`], except that a
+/// `YYYY-MM-DD` date ends at the following UTC midnight so the day is included.
+fn parse_end_timestamp(value: &str) -> Result<Timestamp, String> {
+    match value.parse::<Date>() {
+        Ok(date) => date
+            .tomorrow()
+            .and_then(|day| day.to_zoned(TimeZone::UTC))
+            .map(|zoned| zoned.timestamp())
+            .map_err(|e| e.to_string()),
+        Err(_) => parse_timestamp(value),
+    }
+}
+
+/// Parses an RFC 3339 timestamp, a `YYYY-MM-DD` date (UTC midnight) or unix seconds.
+fn parse_timestamp(value: &str) -> Result<Timestamp, String> {
+    if let Ok(timestamp) = value.parse::<Timestamp>() {
+        return Ok(timestamp);
+    }
+    if let Ok(date) = value.parse::<Date>() {
+        return date
+            .to_zoned(TimeZone::UTC)
+            .map(|zoned| zoned.timestamp())
+            .map_err(|e| e.to_string());
+    }
+    value
+        .parse::<i64>()
+        .ok()
+        .and_then(|seconds| Timestamp::from_second(seconds).ok())
+        .ok_or_else(|| {
+            format!("'{value}' is not an RFC 3339 timestamp, YYYY-MM-DD date or unix seconds")
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::runtime::RuntimeFlavor;
 
-    // Create cancellation token
-    let token = tokio_util::sync::CancellationToken::new();
+    #[test]
+    fn test_end_date_includes_the_whole_day() {
+        assert_eq!(
+            parse_end_timestamp("2026-10-15").unwrap(),
+            "2026-10-16T00:00:00Z".parse::<Timestamp>().unwrap()
+        );
+        assert_eq!(
+            parse_end_timestamp("2026-10-15T12:00:00Z").unwrap(),
+            parse_timestamp("2026-10-15T12:00:00Z").unwrap()
+        );
+        assert_eq!(
+            parse_timestamp("2026-10-15").unwrap(),
+            "2026-10-15T00:00:00Z".parse::<Timestamp>().unwrap()
+        );
+    }
 
-    // Spawn the shutdown handler
-    let shutdown_token = token.clone();
-    tokio::spawn(async move {
-        tokio::signal::ctrl_c()
-            .await
-            .expect("Failed to install CTRL+C handler");
-        shutdown_token.cancel();
-    });
+    #[test]
+    fn test_low_memory_runtime_is_single_threaded() {
+        let runtime = build_runtime(true).unwrap();
+        assert_eq!(
+            runtime.handle().runtime_flavor(),
+            RuntimeFlavor::CurrentThread
+        );
+        // The small blocking pool still runs blocking tasks
+        assert_eq!(
+            runtime
+                .block_on(async { tokio::task::spawn_blocking(|| 42).await })
+                .unwrap(),
+            42
+        );
 
-    // Start monitoring (runs forever)
-    downdetector::monitor_websites(token).await;
+        let runtime = build_runtime(false).unwrap();
+        assert_eq!(
+            runtime.handle().runtime_flavor(),
+            RuntimeFlavor::MultiThread
+        );
+    }
 }
@@ -0,0 +1,158 @@
+use log::{info, warn};
+use std::{fs, path::Path};
+use toml_edit::{DocumentMut, Item, Value, value};
+
+use crate::error::Error;
+
+/// Version of the config format this release reads and writes.
+pub(crate) const CONFIG_VERSION: i64 = 2;
+
+/// Upgrades a config document from the version it was written for to the
+/// next one. The first entry upgrades version 1, the unversioned format.
+type Migration = fn(&mut DocumentMut);
+
+const MIGRATIONS: &[Migration] = &[shorthand_critical_to_severity];
+
+/// Reads the config file at `path`, upgrading it in place to
+/// [`CONFIG_VERSION`] first when it was written for an older version.
+///
+/// The original file is kept next to it as `<name>.v<version>.bak`. If the
+/// upgraded file cannot be written, the upgrade is only applied in memory.
+pub(crate) fn migrate_file(path: &Path) -> Result<String, Error> {
+    let content = fs::read_to_string(path)?;
+    let mut document: DocumentMut = content.parse()?;
+    let Some(from_version) = migrate(&mut document)? else {
+        return Ok(content);
+    };
+
+    let migrated = document.to_string();
+    let backup_path = path.with_extension(format!("toml.v{from_version}.bak"));
+    let saved = fs::write(&backup_path, &content).and_then(|()| fs::write(path, &migrated));
+    match saved {
+        Ok(()) => info!(
+            "Upgraded {} from config version {from_version} to {CONFIG_VERSION}, the original is kept as {}",
+            path.display(),
+            backup_path.display()
+        ),
+        Err(e) => warn!(
+            "Failed to save {} upgraded to config version {CONFIG_VERSION}, upgrading it on every start: {e}",
+            path.display()
+        ),
+    }
+    Ok(migrated)
+}
+
+/// Upgrades `document` to [`CONFIG_VERSION`], returning the version it
+/// was written for, or `None` when it is already current.
+pub(crate) fn migrate(document: &mut DocumentMut) -> Result<Option<i64>, Error> {
+    let version = match document.get("version") {
+        None => 1,
+        Some(item) => item
+            .as_integer()
+            .filter(|version| *version >= 1)
+            .ok_or_else(|| Error::Config("version must be a positive integer".into()))?,
+    };
+    if version > CONFIG_VERSION {
+        return Err(Error::Config(format!(
+            "Config version {version} is newer than this release supports ({CONFIG_VERSION}), upgrade downdetector"
+        )));
+    }
+    if version == CONFIG_VERSION {
+        return Ok(None);
+    }
+
+    for migration in MIGRATIONS
+        .iter()
+        .skip(usize::try_from(version - 1).unwrap_or(0))
+    {
+        migration(document);
+    }
+    document.insert("version", value(CONFIG_VERSION));
+    Ok(Some(version))
+}
+
+/// Version 2: per-site `critical = true` is written `severity = "critical"`.
+fn shorthand_critical_to_severity(document: &mut DocumentMut) {
+    let Some(urls) = document
+        .get_mut("sites")
+        .and_then(|sites| sites.get_mut("urls"))
+        .and_then(Item::as_array_mut)
+    else {
+        return;
+    };
+
+    for site in urls.iter_mut() {
+        let Value::InlineTable(site) = site else {
+            continue;
+        };
+        let Some(critical) = site.get("critical").and_then(Value::as_bool) else {
+            continue;
+        };
+        // Both set is a config error, left for validation to report
+        if site.contains_key("severity") {
+            continue;
+        }
+        site.remove("critical");
+        if critical {
+            site.insert("severity", "critical".into());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    const VERSION_1: &str = r#"# Monitor settings
+[config]
+timeout_secs = 5
+
+[sites]
+urls = [
+    "https://a.example",
+    { url = "https://b.example", critical = true },
+    { url = "https://c.example", critical = false, slo = 99.9 },
+]
+"#;
+
+    #[test]
+    fn test_unversioned_config_is_upgraded() {
+        let mut document: DocumentMut = VERSION_1.parse().unwrap();
+        assert_eq!(migrate(&mut document).unwrap(), Some(1));
+
+        let migrated = document.to_string();
+        assert!(migrated.starts_with("version = 2\n# Monitor settings\n[config]"));
+        assert!(migrated.contains(r#"{ url = "https://b.example", severity = "critical" }"#));
+        assert!(migrated.contains(r#"{ url = "https://c.example", slo = 99.9 }"#));
+
+        assert_eq!(
+            migrate(&mut document).unwrap(),
+            None,
+            "Current configs are left alone"
+        );
+    }
+
+    #[test]
+    fn test_newer_config_is_rejected() {
+        let mut document: DocumentMut = "version = 3".parse().unwrap();
+        assert!(migrate(&mut document).is_err());
+        let mut document: DocumentMut = "version = \"2\"".parse().unwrap();
+        assert!(migrate(&mut document).is_err());
+    }
+
+    #[test]
+    fn test_upgraded_file_keeps_a_backup() {
+        let dir = temp_dir("migrate");
+        let path = dir.join("config.toml");
+        fs::write(&path, VERSION_1).unwrap();
+
+        let migrated = migrate_file(&path).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), migrated);
+        assert_eq!(
+            fs::read_to_string(dir.join("config.toml.v1.bak")).unwrap(),
+            VERSION_1
+        );
+        assert_eq!(migrate_file(&path).unwrap(), migrated);
+    }
+}
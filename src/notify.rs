@@ -0,0 +1,1183 @@
+use jiff::Timestamp;
+use log::{info, warn};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    time::{Duration, SystemTime},
+};
+use url::Url;
+
+use crate::config::{
+    Config, IncidentThreadOptions, Language, RouteOptions, RoutingOptions, Severity,
+};
+use crate::cycle_summary::CycleSummary;
+use crate::dashboard::format_duration;
+use crate::error::Error;
+use crate::history::unix_millis;
+use crate::incident::{Acknowledgement, Incident, IncidentStore, NotificationEvent};
+use crate::locale::{Phrases, phrases};
+use crate::quiet_hours::QuietHours;
+use crate::silence::Silences;
+use crate::status::SiteStatus;
+use crate::templates::{MessageTemplates, Variables, render_phrase, site_name};
+
+/// Discord message flag posting without push and desktop notifications.
+const SUPPRESS_NOTIFICATIONS: u64 = 1 << 12;
+/// Prefix of the custom id of Acknowledge buttons, followed by the incident id.
+pub(crate) const ACKNOWLEDGE_ID_PREFIX: &str = "ack:";
+/// Base URL of the Discord bot API, used to start incident threads.
+const DISCORD_API_URL: &str = "https://discord.com/api/v10/";
+/// Discord rejects longer thread names.
+const MAX_THREAD_NAME_CHARS: usize = 100;
+/// Time given to each notification channel to answer the startup check.
+const CHANNEL_CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Sends alerts through the route of the alerting site's severity, holding
+/// the non-critical ones during quiet hours.
+#[derive(Debug, Default)]
+pub(crate) struct Notifier {
+    routing: RoutingOptions,
+    /// Severity of the sites not using the default one.
+    severities: HashMap<String, Severity>,
+    /// Mentions of the sites overriding the ones of their route.
+    mentions: HashMap<String, SiteMention>,
+    quiet_hours: Option<QuietHours>,
+    /// Whether DOWN alerts carry an Acknowledge button, only useful when
+    /// Discord interactions are received.
+    acknowledgeable: bool,
+    /// Bot starting a thread from the first alert of every incident.
+    threads: Option<IncidentThreads>,
+    incidents: IncidentStore,
+    /// Expected downtimes during which sites are not alerted on.
+    silences: Silences,
+    /// Summary of the down sites sent after each cycle, in place of their alerts.
+    cycle_summary: Option<CycleSummary>,
+    templates: MessageTemplates,
+    /// Language of the built-in phrases not covered by the templates.
+    language: Language,
+}
+
+/// What is known about a site alerted on as DOWN.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DownAlert {
+    pub(crate) consecutive_failures: u64,
+    /// Time the last check took to fail.
+    pub(crate) latency: Duration,
+    /// Network diagnostics summary, only taken when the site goes down.
+    pub(crate) diagnostics: Option<String>,
+    /// Why the last check failed despite a successful status, e.g. an
+    /// unexpected body size.
+    pub(crate) reason: Option<String>,
+}
+
+/// Discord user and role mentioned in the alerts of a site, each replacing
+/// the one of its route when set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SiteMention {
+    discord_id: Option<u64>,
+    role_id: Option<u64>,
+}
+
+#[derive(Debug)]
+struct IncidentThreads {
+    bot_token: String,
+    api_url: Url,
+}
+
+impl IncidentThreads {
+    fn new(options: &IncidentThreadOptions) -> Self {
+        Self {
+            bot_token: options.bot_token.clone(),
+            api_url: Url::parse(DISCORD_API_URL).expect("Discord API URL is valid"),
+        }
+    }
+
+    /// Checks the bot token is accepted, returning what is wrong otherwise.
+    async fn check(&self, client: &Client) -> Option<String> {
+        let result = async {
+            client
+                .get(self.api_url.join("users/@me")?)
+                .header("Authorization", format!("Bot {}", self.bot_token))
+                .send()
+                .await?
+                .error_for_status()?;
+            Ok::<_, Error>(())
+        }
+        .await;
+        result
+            .err()
+            .map(|e| format!("the incident thread bot was refused: {e}"))
+    }
+
+    /// Starts a public thread named `name` from the message `message`,
+    /// returning the id of the thread.
+    async fn start(&self, message: &PostedMessage, name: &str) -> Result<String, Error> {
+        let url = self.api_url.join(&format!(
+            "channels/{}/messages/{}/threads",
+            message.channel_id, message.id
+        ))?;
+        let name: String = name.chars().take(MAX_THREAD_NAME_CHARS).collect();
+        let thread: PostedMessage = Client::new()
+            .post(url)
+            .header("Authorization", format!("Bot {}", self.bot_token))
+            .json(&serde_json::json!({ "name": name }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(thread.id)
+    }
+}
+
+impl Notifier {
+    pub(crate) fn from_config(config: &Config) -> Self {
+        Self {
+            routing: config.routing.clone(),
+            severities: config
+                .sites
+                .options
+                .iter()
+                .map(|(url, options)| (url.clone(), options.severity))
+                .collect(),
+            mentions: config
+                .sites
+                .options
+                .iter()
+                .filter(|(_, options)| options.discord_id.is_some() || options.role_id.is_some())
+                .map(|(url, options)| {
+                    let mention = SiteMention {
+                        discord_id: options.discord_id,
+                        role_id: options.role_id,
+                    };
+                    (url.clone(), mention)
+                })
+                .collect(),
+            quiet_hours: QuietHours::from_config(config),
+            acknowledgeable: config
+                .server
+                .as_ref()
+                .is_some_and(|server| server.discord_public_key.is_some()),
+            threads: config.incident_threads.as_ref().map(IncidentThreads::new),
+            incidents: IncidentStore::from_config(config),
+            silences: Silences::from_config(config),
+            cycle_summary: CycleSummary::from_config(config),
+            templates: config.templates.clone(),
+            language: config.config.language,
+        }
+    }
+
+    /// Severity of `site`, sites checked by agents always have the default one.
+    pub(crate) fn severity(&self, site: &str) -> Severity {
+        self.severities.get(site).copied().unwrap_or_default()
+    }
+
+    /// Route of the alerts of `site`, the one of its severity with the
+    /// mentions of the site itself, if any.
+    fn route(&self, site: &str) -> RouteOptions {
+        let mut route = self.routing.route(self.severity(site)).clone();
+        if let Some(mention) = self.mentions.get(site) {
+            route.discord_id = mention.discord_id.or(route.discord_id);
+            route.role_id = mention.role_id.or(route.role_id);
+        }
+        route
+    }
+
+    pub(crate) fn quiet_hours(&self) -> Option<&QuietHours> {
+        self.quiet_hours.as_ref()
+    }
+
+    pub(crate) fn templates(&self) -> &MessageTemplates {
+        &self.templates
+    }
+
+    pub(crate) fn phrases(&self) -> &'static Phrases {
+        phrases(self.language)
+    }
+
+    /// Variables shared by the templates of messages about `site`.
+    fn site_variables(&self, site: &str, incident: &Incident) -> Variables {
+        Variables::default()
+            .set("site.url", site)
+            .set("site.name", site_name(site))
+            .set("severity", self.severity(site).label())
+            .set("incident.id", incident.id.to_string())
+    }
+
+    /// Writes the incidents to their file. Done after every change, and once
+    /// more on shutdown.
+    pub(crate) async fn save_incidents(&self) {
+        self.incidents.save().await;
+    }
+
+    /// Sends the alert `message` about `site`, or holds it for the quiet hours summary.
+    pub(crate) async fn alert(&self, site: &str, message: &str) -> Result<(), Error> {
+        if self.is_silenced(site, unix_millis(SystemTime::now())) {
+            return Ok(());
+        }
+        self.deliver(site, message, None).await.map(|_| ())
+    }
+
+    /// Whether `site` is silenced at `now_ms`, logging the silence if so.
+    fn is_silenced(&self, site: &str, now_ms: u64) -> bool {
+        let Some(silence) = self.silences.active(site, now_ms) else {
+            return false;
+        };
+        let until =
+            Timestamp::from_millisecond(i64::try_from(silence.until_ms).unwrap_or(i64::MAX))
+                .map_or_else(|_| silence.until_ms.to_string(), |until| until.to_string());
+        match &silence.reason {
+            Some(reason) => info!(
+                "{site}: silenced ({}) until {until} for {reason}, alert not sent",
+                silence.target
+            ),
+            None => info!(
+                "{site}: silenced ({}) until {until}, alert not sent",
+                silence.target
+            ),
+        }
+        true
+    }
+
+    /// Sends the DOWN alert about `site`, opening an incident on the first one.
+    ///
+    /// Later alerts of the incident edit the message already posted with how
+    /// long the site has been down, so a long outage stays a single message.
+    /// With incident threads, they are also posted in the thread started from
+    /// that message. Nothing is sent once the incident is acknowledged.
+    pub(crate) async fn alert_down(&self, site: &str, alert: &DownAlert) -> Result<(), Error> {
+        let result = self.notify_down(site, alert).await;
+        self.incidents.save().await;
+        result
+    }
+
+    async fn notify_down(&self, site: &str, alert: &DownAlert) -> Result<(), Error> {
+        let now_ms = unix_millis(SystemTime::now());
+        let mut incident = self.incidents.open(site, now_ms);
+        if incident.diagnostics.is_none() && alert.diagnostics.is_some() {
+            incident.diagnostics.clone_from(&alert.diagnostics);
+            self.incidents.update_open(incident.id, |open| {
+                open.diagnostics.clone_from(&alert.diagnostics);
+            });
+        }
+        if let Some(by) = &incident.acknowledged_by {
+            info!(
+                "{site}: incident #{} acknowledged by {by}, not notifying again",
+                incident.id
+            );
+            self.incidents
+                .log(incident.id, NotificationEvent::Suppressed, now_ms);
+            return Ok(());
+        }
+        if self.is_silenced(site, now_ms) {
+            self.incidents
+                .log(incident.id, NotificationEvent::Suppressed, now_ms);
+            return Ok(());
+        }
+        let button = self.acknowledgeable.then_some(incident.id);
+        let mut message = self.templates.down.render(
+            &self
+                .site_variables(site, &incident)
+                .set(
+                    "consecutive_failures",
+                    alert.consecutive_failures.to_string(),
+                )
+                .set("latency_ms", alert.latency.as_millis().to_string()),
+        );
+        if let Some(reason) = &alert.reason {
+            message = format!("{message}\n{reason}");
+        }
+        // Kept on every edit of the alert, the summary describes the outage start
+        if let Some(diagnostics) = &incident.diagnostics {
+            message = format!("{message}\n```\n{diagnostics}\n```");
+        }
+
+        if let Some(message_id) = &incident.message_id {
+            let route = &self.route(site);
+            let duration = format_duration(incident.duration(now_ms));
+            let duration = Variables::default().set("duration", duration);
+            let update = format!(
+                "{message}\n{}",
+                render_phrase(self.phrases().still_down, &duration)
+            );
+            let mut discord_message = DiscordMessage::new(route, &update);
+            // Flags other than embed suppression cannot be edited
+            discord_message.flags = None;
+            discord_message.components = button.map(|id| vec![ActionRow::acknowledge(id)]);
+            match edit_discord_message(route, message_id, &discord_message).await {
+                Ok(()) => {
+                    self.incidents
+                        .log(incident.id, NotificationEvent::Updated, now_ms);
+                    if let Some(thread_id) = &incident.thread_id {
+                        let update = render_phrase(self.phrases().thread_update, &duration);
+                        post_in_thread(route, thread_id, update).await?;
+                    }
+                    return Ok(());
+                }
+                Err(e) => warn!(
+                    "{site}: failed to update the alert of incident #{}, posting a new one: {e}",
+                    incident.id
+                ),
+            }
+        }
+
+        let Some(posted) = self.deliver(site, &message, button).await? else {
+            return Ok(());
+        };
+        let thread_id = match &self.threads {
+            Some(threads) => threads
+                .start(&posted, &format!("Incident #{}: {site}", incident.id))
+                .await
+                .inspect_err(|e| {
+                    warn!(
+                        "{site}: failed to start the thread of incident #{}: {e}",
+                        incident.id
+                    );
+                })
+                .ok(),
+            None => None,
+        };
+
+        self.incidents.update_open(incident.id, |open| {
+            open.message_id = Some(posted.id);
+            open.thread_id = thread_id;
+        });
+        self.incidents
+            .log(incident.id, NotificationEvent::Alerted, now_ms);
+        Ok(())
+    }
+
+    /// Posts `message` about `site` unless held for quiet hours, returning
+    /// the posted message.
+    async fn deliver(
+        &self,
+        site: &str,
+        message: &str,
+        acknowledge_button: Option<u64>,
+    ) -> Result<Option<PostedMessage>, Error> {
+        let severity = self.severity(site);
+        let route = &self.route(site);
+        if route.webhook_url.is_none() {
+            return Ok(None);
+        }
+
+        if self.hold_at(severity, message, Timestamp::now()) {
+            info!("{site}: quiet hours, alert held for the summary");
+            return Ok(None);
+        }
+
+        let mut discord_message = DiscordMessage::new(route, message);
+        discord_message.components =
+            acknowledge_button.map(|incident_id| vec![ActionRow::acknowledge(incident_id)]);
+        post_discord_message(route, &discord_message, None).await
+    }
+
+    /// Open incident of `site`, if any.
+    pub(crate) fn incident(&self, site: &str) -> Option<Incident> {
+        self.incidents.get_open(site)
+    }
+
+    /// Every known incident, most recent first.
+    pub(crate) fn incidents(&self) -> Vec<Incident> {
+        self.incidents.list()
+    }
+
+    /// Closes the incident of `site` once it is back up, editing its alert
+    /// to say so, and returns it. The post-incident summary goes to the
+    /// incident thread, if any.
+    pub(crate) async fn resolve(&self, site: &str) -> Result<Option<Incident>, Error> {
+        let now_ms = unix_millis(SystemTime::now());
+        let Some(incident) = self.incidents.resolve(site, now_ms) else {
+            return Ok(None);
+        };
+        self.incidents.save().await;
+
+        if let Some(message_id) = &incident.message_id {
+            let route = &self.route(site);
+            let content = self.templates.recovery.render(
+                &self
+                    .site_variables(site, &incident)
+                    .set("duration", format_duration(incident.duration(now_ms)))
+                    .set_some("acknowledged_by", incident.acknowledged_by.clone()),
+            );
+            let discord_message = DiscordMessage {
+                content,
+                flags: None,
+                components: Some(Vec::new()),
+            };
+            edit_discord_message(route, message_id, &discord_message).await?;
+
+            if let Some(thread_id) = &incident.thread_id {
+                let summary = format!(
+                    "{}\n{}",
+                    render_phrase(
+                        self.phrases().thread_resolved,
+                        &Variables::default().set("site.url", site),
+                    ),
+                    self.incident_summary(&incident, now_ms)
+                );
+                post_in_thread(route, thread_id, summary).await?;
+            }
+        }
+        Ok(Some(incident))
+    }
+
+    /// Post-incident summary, e.g. `Incident #87 lasted 14m 32s, from ... to ...`.
+    fn incident_summary(&self, incident: &Incident, now_ms: u64) -> String {
+        // Discord shows <t:...> timestamps in the reader's time zone
+        let discord_time = |timestamp_ms: u64| format!("<t:{}:f>", timestamp_ms / 1000);
+        render_phrase(
+            self.phrases().incident_summary,
+            &Variables::default()
+                .set("incident.id", incident.id.to_string())
+                .set("duration", format_duration(incident.duration(now_ms)))
+                .set("started_at", discord_time(incident.started_at_ms))
+                .set(
+                    "resolved_at",
+                    discord_time(incident.resolved_at_ms.unwrap_or(now_ms)),
+                )
+                .set_some("acknowledged_by", incident.acknowledged_by.clone()),
+        )
+    }
+
+    /// Records that `user` acknowledged the incident `id`, the first
+    /// acknowledgement is kept.
+    pub(crate) async fn acknowledge(&self, id: u64, user: &str) -> Acknowledgement {
+        let acknowledgement = self
+            .incidents
+            .acknowledge(id, user, unix_millis(SystemTime::now()));
+        if matches!(acknowledgement, Acknowledgement::Recorded { .. }) {
+            self.incidents.save().await;
+        }
+        acknowledgement
+    }
+
+    /// Holds non-critical alerts raised during quiet hours, returns whether it did.
+    fn hold_at(&self, severity: Severity, message: &str, now: Timestamp) -> bool {
+        severity != Severity::Critical
+            && self
+                .quiet_hours
+                .as_ref()
+                .is_some_and(|quiet_hours| quiet_hours.hold_at(message, now))
+    }
+
+    /// Whether the DOWN alerts of `site` are left to the end-of-cycle summary.
+    pub(crate) fn summarizes(&self, site: &str) -> bool {
+        self.cycle_summary
+            .as_ref()
+            .is_some_and(|summary| summary.covers(site))
+    }
+
+    /// Sends the end-of-cycle summaries due given the status of every site,
+    /// each through the route of the most severe of the sites it lists.
+    pub(crate) async fn send_cycle_summaries(&self, sites: &[SiteStatus]) {
+        let Some(summary) = &self.cycle_summary else {
+            return;
+        };
+        let now_ms = unix_millis(SystemTime::now());
+        let due = summary.due(sites, |site| self.silences.active(site, now_ms).is_some());
+
+        for group in due {
+            let severity = [Severity::Critical, Severity::Warning, Severity::Info]
+                .into_iter()
+                .find(|severity| {
+                    group
+                        .down
+                        .iter()
+                        .any(|site| self.severity(site) == *severity)
+                })
+                .unwrap_or_default();
+            let message = render_phrase(
+                self.phrases().cycle_summary,
+                &Variables::default()
+                    .set("down", group.down.len().to_string())
+                    .set("total", group.total.to_string())
+                    .set_some("tag", group.tag)
+                    .set("sites", group.down.join(", ")),
+            );
+            warn!("{message}");
+
+            if self.hold_at(severity, &message, Timestamp::now()) {
+                info!("Quiet hours, cycle summary held for the quiet hours summary");
+            } else if let Err(e) = self.send(severity, &message).await {
+                warn!("Failed to send the cycle summary: {e}");
+            }
+        }
+    }
+
+    /// Sends `message` through the route of `severity`, even during quiet hours.
+    pub(crate) async fn send(&self, severity: Severity, message: &str) -> Result<(), Error> {
+        send_discord_notification(self.routing.route(severity), message).await
+    }
+}
+
+/// Checks every notification channel of `config` answers, so a broken
+/// webhook is found at startup rather than during an outage.
+///
+/// # Errors
+///
+/// Returns an error listing the broken channels.
+pub async fn check_notification_channels(config: &Config) -> Result<(), Error> {
+    let threads = config.incident_threads.as_ref().map(IncidentThreads::new);
+    let problems = check_channels(&config.routing, threads.as_ref()).await;
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::Config(format!(
+            "Broken notification channels: {}",
+            problems.join("; ")
+        )))
+    }
+}
+
+/// Fetches every distinct webhook of `routing`, and checks the token of the
+/// incident `threads` bot, returning what is wrong with each broken one.
+async fn check_channels(
+    routing: &RoutingOptions,
+    threads: Option<&IncidentThreads>,
+) -> Vec<String> {
+    let client = match Client::builder().timeout(CHANNEL_CHECK_TIMEOUT).build() {
+        Ok(client) => client,
+        Err(e) => return vec![format!("failed to build the HTTP client: {e}")],
+    };
+
+    // Severities sharing a webhook are checked once
+    let mut webhooks: Vec<(&str, Vec<&str>)> = Vec::new();
+    for severity in [Severity::Critical, Severity::Warning, Severity::Info] {
+        let Some(url) = routing.route(severity).webhook_url.as_deref() else {
+            continue;
+        };
+        match webhooks.iter_mut().find(|(known, _)| *known == url) {
+            Some((_, severities)) => severities.push(severity.label()),
+            None => webhooks.push((url, vec![severity.label()])),
+        }
+    }
+
+    let mut problems = Vec::new();
+    if webhooks.is_empty() {
+        problems.push("no Discord webhook is configured, no alert will be sent".to_string());
+    }
+    for (url, severities) in webhooks {
+        let result = async { client.get(url).send().await?.error_for_status() }.await;
+        if let Err(e) = result {
+            problems.push(format!(
+                "the webhook of the {} alerts failed: {}",
+                severities.join(" and "),
+                e.without_url()
+            ));
+        }
+    }
+    if let Some(threads) = threads
+        && let Some(problem) = threads.check(&client).await
+    {
+        problems.push(problem);
+    }
+    problems
+}
+
+#[derive(Serialize)]
+struct DiscordMessage {
+    content: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    flags: Option<u64>,
+    /// Buttons of the message, an empty list removes those of an edited message.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    components: Option<Vec<ActionRow>>,
+}
+
+/// Message created by a webhook as returned with `wait=true`, or thread
+/// started from it.
+#[derive(Debug, Deserialize)]
+struct PostedMessage {
+    id: String,
+    #[serde(default)]
+    channel_id: String,
+}
+
+/// Row of Discord message components.
+#[derive(Debug, Serialize)]
+pub(crate) struct ActionRow {
+    #[serde(rename = "type")]
+    kind: u8,
+    components: Vec<Button>,
+}
+
+#[derive(Debug, Serialize)]
+struct Button {
+    #[serde(rename = "type")]
+    kind: u8,
+    style: u8,
+    label: &'static str,
+    custom_id: String,
+}
+
+impl ActionRow {
+    const ACTION_ROW: u8 = 1;
+    const BUTTON: u8 = 2;
+    const PRIMARY_STYLE: u8 = 1;
+
+    /// Row holding the Acknowledge button of incident `incident_id`.
+    fn acknowledge(incident_id: u64) -> Self {
+        Self {
+            kind: Self::ACTION_ROW,
+            components: vec![Button {
+                kind: Self::BUTTON,
+                style: Self::PRIMARY_STYLE,
+                label: "Acknowledge",
+                custom_id: format!("{ACKNOWLEDGE_ID_PREFIX}{incident_id}"),
+            }],
+        }
+    }
+}
+
+impl DiscordMessage {
+    fn new(route: &RouteOptions, message: &str) -> Self {
+        // Roles first, the on-call rotation matters more than a single user
+        let mut content = String::new();
+        if let Some(role_id) = route.role_id {
+            let _ = write!(content, "<@&{role_id}> ");
+        }
+        if let Some(discord_id) = route.discord_id {
+            let _ = write!(content, "<@{discord_id}> ");
+        }
+        content.push_str(message);
+
+        Self {
+            content,
+            flags: route.silent.then_some(SUPPRESS_NOTIFICATIONS),
+            components: None,
+        }
+    }
+}
+
+/// Posts `message` to the webhook of `route`, nothing is sent without one.
+pub(crate) async fn send_discord_notification(
+    route: &RouteOptions,
+    message: &str,
+) -> Result<(), Error> {
+    post_discord_message(route, &DiscordMessage::new(route, message), None)
+        .await
+        .map(|_| ())
+}
+
+/// Posts `content` in the thread `thread_id` of the webhook channel, without
+/// mentioning anyone.
+async fn post_in_thread(
+    route: &RouteOptions,
+    thread_id: &str,
+    content: String,
+) -> Result<(), Error> {
+    let message = DiscordMessage {
+        content,
+        flags: None,
+        components: None,
+    };
+    post_discord_message(route, &message, Some(thread_id))
+        .await
+        .map(|_| ())
+}
+
+/// Posts `message` to the webhook of `route`, in the thread `thread_id` when
+/// set, and returns the created message, `None` without a webhook.
+async fn post_discord_message(
+    route: &RouteOptions,
+    message: &DiscordMessage,
+    thread_id: Option<&str>,
+) -> Result<Option<PostedMessage>, Error> {
+    let Some(webhook_url) = &route.webhook_url else {
+        return Ok(None);
+    };
+
+    // Without wait=true, webhooks answer before creating the message and return nothing
+    let mut request = Client::new()
+        .post(webhook_url)
+        .query(&[("wait", "true")])
+        .json(message);
+    if let Some(thread_id) = thread_id {
+        request = request.query(&[("thread_id", thread_id)]);
+    }
+    let posted = with_components(request, message)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    Ok(Some(posted))
+}
+
+/// Replaces the message `message_id` previously posted to the webhook of `route`.
+async fn edit_discord_message(
+    route: &RouteOptions,
+    message_id: &str,
+    message: &DiscordMessage,
+) -> Result<(), Error> {
+    let Some(webhook_url) = &route.webhook_url else {
+        return Ok(());
+    };
+
+    let mut url = Url::parse(webhook_url)?;
+    url.path_segments_mut()
+        .map_err(|()| Error::Config(format!("Invalid webhook URL: {webhook_url}")))?
+        .extend(["messages", message_id]);
+    with_components(Client::new().patch(url).json(message), message)
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+/// Webhooks ignore components unless asked not to.
+fn with_components(
+    request: reqwest::RequestBuilder,
+    message: &DiscordMessage,
+) -> reqwest::RequestBuilder {
+    if message.components.is_some() {
+        request.query(&[("with_components", "true")])
+    } else {
+        request
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::QuietHoursOptions;
+    use axum::{
+        Json, Router,
+        http::{HeaderMap, Method, StatusCode, Uri},
+        routing::get,
+    };
+    use jiff::{civil::Time, tz::TimeZone};
+    use serde_json::Value;
+    use std::sync::{Arc, Mutex};
+    use tokio::net::TcpListener;
+
+    fn route(silent: bool) -> RouteOptions {
+        RouteOptions {
+            webhook_url: Some("https://discord.com/api/webhooks/1/token".to_string()),
+            discord_id: Some(1234),
+            role_id: Some(42),
+            silent,
+        }
+    }
+
+    #[test]
+    fn test_message_mentions_and_flags() {
+        let message = serde_json::to_value(DiscordMessage::new(&route(false), "Alert!")).unwrap();
+        assert_eq!(
+            message,
+            serde_json::json!({ "content": "<@&42> <@1234> Alert!" })
+        );
+
+        let silent = serde_json::to_value(DiscordMessage::new(
+            &RouteOptions {
+                discord_id: None,
+                role_id: None,
+                ..route(true)
+            },
+            "Alert!",
+        ))
+        .unwrap();
+        assert_eq!(
+            silent,
+            serde_json::json!({ "content": "Alert!", "flags": SUPPRESS_NOTIFICATIONS })
+        );
+    }
+
+    #[test]
+    fn test_site_mentions_override_the_route() {
+        let notifier = Notifier {
+            routing: RoutingOptions {
+                critical: route(false),
+                warning: route(false),
+                info: route(false),
+            },
+            mentions: HashMap::from([
+                (
+                    "https://a.example".to_string(),
+                    SiteMention {
+                        discord_id: Some(7),
+                        role_id: None,
+                    },
+                ),
+                (
+                    "https://b.example".to_string(),
+                    SiteMention {
+                        discord_id: None,
+                        role_id: Some(9),
+                    },
+                ),
+            ]),
+            ..Notifier::default()
+        };
+        let content = |site| DiscordMessage::new(&notifier.route(site), "Alert!").content;
+
+        assert_eq!(content("https://a.example"), "<@&42> <@7> Alert!");
+        assert_eq!(content("https://b.example"), "<@&9> <@1234> Alert!");
+        assert_eq!(content("https://c.example"), "<@&42> <@1234> Alert!");
+    }
+
+    #[tokio::test]
+    async fn test_broken_channels_are_reported() {
+        let app = Router::new()
+            .route("/api/webhooks/1/token", get(|| async { "{}" }))
+            .route(
+                "/api/v10/users/@me",
+                get(|headers: HeaderMap| async move {
+                    if headers["authorization"] == "Bot good" {
+                        StatusCode::OK
+                    } else {
+                        StatusCode::UNAUTHORIZED
+                    }
+                }),
+            );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let webhook = |path: &str| RouteOptions {
+            webhook_url: Some(format!("http://{addr}/api/webhooks/{path}")),
+            ..RouteOptions::default()
+        };
+        let threads = |bot_token: &str| IncidentThreads {
+            bot_token: bot_token.to_string(),
+            api_url: Url::parse(&format!("http://{addr}/api/v10/")).unwrap(),
+        };
+        let healthy = RoutingOptions {
+            critical: webhook("1/token"),
+            warning: webhook("1/token"),
+            info: webhook("1/token"),
+        };
+        assert!(
+            check_channels(&healthy, Some(&threads("good")))
+                .await
+                .is_empty()
+        );
+
+        let broken = RoutingOptions {
+            warning: webhook("2/revoked"),
+            info: webhook("2/revoked"),
+            ..healthy
+        };
+        let problems = check_channels(&broken, Some(&threads("bad"))).await;
+        assert_eq!(problems.len(), 2, "{problems:?}");
+        assert!(problems[0].starts_with("the webhook of the warning and info alerts failed"));
+        assert!(problems[0].contains("404"), "{problems:?}");
+        assert!(problems[1].starts_with("the incident thread bot was refused"));
+
+        let problems = check_channels(&RoutingOptions::default(), None).await;
+        assert_eq!(
+            problems,
+            ["no Discord webhook is configured, no alert will be sent"]
+        );
+    }
+
+    #[test]
+    fn test_acknowledge_button() {
+        let mut message = DiscordMessage::new(&route(false), "Alert!");
+        message.components = Some(vec![ActionRow::acknowledge(87)]);
+
+        assert_eq!(
+            serde_json::to_value(message).unwrap()["components"],
+            serde_json::json!([{
+                "type": 1,
+                "components": [{
+                    "type": 2,
+                    "style": 1,
+                    "label": "Acknowledge",
+                    "custom_id": "ack:87",
+                }],
+            }])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_incidents_are_acknowledged_until_resolved() {
+        let notifier = Notifier::default();
+        notifier
+            .alert_down("https://a.example", &DownAlert::default())
+            .await
+            .unwrap();
+        let first = notifier.incident("https://a.example").unwrap();
+
+        assert_eq!(
+            notifier.acknowledge(first.id, "alice").await,
+            Acknowledgement::Recorded {
+                site: "https://a.example".to_string()
+            }
+        );
+        notifier
+            .alert_down("https://a.example", &DownAlert::default())
+            .await
+            .unwrap();
+
+        let resolved = notifier
+            .resolve("https://a.example")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(resolved.acknowledged_by.as_deref(), Some("alice"));
+        let events: Vec<_> = resolved.notifications.iter().map(|log| log.event).collect();
+        assert_eq!(
+            events,
+            [
+                NotificationEvent::Acknowledged,
+                NotificationEvent::Suppressed,
+                NotificationEvent::Resolved
+            ]
+        );
+        assert_eq!(
+            notifier.acknowledge(first.id, "bob").await,
+            Acknowledgement::NotOpen
+        );
+        assert_eq!(notifier.incident("https://a.example"), None);
+    }
+
+    /// Fake Discord webhook recording every request it receives.
+    async fn spawn_webhook() -> (String, Arc<Mutex<Vec<(String, String, Value)>>>) {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&requests);
+        let app = Router::new().fallback(
+            move |method: Method, uri: Uri, Json(body): Json<Value>| async move {
+                recorded
+                    .lock()
+                    .unwrap()
+                    .push((method.to_string(), uri.to_string(), body));
+                Json(serde_json::json!({ "id": "555", "channel_id": "777" }))
+            },
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        (format!("http://{addr}/api/webhooks/1/token"), requests)
+    }
+
+    /// Notifier sending every severity to `webhook_url`.
+    fn notifier_for(webhook_url: String) -> Notifier {
+        let route = RouteOptions {
+            webhook_url: Some(webhook_url),
+            ..RouteOptions::default()
+        };
+        Notifier {
+            routing: RoutingOptions {
+                critical: route.clone(),
+                warning: route.clone(),
+                info: route,
+            },
+            ..Notifier::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_incident_updates_edit_the_first_alert() {
+        let (webhook_url, requests) = spawn_webhook().await;
+        let notifier = notifier_for(webhook_url);
+
+        let alert = DownAlert::default();
+        notifier
+            .alert_down("https://a.example", &alert)
+            .await
+            .unwrap();
+        notifier
+            .alert_down("https://a.example", &alert)
+            .await
+            .unwrap();
+        notifier.resolve("https://a.example").await.unwrap();
+
+        let requests = requests.lock().unwrap();
+        let summary: Vec<(&str, &str)> = requests
+            .iter()
+            .map(|(method, uri, _)| (method.as_str(), uri.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("POST", "/api/webhooks/1/token?wait=true"),
+                ("PATCH", "/api/webhooks/1/token/messages/555"),
+                (
+                    "PATCH",
+                    "/api/webhooks/1/token/messages/555?with_components=true"
+                ),
+            ]
+        );
+        assert_eq!(
+            requests[1].2["content"],
+            "Alert: https://a.example is DOWN!\nDown for 0s so far"
+        );
+        assert_eq!(
+            requests[2].2,
+            serde_json::json!({
+                "content": "Resolved: https://a.example is back UP, down for 0s (incident #1)",
+                "components": [],
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_diagnostics_are_attached_to_the_alert() {
+        let (webhook_url, requests) = spawn_webhook().await;
+        let notifier = notifier_for(webhook_url);
+
+        let diagnostics = "DNS: a.example timed out after 30 s";
+        let first = DownAlert {
+            diagnostics: Some(diagnostics.to_string()),
+            ..DownAlert::default()
+        };
+        notifier
+            .alert_down("https://a.example", &first)
+            .await
+            .unwrap();
+        notifier
+            .alert_down("https://a.example", &DownAlert::default())
+            .await
+            .unwrap();
+
+        let alert = format!("Alert: https://a.example is DOWN!\n```\n{diagnostics}\n```");
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests[0].2["content"], alert);
+        assert_eq!(
+            requests[1].2["content"],
+            format!("{alert}\nDown for 0s so far")
+        );
+        assert_eq!(
+            notifier
+                .incident("https://a.example")
+                .unwrap()
+                .diagnostics
+                .as_deref(),
+            Some(diagnostics)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_incident_thread_gets_updates_and_summary() {
+        let (webhook_url, requests) = spawn_webhook().await;
+        let api_url = Url::parse(&webhook_url).unwrap().join("/").unwrap();
+        let notifier = Notifier {
+            threads: Some(IncidentThreads {
+                bot_token: "bot-secret".to_string(),
+                api_url,
+            }),
+            ..notifier_for(webhook_url)
+        };
+
+        let alert = DownAlert::default();
+        notifier
+            .alert_down("https://a.example", &alert)
+            .await
+            .unwrap();
+        notifier
+            .alert_down("https://a.example", &alert)
+            .await
+            .unwrap();
+        notifier.resolve("https://a.example").await.unwrap();
+
+        let requests = requests.lock().unwrap();
+        let summary: Vec<(&str, &str)> = requests
+            .iter()
+            .map(|(method, uri, _)| (method.as_str(), uri.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("POST", "/api/webhooks/1/token?wait=true"),
+                ("POST", "/channels/777/messages/555/threads"),
+                ("PATCH", "/api/webhooks/1/token/messages/555"),
+                ("POST", "/api/webhooks/1/token?wait=true&thread_id=555"),
+                (
+                    "PATCH",
+                    "/api/webhooks/1/token/messages/555?with_components=true"
+                ),
+                ("POST", "/api/webhooks/1/token?wait=true&thread_id=555"),
+            ]
+        );
+        assert_eq!(requests[1].2["name"], "Incident #1: https://a.example");
+        assert_eq!(requests[3].2["content"], "Still DOWN after 0s");
+        assert!(requests[5].2["content"].as_str().unwrap().starts_with(
+            "Resolved: https://a.example is back UP\nIncident #1 lasted 0s, from <t:"
+        ));
+    }
+
+    #[test]
+    fn test_only_non_critical_alerts_are_held() {
+        let notifier = Notifier {
+            routing: RoutingOptions::default(),
+            severities: HashMap::from([(
+                "https://critical.example".to_string(),
+                Severity::Critical,
+            )]),
+            quiet_hours: Some(QuietHours::new(QuietHoursOptions {
+                start: Time::constant(23, 0, 0, 0),
+                end: Time::constant(7, 0, 0, 0),
+                timezone: TimeZone::UTC,
+            })),
+            ..Notifier::default()
+        };
+        let night: Timestamp = "2025-03-11T01:00:00Z".parse().unwrap();
+
+        assert_eq!(notifier.severity("https://a.example"), Severity::Warning);
+        assert!(notifier.hold_at(notifier.severity("https://a.example"), "a", night));
+        assert!(!notifier.hold_at(notifier.severity("https://critical.example"), "b", night));
+    }
+
+    #[tokio::test]
+    async fn test_held_alerts_are_sent_on_shutdown() {
+        let (webhook_url, requests) = spawn_webhook().await;
+        let notifier = Arc::new(Notifier {
+            quiet_hours: Some(QuietHours::new(QuietHoursOptions {
+                start: Time::constant(23, 0, 0, 0),
+                end: Time::constant(7, 0, 0, 0),
+                timezone: TimeZone::UTC,
+            })),
+            ..notifier_for(webhook_url)
+        });
+        let night: Timestamp = "2025-03-11T01:00:00Z".parse().unwrap();
+        assert!(notifier.hold_at(
+            Severity::Warning,
+            "Alert: https://a.example is DOWN!",
+            night
+        ));
+
+        let token = tokio_util::sync::CancellationToken::new();
+        token.cancel();
+        crate::quiet_hours::spawn_summary(
+            Arc::clone(&notifier),
+            crate::status::StatusBoard::new(&[]),
+            None,
+            token,
+        )
+        .await
+        .unwrap();
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        let content = requests[0].2["content"].as_str().unwrap();
+        assert!(
+            content.contains("Alert: https://a.example is DOWN!"),
+            "{content}"
+        );
+    }
+
+    #[ignore = "This test requires a valid Discord webhook URL and ID"]
+    #[tokio::test]
+    async fn test_discord_notification() {
+        let webhook_url = dotenvy::var("WEBHOOK_URL").expect("WEBHOOK_URL not set");
+        let discord_id: u64 = dotenvy::var("DISCORD_ID")
+            .expect("DISCORD_ID not set")
+            .parse()
+            .expect("Invalid DISCORD_ID");
+        let message = "Test notification from Rust!";
+        let route = RouteOptions {
+            webhook_url: Some(webhook_url),
+            discord_id: Some(discord_id),
+            ..RouteOptions::default()
+        };
+        let result = send_discord_notification(&route, message).await;
+        assert!(
+            result.is_ok(),
+            "Expected notification to be sent successfully"
+        );
+    }
+}
@@ -0,0 +1,551 @@
+use percent_encoding::percent_decode_str;
+use reqwest::{
+    Client,
+    header::{HeaderMap, HeaderName, HeaderValue},
+};
+use serde::Serialize;
+use std::{
+    fmt::Write,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use url::Url;
+
+use crate::error::Error;
+use crate::worker::CheckRecord;
+
+const DEFAULT_ENDPOINT: &str = "http://localhost:4318";
+const DEFAULT_SERVICE_NAME: &str = "downdetector";
+const SCOPE_NAME: &str = "downdetector";
+
+/// Milliseconds an export request may take, the OTLP default.
+const DEFAULT_TIMEOUT_MS: u64 = 10_000;
+
+// OTLP enum values, see opentelemetry-proto `trace.proto`
+const SPAN_KIND_INTERNAL: u8 = 1;
+const SPAN_KIND_CLIENT: u8 = 3;
+const STATUS_CODE_OK: u8 = 1;
+const STATUS_CODE_ERROR: u8 = 2;
+
+/// Exports check cycles as OTLP traces and metrics over HTTP/JSON.
+///
+/// Every cycle becomes one trace: a root span covering the whole cycle and
+/// one child span per site check. Availability and latency are exported as
+/// gauges alongside the traces.
+#[derive(Debug)]
+pub(crate) struct OtlpExporter {
+    client: Client,
+    traces_endpoint: Url,
+    metrics_endpoint: Url,
+    service_name: String,
+}
+
+impl OtlpExporter {
+    /// Builds an exporter from the standard `OTEL_*` environment variables.
+    ///
+    /// Returns `None` when none of `OTEL_EXPORTER_OTLP_ENDPOINT`,
+    /// `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT` or `OTEL_EXPORTER_OTLP_METRICS_ENDPOINT` is set.
+    pub(crate) fn from_env() -> Result<Option<Self>, Error> {
+        let base = env("OTEL_EXPORTER_OTLP_ENDPOINT");
+        let traces = env("OTEL_EXPORTER_OTLP_TRACES_ENDPOINT");
+        let metrics = env("OTEL_EXPORTER_OTLP_METRICS_ENDPOINT");
+
+        if base.is_none() && traces.is_none() && metrics.is_none() {
+            return Ok(None);
+        }
+
+        let headers = parse_headers(&env("OTEL_EXPORTER_OTLP_HEADERS").unwrap_or_default())?;
+
+        Ok(Some(Self {
+            client: Client::builder()
+                .default_headers(headers)
+                .timeout(parse_timeout(env("OTEL_EXPORTER_OTLP_TIMEOUT").as_deref())?)
+                .build()?,
+            traces_endpoint: signal_endpoint(traces, base.as_deref(), "v1/traces")?,
+            metrics_endpoint: signal_endpoint(metrics, base.as_deref(), "v1/metrics")?,
+            service_name: env("OTEL_SERVICE_NAME")
+                .unwrap_or_else(|| DEFAULT_SERVICE_NAME.to_string()),
+        }))
+    }
+
+    /// Endpoint receiving the trace export requests.
+    pub(crate) fn traces_endpoint(&self) -> &Url {
+        &self.traces_endpoint
+    }
+
+    /// Sends one trace and one batch of gauges for a finished check cycle.
+    pub(crate) async fn export_cycle(
+        &self,
+        cycle_started_at: SystemTime,
+        records: &[CheckRecord],
+    ) -> Result<(), Error> {
+        if records.is_empty() {
+            return Ok(());
+        }
+
+        let resource = Resource {
+            attributes: vec![KeyValue::string("service.name", &self.service_name)],
+        };
+
+        let traces = TracesRequest {
+            resource_spans: vec![ResourceSpans {
+                resource: resource.clone(),
+                scope_spans: vec![ScopeSpans {
+                    scope: Scope::default(),
+                    spans: cycle_spans(
+                        &random_hex_id::<16>()?,
+                        cycle_started_at,
+                        SystemTime::now(),
+                        records,
+                    )?,
+                }],
+            }],
+        };
+        self.client
+            .post(self.traces_endpoint.clone())
+            .json(&traces)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        let metrics = MetricsRequest {
+            resource_metrics: vec![ResourceMetrics {
+                resource,
+                scope_metrics: vec![ScopeMetrics {
+                    scope: Scope::default(),
+                    metrics: check_metrics(records),
+                }],
+            }],
+        };
+        self.client
+            .post(self.metrics_endpoint.clone())
+            .json(&metrics)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+/// Builds the root cycle span followed by one child span per check.
+fn cycle_spans(
+    trace_id: &str,
+    cycle_started_at: SystemTime,
+    cycle_ended_at: SystemTime,
+    records: &[CheckRecord],
+) -> Result<Vec<Span>, Error> {
+    let cycle_span_id = random_hex_id::<8>()?;
+    let failed_checks = records.iter().filter(|record| !record.is_up).count();
+
+    let mut spans = Vec::with_capacity(records.len() + 1);
+    spans.push(Span {
+        trace_id: trace_id.to_string(),
+        span_id: cycle_span_id.clone(),
+        parent_span_id: None,
+        name: "check cycle",
+        kind: SPAN_KIND_INTERNAL,
+        start_time_unix_nano: unix_nanos(cycle_started_at),
+        end_time_unix_nano: unix_nanos(cycle_ended_at),
+        attributes: vec![
+            KeyValue::int("downdetector.cycle.sites", records.len() as u64),
+            KeyValue::int("downdetector.cycle.failed_sites", failed_checks as u64),
+        ],
+        status: SpanStatus {
+            code: STATUS_CODE_OK,
+        },
+    });
+
+    for record in records {
+        spans.push(Span {
+            trace_id: trace_id.to_string(),
+            span_id: random_hex_id::<8>()?,
+            parent_span_id: Some(cycle_span_id.clone()),
+            name: "site check",
+            kind: SPAN_KIND_CLIENT,
+            start_time_unix_nano: unix_nanos(record.checked_at),
+            end_time_unix_nano: unix_nanos(record.checked_at + record.response_time),
+            attributes: vec![
+                KeyValue::string("url.full", &record.url),
+                KeyValue::bool("downdetector.site.up", record.is_up),
+                KeyValue::int(
+                    "downdetector.site.consecutive_failures",
+                    record.consecutive_failures,
+                ),
+            ],
+            status: SpanStatus {
+                code: if record.is_up {
+                    STATUS_CODE_OK
+                } else {
+                    STATUS_CODE_ERROR
+                },
+            },
+        });
+    }
+
+    Ok(spans)
+}
+
+/// Builds the availability and latency gauges, one data point per check.
+fn check_metrics(records: &[CheckRecord]) -> Vec<Metric> {
+    let data_points = |value: fn(&CheckRecord) -> NumberValue| Gauge {
+        data_points: records
+            .iter()
+            .map(|record| NumberDataPoint {
+                attributes: vec![KeyValue::string("url.full", &record.url)],
+                time_unix_nano: unix_nanos(record.checked_at),
+                value: value(record),
+            })
+            .collect(),
+    };
+
+    vec![
+        Metric {
+            name: "downdetector.site.up",
+            description: "Whether the last check of the site succeeded (1) or failed (0)",
+            unit: "1",
+            gauge: data_points(|record| NumberValue::AsInt(u8::from(record.is_up).to_string())),
+        },
+        Metric {
+            name: "downdetector.check.duration",
+            description: "Duration of the last check of the site",
+            unit: "s",
+            gauge: data_points(|record| NumberValue::AsDouble(record.response_time.as_secs_f64())),
+        },
+    ]
+}
+
+/// Resolves a signal endpoint, preferring its dedicated variable over the base endpoint.
+fn signal_endpoint(specific: Option<String>, base: Option<&str>, path: &str) -> Result<Url, Error> {
+    let endpoint = specific.unwrap_or_else(|| {
+        format!(
+            "{}/{path}",
+            base.unwrap_or(DEFAULT_ENDPOINT).trim_end_matches('/')
+        )
+    });
+
+    Url::parse(&endpoint).map_err(|_| Error::Config(format!("Invalid OTLP endpoint: {endpoint}")))
+}
+
+/// Parses `OTEL_EXPORTER_OTLP_HEADERS` (`key1=value1,key2=value2`, values percent-encoded).
+fn parse_headers(raw: &str) -> Result<HeaderMap, Error> {
+    let mut headers = HeaderMap::new();
+
+    for pair in raw.split(',').filter(|pair| !pair.trim().is_empty()) {
+        let (key, value) = pair
+            .split_once('=')
+            .ok_or_else(|| Error::Config(format!("Invalid OTLP header '{pair}'")))?;
+        let value = percent_decode_str(value.trim()).decode_utf8_lossy();
+
+        let name = HeaderName::try_from(key.trim())
+            .map_err(|_| Error::Config(format!("Invalid OTLP header name '{key}'")))?;
+        let value = HeaderValue::try_from(value.as_ref())
+            .map_err(|_| Error::Config(format!("Invalid OTLP header value for '{key}'")))?;
+        headers.insert(name, value);
+    }
+
+    Ok(headers)
+}
+
+/// Parses `OTEL_EXPORTER_OTLP_TIMEOUT`, in milliseconds, so a hung collector
+/// never stalls the checks.
+fn parse_timeout(raw: Option<&str>) -> Result<Duration, Error> {
+    let Some(raw) = raw else {
+        return Ok(Duration::from_millis(DEFAULT_TIMEOUT_MS));
+    };
+    match raw.trim().parse::<u64>() {
+        Ok(ms) if ms > 0 => Ok(Duration::from_millis(ms)),
+        _ => Err(Error::Config(format!(
+            "Invalid OTLP timeout '{raw}', expected milliseconds greater than 0"
+        ))),
+    }
+}
+
+fn env(name: &str) -> Option<String> {
+    dotenvy::var(name)
+        .ok()
+        .filter(|value| !value.trim().is_empty())
+}
+
+/// OTLP/JSON encodes 64-bit integers as decimal strings.
+fn unix_nanos(time: SystemTime) -> String {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+        .to_string()
+}
+
+/// Generates a random trace (16 bytes) or span (8 bytes) ID, hex encoded.
+fn random_hex_id<const N: usize>() -> Result<String, Error> {
+    let mut bytes = [0_u8; N];
+    getrandom::fill(&mut bytes)
+        .map_err(|e| Error::Config(format!("Failed to generate OTLP ID: {e}")))?;
+
+    Ok(bytes
+        .iter()
+        .fold(String::with_capacity(N * 2), |mut id, byte| {
+            let _ = write!(id, "{byte:02x}");
+            id
+        }))
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TracesRequest {
+    resource_spans: Vec<ResourceSpans>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ResourceSpans {
+    resource: Resource,
+    scope_spans: Vec<ScopeSpans>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ScopeSpans {
+    scope: Scope,
+    spans: Vec<Span>,
+}
+
+// Field names mirror the OTLP JSON encoding
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+#[allow(clippy::struct_field_names)]
+struct Span {
+    trace_id: String,
+    span_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parent_span_id: Option<String>,
+    name: &'static str,
+    kind: u8,
+    start_time_unix_nano: String,
+    end_time_unix_nano: String,
+    attributes: Vec<KeyValue>,
+    status: SpanStatus,
+}
+
+#[derive(Serialize)]
+struct SpanStatus {
+    code: u8,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MetricsRequest {
+    resource_metrics: Vec<ResourceMetrics>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ResourceMetrics {
+    resource: Resource,
+    scope_metrics: Vec<ScopeMetrics>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ScopeMetrics {
+    scope: Scope,
+    metrics: Vec<Metric>,
+}
+
+#[derive(Serialize)]
+struct Metric {
+    name: &'static str,
+    description: &'static str,
+    unit: &'static str,
+    gauge: Gauge,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Gauge {
+    data_points: Vec<NumberDataPoint>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct NumberDataPoint {
+    attributes: Vec<KeyValue>,
+    time_unix_nano: String,
+    #[serde(flatten)]
+    value: NumberValue,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+enum NumberValue {
+    AsInt(String),
+    AsDouble(f64),
+}
+
+#[derive(Serialize, Clone)]
+struct Resource {
+    attributes: Vec<KeyValue>,
+}
+
+#[derive(Serialize)]
+struct Scope {
+    name: &'static str,
+    version: &'static str,
+}
+
+impl Default for Scope {
+    fn default() -> Self {
+        Self {
+            name: SCOPE_NAME,
+            version: env!("CARGO_PKG_VERSION"),
+        }
+    }
+}
+
+#[derive(Serialize, Clone)]
+struct KeyValue {
+    key: &'static str,
+    value: AnyValue,
+}
+
+impl KeyValue {
+    fn string(key: &'static str, value: &str) -> Self {
+        Self {
+            key,
+            value: AnyValue::StringValue(value.to_string()),
+        }
+    }
+
+    fn bool(key: &'static str, value: bool) -> Self {
+        Self {
+            key,
+            value: AnyValue::BoolValue(value),
+        }
+    }
+
+    fn int(key: &'static str, value: u64) -> Self {
+        Self {
+            key,
+            value: AnyValue::IntValue(value.to_string()),
+        }
+    }
+}
+
+// Variant names mirror the OTLP JSON encoding
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[allow(clippy::enum_variant_names)]
+enum AnyValue {
+    StringValue(String),
+    BoolValue(bool),
+    IntValue(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn records() -> Vec<CheckRecord> {
+        vec![
+            CheckRecord {
+                url: "https://www.google.com".to_string(),
+                agent: None,
+                checked_at: UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+                is_up: true,
+                response_time: Duration::from_millis(250),
+                consecutive_failures: 0,
+            },
+            CheckRecord {
+                url: "https://invalid.url".to_string(),
+                agent: None,
+                checked_at: UNIX_EPOCH + Duration::from_secs(1_700_000_001),
+                is_up: false,
+                response_time: Duration::from_secs(5),
+                consecutive_failures: 2,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_cycle_spans_nest_site_checks_under_cycle() {
+        let records = records();
+        let spans = cycle_spans(
+            "0af7651916cd43dd8448eb211c80319c",
+            UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+            UNIX_EPOCH + Duration::from_secs(1_700_000_006),
+            &records,
+        )
+        .unwrap();
+
+        assert_eq!(spans.len(), 3);
+        let cycle_span = &spans[0];
+        assert!(cycle_span.parent_span_id.is_none());
+        assert_eq!(cycle_span.span_id.len(), 16);
+
+        for span in &spans[1..] {
+            assert_eq!(span.trace_id, cycle_span.trace_id);
+            assert_eq!(span.parent_span_id.as_ref(), Some(&cycle_span.span_id));
+        }
+        assert_eq!(spans[1].status.code, STATUS_CODE_OK);
+        assert_eq!(spans[2].status.code, STATUS_CODE_ERROR);
+        assert_eq!(spans[2].start_time_unix_nano, "1700000001000000000");
+        assert_eq!(spans[2].end_time_unix_nano, "1700000006000000000");
+    }
+
+    #[test]
+    fn test_metrics_json_shape() {
+        let metrics = serde_json::to_value(check_metrics(&records())).unwrap();
+
+        assert_eq!(metrics[0]["name"], "downdetector.site.up");
+        assert_eq!(metrics[0]["gauge"]["dataPoints"][0]["asInt"], "1");
+        assert_eq!(metrics[0]["gauge"]["dataPoints"][1]["asInt"], "0");
+        assert_eq!(
+            metrics[0]["gauge"]["dataPoints"][1]["attributes"][0]["value"]["stringValue"],
+            "https://invalid.url"
+        );
+        assert_eq!(metrics[1]["gauge"]["dataPoints"][0]["asDouble"], 0.25);
+    }
+
+    #[test]
+    fn test_signal_endpoints() {
+        assert_eq!(
+            signal_endpoint(None, Some("http://collector:4318/"), "v1/traces")
+                .unwrap()
+                .as_str(),
+            "http://collector:4318/v1/traces"
+        );
+        assert_eq!(
+            signal_endpoint(
+                Some("http://tempo:4318/custom".to_string()),
+                Some("http://collector:4318"),
+                "v1/traces"
+            )
+            .unwrap()
+            .as_str(),
+            "http://tempo:4318/custom"
+        );
+        assert_eq!(
+            signal_endpoint(None, None, "v1/metrics").unwrap().as_str(),
+            "http://localhost:4318/v1/metrics"
+        );
+    }
+
+    #[test]
+    fn test_parse_headers() {
+        let headers = parse_headers("api-key=secret, Authorization=Basic%20abc").unwrap();
+
+        assert_eq!(headers["api-key"], "secret");
+        assert_eq!(headers["authorization"], "Basic abc");
+        assert!(parse_headers("missing-value").is_err());
+    }
+
+    #[test]
+    fn test_parse_timeout() {
+        assert_eq!(parse_timeout(None).unwrap(), Duration::from_secs(10));
+        assert_eq!(
+            parse_timeout(Some("2500")).unwrap(),
+            Duration::from_millis(2500)
+        );
+        assert!(parse_timeout(Some("0")).is_err());
+        assert!(parse_timeout(Some("5s")).is_err());
+    }
+}
@@ -0,0 +1,111 @@
+use sha2::{Digest, Sha256};
+use std::fmt::Write;
+
+use crate::interactions::decode_hex;
+
+/// SHA-256 fingerprint of a DER-encoded certificate.
+pub(crate) type Fingerprint = [u8; 32];
+
+/// Parses a fingerprint written as 64 hexadecimal characters, optionally
+/// separated by colons as printed by `openssl x509 -fingerprint -sha256`.
+pub(crate) fn parse_fingerprint(value: &str) -> Option<Fingerprint> {
+    decode_hex(&value.replace(':', ""))?.try_into().ok()
+}
+
+pub(crate) fn fingerprint(certificate_der: &[u8]) -> Fingerprint {
+    Sha256::digest(certificate_der).into()
+}
+
+/// Formats a fingerprint as lowercase hexadecimal.
+pub(crate) fn format_fingerprint(fingerprint: &Fingerprint) -> String {
+    fingerprint.iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{byte:02x}");
+        hex
+    })
+}
+
+/// Outcome of comparing the certificate a site presented to its pin.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum PinCheck {
+    /// Nothing to report, the certificate matches or the check got no response.
+    Unchanged,
+    /// The site presents a certificate not alerted on yet.
+    Mismatch(Fingerprint),
+    /// The site presents its pinned certificate again after a mismatch.
+    Restored,
+}
+
+/// Compares the certificate `presented` by a site to its `pinned` one.
+///
+/// `alerted` remembers the mismatching certificate last alerted on, so a
+/// changed certificate is alerted on once rather than on every check.
+pub(crate) fn check_pin(
+    pinned: &Fingerprint,
+    presented: Option<Fingerprint>,
+    alerted: &mut Option<Fingerprint>,
+) -> PinCheck {
+    match presented {
+        None => PinCheck::Unchanged,
+        Some(presented) if presented == *pinned => {
+            if alerted.take().is_some() {
+                PinCheck::Restored
+            } else {
+                PinCheck::Unchanged
+            }
+        }
+        Some(presented) if *alerted == Some(presented) => PinCheck::Unchanged,
+        Some(presented) => {
+            *alerted = Some(presented);
+            PinCheck::Mismatch(presented)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_fingerprint() {
+        let hex = "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a";
+        let fingerprint = parse_fingerprint(hex).unwrap();
+        assert_eq!(format_fingerprint(&fingerprint), hex);
+
+        let openssl = hex
+            .to_uppercase()
+            .as_bytes()
+            .chunks(2)
+            .map(|pair| std::str::from_utf8(pair).unwrap())
+            .collect::<Vec<_>>()
+            .join(":");
+        assert_eq!(parse_fingerprint(&openssl), Some(fingerprint));
+        assert_eq!(parse_fingerprint(&hex[2..]), None);
+        assert_eq!(parse_fingerprint("not a fingerprint"), None);
+    }
+
+    #[test]
+    fn test_changed_certificates_are_alerted_once() {
+        let pinned = fingerprint(b"pinned");
+        let rotated = fingerprint(b"rotated");
+        let mut alerted = None;
+
+        assert_eq!(
+            check_pin(&pinned, Some(pinned), &mut alerted),
+            PinCheck::Unchanged
+        );
+        assert_eq!(
+            check_pin(&pinned, Some(rotated), &mut alerted),
+            PinCheck::Mismatch(rotated)
+        );
+        assert_eq!(
+            check_pin(&pinned, Some(rotated), &mut alerted),
+            PinCheck::Unchanged
+        );
+        assert_eq!(check_pin(&pinned, None, &mut alerted), PinCheck::Unchanged);
+        assert_eq!(
+            check_pin(&pinned, Some(pinned), &mut alerted),
+            PinCheck::Restored
+        );
+        assert_eq!(alerted, None);
+    }
+}
@@ -0,0 +1,372 @@
+use jiff::{SignedDuration, Timestamp};
+use log::{error, info, warn};
+use std::{
+    fmt::Write,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+use tokio::{select, task::JoinHandle, time::sleep};
+use tokio_util::sync::CancellationToken;
+
+use crate::config::{Config, QuietHoursOptions, Severity};
+use crate::history::HistoryStore;
+use crate::locale::Phrases;
+use crate::notify::Notifier;
+use crate::slo::{SloStatus, SloTargets};
+use crate::status::StatusBoard;
+use crate::templates::{Template, Variables, render_phrase};
+
+/// Discord rejects messages longer than 2000 characters.
+const MAX_SUMMARY_CHARS: usize = 1900;
+/// Delay before trying again when the end of quiet hours cannot be computed.
+const FALLBACK_WAIT: Duration = Duration::from_hours(1);
+
+/// Daily quiet hours and the alerts held during them.
+#[derive(Debug)]
+pub(crate) struct QuietHours {
+    options: QuietHoursOptions,
+    held: Mutex<Vec<HeldAlert>>,
+}
+
+/// An alert held during quiet hours, repeats of the same alert are counted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct HeldAlert {
+    first_held_at: Timestamp,
+    message: String,
+    count: u64,
+}
+
+impl QuietHours {
+    pub(crate) fn new(options: QuietHoursOptions) -> Self {
+        Self {
+            options,
+            held: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub(crate) fn from_config(config: &Config) -> Option<Self> {
+        config.quiet_hours.clone().map(Self::new)
+    }
+
+    /// Holds the alert `message` when quiet hours are on at `now`, returns
+    /// whether it was held.
+    pub(crate) fn hold_at(&self, message: &str, now: Timestamp) -> bool {
+        if !self.is_quiet_at(now) {
+            return false;
+        }
+
+        let mut held = self.held.lock().expect("Held alerts lock poisoned");
+        match held.iter_mut().find(|alert| alert.message == message) {
+            Some(alert) => alert.count += 1,
+            None => held.push(HeldAlert {
+                first_held_at: now,
+                message: message.to_string(),
+                count: 1,
+            }),
+        }
+        true
+    }
+
+    fn is_quiet_at(&self, now: Timestamp) -> bool {
+        let time = now.to_zoned(self.options.timezone.clone()).time();
+        let QuietHoursOptions { start, end, .. } = self.options;
+
+        if start < end {
+            start <= time && time < end
+        } else {
+            time >= start || time < end
+        }
+    }
+
+    /// First end of quiet hours strictly after `now`.
+    fn next_end_after(&self, now: Timestamp) -> Result<Timestamp, jiff::Error> {
+        let timezone = &self.options.timezone;
+        let mut date = now.to_zoned(timezone.clone()).date();
+
+        loop {
+            let end = date
+                .to_datetime(self.options.end)
+                .to_zoned(timezone.clone())?
+                .timestamp();
+            if end > now {
+                return Ok(end);
+            }
+            date = date.tomorrow()?;
+        }
+    }
+
+    /// Builds the summary of the alerts held so far and forgets them, `None`
+    /// when nothing was held.
+    fn take_summary(
+        &self,
+        board: &StatusBoard,
+        slo: &[SloStatus],
+        header: &Template,
+        phrases: &Phrases,
+    ) -> Option<String> {
+        let held = std::mem::take(&mut *self.held.lock().expect("Held alerts lock poisoned"));
+        if held.is_empty() {
+            return None;
+        }
+
+        let timezone = &self.options.timezone;
+        let local_time = |timestamp: Timestamp| {
+            timestamp
+                .to_zoned(timezone.clone())
+                .strftime("%H:%M")
+                .to_string()
+        };
+
+        let mut lines = Vec::new();
+        for alert in &held {
+            let repeats = if alert.count > 1 {
+                format!(" (x{})", alert.count)
+            } else {
+                String::new()
+            };
+            lines.push(format!(
+                "- {} {}{repeats}",
+                local_time(alert.first_held_at),
+                alert.message
+            ));
+        }
+
+        let failing: Vec<String> = board
+            .snapshot()
+            .iter()
+            .filter_map(|site| {
+                let since = Timestamp::try_from(site.failing_since?).ok()?;
+                Some(render_phrase(
+                    phrases.failing_since,
+                    &Variables::default()
+                        .set("site", site.label())
+                        .set("status", site.health.label())
+                        .set("since", local_time(since)),
+                ))
+            })
+            .collect();
+        if failing.is_empty() {
+            lines.push(phrases.all_up.to_string());
+        } else {
+            lines.push(phrases.still_failing.to_string());
+            lines.extend(failing);
+        }
+
+        if !slo.is_empty() {
+            lines.push(phrases.slo_header.to_string());
+            lines.extend(
+                slo.iter()
+                    .map(|status| format!("- {}: {}", status.url, status.summary(phrases))),
+            );
+        }
+
+        let mut summary = header.render(
+            &Variables::default()
+                .set("start", self.options.start.strftime("%H:%M").to_string())
+                .set("end", self.options.end.strftime("%H:%M").to_string())
+                .set("held_count", held.len().to_string()),
+        );
+        for (index, line) in lines.iter().enumerate() {
+            if summary.len() + line.len() + 1 > MAX_SUMMARY_CHARS {
+                let more = Variables::default().set("count", (lines.len() - index).to_string());
+                let _ = write!(summary, "\n{}", render_phrase(phrases.more_lines, &more));
+                break;
+            }
+            let _ = write!(summary, "\n{line}");
+        }
+        Some(summary)
+    }
+}
+
+/// Sends the held alerts as a single summary every time quiet hours end,
+/// along with the sites still failing and the SLO status of every site.
+/// Alerts still held on shutdown are sent then, so none is lost.
+///
+/// The summary goes through the route of warning alerts.
+pub(crate) fn spawn_summary(
+    notifier: Arc<Notifier>,
+    board: StatusBoard,
+    slo: Option<(SloTargets, Arc<HistoryStore>)>,
+    token: CancellationToken,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let Some(quiet_hours) = notifier.quiet_hours() else {
+            return;
+        };
+
+        loop {
+            let now = Timestamp::now();
+            let wait = match quiet_hours.next_end_after(now) {
+                Ok(end) => Duration::try_from(end.duration_since(now).max(SignedDuration::ZERO))
+                    .unwrap_or(FALLBACK_WAIT),
+                Err(e) => {
+                    warn!("Failed to compute the end of quiet hours: {e}");
+                    FALLBACK_WAIT
+                }
+            };
+
+            let shutting_down = select! {
+                () = sleep(wait) => false,
+                () = token.cancelled() => true,
+            };
+
+            let slo_statuses = match &slo {
+                Some((targets, store)) => {
+                    let targets = targets.clone();
+                    let store = Arc::clone(store);
+                    match tokio::task::spawn_blocking(move || {
+                        targets.evaluate(&store, SystemTime::now())
+                    })
+                    .await
+                    {
+                        Ok(Ok(statuses)) => statuses,
+                        Ok(Err(e)) => {
+                            error!("Failed to compute SLOs for the quiet hours summary: {e}");
+                            Vec::new()
+                        }
+                        Err(e) => {
+                            error!("SLO evaluation task failed: {e}");
+                            Vec::new()
+                        }
+                    }
+                }
+                None => Vec::new(),
+            };
+
+            if let Some(summary) = quiet_hours.take_summary(
+                &board,
+                &slo_statuses,
+                &notifier.templates().digest,
+                notifier.phrases(),
+            ) {
+                if shutting_down {
+                    info!("Shutting down, sending the summary of held alerts");
+                } else {
+                    info!("Quiet hours ended, sending the summary of held alerts");
+                }
+                if let Err(e) = notifier.send(Severity::Warning, &summary).await {
+                    error!("Error sending the quiet hours summary: {e}");
+                }
+            }
+            if shutting_down {
+                break;
+            }
+        }
+    })
+}
+
+/// Formats quiet hours for logs, e.g. `23:00 to 07:00 (Europe/Paris)`.
+pub(crate) fn describe(options: &QuietHoursOptions) -> String {
+    format!(
+        "{} to {} ({})",
+        options.start.strftime("%H:%M"),
+        options.end.strftime("%H:%M"),
+        options.timezone.iana_name().unwrap_or("system time zone")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Language;
+    use crate::locale::phrases;
+    use crate::status::SiteHealth;
+    use crate::templates::MessageTemplates;
+    use crate::worker::CheckRecord;
+    use jiff::{civil::Time, tz::TimeZone};
+
+    fn quiet_hours(start: Time, end: Time) -> QuietHours {
+        QuietHours::new(QuietHoursOptions {
+            start,
+            end,
+            timezone: TimeZone::get("Europe/Paris").unwrap(),
+        })
+    }
+
+    fn paris(datetime: &str) -> Timestamp {
+        datetime
+            .parse::<jiff::civil::DateTime>()
+            .unwrap()
+            .to_zoned(TimeZone::get("Europe/Paris").unwrap())
+            .unwrap()
+            .timestamp()
+    }
+
+    #[test]
+    fn test_quiet_hours_span_midnight() {
+        let quiet = quiet_hours(Time::constant(23, 0, 0, 0), Time::constant(7, 0, 0, 0));
+
+        assert!(!quiet.is_quiet_at(paris("2025-03-10T22:59")));
+        assert!(quiet.is_quiet_at(paris("2025-03-10T23:00")));
+        assert!(quiet.is_quiet_at(paris("2025-03-11T06:59")));
+        assert!(!quiet.is_quiet_at(paris("2025-03-11T07:00")));
+
+        let daytime = quiet_hours(Time::constant(12, 0, 0, 0), Time::constant(14, 0, 0, 0));
+        assert!(daytime.is_quiet_at(paris("2025-03-10T13:00")));
+        assert!(!daytime.is_quiet_at(paris("2025-03-10T23:30")));
+    }
+
+    #[test]
+    fn test_next_end_after() {
+        let quiet = quiet_hours(Time::constant(23, 0, 0, 0), Time::constant(7, 0, 0, 0));
+
+        assert_eq!(
+            quiet.next_end_after(paris("2025-03-10T23:30")).unwrap(),
+            paris("2025-03-11T07:00")
+        );
+        assert_eq!(
+            quiet.next_end_after(paris("2025-03-11T07:00")).unwrap(),
+            paris("2025-03-12T07:00")
+        );
+    }
+
+    #[test]
+    fn test_held_alerts_are_summarized_once() {
+        let quiet = quiet_hours(Time::constant(23, 0, 0, 0), Time::constant(7, 0, 0, 0));
+        let night = paris("2025-03-11T01:12");
+
+        assert!(quiet.hold_at("Alert: https://a.example is DOWN!", night));
+        assert!(quiet.hold_at("Alert: https://a.example is DOWN!", night));
+        assert!(
+            !quiet.hold_at(
+                "Alert: https://a.example is DOWN!",
+                paris("2025-03-11T08:00")
+            ),
+            "Alerts outside quiet hours are sent right away"
+        );
+
+        let board = StatusBoard::new(&["https://a.example".to_string()]);
+        board.record(
+            &CheckRecord {
+                url: "https://a.example".to_string(),
+                agent: None,
+                checked_at: SystemTime::now(),
+                is_up: false,
+                response_time: Duration::from_secs(5),
+                consecutive_failures: 5,
+            },
+            SiteHealth::Down,
+        );
+
+        let summary = quiet
+            .take_summary(
+                &board,
+                &[],
+                &MessageTemplates::default().digest,
+                phrases(Language::En),
+            )
+            .unwrap();
+        assert!(summary.starts_with("Quiet hours summary (23:00 to 07:00), 1 alert(s) held:"));
+        assert!(summary.contains("- 01:12 Alert: https://a.example is DOWN! (x2)"));
+        assert!(summary.contains("Still failing:\n- https://a.example is DOWN since"));
+        assert_eq!(
+            quiet.take_summary(
+                &board,
+                &[],
+                &MessageTemplates::default().digest,
+                phrases(Language::En)
+            ),
+            None
+        );
+    }
+}
@@ -0,0 +1,151 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use tokio::time::sleep_until;
+use url::Url;
+
+use crate::config::RateLimitOptions;
+
+/// Paces the checks so they start no faster than the global rate, and no
+/// closer together than the politeness delay for any one host.
+///
+/// Every check reserves the first slot both limits allow, so concurrent
+/// checks queue up instead of all firing at once when a slot frees up.
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    /// Least time between the start of any two checks.
+    interval: Option<Duration>,
+    /// Least time between the start of two checks of the same host.
+    host_delay: Option<Duration>,
+    slots: Mutex<Slots>,
+}
+
+/// Earliest times the next checks may start.
+#[derive(Debug, Default)]
+struct Slots {
+    next: Option<Instant>,
+    by_host: HashMap<String, Instant>,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(options: &RateLimitOptions) -> Self {
+        Self {
+            interval: options
+                .requests_per_sec
+                .map(|rate| Duration::from_secs_f64(1.0 / rate)),
+            host_delay: options.per_host_delay_ms.map(Duration::from_millis),
+            slots: Mutex::default(),
+        }
+    }
+
+    /// Waits until the check of `url` may start.
+    pub(crate) async fn wait(&self, url: &str) {
+        let host = Url::parse(url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_ascii_lowercase));
+        let start = self.reserve(host, Instant::now());
+        sleep_until(start.into()).await;
+    }
+
+    /// Reserves the first slot from `now` on for a check of `host`.
+    fn reserve(&self, host: Option<String>, now: Instant) -> Instant {
+        let mut slots = self.slots.lock().expect("Rate limiter lock poisoned");
+        // Hosts whose delay has passed are not held back anymore
+        slots.by_host.retain(|_, next| *next > now);
+
+        let mut start = now;
+        if let Some(next) = slots.next {
+            start = start.max(next);
+        }
+        let host = host.filter(|_| self.host_delay.is_some());
+        if let Some(next) = host.as_ref().and_then(|host| slots.by_host.get(host)) {
+            start = start.max(*next);
+        }
+
+        if let Some(interval) = self.interval {
+            slots.next = Some(start + interval);
+        }
+        if let (Some(host), Some(delay)) = (host, self.host_delay) {
+            slots.by_host.insert(host, start + delay);
+        }
+        start
+    }
+
+    /// Waits until a request to `url` may be sent, right away without a limiter.
+    pub(crate) async fn wait_for(rate_limiter: Option<&Self>, url: &str) {
+        if let Some(rate_limiter) = rate_limiter {
+            rate_limiter.wait(url).await;
+        }
+    }
+
+    /// Describes the limits, for the startup logs.
+    pub(crate) fn describe(&self) -> String {
+        let rate = self.interval.map(|interval| {
+            format!(
+                "at most {:.2} checks per second",
+                1.0 / interval.as_secs_f64()
+            )
+        });
+        let delay = self
+            .host_delay
+            .map(|delay| format!("{} ms between checks of a host", delay.as_millis()));
+        [rate, delay]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(requests_per_sec: Option<f64>, per_host_delay_ms: Option<u64>) -> RateLimiter {
+        RateLimiter::new(&RateLimitOptions {
+            requests_per_sec,
+            per_host_delay_ms,
+        })
+    }
+
+    #[test]
+    fn test_global_rate_spaces_checks() {
+        let limiter = limiter(Some(4.0), None);
+        let now = Instant::now();
+        let starts: Vec<Duration> = (0..3)
+            .map(|_| limiter.reserve(Some("a.example".into()), now) - now)
+            .collect();
+        assert_eq!(
+            starts,
+            [
+                Duration::ZERO,
+                Duration::from_millis(250),
+                Duration::from_millis(500)
+            ]
+        );
+
+        // A slot already passed is not made up for
+        let later = now + Duration::from_secs(10);
+        assert_eq!(limiter.reserve(None, later), later);
+    }
+
+    #[test]
+    fn test_host_delay_only_holds_the_same_host() {
+        let limiter = limiter(None, Some(1000));
+        let now = Instant::now();
+        let a = || limiter.reserve(Some("a.example".into()), now) - now;
+        assert_eq!(a(), Duration::ZERO);
+        assert_eq!(
+            limiter.reserve(Some("b.example".into()), now),
+            now,
+            "Other hosts are not held back"
+        );
+        assert_eq!(a(), Duration::from_secs(1));
+        assert_eq!(a(), Duration::from_secs(2));
+        assert_eq!(limiter.reserve(None, now), now);
+
+        assert_eq!(limiter.describe(), "1000 ms between checks of a host");
+    }
+}
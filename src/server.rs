@@ -0,0 +1,204 @@
+use axum::{Router, extract::State, response::Html, routing::get};
+use ed25519_dalek::VerifyingKey;
+use log::{error, info, warn};
+use reqwest::Client;
+use std::{sync::Arc, time::SystemTime};
+use tokio::{net::TcpListener, sync::mpsc::Sender};
+use tokio_util::sync::CancellationToken;
+
+use crate::agent::AgentReport;
+use crate::api::{self, SiteManagement};
+use crate::config::{self, Config, ServerOptions};
+use crate::dashboard::{self, HistoryCache};
+use crate::history::HistoryStore;
+use crate::interactions;
+use crate::notify::Notifier;
+use crate::rate_limit::RateLimiter;
+use crate::slo::SloTargets;
+use crate::status::StatusBoard;
+use crate::worker::build_check_client;
+
+/// State shared by every request handler.
+#[derive(Debug, Clone)]
+pub(crate) struct AppState {
+    pub(crate) board: StatusBoard,
+    pub(crate) history: Option<Arc<HistoryStore>>,
+    /// History read by the dashboard for its uptime bars and sparklines,
+    /// `None` in low-memory mode.
+    pub(crate) history_graphs: Option<Arc<HistoryCache>>,
+    pub(crate) site_management: Option<Arc<SiteManagement>>,
+    /// Where agent reports are queued for the worker, `None` unless acting as a central instance.
+    pub(crate) agent_reports: Option<AgentReports>,
+    /// Checks requested by other instances, `None` unless a probe token is configured.
+    pub(crate) probe: Option<Probe>,
+    /// Site SLOs, `None` unless at least one site declares one.
+    pub(crate) slo: Option<SloTargets>,
+    /// Open incidents and who acknowledged them.
+    pub(crate) notifier: Arc<Notifier>,
+    /// Key Discord interactions are signed with, `None` unless configured.
+    pub(crate) discord_public_key: Option<VerifyingKey>,
+}
+
+/// Queue of agent reports and the token agents authenticate with.
+#[derive(Debug, Clone)]
+pub(crate) struct AgentReports {
+    pub(crate) token: Arc<str>,
+    pub(crate) sender: Sender<AgentReport>,
+}
+
+/// Check client and token used to confirm outages for other instances.
+#[derive(Debug, Clone)]
+pub(crate) struct Probe {
+    pub(crate) token: Arc<str>,
+    pub(crate) client: Client,
+    pub(crate) timeout_secs: u64,
+    /// Limiter shared with the checks of this instance, when configured.
+    pub(crate) rate_limiter: Option<Arc<RateLimiter>>,
+}
+
+/// Starts the built-in HTTP server in the background when it is configured.
+///
+/// Agent reports are forwarded to `report_sender` when set and incident
+/// acknowledgements to `notifier`. The server stops once `token` is cancelled.
+pub(crate) fn spawn(
+    config: &Config,
+    board: StatusBoard,
+    notifier: Arc<Notifier>,
+    report_sender: Option<Sender<AgentReport>>,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    token: CancellationToken,
+) {
+    let Some(options) = config.server.clone() else {
+        return;
+    };
+    let state =
+        AppState {
+            board,
+            history: config
+                .history
+                .as_ref()
+                .map(|history| Arc::new(HistoryStore::new(history))),
+            history_graphs: (!config.config.low_memory).then(Arc::default),
+            site_management: options.api_token.clone().map(|token| {
+                let config_path = config::config_path()
+                .inspect_err(|e| {
+                    warn!(
+                        "Sites changed through the API will not be saved to the config file: {e}"
+                    );
+                })
+                .ok();
+                Arc::new(SiteManagement::new(token, config_path))
+            }),
+            agent_reports: options.agent_token.as_deref().zip(report_sender).map(
+                |(token, sender)| AgentReports {
+                    token: token.into(),
+                    sender,
+                },
+            ),
+            probe: options.probe_token.as_deref().and_then(|token| {
+                let client = build_check_client(&config.config, None)
+                    .inspect_err(|e| {
+                        error!("Probe endpoint disabled, failed to build HTTP client: {e}");
+                    })
+                    .ok()?;
+                Some(Probe {
+                    token: token.into(),
+                    client,
+                    timeout_secs: config.config.timeout_secs,
+                    rate_limiter,
+                })
+            }),
+            slo: SloTargets::from_config(config),
+            notifier,
+            discord_public_key: options.discord_public_key,
+        };
+
+    tokio::spawn(serve(options, state, token));
+}
+
+async fn serve(options: ServerOptions, state: AppState, token: CancellationToken) {
+    let listener = match TcpListener::bind(options.bind).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("Failed to start HTTP server on {}: {e}", options.bind);
+            return;
+        }
+    };
+    info!("Dashboard available at http://{}", options.bind);
+
+    if let Err(e) = axum::serve(listener, router(state))
+        .with_graceful_shutdown(token.cancelled_owned())
+        .await
+    {
+        error!("HTTP server stopped unexpectedly: {e}");
+    }
+}
+
+fn router(state: AppState) -> Router {
+    Router::new()
+        .route("/", get(dashboard_page))
+        .nest("/api/v1", api::routes().merge(interactions::routes()))
+        .with_state(state)
+}
+
+async fn dashboard_page(State(state): State<AppState>) -> Html<String> {
+    let sites = state.board.snapshot();
+    let now = SystemTime::now();
+
+    let histories = match state.history.zip(state.history_graphs) {
+        Some((store, cache)) => {
+            let keys: Vec<_> = sites
+                .iter()
+                .map(|site| (site.url.clone(), site.agent.clone()))
+                .collect();
+            match tokio::task::spawn_blocking(move || cache.load(&store, &keys, now)).await {
+                Ok(Ok(histories)) => Some(histories),
+                Ok(Err(e)) => {
+                    error!("Failed to read check history for the dashboard: {e}");
+                    None
+                }
+                Err(e) => {
+                    error!("Dashboard history task failed: {e}");
+                    None
+                }
+            }
+        }
+        None => None,
+    };
+
+    Html(dashboard::render(&sites, histories.as_deref(), now))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_dashboard_is_served() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let state = AppState {
+            board: StatusBoard::new(&["https://a.example".to_string()]),
+            history: None,
+            history_graphs: None,
+            site_management: None,
+            agent_reports: None,
+            probe: None,
+            slo: None,
+            notifier: Arc::default(),
+            discord_public_key: None,
+        };
+        tokio::spawn(async move { axum::serve(listener, router(state)).await });
+
+        let response = reqwest::get(format!("http://{addr}/")).await.unwrap();
+
+        assert!(response.status().is_success());
+        assert!(
+            response.headers()[reqwest::header::CONTENT_TYPE]
+                .to_str()
+                .unwrap()
+                .starts_with("text/html")
+        );
+        assert!(response.text().await.unwrap().contains("https://a.example"));
+    }
+}
@@ -0,0 +1,208 @@
+use clap::Subcommand;
+use downdetector::Config;
+use log::{error, info};
+use std::{ffi::OsString, process::ExitCode, time::Duration};
+use tokio_util::sync::CancellationToken;
+use windows_service::{
+    define_windows_service,
+    service::{
+        ServiceAccess, ServiceControl, ServiceControlAccept, ServiceErrorControl, ServiceExitCode,
+        ServiceInfo, ServiceStartType, ServiceState, ServiceStatus, ServiceType,
+    },
+    service_control_handler::{self, ServiceControlHandlerResult},
+    service_dispatcher,
+    service_manager::{ServiceManager, ServiceManagerAccess},
+};
+
+/// Name of the service, and of its event log source.
+const SERVICE_NAME: &str = "downdetector";
+const SERVICE_DISPLAY_NAME: &str = "Downdetector";
+const SERVICE_DESCRIPTION: &str =
+    "Monitors website availability and sends Discord notifications when sites go down.";
+const SERVICE_TYPE: ServiceType = ServiceType::OWN_PROCESS;
+
+#[derive(Clone, Copy, Subcommand)]
+pub(crate) enum ServiceAction {
+    /// Register the monitor as a service started at boot
+    Install,
+    /// Start the installed service
+    Start,
+    /// Stop the running service
+    Stop,
+    /// Stop and remove the service
+    Uninstall,
+    /// Run as the service, only meant for the service control manager
+    #[command(hide = true)]
+    Run,
+}
+
+pub(crate) fn handle(action: ServiceAction) -> ExitCode {
+    let control = match action {
+        ServiceAction::Install => install,
+        ServiceAction::Start => start,
+        ServiceAction::Stop => stop,
+        ServiceAction::Uninstall => uninstall,
+        ServiceAction::Run => return run(),
+    };
+
+    crate::init_logging();
+    match control() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            error!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn install() -> Result<(), String> {
+    let executable_path =
+        std::env::current_exe().map_err(|e| format!("Failed to locate the executable: {e}"))?;
+    let manager = ServiceManager::local_computer(
+        None::<&str>,
+        ServiceManagerAccess::CONNECT | ServiceManagerAccess::CREATE_SERVICE,
+    )
+    .map_err(|e| format!("Failed to connect to the service manager: {e}"))?;
+
+    let service = manager
+        .create_service(
+            &ServiceInfo {
+                name: SERVICE_NAME.into(),
+                display_name: SERVICE_DISPLAY_NAME.into(),
+                service_type: SERVICE_TYPE,
+                start_type: ServiceStartType::AutoStart,
+                error_control: ServiceErrorControl::Normal,
+                executable_path,
+                launch_arguments: vec!["service".into(), "run".into()],
+                dependencies: vec![],
+                // Runs as LocalSystem
+                account_name: None,
+                account_password: None,
+            },
+            ServiceAccess::CHANGE_CONFIG,
+        )
+        .map_err(|e| format!("Failed to install the service: {e}"))?;
+    service
+        .set_description(SERVICE_DESCRIPTION)
+        .map_err(|e| format!("Failed to describe the service: {e}"))?;
+    eventlog::register(SERVICE_NAME)
+        .map_err(|e| format!("Failed to register the event log source: {e}"))?;
+
+    info!("Installed the {SERVICE_NAME} service, started at boot");
+    Ok(())
+}
+
+fn start() -> Result<(), String> {
+    open_service(ServiceAccess::START)?
+        .start::<&str>(&[])
+        .map_err(|e| format!("Failed to start the service: {e}"))?;
+    info!("Started the {SERVICE_NAME} service");
+    Ok(())
+}
+
+fn stop() -> Result<(), String> {
+    open_service(ServiceAccess::STOP)?
+        .stop()
+        .map_err(|e| format!("Failed to stop the service: {e}"))?;
+    info!("Stopping the {SERVICE_NAME} service");
+    Ok(())
+}
+
+fn uninstall() -> Result<(), String> {
+    let service =
+        open_service(ServiceAccess::QUERY_STATUS | ServiceAccess::STOP | ServiceAccess::DELETE)?;
+    let state = service
+        .query_status()
+        .map_err(|e| format!("Failed to query the service: {e}"))?
+        .current_state;
+    if state != ServiceState::Stopped {
+        service
+            .stop()
+            .map_err(|e| format!("Failed to stop the service: {e}"))?;
+    }
+    // Removed once every handle to it is closed
+    service
+        .delete()
+        .map_err(|e| format!("Failed to uninstall the service: {e}"))?;
+    eventlog::deregister(SERVICE_NAME)
+        .map_err(|e| format!("Failed to deregister the event log source: {e}"))?;
+
+    info!("Uninstalled the {SERVICE_NAME} service");
+    Ok(())
+}
+
+fn open_service(access: ServiceAccess) -> Result<windows_service::service::Service, String> {
+    ServiceManager::local_computer(None::<&str>, ServiceManagerAccess::CONNECT)
+        .and_then(|manager| manager.open_service(SERVICE_NAME, access))
+        .map_err(|e| format!("Failed to open the {SERVICE_NAME} service, is it installed? {e}"))
+}
+
+/// Hands the process over to the service control manager, which calls
+/// [`service_main`] on another thread.
+fn run() -> ExitCode {
+    // Without a console the event log is the only place logs can go
+    if eventlog::init(SERVICE_NAME, log::Level::Info).is_err() {
+        return ExitCode::FAILURE;
+    }
+    match service_dispatcher::start(SERVICE_NAME, ffi_service_main) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            error!("Failed to start as a service: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+define_windows_service!(ffi_service_main, service_main);
+
+fn service_main(_arguments: Vec<OsString>) {
+    if let Err(e) = run_service() {
+        error!("Failed to report the service status: {e}");
+    }
+}
+
+fn run_service() -> windows_service::Result<()> {
+    let token = CancellationToken::new();
+    let stop_token = token.clone();
+    let status_handle =
+        service_control_handler::register(SERVICE_NAME, move |control| match control {
+            ServiceControl::Stop | ServiceControl::Shutdown => {
+                stop_token.cancel();
+                ServiceControlHandlerResult::NoError
+            }
+            ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+            _ => ServiceControlHandlerResult::NotImplemented,
+        })?;
+
+    status_handle.set_service_status(status(
+        ServiceState::Running,
+        ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN,
+        0,
+    ))?;
+
+    let exit_code = match Config::load() {
+        Ok(config) => u32::from(crate::run_monitor(config, token) != ExitCode::SUCCESS),
+        Err(e) => {
+            error!("Failed to load configuration: {e}");
+            1
+        }
+    };
+
+    status_handle.set_service_status(status(
+        ServiceState::Stopped,
+        ServiceControlAccept::empty(),
+        exit_code,
+    ))
+}
+
+fn status(state: ServiceState, accepted: ServiceControlAccept, exit_code: u32) -> ServiceStatus {
+    ServiceStatus {
+        service_type: SERVICE_TYPE,
+        current_state: state,
+        controls_accepted: accepted,
+        exit_code: ServiceExitCode::Win32(exit_code),
+        checkpoint: 0,
+        wait_hint: Duration::default(),
+        process_id: None,
+    }
+}
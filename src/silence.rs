@@ -0,0 +1,250 @@
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, SystemTime},
+};
+use url::Url;
+
+use crate::config::{Config, data_dir, write_atomically};
+use crate::error::Error;
+use crate::history::unix_millis;
+use crate::status::site_url;
+
+/// File the silences are saved to, next to the check history.
+const SILENCES_FILE_NAME: &str = "silences.json";
+
+/// Expected downtime of a site, or of every site with a tag, during which
+/// it is still checked but not alerted on.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Silence {
+    /// URL of the silenced site, or tag of the silenced sites.
+    pub target: String,
+    /// Unix time in milliseconds the silence ends at.
+    pub until_ms: u64,
+    /// Why alerts are silenced, e.g. `deploy`.
+    pub reason: Option<String>,
+}
+
+/// Content of the silences file.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SilenceFile {
+    silences: Vec<Silence>,
+}
+
+/// Silences written by the `silence` command.
+///
+/// The file is read again whenever its modification time changes, so
+/// silences added while the monitor runs apply right away.
+#[derive(Debug, Default)]
+pub(crate) struct Silences {
+    /// File the silences are read from, none apply when `None`.
+    path: Option<PathBuf>,
+    /// Tags of the configured sites having some.
+    tags: HashMap<String, Vec<String>>,
+    /// Silences last read, along with the modification time of the file then.
+    cache: Mutex<Option<(SystemTime, Vec<Silence>)>>,
+}
+
+impl Silences {
+    pub(crate) fn from_config(config: &Config) -> Self {
+        let path = match silences_path(config) {
+            Ok(path) => Some(path),
+            Err(e) => {
+                warn!("Silences are disabled: {e}");
+                None
+            }
+        };
+        Self {
+            path,
+            tags: config
+                .sites
+                .options
+                .iter()
+                .filter(|(_, options)| !options.tags.is_empty())
+                .map(|(url, options)| (url.clone(), options.tags.clone()))
+                .collect(),
+            cache: Mutex::default(),
+        }
+    }
+
+    /// Silence of `site` at `now_ms`, the one ending last when several apply.
+    ///
+    /// Sites checked by agents are silenced along with the site they check.
+    pub(crate) fn active(&self, site: &str, now_ms: u64) -> Option<Silence> {
+        let path = self.path.as_ref()?;
+        let mut cache = self.cache.lock().expect("Silences lock poisoned");
+        let modified = match fs::metadata(path).and_then(|metadata| metadata.modified()) {
+            Ok(modified) => modified,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                *cache = None;
+                return None;
+            }
+            Err(e) => {
+                warn!("Failed to read silences from {}: {e}", path.display());
+                return None;
+            }
+        };
+        if cache
+            .as_ref()
+            .is_none_or(|(read_at, _)| *read_at != modified)
+        {
+            match load(path) {
+                Ok(file) => *cache = Some((modified, file.silences)),
+                Err(e) => {
+                    warn!("Failed to read silences from {}: {e}", path.display());
+                    return None;
+                }
+            }
+        }
+
+        let url = site_url(site);
+        let tags = self.tags.get(url).map_or(&[][..], Vec::as_slice);
+        cache
+            .as_ref()?
+            .1
+            .iter()
+            .filter(|silence| silence.until_ms > now_ms)
+            .filter(|silence| silence.target == url || tags.contains(&silence.target))
+            .max_by_key(|silence| silence.until_ms)
+            .cloned()
+    }
+}
+
+/// Where the silences are saved, next to the check history when enabled.
+pub(crate) fn silences_path(config: &Config) -> Result<PathBuf, Error> {
+    Ok(match &config.history {
+        Some(history) => history.path.with_file_name(SILENCES_FILE_NAME),
+        None => data_dir()?.join(SILENCES_FILE_NAME),
+    })
+}
+
+/// Silences alerts about `target`, the URL of a site or a tag of configured
+/// sites, for `duration` from now. Used by the `silence` command.
+///
+/// Sites keep being checked, and the expired silences are dropped from the file.
+///
+/// # Errors
+///
+/// Returns an error when `target` is neither a URL nor a configured tag, or
+/// when the silences file cannot be read or written.
+pub fn silence(
+    config: &Config,
+    target: &str,
+    duration: Duration,
+    reason: Option<String>,
+) -> Result<Silence, Error> {
+    if !config.sites.has_tag(target) && Url::parse(target).is_err() {
+        return Err(Error::Config(format!(
+            "{target} is neither a site URL nor a tag of a configured site"
+        )));
+    }
+    if duration.is_zero() {
+        return Err(Error::Config("A silence must last longer than 0".into()));
+    }
+
+    let path = silences_path(config)?;
+    let now_ms = unix_millis(SystemTime::now());
+    let mut file = load(&path)?;
+    file.silences.retain(|silence| silence.until_ms > now_ms);
+
+    let silence = Silence {
+        target: target.to_string(),
+        until_ms: now_ms.saturating_add(u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)),
+        reason,
+    };
+    file.silences.push(silence.clone());
+    write_atomically(&path, &serde_json::to_vec_pretty(&file)?)?;
+    Ok(silence)
+}
+
+/// Reads the silences file, a missing file holds no silences.
+fn load(path: &Path) -> Result<SilenceFile, Error> {
+    match fs::read(path) {
+        Ok(content) => Ok(serde_json::from_slice(&content)?),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(SilenceFile::default()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    fn config(name: &str) -> Config {
+        let dir = temp_dir(&format!("silences-{name}"));
+        let config_path = dir.join("config.toml");
+        fs::write(
+            &config_path,
+            format!(
+                r#"
+                version = 2
+
+                [sites]
+                urls = [
+                    {{ url = "https://a.example", tags = ["web"] }},
+                    "https://b.example",
+                ]
+
+                [history]
+                path = "{}"
+                "#,
+                dir.join("history.jsonl").display()
+            ),
+        )
+        .unwrap();
+        Config::load_from(&config_path).unwrap()
+    }
+
+    #[test]
+    fn test_silences_match_urls_and_tags() {
+        let config = config("match");
+        let silences = Silences::from_config(&config);
+        let now_ms = unix_millis(SystemTime::now());
+        assert_eq!(silences.active("https://a.example", now_ms), None);
+
+        let hour = Duration::from_hours(1);
+        silence(&config, "web", hour, Some("deploy".into())).unwrap();
+        silence(&config, "https://b.example", 2 * hour, None).unwrap();
+
+        let web = silences
+            .active("https://a.example (via eu)", now_ms)
+            .unwrap();
+        assert_eq!(web.target, "web");
+        assert_eq!(web.reason.as_deref(), Some("deploy"));
+        assert_eq!(
+            silences.active("https://b.example", now_ms).unwrap().target,
+            "https://b.example"
+        );
+        assert_eq!(silences.active("https://c.example", now_ms), None);
+
+        // Expired silences no longer apply
+        let later_ms = now_ms + 3 * 3_600_000;
+        assert_eq!(silences.active("https://b.example", later_ms), None);
+
+        // Silences added after the file was read apply too
+        silence(&config, "https://c.example", hour, None).unwrap();
+        assert!(silences.active("https://c.example", now_ms).is_some());
+    }
+
+    #[test]
+    fn test_unknown_targets_are_refused() {
+        let config = config("unknown");
+        assert!(silence(&config, "api", Duration::from_hours(1), None).is_err());
+        assert!(silence(&config, "web", Duration::ZERO, None).is_err());
+        assert!(
+            silence(
+                &config,
+                "https://new.example",
+                Duration::from_hours(1),
+                None
+            )
+            .is_ok()
+        );
+    }
+}
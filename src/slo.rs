@@ -0,0 +1,363 @@
+use log::{error, info, warn};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+use tokio::{select, time::interval};
+use tokio_util::sync::CancellationToken;
+
+use crate::config::{Config, Language};
+use crate::error::Error;
+use crate::history::{HistoryStore, unix_millis};
+use crate::locale::{Phrases, phrases};
+use crate::notify::Notifier;
+use crate::templates::{Variables, render_phrase};
+
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+/// Window the burn rate is measured over.
+const BURN_RATE_WINDOW_MS: u64 = 60 * 60 * 1000;
+/// How often compliance and burn rates are recomputed from history.
+const EVALUATION_INTERVAL: Duration = Duration::from_mins(5);
+/// Number of check intervals a check result is trusted for. Longer gaps
+/// between two checks, e.g. while the monitor was stopped, count as neither
+/// up nor down.
+const MAX_GAP_CHECK_INTERVALS: u64 = 4;
+
+/// Per-site SLO targets and how to measure them.
+#[derive(Debug, Clone)]
+pub(crate) struct SloTargets {
+    /// Target in percent per site, in configuration order.
+    targets: Vec<(String, f64)>,
+    window_days: u64,
+    burn_rate_alert: f64,
+    check_interval_secs: u64,
+}
+
+/// Current standing of a site against its SLO.
+///
+/// Every value is `None` until the history holds enough checks to compute it.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct SloStatus {
+    pub(crate) url: String,
+    pub(crate) target: f64,
+    pub(crate) window_days: u64,
+    /// Percentage of the window the site was up.
+    pub(crate) compliance: Option<f64>,
+    /// Percentage of the window's error budget left, negative once overspent.
+    pub(crate) error_budget_remaining: Option<f64>,
+    /// Rate the error budget was spent at over the last hour, `1.0` spending
+    /// it exactly over the window.
+    pub(crate) burn_rate: Option<f64>,
+}
+
+impl SloStatus {
+    /// One-line summary, e.g. `99.95% over 30 days (target 99.9%, 50.0% of error budget left)`.
+    pub(crate) fn summary(&self, phrases: &Phrases) -> String {
+        let variables = Variables::default().set("target", self.target.to_string());
+        match (self.compliance, self.error_budget_remaining) {
+            (Some(compliance), Some(remaining)) => render_phrase(
+                phrases.slo_summary,
+                &variables
+                    .set("compliance", format!("{compliance:.2}"))
+                    .set("window_days", self.window_days.to_string())
+                    .set("remaining", format!("{remaining:.1}")),
+            ),
+            _ => render_phrase(phrases.slo_no_data, &variables),
+        }
+    }
+}
+
+impl SloTargets {
+    /// Collects the SLO of every site declaring one, `None` when there are none.
+    pub(crate) fn from_config(config: &Config) -> Option<Self> {
+        let options = config.slo.as_ref()?;
+        let targets = config
+            .sites
+            .urls
+            .iter()
+            .filter_map(|url| {
+                let target = config.sites.options.get(url)?.slo?;
+                Some((url.clone(), target))
+            })
+            .collect();
+
+        Some(Self {
+            targets,
+            window_days: options.window_days,
+            burn_rate_alert: options.burn_rate_alert,
+            check_interval_secs: config.config.check_interval_secs,
+        })
+    }
+
+    /// Computes the SLO status of every site from the check history.
+    ///
+    /// Each check is weighted by the time until the next check of the same
+    /// site, so the fast retries of a failing site do not overstate downtime.
+    pub(crate) fn evaluate(
+        &self,
+        store: &HistoryStore,
+        now: SystemTime,
+    ) -> Result<Vec<SloStatus>, Error> {
+        let now_ms = unix_millis(now);
+        let window = Window {
+            from_ms: now_ms.saturating_sub(self.window_days * DAY_MS),
+            burn_from_ms: now_ms.saturating_sub(BURN_RATE_WINDOW_MS),
+            max_gap_ms: self
+                .check_interval_secs
+                .saturating_mul(1000 * MAX_GAP_CHECK_INTERVALS),
+        };
+
+        let mut timelines: HashMap<&str, Timeline> = self
+            .targets
+            .iter()
+            .map(|(url, _)| (url.as_str(), Timeline::default()))
+            .collect();
+        for record in store.read_range(Some(window.from_ms), None)? {
+            let record = record?;
+            // Agents check from elsewhere, the SLO is about what this instance sees
+            if record.agent.is_some() {
+                continue;
+            }
+            if let Some(timeline) = timelines.get_mut(record.url.as_str()) {
+                timeline.add(record.timestamp_ms, record.up, &window);
+            }
+        }
+
+        Ok(self
+            .targets
+            .iter()
+            .map(|(url, target)| {
+                let mut timeline = timelines.remove(url.as_str()).unwrap_or_default();
+                timeline.finish(now_ms, &window);
+                self.status(url, *target, &timeline)
+            })
+            .collect())
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn status(&self, url: &str, target: f64, timeline: &Timeline) -> SloStatus {
+        let allowed_down = 1.0 - target / 100.0;
+        let down_ratio = |(up_ms, total_ms): (u64, u64)| {
+            (total_ms > 0).then(|| (total_ms - up_ms) as f64 / total_ms as f64)
+        };
+        let window_down = down_ratio(timeline.window);
+
+        SloStatus {
+            url: url.to_string(),
+            target,
+            window_days: self.window_days,
+            compliance: window_down.map(|down| (1.0 - down) * 100.0),
+            error_budget_remaining: window_down.map(|down| (1.0 - down / allowed_down) * 100.0),
+            burn_rate: down_ratio(timeline.recent).map(|down| down / allowed_down),
+        }
+    }
+}
+
+/// Bounds used while replaying the history of a site.
+#[allow(clippy::struct_field_names)]
+struct Window {
+    from_ms: u64,
+    burn_from_ms: u64,
+    max_gap_ms: u64,
+}
+
+/// Time a site spent up out of the time covered by its checks, in milliseconds.
+#[derive(Debug, Default)]
+struct Timeline {
+    last_check: Option<(u64, bool)>,
+    /// Up and total time over the whole SLO window.
+    window: (u64, u64),
+    /// Up and total time over the burn rate window.
+    recent: (u64, u64),
+}
+
+impl Timeline {
+    fn add(&mut self, timestamp_ms: u64, up: bool, window: &Window) {
+        self.cover_until(timestamp_ms, window);
+        self.last_check = Some((timestamp_ms, up));
+    }
+
+    fn finish(&mut self, now_ms: u64, window: &Window) {
+        self.cover_until(now_ms, window);
+        self.last_check = None;
+    }
+
+    /// Credits the time since the last check with that check's result.
+    fn cover_until(&mut self, until_ms: u64, window: &Window) {
+        let Some((from_ms, up)) = self.last_check else {
+            return;
+        };
+        let until_ms = until_ms.min(from_ms.saturating_add(window.max_gap_ms));
+        let from_ms = from_ms.max(window.from_ms);
+
+        let add = |(up_ms, total_ms): &mut (u64, u64), from_ms: u64| {
+            let covered_ms = until_ms.saturating_sub(from_ms);
+            *total_ms += covered_ms;
+            if up {
+                *up_ms += covered_ms;
+            }
+        };
+        add(&mut self.window, from_ms);
+        add(&mut self.recent, from_ms.max(window.burn_from_ms));
+    }
+}
+
+/// Periodically recomputes every SLO and alerts on sites burning their
+/// error budget faster than allowed.
+///
+/// A site is alerted on once when its burn rate crosses the threshold and
+/// again only after it went back below it.
+pub(crate) fn spawn_monitor(
+    targets: SloTargets,
+    store: Arc<HistoryStore>,
+    notifier: Arc<Notifier>,
+    token: CancellationToken,
+) {
+    tokio::spawn(async move {
+        let mut ticks = interval(EVALUATION_INTERVAL);
+        let mut burning: HashSet<String> = HashSet::new();
+
+        loop {
+            select! {
+                _ = ticks.tick() => {},
+                () = token.cancelled() => break,
+            }
+
+            let evaluation = {
+                let targets = targets.clone();
+                let store = Arc::clone(&store);
+                tokio::task::spawn_blocking(move || targets.evaluate(&store, SystemTime::now()))
+                    .await
+            };
+            let statuses = match evaluation {
+                Ok(Ok(statuses)) => statuses,
+                Ok(Err(e)) => {
+                    error!("Failed to compute SLOs from check history: {e}");
+                    continue;
+                }
+                Err(e) => {
+                    error!("SLO evaluation task failed: {e}");
+                    continue;
+                }
+            };
+
+            for status in statuses {
+                let Some(burn_rate) = status.burn_rate else {
+                    continue;
+                };
+
+                if burn_rate < targets.burn_rate_alert {
+                    if burning.remove(&status.url) {
+                        info!(
+                            "{}: SLO burn rate back to {burn_rate:.1}x, {}",
+                            status.url,
+                            status.summary(phrases(Language::En))
+                        );
+                    }
+                    continue;
+                }
+                if !burning.insert(status.url.clone()) {
+                    continue;
+                }
+
+                let message = render_phrase(
+                    notifier.phrases().slo_alert,
+                    &Variables::default()
+                        .set("site.url", status.url.clone())
+                        .set("burn_rate", format!("{burn_rate:.1}"))
+                        .set("summary", status.summary(notifier.phrases())),
+                );
+                warn!("{message}");
+                if let Err(e) = notifier.alert(&status.url, &message).await {
+                    error!("Error sending SLO alert for {}: {e}", status.url);
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{check_record, temp_history};
+    use std::time::UNIX_EPOCH;
+
+    const NOW_MS: u64 = 1_700_000_000_000;
+    const MINUTE_MS: u64 = 60 * 1000;
+
+    fn targets() -> SloTargets {
+        SloTargets {
+            targets: vec![
+                ("https://a.example".to_string(), 99.0),
+                ("https://b.example".to_string(), 99.9),
+            ],
+            window_days: 1,
+            burn_rate_alert: 14.4,
+            check_interval_secs: 60,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_compliance_is_time_weighted() {
+        let store = temp_history("slo-weighted", 3650);
+        let mut records = Vec::new();
+        // Up every minute for 10 hours, then down with 10 second retries for the last 6 minutes
+        let started_ms = NOW_MS - 10 * 60 * MINUTE_MS;
+        for minute in 0..594 {
+            records.push(check_record(
+                "https://a.example",
+                started_ms + minute * MINUTE_MS,
+                true,
+            ));
+        }
+        let down_from_ms = NOW_MS - 6 * MINUTE_MS;
+        for retry in 0..36 {
+            records.push(check_record(
+                "https://a.example",
+                down_from_ms + retry * 10_000,
+                false,
+            ));
+        }
+        store.append(&records).await.unwrap();
+
+        let statuses = targets()
+            .evaluate(&store, UNIX_EPOCH + Duration::from_millis(NOW_MS))
+            .unwrap();
+
+        let a = &statuses[0];
+        assert!((a.compliance.unwrap() - 99.0).abs() < 0.01, "{a:?}");
+        assert!(a.error_budget_remaining.unwrap().abs() < 1.0, "{a:?}");
+        // 6 of the last 60 minutes down against a 1% budget
+        assert!((a.burn_rate.unwrap() - 10.0).abs() < 0.1, "{a:?}");
+
+        let b = &statuses[1];
+        assert_eq!(b.compliance, None);
+        assert_eq!(
+            b.summary(phrases(Language::En)),
+            "no data yet (target 99.9%)"
+        );
+        assert_eq!(
+            b.summary(phrases(Language::Fr)),
+            "pas encore de données (objectif 99.9 %)"
+        );
+    }
+
+    #[test]
+    fn test_long_gaps_are_not_counted() {
+        let window = Window {
+            from_ms: 0,
+            burn_from_ms: NOW_MS - BURN_RATE_WINDOW_MS,
+            max_gap_ms: 4 * MINUTE_MS,
+        };
+        let mut timeline = Timeline::default();
+
+        // Down, then nothing for an hour while the monitor was stopped
+        timeline.add(NOW_MS - 2 * BURN_RATE_WINDOW_MS, false, &window);
+        timeline.add(NOW_MS - BURN_RATE_WINDOW_MS, true, &window);
+        timeline.finish(NOW_MS - BURN_RATE_WINDOW_MS + MINUTE_MS, &window);
+
+        assert_eq!(timeline.window, (MINUTE_MS, 5 * MINUTE_MS));
+        assert_eq!(timeline.recent, (MINUTE_MS, MINUTE_MS));
+    }
+}
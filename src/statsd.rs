@@ -0,0 +1,211 @@
+use std::io;
+use tokio::net::{UdpSocket, lookup_host};
+
+use crate::config::{StatsdOptions, StatsdTagFormat};
+use crate::error::Error;
+use crate::worker::CheckRecord;
+
+// Keeps datagrams under the common Ethernet MTU once IP/UDP headers are added
+const MAX_DATAGRAM_BYTES: usize = 1432;
+
+/// Sends per-check gauges and timings to the configured StatsD agent.
+pub(crate) async fn send_checks(
+    options: &StatsdOptions,
+    records: &[CheckRecord],
+) -> Result<(), Error> {
+    if records.is_empty() {
+        return Ok(());
+    }
+
+    let agent = lookup_host((options.host.as_str(), options.port))
+        .await?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "StatsD host did not resolve"))?;
+    let socket = UdpSocket::bind(if agent.is_ipv6() {
+        "[::]:0"
+    } else {
+        "0.0.0.0:0"
+    })
+    .await?;
+    socket.connect(agent).await?;
+
+    let lines: Vec<String> = records
+        .iter()
+        .flat_map(|record| metric_lines(options, record))
+        .collect();
+
+    for datagram in pack_datagrams(&lines) {
+        socket.send(datagram.as_bytes()).await?;
+    }
+    Ok(())
+}
+
+/// Formats the metrics of a single check: availability, failure streak and response time.
+fn metric_lines(options: &StatsdOptions, record: &CheckRecord) -> [String; 3] {
+    let site = site_tag_value(&record.url);
+
+    [
+        metric_line(options, "site.up", &site, u8::from(record.is_up), "g"),
+        metric_line(
+            options,
+            "site.consecutive_failures",
+            &site,
+            record.consecutive_failures,
+            "g",
+        ),
+        metric_line(
+            options,
+            "check.response_time",
+            &site,
+            record.response_time.as_millis(),
+            "ms",
+        ),
+    ]
+}
+
+fn metric_line(
+    options: &StatsdOptions,
+    name: &str,
+    site: &str,
+    value: impl std::fmt::Display,
+    metric_type: &str,
+) -> String {
+    let prefix = &options.prefix;
+
+    match options.tag_format {
+        StatsdTagFormat::None => {
+            format!(
+                "{prefix}.{name}.{}:{value}|{metric_type}",
+                site.replace(['.', '/'], "_")
+            )
+        }
+        StatsdTagFormat::DogStatsd => {
+            format!("{prefix}.{name}:{value}|{metric_type}|#site:{site}")
+        }
+        StatsdTagFormat::Influxdb => format!("{prefix}.{name},site={site}:{value}|{metric_type}"),
+        StatsdTagFormat::Graphite => format!("{prefix}.{name};site={site}:{value}|{metric_type}"),
+    }
+}
+
+/// Turns a URL into a value safe for every tag dialect, e.g. `example.com/status`.
+fn site_tag_value(url: &str) -> String {
+    let without_scheme = url.split_once("://").map_or(url, |(_, rest)| rest);
+
+    without_scheme
+        .trim_end_matches('/')
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | '/') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Joins metric lines with newlines into as few datagrams as the size limit allows.
+fn pack_datagrams(lines: &[String]) -> Vec<String> {
+    let mut datagrams = Vec::new();
+    let mut current = String::new();
+
+    for line in lines {
+        if !current.is_empty() && current.len() + 1 + line.len() > MAX_DATAGRAM_BYTES {
+            datagrams.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push('\n');
+        }
+        current.push_str(line);
+    }
+
+    if !current.is_empty() {
+        datagrams.push(current);
+    }
+    datagrams
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::check_record;
+    use std::time::Duration;
+
+    fn options(tag_format: StatsdTagFormat) -> StatsdOptions {
+        StatsdOptions {
+            host: "127.0.0.1".to_string(),
+            port: 8125,
+            prefix: "downdetector".to_string(),
+            tag_format,
+        }
+    }
+
+    fn record() -> CheckRecord {
+        CheckRecord {
+            response_time: Duration::from_millis(842),
+            consecutive_failures: 2,
+            ..check_record("https://example.com:8443/status?full=1", 0, false)
+        }
+    }
+
+    #[test]
+    fn test_site_tag_value_is_sanitized() {
+        assert_eq!(
+            site_tag_value("https://example.com:8443/status?full=1"),
+            "example.com_8443/status_full_1"
+        );
+        assert_eq!(site_tag_value("https://www.google.com/"), "www.google.com");
+    }
+
+    #[test]
+    fn test_metric_lines_per_tag_format() {
+        assert_eq!(
+            metric_lines(&options(StatsdTagFormat::None), &record()),
+            [
+                "downdetector.site.up.example_com_8443_status_full_1:0|g",
+                "downdetector.site.consecutive_failures.example_com_8443_status_full_1:2|g",
+                "downdetector.check.response_time.example_com_8443_status_full_1:842|ms",
+            ]
+        );
+        assert_eq!(
+            metric_lines(&options(StatsdTagFormat::DogStatsd), &record())[0],
+            "downdetector.site.up:0|g|#site:example.com_8443/status_full_1"
+        );
+        assert_eq!(
+            metric_lines(&options(StatsdTagFormat::Influxdb), &record())[2],
+            "downdetector.check.response_time,site=example.com_8443/status_full_1:842|ms"
+        );
+        assert_eq!(
+            metric_lines(&options(StatsdTagFormat::Graphite), &record())[1],
+            "downdetector.site.consecutive_failures;site=example.com_8443/status_full_1:2|g"
+        );
+    }
+
+    #[test]
+    fn test_pack_datagrams_respects_size_limit() {
+        let lines: Vec<String> = (0..100)
+            .map(|i| format!("downdetector.site.up.site_{i:03}:1|g"))
+            .collect();
+
+        let datagrams = pack_datagrams(&lines);
+
+        assert!(datagrams.len() > 1);
+        assert!(datagrams.iter().all(|d| d.len() <= MAX_DATAGRAM_BYTES));
+        assert_eq!(datagrams.join("\n").lines().count(), lines.len());
+    }
+
+    #[tokio::test]
+    async fn test_send_checks_over_udp() {
+        let agent = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let mut options = options(StatsdTagFormat::DogStatsd);
+        options.port = agent.local_addr().unwrap().port();
+
+        send_checks(&options, &[record()]).await.unwrap();
+
+        let mut buf = [0_u8; MAX_DATAGRAM_BYTES];
+        let len = agent.recv(&mut buf).await.unwrap();
+        let datagram = std::str::from_utf8(&buf[..len]).unwrap();
+        assert_eq!(datagram.lines().count(), 3);
+        assert!(datagram.starts_with("downdetector.site.up:0|g|#site:"));
+    }
+}
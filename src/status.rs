@@ -0,0 +1,369 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    sync::{Arc, PoisonError, RwLock},
+    time::SystemTime,
+};
+
+use tokio::sync::Notify;
+
+use crate::worker::CheckRecord;
+
+/// Health of a site as of its latest check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum SiteHealth {
+    /// Not checked yet since startup.
+    Pending,
+    Up,
+    /// Failing, but not for long enough to be considered down.
+    Unreachable,
+    Down,
+    /// Checked by an agent that stopped reporting.
+    Unknown,
+}
+
+impl SiteHealth {
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            Self::Pending => "PENDING",
+            Self::Up => "UP",
+            Self::Unreachable => "UNREACHABLE",
+            Self::Down => "DOWN",
+            Self::Unknown => "UNKNOWN",
+        }
+    }
+}
+
+/// Latest known status of a monitored site.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SiteStatus {
+    pub(crate) url: String,
+    /// Agent reporting the site, `None` for sites checked by this instance.
+    pub(crate) agent: Option<String>,
+    pub(crate) health: SiteHealth,
+    pub(crate) consecutive_failures: u64,
+    pub(crate) last_checked_at: Option<SystemTime>,
+    pub(crate) last_response_time_ms: Option<u64>,
+    /// Time of the first failed check of the current failure streak.
+    pub(crate) failing_since: Option<SystemTime>,
+    /// Discovery source the site was found by, `None` for configured sites.
+    pub(crate) discovered_by: Option<&'static str>,
+}
+
+impl SiteStatus {
+    fn pending(url: String, agent: Option<String>) -> Self {
+        Self {
+            url,
+            agent,
+            health: SiteHealth::Pending,
+            consecutive_failures: 0,
+            last_checked_at: None,
+            last_response_time_ms: None,
+            failing_since: None,
+            discovered_by: None,
+        }
+    }
+
+    pub(crate) fn id(&self) -> String {
+        site_id(&self.url, self.agent.as_deref())
+    }
+
+    /// URL as shown in logs and notifications, naming the reporting agent if any.
+    pub(crate) fn label(&self) -> String {
+        site_label(&self.url, self.agent.as_deref())
+    }
+
+    fn is_local(&self) -> bool {
+        self.agent.is_none()
+    }
+}
+
+pub(crate) fn site_label(url: &str, agent: Option<&str>) -> String {
+    match agent {
+        Some(agent) => format!("{url} (via {agent})"),
+        None => url.to_string(),
+    }
+}
+
+/// URL of the site named by `label`, a label made by [`site_label`].
+pub(crate) fn site_url(label: &str) -> &str {
+    label.split_once(" (via ").map_or(label, |(url, _)| url)
+}
+
+/// Stable identifier of a site, derived from its URL and reporting agent.
+///
+/// Uses the 64-bit FNV-1a hash so ids survive restarts and config reordering.
+pub(crate) fn site_id(url: &str, agent: Option<&str>) -> String {
+    const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    // The agent name is kept apart from the URL by a NUL byte, which neither can contain
+    let bytes = agent
+        .into_iter()
+        .flat_map(|agent| agent.bytes().chain([0]))
+        .chain(url.bytes());
+    let hash = bytes.fold(FNV_OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+    });
+    format!("{hash:016x}")
+}
+
+/// Live view of every monitored site, updated by the worker after each check
+/// and read by the embedded HTTP server.
+///
+/// The board is also the list of monitored sites: sites added or removed
+/// through the API are picked up by the worker on its next loop. Sites
+/// reported by agents appear after their first report.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct StatusBoard {
+    sites: Arc<RwLock<Vec<SiteStatus>>>,
+    changed: Arc<Notify>,
+}
+
+impl StatusBoard {
+    /// Creates a board with every site pending, in configuration order.
+    pub(crate) fn new(urls: &[String]) -> Self {
+        let sites = urls
+            .iter()
+            .map(|url| SiteStatus::pending(url.clone(), None))
+            .collect();
+
+        Self {
+            sites: Arc::new(RwLock::new(sites)),
+            changed: Arc::default(),
+        }
+    }
+
+    /// URLs of every site checked by this instance, in order.
+    pub(crate) fn urls(&self) -> Vec<String> {
+        self.sites
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .filter(|site| site.is_local())
+            .map(|site| site.url.clone())
+            .collect()
+    }
+
+    /// URLs of the sites checked by this instance that were configured
+    /// rather than discovered, in order.
+    pub(crate) fn configured_urls(&self) -> Vec<String> {
+        self.sites
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .filter(|site| site.is_local() && site.discovered_by.is_none())
+            .map(|site| site.url.clone())
+            .collect()
+    }
+
+    /// URLs of the sites found by the discovery `source`.
+    pub(crate) fn discovered_urls(&self, source: &str) -> Vec<String> {
+        self.sites
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .filter(|site| site.discovered_by == Some(source))
+            .map(|site| site.url.clone())
+            .collect()
+    }
+
+    /// Starts monitoring `url`, returning `false` if it already is.
+    pub(crate) fn add_site(&self, url: &str) -> bool {
+        self.insert(url, None)
+    }
+
+    /// Starts monitoring `url` as found by the discovery `source`, returning
+    /// `false` if it already is monitored.
+    pub(crate) fn add_discovered_site(&self, url: &str, source: &'static str) -> bool {
+        self.insert(url, Some(source))
+    }
+
+    fn insert(&self, url: &str, discovered_by: Option<&'static str>) -> bool {
+        let mut sites = self.sites.write().unwrap_or_else(PoisonError::into_inner);
+        if sites.iter().any(|site| site.is_local() && site.url == url) {
+            return false;
+        }
+
+        sites.push(SiteStatus {
+            discovered_by,
+            ..SiteStatus::pending(url.to_string(), None)
+        });
+        drop(sites);
+        self.changed.notify_one();
+        true
+    }
+
+    /// Stops monitoring `url`, returning `false` if it was not monitored.
+    pub(crate) fn remove_site(&self, url: &str) -> bool {
+        self.delete(url, |_| true)
+    }
+
+    /// Stops monitoring `url` if it was found by the discovery `source`,
+    /// returning `false` if it was not.
+    pub(crate) fn remove_discovered_site(&self, url: &str, source: &str) -> bool {
+        self.delete(url, |site| site.discovered_by == Some(source))
+    }
+
+    fn delete(&self, url: &str, filter: impl Fn(&SiteStatus) -> bool) -> bool {
+        let mut sites = self.sites.write().unwrap_or_else(PoisonError::into_inner);
+        let Some(index) = sites
+            .iter()
+            .position(|site| site.is_local() && site.url == url && filter(site))
+        else {
+            return false;
+        };
+
+        sites.remove(index);
+        drop(sites);
+        self.changed.notify_one();
+        true
+    }
+
+    /// Completes the next time a site is added or removed.
+    pub(crate) async fn sites_changed(&self) {
+        self.changed.notified().await;
+    }
+
+    /// Records the outcome of a check.
+    ///
+    /// Sites reported by an agent for the first time are added to the board.
+    pub(crate) fn record(&self, record: &CheckRecord, health: SiteHealth) {
+        let mut sites = self.sites.write().unwrap_or_else(PoisonError::into_inner);
+
+        let index = sites
+            .iter()
+            .position(|site| site.url == record.url && site.agent == record.agent)
+            .or_else(|| {
+                record.agent.as_ref()?;
+                sites.push(SiteStatus::pending(
+                    record.url.clone(),
+                    record.agent.clone(),
+                ));
+                Some(sites.len() - 1)
+            });
+
+        if let Some(site) = index.map(|index| &mut sites[index]) {
+            site.health = health;
+            site.consecutive_failures = record.consecutive_failures;
+            site.last_checked_at = Some(record.checked_at);
+            site.last_response_time_ms =
+                Some(u64::try_from(record.response_time.as_millis()).unwrap_or(u64::MAX));
+            site.failing_since = if record.is_up {
+                None
+            } else {
+                site.failing_since.or(Some(record.checked_at))
+            };
+        }
+    }
+
+    /// Marks every site reported by `agent` unknown, returning how many there are.
+    pub(crate) fn mark_agent_unknown(&self, agent: &str) -> usize {
+        let mut sites = self.sites.write().unwrap_or_else(PoisonError::into_inner);
+        let mut count = 0;
+        for site in sites
+            .iter_mut()
+            .filter(|site| site.agent.as_deref() == Some(agent))
+        {
+            site.health = SiteHealth::Unknown;
+            count += 1;
+        }
+        count
+    }
+
+    /// Copy of the current status of every site.
+    pub(crate) fn snapshot(&self) -> Vec<SiteStatus> {
+        self.sites
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::check_record;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn test_site_id_is_stable() {
+        assert_eq!(site_id("", None), "cbf29ce484222325");
+        assert_eq!(
+            site_id("https://example.com", None),
+            site_id("https://example.com", None)
+        );
+        assert_ne!(
+            site_id("https://example.com", None),
+            site_id("https://example.org", None)
+        );
+        assert_ne!(
+            site_id("https://example.com", None),
+            site_id("https://example.com", Some("vpc-a"))
+        );
+    }
+
+    #[test]
+    fn test_failing_since_tracks_first_failure() {
+        let board = StatusBoard::new(&["https://a.example".to_string()]);
+
+        board.record(
+            &check_record("https://a.example", 10_000, false),
+            SiteHealth::Unreachable,
+        );
+        board.record(
+            &check_record("https://a.example", 20_000, false),
+            SiteHealth::Down,
+        );
+        let site = &board.snapshot()[0];
+        assert_eq!(site.health, SiteHealth::Down);
+        assert_eq!(
+            site.failing_since,
+            Some(UNIX_EPOCH + Duration::from_secs(10))
+        );
+
+        board.record(
+            &check_record("https://a.example", 30_000, true),
+            SiteHealth::Up,
+        );
+        assert_eq!(board.snapshot()[0].failing_since, None);
+    }
+
+    #[test]
+    fn test_add_and_remove_sites() {
+        let board = StatusBoard::new(&["https://a.example".to_string()]);
+
+        assert!(board.add_site("https://b.example"));
+        assert!(!board.add_site("https://a.example"));
+        assert_eq!(board.urls(), ["https://a.example", "https://b.example"]);
+
+        assert!(board.remove_site("https://a.example"));
+        assert!(!board.remove_site("https://a.example"));
+        assert_eq!(board.urls(), ["https://b.example"]);
+        assert_eq!(board.snapshot()[0].health, SiteHealth::Pending);
+    }
+
+    #[test]
+    fn test_agent_sites_are_added_on_first_report() {
+        let board = StatusBoard::new(&["https://a.example".to_string()]);
+        let mut remote = check_record("https://a.example", 10_000, false);
+        remote.agent = Some("vpc-a".to_string());
+
+        board.record(&remote, SiteHealth::Unreachable);
+        board.record(&remote, SiteHealth::Unreachable);
+
+        let sites = board.snapshot();
+        assert_eq!(sites.len(), 2);
+        assert_eq!(sites[0].health, SiteHealth::Pending);
+        assert_eq!(sites[1].label(), "https://a.example (via vpc-a)");
+        assert_eq!(site_url(&sites[1].label()), "https://a.example");
+        assert_eq!(board.urls(), ["https://a.example"]);
+        assert!(!board.remove_site("https://b.example"));
+
+        assert_eq!(board.mark_agent_unknown("vpc-a"), 1);
+        assert_eq!(board.mark_agent_unknown("vpc-b"), 0);
+        let sites = board.snapshot();
+        assert_eq!(sites[0].health, SiteHealth::Pending);
+        assert_eq!(sites[1].health, SiteHealth::Unknown);
+    }
+}
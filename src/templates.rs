@@ -0,0 +1,259 @@
+use std::collections::HashMap;
+
+use crate::config::Language;
+use crate::locale::phrases;
+use crate::status::site_url;
+
+/// Variables of the DOWN alert template.
+pub(crate) const DOWN_VARIABLES: &[&str] = &[
+    "site.url",
+    "site.name",
+    "severity",
+    "incident.id",
+    "consecutive_failures",
+    "latency_ms",
+];
+/// Variables of the recovery template.
+pub(crate) const RECOVERY_VARIABLES: &[&str] = &[
+    "site.url",
+    "site.name",
+    "severity",
+    "incident.id",
+    "duration",
+    "acknowledged_by",
+];
+/// Variables of the degraded alert template.
+pub(crate) const DEGRADED_VARIABLES: &[&str] = &["site.url", "site.name", "severity", "reason"];
+/// Variables of the quiet hours digest template.
+pub(crate) const DIGEST_VARIABLES: &[&str] = &["start", "end", "held_count"];
+
+/// Templates of the notification messages, the built-in phrasing in the
+/// configured language unless overridden in the `[templates]` section.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MessageTemplates {
+    /// First DOWN alert of an incident.
+    pub down: Template,
+    /// Replaces the DOWN alert once the site is back up.
+    pub recovery: Template,
+    /// First line of the summary sent when quiet hours end.
+    pub digest: Template,
+    /// Alert sent when a site is up but its response body is out of bounds.
+    pub degraded: Template,
+}
+
+impl MessageTemplates {
+    /// Built-in templates in `language`.
+    pub fn builtin(language: Language) -> Self {
+        let phrases = phrases(language);
+        let builtin = |source, variables| {
+            Template::parse(source, variables).expect("Built-in templates are valid")
+        };
+        Self {
+            down: builtin(phrases.down, DOWN_VARIABLES),
+            recovery: builtin(phrases.recovery, RECOVERY_VARIABLES),
+            digest: builtin(phrases.digest, DIGEST_VARIABLES),
+            degraded: builtin(phrases.degraded, DEGRADED_VARIABLES),
+        }
+    }
+}
+
+impl Default for MessageTemplates {
+    fn default() -> Self {
+        Self::builtin(Language::default())
+    }
+}
+
+/// A message with `{{variable}}` placeholders and `{{#if variable}}...{{/if}}`
+/// sections, shown only when the variable is set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+    Text(String),
+    Variable(String),
+    If(String, Vec<Node>),
+}
+
+/// Values of the variables a template is rendered with, unset ones render empty.
+#[derive(Debug, Default)]
+pub(crate) struct Variables(HashMap<&'static str, String>);
+
+impl Variables {
+    pub(crate) fn set(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.0.insert(name, value.into());
+        self
+    }
+
+    /// Sets `name` only when `value` is some.
+    pub(crate) fn set_some(self, name: &'static str, value: Option<impl Into<String>>) -> Self {
+        match value {
+            Some(value) => self.set(name, value),
+            None => self,
+        }
+    }
+
+    fn get(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(String::as_str)
+    }
+}
+
+impl Template {
+    /// Parses `source`, which may only use the given `variables`.
+    pub fn parse(source: &str, variables: &[&str]) -> Result<Self, String> {
+        Self::parse_with(source, Some(variables))
+    }
+
+    /// Parses a built-in phrase, whose variables are not checked.
+    pub(crate) fn parse_builtin(source: &str) -> Result<Self, String> {
+        Self::parse_with(source, None)
+    }
+
+    fn parse_with(source: &str, variables: Option<&[&str]>) -> Result<Self, String> {
+        // Sections opened so far, with the nodes preceding each of them
+        let mut open: Vec<(String, Vec<Node>)> = Vec::new();
+        let mut nodes = Vec::new();
+        let mut rest = source;
+
+        while let Some(start) = rest.find("{{") {
+            if start > 0 {
+                nodes.push(Node::Text(rest[..start].to_string()));
+            }
+            let end = rest[start..]
+                .find("}}")
+                .ok_or_else(|| format!("Unclosed {{{{ in template: {source}"))?;
+            let tag = rest[start + 2..start + end].trim();
+            rest = &rest[start + end + 2..];
+
+            let known = |name: &str| match variables {
+                Some(variables) if !variables.contains(&name) => Err(format!(
+                    "Unknown template variable {name}, expected one of: {}",
+                    variables.join(", ")
+                )),
+                _ => Ok(name.to_string()),
+            };
+            if let Some(name) = tag.strip_prefix("#if ") {
+                open.push((known(name.trim())?, std::mem::take(&mut nodes)));
+            } else if tag == "/if" {
+                let (name, before) = open.pop().ok_or_else(|| {
+                    format!("{{{{/if}}}} without {{{{#if}}}} in template: {source}")
+                })?;
+                let section = std::mem::replace(&mut nodes, before);
+                nodes.push(Node::If(name, section));
+            } else {
+                nodes.push(Node::Variable(known(tag)?));
+            }
+        }
+        if !rest.is_empty() {
+            nodes.push(Node::Text(rest.to_string()));
+        }
+
+        if open.is_empty() {
+            Ok(Self { nodes })
+        } else {
+            Err(format!(
+                "{{{{#if}}}} without {{{{/if}}}} in template: {source}"
+            ))
+        }
+    }
+
+    pub(crate) fn render(&self, variables: &Variables) -> String {
+        let mut output = String::new();
+        render_nodes(&self.nodes, variables, &mut output);
+        output
+    }
+}
+
+/// Renders the built-in phrase `source`, see [`crate::locale::Phrases`].
+pub(crate) fn render_phrase(source: &str, variables: &Variables) -> String {
+    Template::parse_builtin(source)
+        .expect("Built-in phrases are valid")
+        .render(variables)
+}
+
+fn render_nodes(nodes: &[Node], variables: &Variables, output: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => output.push_str(text),
+            Node::Variable(name) => output.push_str(variables.get(name).unwrap_or_default()),
+            Node::If(name, section) => {
+                if variables.get(name).is_some_and(|value| !value.is_empty()) {
+                    render_nodes(section, variables, output);
+                }
+            }
+        }
+    }
+}
+
+/// Short name of a site for templates, the host of its URL.
+pub(crate) fn site_name(site: &str) -> String {
+    url::Url::parse(site_url(site))
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or_else(|| site.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_templates_render() {
+        let templates = MessageTemplates::default();
+        let recovery = |acknowledged_by: Option<&str>| {
+            templates.recovery.render(
+                &Variables::default()
+                    .set("site.url", "https://a.example")
+                    .set("duration", "14m 32s")
+                    .set("incident.id", "87")
+                    .set_some("acknowledged_by", acknowledged_by),
+            )
+        };
+
+        assert_eq!(
+            recovery(None),
+            "Resolved: https://a.example is back UP, down for 14m 32s (incident #87)"
+        );
+        assert_eq!(
+            recovery(Some("alice")),
+            "Resolved: https://a.example is back UP, down for 14m 32s (incident #87), acknowledged by alice"
+        );
+    }
+
+    #[test]
+    fn test_builtin_templates_are_localized() {
+        let down = |language| {
+            MessageTemplates::builtin(language)
+                .down
+                .render(&Variables::default().set("site.url", "https://a.example"))
+        };
+
+        assert_eq!(down(Language::En), "Alert: https://a.example is DOWN!");
+        assert_eq!(
+            down(Language::Fr),
+            "Alerte : https://a.example est HORS LIGNE !"
+        );
+        assert_eq!(down(Language::De), "Alarm: https://a.example ist DOWN!");
+        assert_eq!(down(Language::Es), "Alerta: ¡https://a.example está CAÍDO!");
+    }
+
+    #[test]
+    fn test_invalid_templates() {
+        assert!(
+            Template::parse("{{ site.url }} is down", DOWN_VARIABLES).is_ok(),
+            "Spaces inside braces are allowed"
+        );
+        assert!(Template::parse("{{site.url", DOWN_VARIABLES).is_err());
+        assert!(Template::parse("{{duration}}", DOWN_VARIABLES).is_err());
+        assert!(Template::parse("{{#if acknowledged_by}}", RECOVERY_VARIABLES).is_err());
+        assert!(Template::parse("{{/if}}", RECOVERY_VARIABLES).is_err());
+    }
+
+    #[test]
+    fn test_site_name() {
+        assert_eq!(site_name("https://a.example/health"), "a.example");
+        assert_eq!(site_name("https://a.example (via paris)"), "a.example");
+    }
+}
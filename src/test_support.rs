@@ -0,0 +1,101 @@
+//! Fixtures shared by the unit tests of several modules.
+
+use std::{
+    fs,
+    path::PathBuf,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+
+use crate::config::{ConfigOptions, HistoryOptions, Language, NotificationCheck, SuccessStatuses};
+use crate::history::HistoryStore;
+use crate::worker::CheckRecord;
+
+/// Empty directory for the test `name`, cleared of what a previous run left.
+pub(crate) fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("downdetector-test-{}-{name}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// History store in the empty directory of the test `name`.
+pub(crate) fn temp_history(name: &str, retention_days: u64) -> HistoryStore {
+    HistoryStore::new(&HistoryOptions {
+        path: temp_dir(name).join("history.jsonl"),
+        retention_days,
+    })
+}
+
+/// Check of `url` made by this instance at `checked_at_ms`, in its first
+/// failure when down.
+pub(crate) fn check_record(url: &str, checked_at_ms: u64, is_up: bool) -> CheckRecord {
+    CheckRecord {
+        url: url.to_string(),
+        agent: None,
+        checked_at: UNIX_EPOCH + Duration::from_millis(checked_at_ms),
+        is_up,
+        response_time: Duration::from_millis(120),
+        consecutive_failures: u64::from(!is_up),
+    }
+}
+
+/// Check options with a 5 second timeout and a failure threshold of 5.
+pub(crate) fn check_options(check_interval_secs: u64) -> ConfigOptions {
+    ConfigOptions {
+        timeout_secs: 5,
+        connect_timeout_secs: None,
+        read_timeout_secs: None,
+        check_interval_secs,
+        failure_threshold: 5,
+        low_bandwidth: false,
+        low_memory: false,
+        webhook_url: None,
+        discord_id: None,
+        success_status_classes: SuccessStatuses::default(),
+        down_backoff_max_secs: None,
+        language: Language::default(),
+        notification_check: NotificationCheck::default(),
+        group_by_host: false,
+    }
+}
+
+/// Serves a single connection with `response`, returning the base URL.
+pub(crate) async fn spawn_test_http_server(response: &'static str) -> String {
+    spawn_test_http_server_sequence(vec![response]).await
+}
+
+/// Serves one connection per response, in order, returning the base URL.
+pub(crate) async fn spawn_test_http_server_sequence(responses: Vec<&'static str>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("Failed to bind local test server");
+    let addr = listener
+        .local_addr()
+        .expect("Failed to read local test server address");
+
+    tokio::spawn(async move {
+        for response in responses {
+            let (mut stream, _) = listener
+                .accept()
+                .await
+                .expect("Failed to accept local test connection");
+            let mut request_buf = [0_u8; 1024];
+            let _ = stream.read(&mut request_buf).await;
+            stream
+                .write_all(response.as_bytes())
+                .await
+                .expect("Failed to write local test response");
+            stream
+                .shutdown()
+                .await
+                .expect("Failed to close local test response stream");
+        }
+    });
+
+    format!("http://{addr}")
+}
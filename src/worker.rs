@@ -1,17 +1,66 @@
 use log::{error, info, warn};
-use reqwest::Client;
-use serde::Serialize;
+use reqwest::{
+    Client, Proxy, RequestBuilder, Response, StatusCode,
+    header::{LOCATION, RANGE},
+    redirect,
+    tls::TlsInfo,
+};
 use std::{
-    collections::HashMap,
-    time::{Duration, Instant},
+    collections::{HashMap, HashSet},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant, SystemTime},
+};
+use tokio::{
+    net::TcpStream,
+    select,
+    sync::mpsc::{self, Receiver},
+    task::JoinHandle,
+    time::{sleep, timeout},
 };
-use tokio::{select, time::sleep};
 use tokio_util::sync::CancellationToken;
+use url::Url;
 
-use crate::config::Config;
+use crate::agent::{AgentLiveness, AgentReport};
+use crate::capture::{CaptureStore, FailedResponse};
+use crate::config::{
+    BodySizeBounds, BodySizeMismatch, Config, ConfigOptions, DiagnosticsOptions, LatencyOptions,
+    SiteList, SuccessStatuses, Timeouts,
+};
+use crate::confirm::Confirmer;
+use crate::cycle_summary;
+use crate::dashboard::format_duration;
+use crate::diagnostics::diagnose;
+use crate::discovery;
+#[cfg(unix)]
+use crate::docker;
 use crate::error::Error;
-
-const FAILED_SITE_RETRY_SECS: u64 = 10;
+use crate::export::Exporters;
+use crate::history::{HistoryStore, unix_millis};
+#[cfg(feature = "k8s")]
+use crate::kubernetes;
+use crate::latency::{LatencyCheck, LatencyWindow};
+use crate::locale::Phrases;
+use crate::notify::{DownAlert, Notifier};
+use crate::pinning::{self, Fingerprint, PinCheck, check_pin, format_fingerprint};
+use crate::quiet_hours;
+use crate::rate_limit::RateLimiter;
+use crate::server;
+use crate::slo::{self, SloTargets};
+use crate::status::{SiteHealth, StatusBoard, site_label};
+use crate::templates::{Variables, render_phrase, site_name};
+
+pub(crate) const FAILED_SITE_RETRY_SECS: u64 = 10;
+const LOW_BANDWIDTH_INTERVAL_MULTIPLIER: u64 = 4;
+const LOW_BANDWIDTH_MAX_BODY_BYTES: u64 = 1024;
+const LOW_MEMORY_MAX_BUFFERED_RECORDS: usize = 16;
+/// Redirects followed by a check, as many as reqwest follows by default.
+const MAX_REDIRECTS: usize = 10;
+const AGENT_REPORT_QUEUE: usize = 64;
+/// Time given on shutdown to the check in flight and to the last alerts.
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(5);
 
 /// Continuously monitors configured websites and reports their status.
 ///
@@ -20,58 +69,108 @@ const FAILED_SITE_RETRY_SECS: u64 = 10;
 ///
 /// # Behavior
 ///
-/// - Loads configuration from the default config file location
 /// - Checks each configured URL for availability
 /// - Tracks consecutive failures per site to reduce false positives
 /// - Logs the status of each site (UP/UNREACHABLE/DOWN)
 /// - Sends Discord webhook notifications for sites that stay down long enough
+/// - Exports each cycle's check results to InfluxDB, StatsD and OpenTelemetry when configured
 /// - Retries unreachable sites every 10 seconds until they recover
 /// - Sleeps until the next site-specific check is due
+/// - Switches low-bandwidth mode on or off when receiving `SIGUSR1` (Unix only)
+/// - Keeps export buffers and HTTP decoding state small in low-memory mode
+/// - Serves a status dashboard and JSON API over HTTP when the server is configured
+/// - Pushes check results to a central instance instead of notifying in agent mode
+/// - Tracks and alerts on the results pushed by agents when acting as a central instance
+/// - Re-checks sites through a proxy or probe instance before alerting when confirmation is configured
+/// - Alerts when a site burns its SLO error budget too fast
+/// - Holds non-critical alerts during quiet hours and sends them as one summary afterwards
+/// - Edits a single Discord message per outage instead of posting every repeat alert
+/// - Posts incident updates and summaries in a thread per incident when configured
+/// - Stops repeating the DOWN alerts of an incident once acknowledged from Discord
+/// - Alerts when an HTTPS site presents another certificate than its pinned one
+/// - Attaches DNS, TCP connect and traceroute diagnostics to new DOWN alerts when configured
+/// - Saves the failing response of every incident to a file when configured
+/// - Monitors the sites of a remote list along with the configured ones when configured
+/// - Monitors the hosts of the Kubernetes Ingresses of the cluster when configured (`k8s` feature)
+/// - Monitors the URLs labeled on the running Docker containers when configured (Unix)
+/// - Paces the checks globally and per host when a rate limit is configured
+/// - On shutdown, handles the agent reports already received, sends the alerts held for
+///   quiet hours and saves the incidents
 ///
 /// # Panics
 ///
-/// Panics if the configuration cannot be loaded at startup.
-pub async fn monitor_websites(token: CancellationToken) {
-    let config = Config::load().expect("Failed to load configuration");
-    let now = Instant::now();
-    let mut site_states: HashMap<String, SiteState> = config
-        .sites
-        .urls
-        .iter()
-        .cloned()
-        .map(|url| (url, SiteState::new(now)))
-        .collect();
+/// Panics if the HTTP clients cannot be initialized at startup.
+pub async fn monitor_websites(config: Config, token: CancellationToken) {
+    let clients = CheckClients::new(&config).expect("Failed to build HTTP client");
+    let confirmer = config.confirmation.as_ref().map(|confirmation| {
+        Confirmer::new(confirmation, &config.config)
+            .expect("Failed to build confirmation HTTP client")
+    });
+    let captures = capture_store(&config);
+    let rate_limiter = config
+        .rate_limit
+        .as_ref()
+        .map(|options| Arc::new(RateLimiter::new(options)));
+    let exporters = Exporters::from_config(&config).expect("Failed to build export HTTP client");
+    let board = StatusBoard::new(&config.sites.urls);
+    let mut site_states: HashMap<String, SiteState> = HashMap::new();
+    let mut remote = RemoteSites::default();
+    let low_bandwidth = Arc::new(AtomicBool::new(config.config.low_bandwidth));
+
+    #[cfg(unix)]
+    spawn_low_bandwidth_toggle(Arc::clone(&low_bandwidth), token.clone());
+
+    log_startup_configuration(&config);
+    exporters.log_enabled();
+
+    let notifier = Arc::new(Notifier::from_config(&config));
+    let summary_task = if config.agent.is_none() {
+        spawn_alerting_tasks(&config, &board, &notifier, &token)
+    } else {
+        None
+    };
 
-    // Intial Configuration Logging
-    info!("Starting website monitoring...");
-    info!(
-        "Check interval: {} seconds",
-        config.config.check_interval_secs
+    // Agent reports are only accepted when acting as a central instance
+    let agent_timeout = agent_timeout(&config);
+    let (report_sender, mut agent_reports) = agent_timeout
+        .map(|_| mpsc::channel(AGENT_REPORT_QUEUE))
+        .unzip();
+    spawn_discovery_sources(&config, &board, &token);
+    server::spawn(
+        &config,
+        board.clone(),
+        Arc::clone(&notifier),
+        report_sender,
+        rate_limiter.clone(),
+        token.clone(),
     );
-    info!("Failed site retry interval: {FAILED_SITE_RETRY_SECS} seconds");
-    info!("Timeout: {} seconds", config.config.timeout_secs);
-    info!(
-        "Failure threshold: {} consecutive failed checks",
-        config.config.failure_threshold
-    );
-    match (
-        config.config.webhook_url.is_some(),
-        config.config.discord_id.is_some(),
-    ) {
-        (true, true) => {
-            info!("Webhook is set, a notification will be sent on failure");
-            info!("Discord ID is set, notifications will be tagged for the user");
-        }
-        (true, false) => {
-            info!("Webhook is set, a notification will be sent on failure");
-            warn!("Discord ID is not set, notifications will not tag any user");
-        }
-        (false, _) => warn!("Webhook is not set, no notifications will be sent"),
-    }
-    info!("Monitoring {} websites", config.sites.urls.len());
 
     // Main monitoring loop
-    'monitor: loop {
+    let mut idle = false;
+    loop {
+        let ctx = CheckContext {
+            clients: &clients,
+            options: &config.config,
+            sites: &config.sites,
+            board: &board,
+            low_bandwidth: low_bandwidth.load(Ordering::Relaxed),
+            notify: config.agent.is_none(),
+            confirmer: confirmer.as_ref(),
+            diagnostics: config.diagnostics.as_ref(),
+            captures: captures.as_ref(),
+            rate_limiter: rate_limiter.as_deref(),
+            latency: &config.latency,
+            notifier: &notifier,
+        };
+
+        // Reports received while checking are handled before the next cycle
+        if let Some(reports) = &mut agent_reports {
+            while let Ok(report) = reports.try_recv() {
+                process_agent_report(&ctx, report, &mut remote, &exporters).await;
+            }
+        }
+        mark_stale_agents(&ctx, &mut remote.liveness, agent_timeout).await;
+
         // Check if we should shutdown before starting new cycle
         if token.is_cancelled() {
             info!("Shutdown requested, stopping monitor");
@@ -79,70 +178,519 @@ pub async fn monitor_websites(token: CancellationToken) {
         }
 
         let now = Instant::now();
-        let due_urls: Vec<&str> = config
-            .sites
-            .urls
+        let urls = board.urls();
+        idle = log_idle(idle, &urls);
+        sync_site_states(&mut site_states, &urls, now);
+        let due_urls: Vec<&str> = urls
             .iter()
-            .filter_map(|url| {
-                site_states
-                    .get(url.as_str())
-                    .filter(|state| state.is_due(now))
-                    .map(|_| url.as_str())
-            })
+            .filter(|url| site_states[url.as_str()].is_due(now))
+            .map(String::as_str)
             .collect();
 
         if due_urls.is_empty() {
             let sleep_duration =
                 next_sleep_duration(site_states.values(), config.config.check_interval_secs, now);
+            // Wakes up in time to notice an agent going stale
+            let sleep_duration = agent_timeout
+                .and_then(|timeout| remote.liveness.next_timeout(timeout, now))
+                .map_or(sleep_duration, |timeout| timeout.min(sleep_duration));
 
             select! {
                 () = sleep(sleep_duration) => {},
-                () = token.cancelled() => {
-                    info!("Shutdown requested during sleep");
-                    break;
-                }
+                () = board.sites_changed() => {},
+                Some(report) = next_agent_report(&mut agent_reports) => {
+                    process_agent_report(&ctx, report, &mut remote, &exporters).await;
+                },
+                // Stops at the top of the loop, once queued reports are handled
+                () = token.cancelled() => {}
             }
 
             continue;
         }
 
-        info!("Checking {} website(s)...", due_urls.len());
+        run_check_cycle(&ctx, &due_urls, &mut site_states, &exporters, &token).await;
+    }
+
+    // Cleanup and shutdown
+    if let Some(task) = summary_task
+        && tokio::time::timeout(SHUTDOWN_GRACE, task).await.is_err()
+    {
+        warn!("Gave up sending the alerts held for quiet hours on shutdown");
+    }
+    notifier.save_incidents().await;
+    info!("Website monitoring stopped gracefully");
+}
+
+/// Logs when the monitor runs out of sites to check, returning whether it is
+/// idle now.
+fn log_idle(was_idle: bool, urls: &[String]) -> bool {
+    let idle = urls.is_empty();
+    if idle && !was_idle {
+        info!("No sites to monitor, idling until sites are added or discovered");
+    }
+    idle
+}
 
-        for url in due_urls {
-            if token.is_cancelled() {
-                info!("Shutdown requested, stopping monitor");
-                break 'monitor;
+/// Starts the background tasks adding the sites found by the discovery
+/// sources to `board`, when configured.
+fn spawn_discovery_sources(config: &Config, board: &StatusBoard, token: &CancellationToken) {
+    if let Some(source) = &config.sites.source {
+        discovery::spawn_remote_source(
+            source.clone(),
+            Duration::from_secs(config.config.timeout_secs),
+            board.clone(),
+            token.clone(),
+        );
+    }
+    #[cfg(feature = "k8s")]
+    if let Some(options) = &config.kubernetes {
+        kubernetes::spawn_discovery(options.clone(), board.clone(), token.clone());
+    }
+    #[cfg(unix)]
+    if let Some(options) = &config.docker {
+        docker::spawn_discovery(options.clone(), board.clone(), token.clone());
+    }
+}
+
+/// Starts the background tasks alerting on SLO burn rates and sending the
+/// quiet hours summaries, when configured. Returns the summary task, which
+/// sends the alerts still held once `token` is cancelled.
+fn spawn_alerting_tasks(
+    config: &Config,
+    board: &StatusBoard,
+    notifier: &Arc<Notifier>,
+    token: &CancellationToken,
+) -> Option<JoinHandle<()>> {
+    let slo = SloTargets::from_config(config).zip(
+        config
+            .history
+            .as_ref()
+            .map(|history| Arc::new(HistoryStore::new(history))),
+    );
+
+    if let Some((targets, store)) = &slo {
+        slo::spawn_monitor(
+            targets.clone(),
+            Arc::clone(store),
+            Arc::clone(notifier),
+            token.clone(),
+        );
+    }
+    notifier.quiet_hours().is_some().then(|| {
+        quiet_hours::spawn_summary(Arc::clone(notifier), board.clone(), slo, token.clone())
+    })
+}
+
+/// Checks every due site once, then exports the results.
+async fn run_check_cycle(
+    ctx: &CheckContext<'_>,
+    due_urls: &[&str],
+    site_states: &mut HashMap<String, SiteState>,
+    exporters: &Exporters,
+    token: &CancellationToken,
+) {
+    info!("Checking {} website(s)...", due_urls.len());
+
+    let cycle_started_at = SystemTime::now();
+    let mut cycle_records = Vec::with_capacity(due_urls.len());
+    let mut host_checks = HostChecks::new(ctx.options, due_urls);
+    for &url in due_urls {
+        // Stop checking but still export what this cycle already gathered
+        if token.is_cancelled() {
+            break;
+        }
+
+        let site_state = site_states
+            .get_mut(url)
+            .expect("Site state missing for monitored URL");
+
+        // A check in flight at shutdown gets a moment to finish, then is dropped
+        let result = select! {
+            result = async {
+                let unreachable_host = host_checks.unreachable_host(url, ctx.rate_limiter).await;
+                monitor_website_status(ctx, url, site_state, &mut cycle_records, unreachable_host).await
+            } => result,
+            () = shutdown_grace(token) => {
+                warn!("Shutdown requested, abandoning the check of {url}");
+                break;
             }
+        };
+        if let Err(e) = result {
+            error!("Error checking {url}: {e}");
+        }
 
-            let site_state = site_states
-                .get_mut(url)
-                .expect("Site state missing for configured URL");
-
-            if let Err(e) = monitor_website_status(
-                url,
-                config.config.timeout_secs,
-                config.config.check_interval_secs,
-                config.config.failure_threshold,
-                site_state,
-                config.config.discord_id.as_ref(),
-                config.config.webhook_url.as_ref(),
-            )
-            .await
-            {
-                error!("Error checking {url}: {e}");
+        if flush_due(ctx.options, cycle_records.len()) {
+            exporters.export(cycle_started_at, &cycle_records).await;
+            cycle_records.clear();
+        }
+    }
+
+    exporters.export(cycle_started_at, &cycle_records).await;
+    if ctx.notify {
+        ctx.notifier
+            .send_cycle_summaries(&ctx.board.snapshot())
+            .await;
+    }
+}
+
+/// Whether the check results buffered during a cycle are exported right away,
+/// rather than holding a whole cycle of results in memory in low-memory mode.
+fn flush_due(options: &ConfigOptions, buffered: usize) -> bool {
+    options.low_memory && buffered >= LOW_MEMORY_MAX_BUFFERED_RECORDS
+}
+
+/// Connection checks of the hosts shared by several URLs of a cycle, each
+/// done once before the first of their URLs is requested.
+#[derive(Debug, Default)]
+struct HostChecks {
+    /// Whether each shared host and port accepted a connection, `None`
+    /// until checked.
+    hosts: HashMap<(String, u16), Option<bool>>,
+    timeout: Duration,
+}
+
+impl HostChecks {
+    /// Checks nothing unless `group_by_host` is enabled.
+    fn new(options: &ConfigOptions, due_urls: &[&str]) -> Self {
+        if !options.group_by_host {
+            return Self::default();
+        }
+        let mut counts: HashMap<(String, u16), usize> = HashMap::new();
+        for key in due_urls.iter().filter_map(|url| host_key(url)) {
+            *counts.entry(key).or_default() += 1;
+        }
+        let timeouts = options.timeouts();
+        Self {
+            hosts: counts
+                .into_iter()
+                .filter(|(_, count)| *count > 1)
+                .map(|(key, _)| (key, None))
+                .collect(),
+            timeout: Duration::from_secs(timeouts.connect.unwrap_or(timeouts.total)),
+        }
+    }
+
+    /// Host of `url` when it is shared with other URLs of the cycle and
+    /// accepts no connection. The connection waits for `rate_limiter`.
+    async fn unreachable_host(
+        &mut self,
+        url: &str,
+        rate_limiter: Option<&RateLimiter>,
+    ) -> Option<String> {
+        let (host, port) = host_key(url)?;
+        let reachable = self.hosts.get_mut(&(host.clone(), port))?;
+        if reachable.is_none() {
+            RateLimiter::wait_for(rate_limiter, url).await;
+            let connected = matches!(
+                timeout(self.timeout, TcpStream::connect((host.as_str(), port))).await,
+                Ok(Ok(_))
+            );
+            if !connected {
+                warn!(
+                    "{host}:{port}: accepts no connection, failing its URLs without requesting them"
+                );
             }
+            *reachable = Some(connected);
         }
+        (*reachable == Some(false)).then(|| format!("{host}:{port}"))
     }
+}
 
-    // Cleanup and shutdown
-    info!("Website monitoring stopped gracefully");
+/// Host and port `url` connects to.
+fn host_key(url: &str) -> Option<(String, u16)> {
+    let url = Url::parse(url).ok()?;
+    let host = url
+        .host_str()?
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_ascii_lowercase();
+    Some((host, url.port_or_known_default()?))
 }
 
-#[derive(Debug, Clone, Copy)]
+/// Resolves [`SHUTDOWN_GRACE`] after `token` is cancelled.
+async fn shutdown_grace(token: &CancellationToken) {
+    token.cancelled().await;
+    sleep(SHUTDOWN_GRACE).await;
+}
+
+/// State of the sites checked by agents, kept by the central instance.
+#[derive(Debug, Default)]
+struct RemoteSites {
+    /// Failure tracking per agent and site.
+    states: HashMap<(String, String), SiteState>,
+    liveness: AgentLiveness,
+}
+
+/// How long agents may stay silent, `None` when not acting as a central instance.
+fn agent_timeout(config: &Config) -> Option<Duration> {
+    config
+        .server
+        .as_ref()
+        .filter(|server| server.agent_token.is_some())
+        .map(|server| Duration::from_secs(server.agent_timeout_secs))
+}
+
+/// Runs the results pushed by an agent through the same failure tracking and
+/// alerting as local checks, then exports them.
+///
+/// Each agent keeps its own state per site, so a site reachable from one
+/// network but not another is tracked separately for each agent.
+async fn process_agent_report(
+    ctx: &CheckContext<'_>,
+    report: AgentReport,
+    remote: &mut RemoteSites,
+    exporters: &Exporters,
+) {
+    let received_at = SystemTime::now();
+    let now = Instant::now();
+    if remote.liveness.reported(&report.agent, now) {
+        info!("Agent {} is reporting again", report.agent);
+    }
+    let mut records = Vec::with_capacity(report.results.len());
+
+    for result in report.results {
+        let mut record = result.into_record(&report.agent);
+        let site_state = remote
+            .states
+            .entry((report.agent.clone(), record.url.clone()))
+            .or_insert_with(|| SiteState::new(now));
+        let status = apply_check(ctx, &mut record, site_state, now);
+        let label = site_label(&record.url, Some(&report.agent));
+
+        if let Err(e) =
+            report_status(ctx, &label, status, record.response_time, None, None, None).await
+        {
+            error!("Error reporting {label}: {e}");
+        }
+        records.push(record);
+    }
+
+    exporters.export(received_at, &records).await;
+}
+
+/// Marks the sites of the agents silent for `timeout` unknown, alerting once
+/// per agent until it reports again.
+async fn mark_stale_agents(
+    ctx: &CheckContext<'_>,
+    liveness: &mut AgentLiveness,
+    timeout: Option<Duration>,
+) {
+    let Some(timeout) = timeout else {
+        return;
+    };
+    for (agent, silent_for) in liveness.newly_stale(timeout, Instant::now()) {
+        let sites = ctx.board.mark_agent_unknown(&agent);
+        let duration = format_duration(silent_for);
+        warn!(
+            "Agent {agent} has not reported for {duration}, the status of its {sites} sites is unknown"
+        );
+        if !ctx.notify {
+            continue;
+        }
+
+        let message = render_phrase(
+            ctx.notifier.phrases().agent_stale,
+            &Variables::default()
+                .set("agent", agent.as_str())
+                .set("duration", duration)
+                .set("sites", sites.to_string()),
+        );
+        if let Err(e) = ctx.notifier.alert(&agent, &message).await {
+            error!("Error alerting about agent {agent}: {e}");
+        }
+    }
+}
+
+/// Waits for the next agent report, forever when not acting as a central instance.
+async fn next_agent_report(reports: &mut Option<Receiver<AgentReport>>) -> Option<AgentReport> {
+    match reports {
+        Some(reports) => reports.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Logs the timeouts and the pacing of the checks.
+fn log_request_limits(config: &Config) {
+    let options = &config.config;
+    info!("Timeout: {} seconds", options.timeout_secs);
+    if let Some(connect) = options.connect_timeout_secs {
+        info!("Connect timeout: {connect} seconds");
+    }
+    if let Some(read) = options.read_timeout_secs {
+        info!("Read timeout: {read} seconds");
+    }
+    if let Some(rate_limit) = &config.rate_limit {
+        info!("Rate limit: {}", RateLimiter::new(rate_limit).describe());
+    }
+    if options.group_by_host {
+        info!(
+            "Host grouping is enabled, URLs sharing a host are only requested if it accepts a connection"
+        );
+    }
+}
+
+/// Logs the effective configuration once at startup.
+fn log_startup_configuration(config: &Config) {
+    info!("Starting website monitoring...");
+    info!(
+        "Check interval: {} seconds",
+        config.config.check_interval_secs
+    );
+    info!("Failed site retry interval: {FAILED_SITE_RETRY_SECS} seconds");
+    if let Some(max_secs) = config.config.down_backoff_max_secs {
+        info!("Down site backoff: retry interval doubles up to {max_secs} seconds");
+    }
+    log_request_limits(config);
+    if let Some(source) = &config.sites.source {
+        info!(
+            "Fetching sites from {} every {} seconds",
+            source.url, source.refresh_secs
+        );
+    }
+    #[cfg(feature = "k8s")]
+    if let Some(options) = &config.kubernetes {
+        kubernetes::log_enabled(options);
+    }
+    #[cfg(unix)]
+    if let Some(options) = &config.docker {
+        docker::log_enabled(options);
+    }
+    info!(
+        "Failure threshold: {} consecutive failed checks",
+        config.config.failure_threshold
+    );
+    if config.config.low_bandwidth {
+        info!(
+            "Low-bandwidth mode is enabled, intervals are stretched {LOW_BANDWIDTH_INTERVAL_MULTIPLIER}x"
+        );
+    }
+    if config.config.low_memory {
+        info!(
+            "Low-memory mode is enabled, buffering at most {LOW_MEMORY_MAX_BUFFERED_RECORDS} check results, without dashboard graphs or response captures"
+        );
+    }
+    if let Some(agent) = &config.agent {
+        info!(
+            "Agent mode is enabled, pushing results to {} as '{}'; the central instance sends the notifications",
+            agent.central_url, agent.name
+        );
+    } else {
+        log_alerting_configuration(config);
+    }
+    if let Some(server) = config
+        .server
+        .as_ref()
+        .filter(|server| server.agent_token.is_some())
+    {
+        info!(
+            "Central mode is enabled, accepting check results pushed by agents; agents silent for {} seconds are alerted on",
+            server.agent_timeout_secs
+        );
+    }
+    info!("Monitoring {} websites", config.sites.urls.len());
+}
+
+/// Logs how alerts are sent, by instances not leaving them to a central one.
+fn log_alerting_configuration(config: &Config) {
+    log_notification_configuration(&config.config);
+    if let Some(slo) = &config.slo {
+        info!(
+            "SLO reporting is enabled over {} days, alerting from a {}x burn rate",
+            slo.window_days, slo.burn_rate_alert
+        );
+    }
+    if let Some(options) = &config.quiet_hours {
+        info!(
+            "Quiet hours are enabled from {}, non-critical alerts are held until they end",
+            quiet_hours::describe(options)
+        );
+    }
+    if config.incident_threads.is_some() {
+        info!("Incident threads are enabled, incident updates are posted in a thread");
+    }
+    if let Some(confirmation) = &config.confirmation {
+        let vantages: Vec<&str> = [
+            confirmation.proxy.as_ref().map(|_| "a proxy"),
+            confirmation.probe_url.as_ref().map(|_| "a probe instance"),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        info!(
+            "Down confirmation is enabled, sites are re-checked through {} before alerting",
+            vantages.join(" and ")
+        );
+    }
+    if let Some(diagnostics) = &config.diagnostics {
+        info!(
+            "Network diagnostics are enabled{}, attached to the first DOWN alert of an incident",
+            if diagnostics.traceroute {
+                " with traceroute"
+            } else {
+                ""
+            }
+        );
+    }
+    if let Some(capture) = config
+        .capture
+        .as_ref()
+        .filter(|_| !config.config.low_memory)
+    {
+        info!(
+            "Failing responses are captured to {} (first {} KB of the body, kept {} days)",
+            capture.path.display(),
+            capture.max_body_kb,
+            capture.retention_days
+        );
+    }
+    if let Some(summary) = &config.cycle_summary {
+        cycle_summary::log_enabled(summary);
+    }
+    if let Some(p95_ms) = config.latency.p95_ms {
+        info!(
+            "Latency alerts are enabled above a p95 of {p95_ms} ms over the last {} checks",
+            config.latency.window_checks
+        );
+    }
+}
+
+fn log_notification_configuration(options: &ConfigOptions) {
+    match (options.webhook_url.is_some(), options.discord_id.is_some()) {
+        (true, true) => {
+            info!("Webhook is set, a notification will be sent on failure");
+            info!("Discord ID is set, notifications will be tagged for the user");
+        }
+        (true, false) => {
+            info!("Webhook is set, a notification will be sent on failure");
+            warn!("Discord ID is not set, notifications will not tag any user");
+        }
+        (false, _) => warn!("Webhook is not set, no notifications will be sent"),
+    }
+}
+
+/// Result of a single site check, as handed to the exporters.
+#[derive(Debug, Clone)]
+pub(crate) struct CheckRecord {
+    pub(crate) url: String,
+    /// Agent that performed the check, `None` for checks made by this instance.
+    pub(crate) agent: Option<String>,
+    pub(crate) checked_at: SystemTime,
+    pub(crate) is_up: bool,
+    pub(crate) response_time: Duration,
+    pub(crate) consecutive_failures: u64,
+}
+
+#[derive(Debug, Clone)]
 struct SiteState {
     consecutive_failures: u64,
     last_alert_at: Option<Instant>,
     next_check_at: Instant,
+    /// Certificate not matching the site's pin that was last alerted on.
+    alerted_certificate: Option<Fingerprint>,
+    /// Whether the site was alerted on as degraded by its body size.
+    alerted_body_size: bool,
+    /// Response times of the last successful checks, for latency alerts.
+    latencies: LatencyWindow,
 }
 
 impl SiteState {
@@ -151,6 +699,9 @@ impl SiteState {
             consecutive_failures: 0,
             last_alert_at: None,
             next_check_at: now,
+            alerted_certificate: None,
+            alerted_body_size: false,
+            latencies: LatencyWindow::default(),
         }
     }
 
@@ -158,189 +709,993 @@ impl SiteState {
         self.next_check_at <= now
     }
 
-    fn schedule_next_check(&mut self, checked_at: Instant, check_interval_secs: u64) {
-        self.next_check_at = checked_at + self.next_check_delay(check_interval_secs);
+    fn schedule_next_check(
+        &mut self,
+        checked_at: Instant,
+        options: &ConfigOptions,
+        low_bandwidth: bool,
+    ) {
+        self.next_check_at = checked_at + self.next_check_delay(options, low_bandwidth);
     }
 
-    fn next_check_delay(&self, check_interval_secs: u64) -> Duration {
-        if self.consecutive_failures == 0 {
-            Duration::from_secs(check_interval_secs)
+    fn next_check_delay(&self, options: &ConfigOptions, low_bandwidth: bool) -> Duration {
+        let delay_secs = if self.consecutive_failures == 0 {
+            options.check_interval_secs
         } else {
-            Duration::from_secs(FAILED_SITE_RETRY_SECS)
+            match options.down_backoff_max_secs {
+                // Doubles with every failed check since the site went down
+                Some(max_secs) if self.consecutive_failures >= options.failure_threshold => {
+                    let doublings = self.consecutive_failures - options.failure_threshold;
+                    let factor = u32::try_from(doublings)
+                        .ok()
+                        .and_then(|doublings| 1u64.checked_shl(doublings))
+                        .unwrap_or(u64::MAX);
+                    FAILED_SITE_RETRY_SECS
+                        .saturating_mul(factor)
+                        .clamp(FAILED_SITE_RETRY_SECS, max_secs)
+                }
+                _ => FAILED_SITE_RETRY_SECS,
+            }
+        };
+
+        if low_bandwidth {
+            Duration::from_secs(delay_secs.saturating_mul(LOW_BANDWIDTH_INTERVAL_MULTIPLIER))
+        } else {
+            Duration::from_secs(delay_secs)
         }
     }
 }
 
+/// Settings shared by every check of a monitoring loop iteration.
+struct CheckContext<'a> {
+    clients: &'a CheckClients,
+    options: &'a ConfigOptions,
+    sites: &'a SiteList,
+    board: &'a StatusBoard,
+    low_bandwidth: bool,
+    /// Whether alerts are sent, agents leave them to the central instance.
+    notify: bool,
+    /// Second opinion asked before alerting, when confirmation is configured.
+    confirmer: Option<&'a Confirmer>,
+    /// Network diagnostics run when a site goes down, when configured.
+    diagnostics: Option<&'a DiagnosticsOptions>,
+    /// Where the response that made a site go down is saved, when configured.
+    captures: Option<&'a CaptureStore>,
+    /// Paces the checks globally and per host, when configured.
+    rate_limiter: Option<&'a RateLimiter>,
+    /// Response time percentile alerting.
+    latency: &'a LatencyOptions,
+    /// Routes alerts by severity and holds them during quiet hours.
+    notifier: &'a Notifier,
+}
+
+/// Checks `url` and reports its status, failing the check without a request
+/// when its shared `unreachable_host` accepts no connection.
 async fn monitor_website_status(
+    ctx: &CheckContext<'_>,
     url: &str,
-    timeout_secs: u64,
-    check_interval_secs: u64,
-    failure_threshold: u64,
     site_state: &mut SiteState,
-    discord_id: Option<&u64>,
-    webhook_url: Option<&String>,
+    cycle_records: &mut Vec<CheckRecord>,
+    unreachable_host: Option<String>,
 ) -> Result<(), Error> {
+    let options = ctx.options;
+    if unreachable_host.is_none() {
+        RateLimiter::wait_for(ctx.rate_limiter, url).await;
+    }
     let checked_at = Instant::now();
-    let is_up = is_url_up(url, timeout_secs).await?;
+    let checked_at_wall = SystemTime::now();
+    let timeouts = ctx.sites.timeouts(url, options);
+    let body_size = ctx
+        .sites
+        .options
+        .get(url)
+        .and_then(|options| options.body_size);
+    let outcome = if unreachable_host.is_some() {
+        CheckOutcome::default()
+    } else {
+        check_url(
+            PacedClient {
+                client: ctx.clients.for_site(url),
+                rate_limiter: ctx.rate_limiter,
+            },
+            url,
+            ctx.sites
+                .success_statuses(url, &options.success_status_classes),
+            timeouts,
+            ctx.low_bandwidth,
+            ctx.captures.map(CaptureStore::max_body_bytes),
+            body_size.map(BodySizeBounds::read_limit),
+        )
+        .await
+    };
+    if let Some(phase) = outcome.timed_out {
+        warn!("{url}: {}", phase.describe(timeouts));
+    }
+    let violation = body_size
+        .zip(outcome.body_bytes)
+        .and_then(|(bounds, size)| BodySizeViolation::of(bounds, size));
+    let reason = violation.map(|violation| violation.describe(ctx.notifier.phrases()));
+    let degraded = body_size.is_some_and(|bounds| bounds.mismatch == BodySizeMismatch::Degraded);
+    let failure_reason = match unreachable_host {
+        Some(host) => Some(render_phrase(
+            ctx.notifier.phrases().host_unreachable,
+            &Variables::default().set("host", host),
+        )),
+        None => reason.clone().filter(|_| !degraded),
+    };
+    if let Some(reason) = &failure_reason {
+        warn!("{url}: {reason}");
+    }
 
+    let mut record = CheckRecord {
+        url: url.to_string(),
+        agent: None,
+        checked_at: checked_at_wall,
+        is_up: outcome.is_up && failure_reason.is_none(),
+        response_time: checked_at.elapsed(),
+        consecutive_failures: 0,
+    };
+    let status = apply_check(ctx, &mut record, site_state, checked_at);
+    let status = confirm_down(ctx, url, status, site_state).await;
+    site_state.schedule_next_check(checked_at, options, ctx.low_bandwidth);
+    let latency = record.response_time;
+    cycle_records.push(record);
+    let retry_secs = site_state
+        .next_check_delay(options, ctx.low_bandwidth)
+        .as_secs();
+
+    report_status(
+        ctx,
+        url,
+        status,
+        latency,
+        Some(retry_secs),
+        outcome.failed_response.as_ref(),
+        failure_reason.as_deref(),
+    )
+    .await?;
+
+    // Only measured bodies tell whether a degraded site is back in bounds
+    if degraded && outcome.body_bytes.is_some() {
+        check_body_size(ctx, url, reason, site_state).await?;
+    }
+    if outcome.is_up {
+        check_latency(ctx, url, latency, site_state).await?;
+    }
+
+    let pinned = ctx
+        .sites
+        .options
+        .get(url)
+        .and_then(|options| options.pinned_cert_sha256.as_ref());
+    if let Some(pinned) = pinned {
+        check_certificate(ctx, url, pinned, outcome.certificate, site_state).await?;
+    }
+    Ok(())
+}
+
+/// Store of failing responses, when configured and not in low-memory mode,
+/// where holding response bodies does not fit.
+fn capture_store(config: &Config) -> Option<CaptureStore> {
+    let options = config.capture.as_ref()?;
+    if config.config.low_memory {
+        warn!("Failing response capture is disabled in low-memory mode");
+        return None;
+    }
+    Some(CaptureStore::new(options))
+}
+
+/// Saves the response that opened the incident of `url`, failures are logged.
+async fn save_capture(
+    notifier: &Notifier,
+    captures: &CaptureStore,
+    url: &str,
+    response: &FailedResponse,
+) {
+    let Some(incident) = notifier.incident(url) else {
+        return;
+    };
+    match captures.save(incident.id, url, response).await {
+        Ok(path) => info!(
+            "{url}: failing response of incident #{} saved to {}",
+            incident.id,
+            path.display()
+        ),
+        Err(e) => warn!(
+            "{url}: failed to save the failing response of incident #{}: {e}",
+            incident.id
+        ),
+    }
+}
+
+/// Alerts once when a site presents another certificate than its pinned one.
+async fn check_certificate(
+    ctx: &CheckContext<'_>,
+    url: &str,
+    pinned: &Fingerprint,
+    presented: Option<Fingerprint>,
+    site_state: &mut SiteState,
+) -> Result<(), Error> {
+    match check_pin(pinned, presented, &mut site_state.alerted_certificate) {
+        PinCheck::Unchanged => {}
+        PinCheck::Restored => info!("{url}: presents its pinned certificate again"),
+        PinCheck::Mismatch(presented) => {
+            let presented = format_fingerprint(&presented);
+            let pinned = format_fingerprint(pinned);
+            warn!("{url}: certificate SHA-256 {presented} does not match the pinned {pinned}");
+
+            if ctx.notify {
+                let message = render_phrase(
+                    ctx.notifier.phrases().certificate_mismatch,
+                    &Variables::default()
+                        .set("site.url", url)
+                        .set("fingerprint", presented)
+                        .set("pinned", pinned),
+                );
+                ctx.notifier.alert(url, &message).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Alerts once when the body of a site leaving degraded ones up gets out of
+/// its size bounds, described by `reason`, and logs when it is back in them.
+async fn check_body_size(
+    ctx: &CheckContext<'_>,
+    url: &str,
+    reason: Option<String>,
+    site_state: &mut SiteState,
+) -> Result<(), Error> {
+    match (reason, site_state.alerted_body_size) {
+        (Some(reason), false) => {
+            warn!("{url}: DEGRADED, {reason}");
+            site_state.alerted_body_size = true;
+            if ctx.notify {
+                let message = ctx.notifier.templates().degraded.render(
+                    &Variables::default()
+                        .set("site.url", url)
+                        .set("site.name", site_name(url))
+                        .set("severity", ctx.notifier.severity(url).label())
+                        .set("reason", reason),
+                );
+                ctx.notifier.alert(url, &message).await?;
+            }
+        }
+        (None, true) => {
+            info!("{url}: response body size is back within bounds");
+            site_state.alerted_body_size = false;
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Alerts once when the p95 response time of the last successful checks of
+/// `url` goes above its threshold, and logs when it is back under it.
+async fn check_latency(
+    ctx: &CheckContext<'_>,
+    url: &str,
+    latency: Duration,
+    site_state: &mut SiteState,
+) -> Result<(), Error> {
+    let Some(threshold_ms) = ctx.sites.latency_p95_ms(url, ctx.latency.p95_ms) else {
+        return Ok(());
+    };
+    let window_checks = ctx.latency.window_checks;
+    match site_state
+        .latencies
+        .record(latency, window_checks, Duration::from_millis(threshold_ms))
+    {
+        LatencyCheck::Unchanged => {}
+        LatencyCheck::Restored(p95) => info!(
+            "{url}: p95 response time back to {} ms over the last {window_checks} checks",
+            p95.as_millis()
+        ),
+        LatencyCheck::Exceeded(p95) => {
+            warn!(
+                "{url}: p95 response time of {} ms over the last {window_checks} checks exceeds {threshold_ms} ms",
+                p95.as_millis()
+            );
+            if ctx.notify {
+                let message = render_phrase(
+                    ctx.notifier.phrases().latency_alert,
+                    &Variables::default()
+                        .set("site.url", url)
+                        .set("p95_ms", p95.as_millis().to_string())
+                        .set("checks", window_checks.to_string())
+                        .set("threshold_ms", threshold_ms.to_string()),
+                );
+                ctx.notifier.alert(url, &message).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Holds back a DOWN alert when another vantage point still reaches the site.
+///
+/// The alert throttle is reset, so the next failed check asks for confirmation again.
+async fn confirm_down(
+    ctx: &CheckContext<'_>,
+    url: &str,
+    status: SiteCheckStatus,
+    site_state: &mut SiteState,
+) -> SiteCheckStatus {
+    let (
+        SiteCheckStatus::Down {
+            consecutive_failures,
+            should_alert: true,
+        },
+        Some(confirmer),
+        true,
+    ) = (status, ctx.confirmer, ctx.notify)
+    else {
+        return status;
+    };
+
+    match confirmer
+        .up_vantage(
+            url,
+            ctx.sites
+                .success_statuses(url, &ctx.options.success_status_classes),
+            ctx.sites.timeouts(url, ctx.options).total,
+            ctx.low_bandwidth,
+            ctx.rate_limiter,
+        )
+        .await
+    {
+        Some(vantage) => {
+            warn!("{url}: DOWN from here but reachable through {vantage}, not alerting");
+            site_state.last_alert_at = None;
+            SiteCheckStatus::Down {
+                consecutive_failures,
+                should_alert: false,
+            }
+        }
+        None => status,
+    }
+}
+
+/// Updates the failure tracking of a site with a check result and publishes
+/// it on the status board.
+fn apply_check(
+    ctx: &CheckContext<'_>,
+    record: &mut CheckRecord,
+    site_state: &mut SiteState,
+    checked_at: Instant,
+) -> SiteCheckStatus {
     let status = record_site_check(
         site_state,
-        is_up,
-        failure_threshold,
-        check_interval_secs,
+        record.is_up,
+        ctx.options.failure_threshold,
+        ctx.options.check_interval_secs,
         checked_at,
     );
-    site_state.schedule_next_check(checked_at, check_interval_secs);
+    record.consecutive_failures = site_state.consecutive_failures;
+    ctx.board.record(record, status.health());
+    status
+}
 
+/// Logs the status of a site and sends an alert when it is due.
+///
+/// `latency` is how long the check took and `retry_secs` the delay before
+/// the next check, unknown for sites checked by agents. The network path of
+/// those is not diagnosed either, agents reach them from elsewhere.
+/// `failed_response` is the response of a failed check, when captured, and
+/// `reason` why it failed despite a successful status.
+async fn report_status(
+    ctx: &CheckContext<'_>,
+    url: &str,
+    status: SiteCheckStatus,
+    latency: Duration,
+    retry_secs: Option<u64>,
+    failed_response: Option<&FailedResponse>,
+    reason: Option<&str>,
+) -> Result<(), Error> {
     match status {
-        SiteCheckStatus::Up {
-            recovered_after_failures: 0,
-        } => info!("{url}: UP"),
         SiteCheckStatus::Up {
             recovered_after_failures,
-        } => info!(
-            "{url}: UP (recovered after {recovered_after_failures} consecutive failed checks)"
-        ),
+        } => {
+            if recovered_after_failures == 0 {
+                info!("{url}: UP");
+            } else {
+                info!(
+                    "{url}: UP (recovered after {recovered_after_failures} consecutive failed checks)"
+                );
+            }
+            // Also closes incidents left open by a previous run
+            if let Some(incident) = ctx.notifier.resolve(url).await? {
+                info!(
+                    "{url}: incident #{} resolved after {}",
+                    incident.id,
+                    format_duration(incident.duration(unix_millis(SystemTime::now())))
+                );
+            }
+        }
         SiteCheckStatus::Unreachable {
             consecutive_failures,
             failure_threshold,
-        } => warn!(
-            "{url}: UNREACHABLE ({consecutive_failures}/{failure_threshold} consecutive failed checks before alerting; retrying in {FAILED_SITE_RETRY_SECS} seconds)"
-        ),
+        } => match retry_secs {
+            Some(retry_secs) => warn!(
+                "{url}: UNREACHABLE ({consecutive_failures}/{failure_threshold} consecutive failed checks before alerting; retrying in {retry_secs} seconds)"
+            ),
+            None => warn!(
+                "{url}: UNREACHABLE ({consecutive_failures}/{failure_threshold} consecutive failed checks before alerting)"
+            ),
+        },
         SiteCheckStatus::Down {
             consecutive_failures,
             should_alert: true,
         } => {
             warn!("{url}: DOWN ({consecutive_failures} consecutive failed checks)");
 
-            if let Some(webhook) = webhook_url {
-                let message = format!("Alert: {url} is DOWN!");
-                send_discord_notification(webhook, &message, discord_id).await?;
+            if ctx.notify && ctx.notifier.summarizes(url) {
+                info!("{url}: left to the cycle summary, alert not sent");
+            } else if ctx.notify {
+                // Only the transition to DOWN is diagnosed, not repeat alerts
+                let went_down = retry_secs.is_some() && ctx.notifier.incident(url).is_none();
+                let diagnostics = match ctx.diagnostics {
+                    Some(options) if went_down => {
+                        let timeout = Duration::from_secs(ctx.options.timeout_secs);
+                        Some(diagnose(url, options, timeout).await)
+                    }
+                    _ => None,
+                };
+                let alert = DownAlert {
+                    consecutive_failures,
+                    latency,
+                    diagnostics,
+                    reason: reason.map(str::to_string),
+                };
+                ctx.notifier.alert_down(url, &alert).await?;
+
+                if let (Some(captures), Some(response), true) =
+                    (ctx.captures, failed_response, went_down)
+                {
+                    save_capture(ctx.notifier, captures, url, response).await;
+                }
+            }
+        }
+        SiteCheckStatus::Down {
+            consecutive_failures,
+            should_alert: false,
+        } => warn!("{url}: DOWN ({consecutive_failures} consecutive failed checks)"),
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SiteCheckStatus {
+    Up {
+        recovered_after_failures: u64,
+    },
+    Unreachable {
+        consecutive_failures: u64,
+        failure_threshold: u64,
+    },
+    Down {
+        consecutive_failures: u64,
+        should_alert: bool,
+    },
+}
+
+impl SiteCheckStatus {
+    fn health(self) -> SiteHealth {
+        match self {
+            Self::Up { .. } => SiteHealth::Up,
+            Self::Unreachable { .. } => SiteHealth::Unreachable,
+            Self::Down { .. } => SiteHealth::Down,
+        }
+    }
+}
+
+fn record_site_check(
+    site_state: &mut SiteState,
+    is_up: bool,
+    failure_threshold: u64,
+    alert_repeat_interval_secs: u64,
+    checked_at: Instant,
+) -> SiteCheckStatus {
+    if is_up {
+        let recovered_after_failures = site_state.consecutive_failures;
+        site_state.consecutive_failures = 0;
+        site_state.last_alert_at = None;
+
+        return SiteCheckStatus::Up {
+            recovered_after_failures,
+        };
+    }
+
+    site_state.consecutive_failures += 1;
+
+    if site_state.consecutive_failures < failure_threshold {
+        return SiteCheckStatus::Unreachable {
+            consecutive_failures: site_state.consecutive_failures,
+            failure_threshold,
+        };
+    }
+
+    let should_alert = match site_state.last_alert_at {
+        None => true,
+        Some(last_alert_at) => {
+            checked_at.saturating_duration_since(last_alert_at)
+                >= Duration::from_secs(alert_repeat_interval_secs)
+        }
+    };
+
+    if should_alert {
+        site_state.last_alert_at = Some(checked_at);
+    }
+
+    SiteCheckStatus::Down {
+        consecutive_failures: site_state.consecutive_failures,
+        should_alert,
+    }
+}
+
+/// Starts tracking sites added since the last loop and forgets removed ones.
+fn sync_site_states(site_states: &mut HashMap<String, SiteState>, urls: &[String], now: Instant) {
+    let monitored: HashSet<&str> = urls.iter().map(String::as_str).collect();
+    site_states.retain(|url, _| monitored.contains(url.as_str()));
+    for url in urls {
+        site_states
+            .entry(url.clone())
+            .or_insert_with(|| SiteState::new(now));
+    }
+}
+
+fn next_sleep_duration<'a>(
+    site_states: impl Iterator<Item = &'a SiteState>,
+    check_interval_secs: u64,
+    now: Instant,
+) -> Duration {
+    site_states
+        .map(|state| state.next_check_at.saturating_duration_since(now))
+        .min()
+        .unwrap_or_else(|| Duration::from_secs(check_interval_secs))
+}
+
+/// Builds the HTTP client shared by every site check, sending its requests
+/// through `proxy` when set.
+///
+/// Idle connections are never kept, so each check opens a fresh connection
+/// just like a first-time visitor would.
+pub(crate) fn build_check_client(
+    options: &ConfigOptions,
+    proxy: Option<&Url>,
+) -> Result<Client, Error> {
+    build_client(options, proxy, options.timeouts())
+}
+
+/// Builds a check client like [`build_check_client`], with the connect and
+/// read timeouts of `timeouts`.
+fn build_client(
+    options: &ConfigOptions,
+    proxy: Option<&Url>,
+    timeouts: Timeouts,
+) -> Result<Client, Error> {
+    let builder = Client::builder().pool_max_idle_per_host(0);
+    let builder = match timeouts.connect {
+        Some(secs) => builder.connect_timeout(Duration::from_secs(secs)),
+        None => builder,
+    };
+    let builder = match timeouts.read {
+        Some(secs) => builder.read_timeout(Duration::from_secs(secs)),
+        None => builder,
+    };
+    let builder = match proxy {
+        Some(proxy) => builder.proxy(Proxy::all(proxy.as_str())?),
+        None => builder,
+    };
+
+    // Decompression state is the largest per-response allocation, skip it when memory is tight
+    let builder = if options.low_memory {
+        builder.no_gzip()
+    } else {
+        builder
+    };
+
+    // Redirects are followed by the checks themselves, see `send_check`
+    let builder = builder.redirect(redirect::Policy::none());
+
+    // Keeps the leaf certificate of each response for certificate pinning
+    Ok(builder.tls_info(true).build()?)
+}
+
+/// HTTP clients of the site checks.
+///
+/// reqwest only sets connect and read timeouts on a whole client, so the
+/// sites overriding them get a client of their own.
+pub(crate) struct CheckClients {
+    default: Client,
+    /// Clients of the sites with their own connect or read timeout, by URL.
+    sites: HashMap<String, Client>,
+}
+
+impl CheckClients {
+    fn new(config: &Config) -> Result<Self, Error> {
+        let options = &config.config;
+        let mut by_timeouts: HashMap<(Option<u64>, Option<u64>), Client> = HashMap::new();
+        let mut sites = HashMap::new();
+
+        for url in config.sites.options.keys() {
+            let timeouts = config.sites.timeouts(url, options);
+            let key = (timeouts.connect, timeouts.read);
+            if key == (options.connect_timeout_secs, options.read_timeout_secs) {
+                continue;
+            }
+            let client = if let Some(client) = by_timeouts.get(&key) {
+                client.clone()
+            } else {
+                let client = build_client(options, None, timeouts)?;
+                by_timeouts.insert(key, client.clone());
+                client
+            };
+            sites.insert(url.clone(), client);
+        }
+
+        Ok(Self {
+            default: build_check_client(options, None)?,
+            sites,
+        })
+    }
+
+    /// Client checking `url`.
+    fn for_site(&self, url: &str) -> &Client {
+        self.sites.get(url).unwrap_or(&self.default)
+    }
+}
+
+impl From<Client> for CheckClients {
+    fn from(default: Client) -> Self {
+        Self {
+            default,
+            sites: HashMap::new(),
+        }
+    }
+}
+
+/// Phase of a check that ran out of time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TimeoutPhase {
+    /// Resolving or connecting to the site, bounded by the connect timeout.
+    Connect,
+    /// Waiting for the next bytes of the response, bounded by the read timeout.
+    Read,
+    /// The whole check, redirects included.
+    Total,
+}
+
+impl TimeoutPhase {
+    /// Phase of the check started at `started` that `error` ended, if it timed out.
+    fn of(error: &reqwest::Error, timeouts: Timeouts, started: Instant) -> Option<Self> {
+        if !error.is_timeout() {
+            return None;
+        }
+        if error.is_connect() {
+            Some(Self::Connect)
+        } else if timeouts.read.is_some() && started.elapsed() < Duration::from_secs(timeouts.total)
+        {
+            Some(Self::Read)
+        } else {
+            Some(Self::Total)
+        }
+    }
+
+    fn describe(self, timeouts: Timeouts) -> String {
+        match self {
+            Self::Connect => match timeouts.connect {
+                Some(secs) => format!("connecting timed out after {secs} s"),
+                None => "connecting timed out".to_string(),
+            },
+            Self::Read => match timeouts.read {
+                Some(secs) => format!("reading the response timed out after {secs} s"),
+                None => "reading the response timed out".to_string(),
+            },
+            Self::Total => format!("check timed out after {} s", timeouts.total),
+        }
+    }
+}
+
+/// Result of a single site check.
+#[derive(Debug, Clone, Default)]
+struct CheckOutcome {
+    is_up: bool,
+    /// Phase the check ran out of time in, when it timed out.
+    timed_out: Option<TimeoutPhase>,
+    /// Fingerprint of the leaf certificate the site presented, `None`
+    /// without an HTTPS response.
+    certificate: Option<Fingerprint>,
+    /// Response of a failed check, when asked to capture it.
+    failed_response: Option<FailedResponse>,
+    /// Bytes read from the body of a successful response, when asked to
+    /// measure it.
+    body_bytes: Option<u64>,
+}
+
+impl CheckOutcome {
+    /// `capture_bytes` caps the body kept from a failing response, whose
+    /// body is not read at all when `None`. Likewise, `body_limit` caps the
+    /// body of a successful response read to measure its size.
+    async fn new(
+        response: reqwest::Result<Response>,
+        success: &SuccessStatuses,
+        capture_bytes: Option<usize>,
+        body_limit: Option<u64>,
+        timeouts: Timeouts,
+        started: Instant,
+    ) -> Self {
+        // Request errors count as down since we have no way of distinguishing between a network error and a real down on the server side
+        let response = match response {
+            Ok(response) => response,
+            Err(e) => {
+                return Self {
+                    timed_out: TimeoutPhase::of(&e, timeouts, started),
+                    ..Self::default()
+                };
+            }
+        };
+        let status = response.status();
+        // Ranged low-bandwidth GETs are answered with 206 where a full GET gets 200
+        let is_up = success.matches(status.as_u16())
+            || (status == StatusCode::PARTIAL_CONTENT && success.matches(200));
+        let certificate = response
+            .extensions()
+            .get::<TlsInfo>()
+            .and_then(TlsInfo::peer_certificate)
+            .map(pinning::fingerprint);
+        let mut outcome = Self {
+            is_up,
+            certificate,
+            ..Self::default()
+        };
+        match (capture_bytes, body_limit) {
+            (Some(max_body_bytes), _) if !is_up => {
+                outcome.failed_response =
+                    Some(FailedResponse::read(response, max_body_bytes).await);
             }
+            (_, Some(limit)) if is_up => match read_body_size(response, limit).await {
+                Ok(size) => outcome.body_bytes = Some(size),
+                // A body cut short is as much of a failure as no answer
+                Err(e) => {
+                    outcome.is_up = false;
+                    outcome.timed_out = TimeoutPhase::of(&e, timeouts, started);
+                }
+            },
+            _ => {}
         }
-        SiteCheckStatus::Down {
-            consecutive_failures,
-            should_alert: false,
-        } => warn!("{url}: DOWN ({consecutive_failures} consecutive failed checks)"),
+        outcome
     }
-    Ok(())
 }
 
+/// Reads the body of `response` until its end or `limit` bytes, returning
+/// how many bytes were read.
+async fn read_body_size(mut response: Response, limit: u64) -> reqwest::Result<u64> {
+    let mut size = 0;
+    while size < limit
+        && let Some(chunk) = response.chunk().await?
+    {
+        size += u64::try_from(chunk.len()).unwrap_or(u64::MAX);
+    }
+    Ok(size)
+}
+
+/// Response body size outside the bounds of its site.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum SiteCheckStatus {
-    Up {
-        recovered_after_failures: u64,
-    },
-    Unreachable {
-        consecutive_failures: u64,
-        failure_threshold: u64,
+enum BodySizeViolation {
+    TooSmall {
+        size: u64,
+        min_bytes: u64,
     },
-    Down {
-        consecutive_failures: u64,
-        should_alert: bool,
+    /// The body is only read one byte past the maximum, so its full size is unknown.
+    TooLarge {
+        max_bytes: u64,
     },
 }
 
-fn record_site_check(
-    site_state: &mut SiteState,
-    is_up: bool,
-    failure_threshold: u64,
-    alert_repeat_interval_secs: u64,
-    checked_at: Instant,
-) -> SiteCheckStatus {
-    if is_up {
-        let recovered_after_failures = site_state.consecutive_failures;
-        site_state.consecutive_failures = 0;
-        site_state.last_alert_at = None;
-
-        return SiteCheckStatus::Up {
-            recovered_after_failures,
-        };
+impl BodySizeViolation {
+    fn of(bounds: BodySizeBounds, size: u64) -> Option<Self> {
+        match (bounds.min_bytes, bounds.max_bytes) {
+            (Some(min_bytes), _) if size < min_bytes => Some(Self::TooSmall { size, min_bytes }),
+            (_, Some(max_bytes)) if size > max_bytes => Some(Self::TooLarge { max_bytes }),
+            _ => None,
+        }
     }
 
-    site_state.consecutive_failures += 1;
-
-    if site_state.consecutive_failures < failure_threshold {
-        return SiteCheckStatus::Unreachable {
-            consecutive_failures: site_state.consecutive_failures,
-            failure_threshold,
-        };
+    fn describe(self, phrases: &Phrases) -> String {
+        match self {
+            Self::TooSmall { size, min_bytes } => render_phrase(
+                phrases.body_too_small,
+                &Variables::default()
+                    .set("size", size.to_string())
+                    .set("min_bytes", min_bytes.to_string()),
+            ),
+            Self::TooLarge { max_bytes } => render_phrase(
+                phrases.body_too_large,
+                &Variables::default().set("max_bytes", max_bytes.to_string()),
+            ),
+        }
     }
+}
 
-    let should_alert = match site_state.last_alert_at {
-        None => true,
-        Some(last_alert_at) => {
-            checked_at.saturating_duration_since(last_alert_at)
-                >= Duration::from_secs(alert_repeat_interval_secs)
-        }
+/// HTTP client of a check, with the rate limiter its requests wait for when
+/// one is configured.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct PacedClient<'a> {
+    pub(crate) client: &'a Client,
+    pub(crate) rate_limiter: Option<&'a RateLimiter>,
+}
+
+/// Asynchronously checks if a given URL is up (returns a status in `success`).
+///
+/// In low-bandwidth mode a HEAD request is sent instead, falling back to a ranged
+/// GET capped to the first [`LOW_BANDWIDTH_MAX_BODY_BYTES`] when HEAD is not supported.
+pub(crate) async fn is_url_up(
+    client: PacedClient<'_>,
+    url: &str,
+    success: &SuccessStatuses,
+    timeout_secs: u64,
+    low_bandwidth: bool,
+) -> Result<bool, Error> {
+    let timeouts = Timeouts {
+        total: timeout_secs,
+        connect: None,
+        read: None,
     };
+    RateLimiter::wait_for(client.rate_limiter, url).await;
+    Ok(
+        check_url(client, url, success, timeouts, low_bandwidth, None, None)
+            .await
+            .is_up,
+    )
+}
 
-    if should_alert {
-        site_state.last_alert_at = Some(checked_at);
+/// Checks a URL like [`is_url_up`], also returning the certificate it
+/// presented and, with `capture_bytes`, the start of a failing response.
+///
+/// With `body_limit`, the body of a successful response is read up to that
+/// many bytes to measure it, except in low-bandwidth mode.
+///
+/// Every request but the first waits for the rate limiter of `client`, the
+/// caller waits for the first one before timing the check.
+async fn check_url(
+    client: PacedClient<'_>,
+    url: &str,
+    success: &SuccessStatuses,
+    timeouts: Timeouts,
+    low_bandwidth: bool,
+    capture_bytes: Option<usize>,
+    body_limit: Option<u64>,
+) -> CheckOutcome {
+    let timeout = Duration::from_secs(timeouts.total);
+    let started = Instant::now();
+
+    if !low_bandwidth {
+        let response = send_check(url, success, timeout, client.rate_limiter, |url| {
+            client.client.get(url)
+        })
+        .await;
+        return CheckOutcome::new(
+            response,
+            success,
+            capture_bytes,
+            body_limit,
+            timeouts,
+            started,
+        )
+        .await;
     }
 
-    SiteCheckStatus::Down {
-        consecutive_failures: site_state.consecutive_failures,
-        should_alert,
+    match send_check(url, success, timeout, client.rate_limiter, |url| {
+        client.client.head(url)
+    })
+    .await
+    {
+        Ok(resp)
+            if matches!(
+                resp.status(),
+                StatusCode::METHOD_NOT_ALLOWED | StatusCode::NOT_IMPLEMENTED
+            ) => {}
+        head_result => {
+            return CheckOutcome::new(head_result, success, capture_bytes, None, timeouts, started)
+                .await;
+        }
     }
-}
 
-fn next_sleep_duration<'a>(
-    site_states: impl Iterator<Item = &'a SiteState>,
-    check_interval_secs: u64,
-    now: Instant,
-) -> Duration {
-    site_states
-        .map(|state| state.next_check_at.saturating_duration_since(now))
-        .min()
-        .unwrap_or_else(|| Duration::from_secs(check_interval_secs))
+    RateLimiter::wait_for(client.rate_limiter, url).await;
+    let started = Instant::now();
+    let response = send_check(url, success, timeout, client.rate_limiter, |url| {
+        client.client.get(url).header(
+            RANGE,
+            format!("bytes=0-{}", LOW_BANDWIDTH_MAX_BODY_BYTES - 1),
+        )
+    })
+    .await;
+    CheckOutcome::new(response, success, capture_bytes, None, timeouts, started).await
 }
 
-/// Asynchronously checks if a given URL is up (returns a 2xx status).
-async fn is_url_up(url: &str, timeout_secs: u64) -> Result<bool, Error> {
-    let client = Client::builder()
-        .timeout(Duration::from_secs(timeout_secs))
-        .build()?;
-
-    Ok(client
-        .get(url)
-        .send()
-        .await
-        .map(|resp| resp.status().is_success())
-        // We unwrap here since we have no way of distinguishing between a network error and a real down on the server side
-        .unwrap_or(false))
-}
+/// Sends the check `request` for `url`, following redirects unless their
+/// status counts as success itself, so a site accepting `3xx` is judged
+/// on its own redirect. `timeout` covers the whole chain, including the
+/// waits for `rate_limiter` before each redirect hop.
+async fn send_check(
+    url: &str,
+    success: &SuccessStatuses,
+    timeout: Duration,
+    rate_limiter: Option<&RateLimiter>,
+    request: impl Fn(&str) -> RequestBuilder,
+) -> reqwest::Result<Response> {
+    let deadline = Instant::now() + timeout;
+    let mut url = url.to_string();
+    let mut redirects = 0;
+
+    loop {
+        let response = request(&url)
+            .timeout(deadline.saturating_duration_since(Instant::now()))
+            .send()
+            .await?;
+        let status = response.status();
+        if !status.is_redirection()
+            || success.matches(status.as_u16())
+            || redirects == MAX_REDIRECTS
+        {
+            return Ok(response);
+        }
 
-#[derive(Serialize)]
-struct DiscordMessage {
-    content: String,
+        let Some(next) = response
+            .headers()
+            .get(LOCATION)
+            .and_then(|location| location.to_str().ok())
+            .and_then(|location| response.url().join(location).ok())
+        else {
+            return Ok(response);
+        };
+        url = next.into();
+        redirects += 1;
+        RateLimiter::wait_for(rate_limiter, &url).await;
+    }
 }
 
-async fn send_discord_notification(
-    webhook_url: &str,
-    message: &str,
-    discord_id: Option<&u64>,
-) -> Result<(), Error> {
-    let client = Client::new();
-
-    // If discord_id is None, we don't want to mention anyone
-    let tag = discord_id.map_or(String::new(), |id| format!("<@{id}> "));
+/// Flips low-bandwidth mode every time the process receives `SIGUSR1`.
+#[cfg(unix)]
+fn spawn_low_bandwidth_toggle(low_bandwidth: Arc<AtomicBool>, token: CancellationToken) {
+    use tokio::signal::unix::{SignalKind, signal};
 
-    let payload = DiscordMessage {
-        content: format!("{tag}{message}").to_string(),
+    let mut toggles = match signal(SignalKind::user_defined1()) {
+        Ok(toggles) => toggles,
+        Err(e) => {
+            warn!("Failed to install SIGUSR1 handler, low-bandwidth mode cannot be toggled: {e}");
+            return;
+        }
     };
 
-    client.post(webhook_url).json(&payload).send().await?;
-    Ok(())
+    tokio::spawn(async move {
+        loop {
+            select! {
+                _ = toggles.recv() => {
+                    let enabled = !low_bandwidth.fetch_xor(true, Ordering::Relaxed);
+                    if enabled {
+                        info!("Low-bandwidth mode enabled");
+                    } else {
+                        info!("Low-bandwidth mode disabled");
+                    }
+                }
+                () = token.cancelled() => break,
+            }
+        }
+    });
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use tokio::{
-        io::{AsyncReadExt, AsyncWriteExt},
-        net::TcpListener,
+    use crate::config::{Language, RateLimitOptions};
+    use crate::locale::phrases;
+    use crate::test_support::{
+        check_options, spawn_test_http_server, spawn_test_http_server_sequence,
     };
+    use tokio::net::TcpListener;
 
     #[test]
     fn test_failures_do_not_alert_before_threshold() {
@@ -458,7 +1813,7 @@ mod tests {
             false,
             5,
             60,
-            started_at + Duration::from_secs(60),
+            started_at + Duration::from_mins(1),
         );
         assert_eq!(
             next_failure,
@@ -474,32 +1829,86 @@ mod tests {
         let checked_at = Instant::now();
         let mut site_state = SiteState::new(checked_at);
 
-        site_state.schedule_next_check(checked_at, 60);
+        site_state.schedule_next_check(checked_at, &check_options(60), false);
         assert_eq!(
             site_state.next_check_at.duration_since(checked_at),
-            Duration::from_secs(60)
+            Duration::from_mins(1)
         );
 
         site_state.consecutive_failures = 1;
-        site_state.schedule_next_check(checked_at, 60);
+        site_state.schedule_next_check(checked_at, &check_options(60), false);
         assert_eq!(
             site_state.next_check_at.duration_since(checked_at),
             Duration::from_secs(FAILED_SITE_RETRY_SECS)
         );
     }
 
+    #[test]
+    fn test_long_down_sites_back_off() {
+        let options = ConfigOptions {
+            down_backoff_max_secs: Some(60),
+            ..check_options(300)
+        };
+        let mut site_state = SiteState::new(Instant::now());
+
+        let delays: Vec<u64> = (1..=10)
+            .map(|consecutive_failures| {
+                site_state.consecutive_failures = consecutive_failures;
+                site_state.next_check_delay(&options, false).as_secs()
+            })
+            .collect();
+        assert_eq!(delays, [10, 10, 10, 10, 10, 20, 40, 60, 60, 60]);
+
+        site_state.consecutive_failures = u64::MAX;
+        assert_eq!(
+            site_state.next_check_delay(&options, false),
+            Duration::from_mins(1)
+        );
+        site_state.consecutive_failures = 0;
+        assert_eq!(
+            site_state.next_check_delay(&options, false),
+            Duration::from_mins(5),
+            "Recovered sites are back to the check interval"
+        );
+    }
+
+    #[test]
+    fn test_low_bandwidth_mode_stretches_check_intervals() {
+        let checked_at = Instant::now();
+        let mut site_state = SiteState::new(checked_at);
+
+        site_state.schedule_next_check(checked_at, &check_options(60), true);
+        assert_eq!(
+            site_state.next_check_at.duration_since(checked_at),
+            Duration::from_secs(60 * LOW_BANDWIDTH_INTERVAL_MULTIPLIER)
+        );
+
+        site_state.consecutive_failures = 1;
+        site_state.schedule_next_check(checked_at, &check_options(60), true);
+        assert_eq!(
+            site_state.next_check_at.duration_since(checked_at),
+            Duration::from_secs(FAILED_SITE_RETRY_SECS * LOW_BANDWIDTH_INTERVAL_MULTIPLIER)
+        );
+    }
+
     #[test]
     fn test_next_sleep_duration_uses_earliest_site_check() {
         let now = Instant::now();
         let healthy_site = SiteState {
             consecutive_failures: 0,
             last_alert_at: None,
-            next_check_at: now + Duration::from_secs(60),
+            next_check_at: now + Duration::from_mins(1),
+            alerted_certificate: None,
+            alerted_body_size: false,
+            latencies: LatencyWindow::default(),
         };
         let failing_site = SiteState {
             consecutive_failures: 1,
             last_alert_at: None,
             next_check_at: now + Duration::from_secs(FAILED_SITE_RETRY_SECS),
+            alerted_certificate: None,
+            alerted_body_size: false,
+            latencies: LatencyWindow::default(),
         };
 
         let sleep_duration =
@@ -508,6 +1917,147 @@ mod tests {
         assert_eq!(sleep_duration, Duration::from_secs(FAILED_SITE_RETRY_SECS));
     }
 
+    #[test]
+    fn test_sync_site_states_tracks_added_and_removed_sites() {
+        let started_at = Instant::now();
+        let mut site_states = HashMap::new();
+        sync_site_states(
+            &mut site_states,
+            &["https://a.example".to_string()],
+            started_at,
+        );
+        site_states
+            .get_mut("https://a.example")
+            .unwrap()
+            .consecutive_failures = 2;
+
+        let later = started_at + Duration::from_mins(1);
+        sync_site_states(
+            &mut site_states,
+            &[
+                "https://a.example".to_string(),
+                "https://b.example".to_string(),
+            ],
+            later,
+        );
+        assert_eq!(site_states["https://a.example"].consecutive_failures, 2);
+        assert_eq!(site_states["https://b.example"].next_check_at, later);
+
+        sync_site_states(&mut site_states, &["https://b.example".to_string()], later);
+        assert!(!site_states.contains_key("https://a.example"));
+    }
+
+    #[tokio::test]
+    async fn test_unconfirmed_down_does_not_alert() {
+        let clients = CheckClients::from(Client::new());
+        let options = ConfigOptions {
+            failure_threshold: 1,
+            ..check_options(60)
+        };
+        let proxy = spawn_test_http_server("HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n").await;
+        let confirmer = Confirmer::new(
+            &crate::config::ConfirmationOptions {
+                proxy: Some(Url::parse(&proxy).unwrap()),
+                probe_url: None,
+                probe_token: None,
+            },
+            &options,
+        )
+        .unwrap();
+        let board = StatusBoard::new(&[]);
+        let ctx = CheckContext {
+            clients: &clients,
+            options: &options,
+            sites: &SiteList::default(),
+            board: &board,
+            low_bandwidth: false,
+            notify: true,
+            confirmer: Some(&confirmer),
+            diagnostics: None,
+            captures: None,
+            rate_limiter: None,
+            latency: &LatencyOptions::default(),
+            notifier: &Notifier::default(),
+        };
+        let checked_at = Instant::now();
+        let mut site_state = SiteState::new(checked_at);
+        let status = record_site_check(&mut site_state, false, 1, 60, checked_at);
+
+        let status =
+            confirm_down(&ctx, "http://unreachable.invalid", status, &mut site_state).await;
+
+        assert_eq!(
+            status,
+            SiteCheckStatus::Down {
+                consecutive_failures: 1,
+                should_alert: false,
+            }
+        );
+        assert_eq!(
+            site_state.last_alert_at, None,
+            "The next failed check must ask for confirmation again"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_agent_reports_are_tracked_per_agent() {
+        let clients = CheckClients::from(Client::new());
+        let options = ConfigOptions {
+            failure_threshold: 2,
+            ..check_options(60)
+        };
+        let board = StatusBoard::new(&[]);
+        let ctx = CheckContext {
+            clients: &clients,
+            options: &options,
+            sites: &SiteList::default(),
+            board: &board,
+            low_bandwidth: false,
+            notify: true,
+            confirmer: None,
+            diagnostics: None,
+            captures: None,
+            rate_limiter: None,
+            latency: &LatencyOptions::default(),
+            notifier: &Notifier::default(),
+        };
+        let report = |agent: &str| AgentReport {
+            agent: agent.to_string(),
+            results: vec![crate::agent::AgentResult {
+                url: "http://internal.example".to_string(),
+                checked_at_ms: 1_700_000_000_000,
+                up: false,
+                response_time_ms: 12,
+            }],
+        };
+        let mut remote = RemoteSites::default();
+        let exporters = Exporters::default();
+
+        for agent in ["vpc-a", "vpc-a", "vpc-b"] {
+            process_agent_report(&ctx, report(agent), &mut remote, &exporters).await;
+        }
+
+        let sites = board.snapshot();
+        assert_eq!(sites.len(), 2);
+        assert_eq!(sites[0].agent.as_deref(), Some("vpc-a"));
+        assert_eq!(sites[0].health, SiteHealth::Down);
+        assert_eq!(sites[1].agent.as_deref(), Some("vpc-b"));
+        assert_eq!(sites[1].health, SiteHealth::Unreachable);
+
+        // Agents that stop reporting leave their sites unknown until they resume
+        mark_stale_agents(&ctx, &mut remote.liveness, Some(Duration::ZERO)).await;
+        assert!(
+            board
+                .snapshot()
+                .iter()
+                .all(|site| site.health == SiteHealth::Unknown)
+        );
+        process_agent_report(&ctx, report("vpc-a"), &mut remote, &exporters).await;
+        let sites = board.snapshot();
+        assert_eq!(sites[0].health, SiteHealth::Down);
+        assert_eq!(sites[1].health, SiteHealth::Unknown);
+    }
+
     #[tokio::test]
     async fn test_local_success_url_is_up() {
         let url = spawn_test_http_server(
@@ -515,10 +2065,146 @@ mod tests {
         )
         .await;
 
-        let result = is_url_up(&url, 5).await.unwrap();
+        let result = is_url_up(
+            PacedClient {
+                client: &Client::new(),
+                rate_limiter: None,
+            },
+            &url,
+            &SuccessStatuses::default(),
+            5,
+            false,
+        )
+        .await
+        .unwrap();
         assert!(result, "Expected local test server to be up");
     }
 
+    #[tokio::test]
+    async fn test_low_bandwidth_head_url_is_up() {
+        let url = spawn_test_http_server(
+            "HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        )
+        .await;
+
+        let result = is_url_up(
+            PacedClient {
+                client: &Client::new(),
+                rate_limiter: None,
+            },
+            &url,
+            &SuccessStatuses::default(),
+            5,
+            true,
+        )
+        .await
+        .unwrap();
+        assert!(
+            result,
+            "Expected HEAD check against local test server to be up"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_low_bandwidth_falls_back_to_ranged_get() {
+        let url = spawn_test_http_server_sequence(vec![
+            "HTTP/1.1 405 Method Not Allowed\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            "HTTP/1.1 206 Partial Content\r\nContent-Length: 2\r\nConnection: close\r\n\r\nOK",
+        ])
+        .await;
+
+        let result = is_url_up(
+            PacedClient {
+                client: &Client::new(),
+                rate_limiter: None,
+            },
+            &url,
+            &SuccessStatuses::default(),
+            5,
+            true,
+        )
+        .await
+        .unwrap();
+        assert!(
+            result,
+            "Expected ranged GET fallback to report the site as up"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_redirect_hops_wait_for_the_rate_limiter() {
+        let client = build_check_client(&check_options(60), None).unwrap();
+        let url = spawn_test_http_server_sequence(vec![
+            "HTTP/1.1 302 Found\r\nLocation: /next\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        ])
+        .await;
+        let rate_limiter = RateLimiter::new(&RateLimitOptions {
+            requests_per_sec: None,
+            per_host_delay_ms: Some(300),
+        });
+
+        let started = Instant::now();
+        let up = is_url_up(
+            PacedClient {
+                client: &client,
+                rate_limiter: Some(&rate_limiter),
+            },
+            &url,
+            &SuccessStatuses::default(),
+            5,
+            false,
+        )
+        .await
+        .unwrap();
+        assert!(up);
+        assert!(
+            started.elapsed() >= Duration::from_millis(300),
+            "The redirect hop waited for the host delay"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_redirects_are_followed_unless_accepted() {
+        let client = build_check_client(&check_options(60), None).unwrap();
+        // Nothing listens on port 1, following the redirect fails
+        let url = spawn_test_http_server_sequence(vec![
+            "HTTP/1.1 301 Moved Permanently\r\nLocation: http://127.0.0.1:1/\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+            2
+        ])
+        .await;
+
+        let followed = is_url_up(
+            PacedClient {
+                client: &client,
+                rate_limiter: None,
+            },
+            &url,
+            &SuccessStatuses::default(),
+            5,
+            false,
+        )
+        .await
+        .unwrap();
+        assert!(!followed, "The redirect target is down");
+
+        let accepted: SuccessStatuses =
+            serde_json::from_value(serde_json::json!(["2xx", "3xx"])).unwrap();
+        let up = is_url_up(
+            PacedClient {
+                client: &client,
+                rate_limiter: None,
+            },
+            &url,
+            &accepted,
+            5,
+            false,
+        )
+        .await
+        .unwrap();
+        assert!(up, "The redirect itself counts as up");
+    }
+
     #[tokio::test]
     async fn test_local_non_success_url_is_down() {
         let url = spawn_test_http_server(
@@ -526,51 +2212,189 @@ mod tests {
         )
         .await;
 
-        let result = is_url_up(&url, 5).await.unwrap();
+        let result = is_url_up(
+            PacedClient {
+                client: &Client::new(),
+                rate_limiter: None,
+            },
+            &url,
+            &SuccessStatuses::default(),
+            5,
+            false,
+        )
+        .await
+        .unwrap();
         assert!(!result, "Expected local test server to be reported as down");
     }
 
-    #[ignore = "This test requires a valid Discord webhook URL and ID"]
     #[tokio::test]
-    async fn test_discord_notification() {
-        let webhook_url = dotenvy::var("WEBHOOK_URL").expect("WEBHOOK_URL not set");
-        let discord_id: u64 = dotenvy::var("DISCORD_ID")
-            .expect("DISCORD_ID not set")
-            .parse()
-            .expect("Invalid DISCORD_ID");
-        let message = "Test notification from Rust!";
-        let result = send_discord_notification(&webhook_url, message, Some(&discord_id)).await;
-        assert!(
-            result.is_ok(),
-            "Expected notification to be sent successfully"
+    async fn test_body_size_is_measured_against_bounds() {
+        let url = spawn_test_http_server_sequence(vec![
+            "HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nContent-Length: 10\r\nConnection: close\r\n\r\n0123456789",
+        ])
+        .await;
+        let bounds = BodySizeBounds {
+            min_bytes: Some(1),
+            max_bytes: Some(4),
+            mismatch: BodySizeMismatch::Down,
+        };
+        let timeouts = Timeouts {
+            total: 5,
+            connect: None,
+            read: None,
+        };
+        let client = Client::new();
+        let success = SuccessStatuses::default();
+        let check = || {
+            check_url(
+                PacedClient {
+                    client: &client,
+                    rate_limiter: None,
+                },
+                &url,
+                &success,
+                timeouts,
+                false,
+                None,
+                Some(bounds.read_limit()),
+            )
+        };
+
+        let empty = check().await;
+        assert!(empty.is_up, "The status alone is a success");
+        assert_eq!(empty.body_bytes, Some(0));
+        assert_eq!(
+            BodySizeViolation::of(bounds, 0),
+            Some(BodySizeViolation::TooSmall {
+                size: 0,
+                min_bytes: 1
+            })
         );
+
+        // Reading stops once past the maximum
+        let size = check().await.body_bytes.unwrap();
+        assert!(size > 4, "{size}");
+        let too_large = BodySizeViolation::of(bounds, size).unwrap();
+        assert_eq!(
+            too_large.describe(phrases(Language::En)),
+            "Response body over 4 bytes, the most expected"
+        );
+        assert_eq!(BodySizeViolation::of(bounds, 4), None);
     }
 
-    async fn spawn_test_http_server(response: &'static str) -> String {
-        let listener = TcpListener::bind("127.0.0.1:0")
+    #[tokio::test]
+    async fn test_shared_hosts_are_checked_once() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let up = listener.local_addr().unwrap();
+        // Nothing listens on a freed port
+        let down = TcpListener::bind("127.0.0.1:0")
             .await
-            .expect("Failed to bind local test server");
-        let addr = listener
+            .unwrap()
             .local_addr()
-            .expect("Failed to read local test server address");
+            .unwrap();
+
+        let urls = [
+            format!("http://{up}/a"),
+            format!("http://{up}/b"),
+            format!("http://{down}/a"),
+            format!("http://{down}/b"),
+            "http://127.0.0.1:1/alone".to_string(),
+        ];
+        let due_urls: Vec<&str> = urls.iter().map(String::as_str).collect();
+
+        let mut disabled = HostChecks::new(&check_options(60), &due_urls);
+        assert_eq!(disabled.unreachable_host(&urls[2], None).await, None);
+
+        let options = ConfigOptions {
+            group_by_host: true,
+            ..check_options(60)
+        };
+        let mut host_checks = HostChecks::new(&options, &due_urls);
+        assert_eq!(
+            host_checks.hosts.len(),
+            2,
+            "Hosts of a single URL are not grouped"
+        );
+        assert_eq!(host_checks.unreachable_host(&urls[0], None).await, None);
+        assert_eq!(host_checks.unreachable_host(&urls[1], None).await, None);
+        for url in &urls[2..4] {
+            assert_eq!(
+                host_checks.unreachable_host(url, None).await,
+                Some(down.to_string())
+            );
+        }
+        assert_eq!(host_checks.unreachable_host(&urls[4], None).await, None);
+    }
 
+    #[tokio::test]
+    async fn test_timeouts_report_their_phase() {
+        // Accepts connections and never answers them
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
         tokio::spawn(async move {
-            let (mut stream, _) = listener
-                .accept()
-                .await
-                .expect("Failed to accept local test connection");
-            let mut request_buf = [0_u8; 1024];
-            let _ = stream.read(&mut request_buf).await;
-            stream
-                .write_all(response.as_bytes())
-                .await
-                .expect("Failed to write local test response");
-            stream
-                .shutdown()
-                .await
-                .expect("Failed to close local test response stream");
+            let mut streams = Vec::new();
+            while let Ok((stream, _)) = listener.accept().await {
+                streams.push(stream);
+            }
         });
 
-        format!("http://{addr}")
+        let timeouts = Timeouts {
+            total: 1,
+            connect: None,
+            read: None,
+        };
+        let client = build_client(&check_options(60), None, timeouts).unwrap();
+        let success = SuccessStatuses::default();
+        let outcome = check_url(
+            PacedClient {
+                client: &client,
+                rate_limiter: None,
+            },
+            &url,
+            &success,
+            timeouts,
+            false,
+            None,
+            None,
+        )
+        .await;
+        assert!(!outcome.is_up);
+        assert_eq!(outcome.timed_out, Some(TimeoutPhase::Total));
+
+        let timeouts = Timeouts {
+            total: 30,
+            connect: Some(1),
+            read: Some(1),
+        };
+        let client = build_client(&check_options(60), None, timeouts).unwrap();
+        let outcome = check_url(
+            PacedClient {
+                client: &client,
+                rate_limiter: None,
+            },
+            &url,
+            &success,
+            timeouts,
+            false,
+            None,
+            None,
+        )
+        .await;
+        assert_eq!(outcome.timed_out, Some(TimeoutPhase::Read));
+        assert_eq!(
+            TimeoutPhase::Read.describe(timeouts),
+            "reading the response timed out after 1 s"
+        );
+    }
+
+    #[test]
+    fn test_low_memory_flushes_early() {
+        let mut options = check_options(60);
+        assert!(!flush_due(&options, 1000), "Flushed once per cycle");
+
+        options.low_memory = true;
+        assert!(!flush_due(&options, LOW_MEMORY_MAX_BUFFERED_RECORDS - 1));
+        assert!(flush_due(&options, LOW_MEMORY_MAX_BUFFERED_RECORDS));
     }
 }